@@ -0,0 +1,18 @@
+import { request } from '@playwright/test';
+
+/**
+ * Logs in as the user the web server command creates and saves the session cookie
+ * for the tests to reuse.
+ */
+export default async function globalSetup() {
+  const context = await request.newContext({ baseURL: 'http://localhost:3001' });
+  const response = await context.post('/login', {
+    form: { username: 'e2e', password: 'e2e-password' },
+    maxRedirects: 0,
+  });
+  if (response.status() !== 303) {
+    throw new Error(`Login for the E2E tests failed with status ${response.status()}`);
+  }
+  await context.storageState({ path: './e2e-test-data/auth.json' });
+  await context.dispose();
+}
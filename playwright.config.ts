@@ -5,6 +5,8 @@ import { defineConfig, devices } from '@playwright/test';
  */
 export default defineConfig({
   testDir: './e2e-tests',
+  /* Log in once and share the session cookie with every test */
+  globalSetup: './e2e-tests/global-setup.ts',
   /* Run tests in files in parallel */
   fullyParallel: true,
   /* Fail the build on CI if you accidentally left test.only in the source code. */
@@ -23,6 +25,7 @@ export default defineConfig({
   use: {
     /* Base URL to use in actions like `await page.goto('/')`. */
     baseURL: 'http://localhost:3001',
+    storageState: './e2e-test-data/auth.json',
 
     /* Collect trace when retrying the failed test. See https://playwright.dev/docs/trace-viewer */
     trace: 'on-first-retry',
@@ -90,7 +93,8 @@ export default defineConfig({
 
   /* Run your local dev server before starting the tests */
   webServer: {
-    command: 'cargo run -- --port 3001 --address 0.0.0.0 --data-dir ./e2e-test-data',
+    /* The admin may already exist from an earlier run, so a failing create-admin is fine */
+    command: "echo 'e2e-password' | cargo run -- --data-dir ./e2e-test-data create-admin e2e; cargo run -- --port 3001 --address 0.0.0.0 --data-dir ./e2e-test-data",
     url: 'http://localhost:3001',
     reuseExistingServer: !process.env.CI,
     timeout: 120 * 1000, // 2 minutes for server start
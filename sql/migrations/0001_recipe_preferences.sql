@@ -0,0 +1,4 @@
+-- Favourites, ratings and tags used by the meal planner
+ALTER TABLE recipes ADD COLUMN favourite INTEGER NOT NULL DEFAULT 0 CHECK(favourite IN (0,1));
+ALTER TABLE recipes ADD COLUMN rating INTEGER NOT NULL DEFAULT 0 CHECK(rating BETWEEN 0 AND 5);
+ALTER TABLE recipes ADD COLUMN tags TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,85 @@
+-- Store created/modified timestamps in UTC instead of the server's local time.
+-- The household timezone from the settings table is applied when reading them.
+DROP TRIGGER IF EXISTS update_tasks_modified;
+DROP TRIGGER IF EXISTS update_lists_modified;
+DROP TRIGGER IF EXISTS update_recipes_modified;
+
+UPDATE lists SET
+  created = strftime('%Y-%m-%d %H:%M:%S', substr(created, 1, 19), 'utc') || substr(created, 20),
+  modified = strftime('%Y-%m-%d %H:%M:%S', substr(modified, 1, 19), 'utc') || substr(modified, 20);
+UPDATE tasks SET
+  created = strftime('%Y-%m-%d %H:%M:%S', substr(created, 1, 19), 'utc') || substr(created, 20),
+  modified = strftime('%Y-%m-%d %H:%M:%S', substr(modified, 1, 19), 'utc') || substr(modified, 20);
+UPDATE recipes SET
+  created = strftime('%Y-%m-%d %H:%M:%S', substr(created, 1, 19), 'utc') || substr(created, 20),
+  modified = strftime('%Y-%m-%d %H:%M:%S', substr(modified, 1, 19), 'utc') || substr(modified, 20);
+UPDATE meal_plan SET
+  created = strftime('%Y-%m-%d %H:%M:%S', substr(created, 1, 19), 'utc') || substr(created, 20);
+UPDATE recipe_photos SET
+  created = strftime('%Y-%m-%d %H:%M:%S', substr(created, 1, 19), 'utc') || substr(created, 20);
+
+CREATE TRIGGER update_tasks_modified
+BEFORE UPDATE
+    ON tasks
+BEGIN
+    UPDATE tasks
+       SET modified = strftime('%Y-%m-%d %H:%M:%S:%s', 'now')
+     WHERE id = old.id;
+END;
+
+CREATE TRIGGER update_lists_modified
+BEFORE UPDATE
+    ON tasks
+BEGIN
+    UPDATE lists
+       SET modified = strftime('%Y-%m-%d %H:%M:%S:%s', 'now')
+     WHERE id = old.list_id;
+END;
+
+CREATE TRIGGER update_recipes_modified
+BEFORE UPDATE ON recipes
+BEGIN
+    UPDATE recipes SET modified = strftime('%Y-%m-%d %H:%M:%S:%s', 'now')
+    WHERE id = old.id;
+END;
+
+-- Column defaults can't be altered in place, so tables created before this
+-- migration get their insert timestamps rewritten to UTC by triggers.
+CREATE TRIGGER set_lists_created
+AFTER INSERT ON lists
+BEGIN
+    UPDATE lists
+       SET created = strftime('%Y-%m-%d %H:%M:%S:%s', 'now'),
+           modified = strftime('%Y-%m-%d %H:%M:%S:%s', 'now')
+     WHERE id = new.id;
+END;
+
+CREATE TRIGGER set_tasks_created
+AFTER INSERT ON tasks
+BEGIN
+    UPDATE tasks
+       SET created = strftime('%Y-%m-%d %H:%M:%S:%s', 'now'),
+           modified = strftime('%Y-%m-%d %H:%M:%S:%s', 'now')
+     WHERE id = new.id;
+END;
+
+CREATE TRIGGER set_recipes_created
+AFTER INSERT ON recipes
+BEGIN
+    UPDATE recipes
+       SET created = strftime('%Y-%m-%d %H:%M:%S:%s', 'now'),
+           modified = strftime('%Y-%m-%d %H:%M:%S:%s', 'now')
+     WHERE id = new.id;
+END;
+
+CREATE TRIGGER set_meal_plan_created
+AFTER INSERT ON meal_plan
+BEGIN
+    UPDATE meal_plan SET created = strftime('%Y-%m-%d %H:%M:%S:%s', 'now') WHERE id = new.id;
+END;
+
+CREATE TRIGGER set_recipe_photos_created
+AFTER INSERT ON recipe_photos
+BEGIN
+    UPDATE recipe_photos SET created = strftime('%Y-%m-%d %H:%M:%S:%s', 'now') WHERE id = new.id;
+END;
@@ -0,0 +1,2 @@
+-- Leftover entries point at the earlier meal they reuse instead of a recipe
+ALTER TABLE meal_plan ADD COLUMN leftovers_of INTEGER REFERENCES meal_plan(id) ON DELETE SET NULL;
@@ -0,0 +1,3 @@
+-- How many people a recipe serves, and how many a planned meal is for
+ALTER TABLE recipes ADD COLUMN servings INTEGER CHECK(servings > 0);
+ALTER TABLE meal_plan ADD COLUMN servings INTEGER CHECK(servings > 0);
@@ -0,0 +1,2 @@
+-- Checked off tasks are archived into the shopping trip they were bought on
+ALTER TABLE tasks ADD COLUMN trip_id INTEGER REFERENCES shopping_trips(id) ON DELETE CASCADE;
@@ -0,0 +1,3 @@
+-- Price paid for a shopping list item, and the store it was bought in
+ALTER TABLE tasks ADD COLUMN price REAL CHECK(price >= 0);
+ALTER TABLE tasks ADD COLUMN store_id INTEGER REFERENCES stores(id) ON DELETE SET NULL;
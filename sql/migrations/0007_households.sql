@@ -0,0 +1,47 @@
+-- Lists, recipes, the meal plan, the pantry and stores belong to a household. Everything
+-- that exists already, and every user, moves into the first one.
+INSERT OR IGNORE INTO households (id, name) VALUES (1, 'Home');
+INSERT OR IGNORE INTO household_members (household_id, user_id) SELECT 1, id FROM users;
+
+-- The pantry shopping list was a global setting
+UPDATE households
+   SET shopping_list_id = (SELECT CAST(value AS INTEGER) FROM settings WHERE key = 'pantry_shopping_list')
+ WHERE id = 1;
+DELETE FROM settings WHERE key = 'pantry_shopping_list';
+
+ALTER TABLE lists ADD COLUMN household_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE recipes ADD COLUMN household_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE meal_plan ADD COLUMN household_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE pantry_items ADD COLUMN household_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE stores ADD COLUMN household_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE price_history ADD COLUMN household_id INTEGER NOT NULL DEFAULT 1;
+
+CREATE INDEX idx_lists_household ON lists(household_id);
+CREATE INDEX idx_recipes_household ON recipes(household_id);
+CREATE INDEX idx_meal_plan_household_date ON meal_plan(household_id, date);
+CREATE INDEX idx_pantry_items_household ON pantry_items(household_id);
+CREATE INDEX idx_stores_household ON stores(household_id);
+
+-- Tables keyed by date or name get the household in their primary key
+CREATE TABLE meal_plan_notes_new (
+  household_id INTEGER NOT NULL DEFAULT 1,
+  date TEXT NOT NULL, -- YYYY-MM-DD format
+  note TEXT NOT NULL,
+  PRIMARY KEY(household_id, date)
+);
+INSERT INTO meal_plan_notes_new (date, note) SELECT date, note FROM meal_plan_notes;
+DROP TABLE meal_plan_notes;
+ALTER TABLE meal_plan_notes_new RENAME TO meal_plan_notes;
+
+CREATE TABLE ingredient_categories_new (
+  household_id INTEGER NOT NULL DEFAULT 1,
+  name TEXT NOT NULL,
+  category TEXT NOT NULL,
+  PRIMARY KEY(household_id, name)
+);
+INSERT INTO ingredient_categories_new (name, category) SELECT name, category FROM ingredient_categories;
+DROP TABLE ingredient_categories;
+ALTER TABLE ingredient_categories_new RENAME TO ingredient_categories;
+
+-- Household a session is working in, NULL until the user picks one
+ALTER TABLE sessions ADD COLUMN household_id INTEGER REFERENCES households(id) ON DELETE SET NULL;
@@ -0,0 +1,5 @@
+-- Counters bumped on every edit, checked against If-Match so that simultaneous edits
+-- conflict instead of overwriting each other. A list's version covers its task order.
+ALTER TABLE tasks ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE recipes ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE lists ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
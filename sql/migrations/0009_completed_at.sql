@@ -0,0 +1,2 @@
+-- When a task was checked off, as a Unix timestamp. NULL while it is open.
+ALTER TABLE tasks ADD COLUMN completed_at INTEGER;
@@ -0,0 +1,5 @@
+-- todo.txt priority, A being the highest. NULL for tasks without one.
+ALTER TABLE tasks ADD COLUMN priority TEXT CHECK(priority GLOB '[A-Z]');
+-- Tags of a task, the @contexts of todo.txt files it was imported from. Space separated,
+-- without the @.
+ALTER TABLE tasks ADD COLUMN tags TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,5 @@
+-- Name and UID a CalDAV client gave a task it created. Tasks made anywhere else are
+-- served as <id>.ics with a UID made from the id.
+ALTER TABLE tasks ADD COLUMN caldav_name TEXT;
+ALTER TABLE tasks ADD COLUMN caldav_uid TEXT;
+CREATE UNIQUE INDEX tasks_caldav_name ON tasks(list_id, caldav_name);
@@ -0,0 +1,5 @@
+-- When a checked off shopping list task was added to the pantry, so checking it off again
+-- after unchecking it doesn't add it twice. Tasks already checked off there were stocked.
+ALTER TABLE tasks ADD COLUMN stocked_at INTEGER;
+UPDATE tasks SET stocked_at = COALESCE(completed_at, CAST(strftime('%s', 'now') AS INTEGER))
+WHERE completed AND list_id IN (SELECT shopping_list_id FROM households);
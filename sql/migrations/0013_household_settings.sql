@@ -0,0 +1,13 @@
+-- Language, week start, date format and timezone are chosen per household. Every household
+-- keeps what the server-wide settings were.
+CREATE TABLE settings_new (
+  household_id INTEGER NOT NULL DEFAULT 1,
+  key TEXT NOT NULL,
+  value TEXT NOT NULL,
+  PRIMARY KEY(household_id, key),
+  FOREIGN KEY(household_id) REFERENCES households(id) ON DELETE CASCADE
+);
+INSERT INTO settings_new (household_id, key, value)
+SELECT households.id, settings.key, settings.value FROM households, settings;
+DROP TABLE settings;
+ALTER TABLE settings_new RENAME TO settings;
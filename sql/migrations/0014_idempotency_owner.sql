@@ -0,0 +1,15 @@
+-- Responses to requests sent with an Idempotency-Key, to answer repeats of them with. A key
+-- belongs to the user, or share link, that sent it and the household it was sent in. Keys
+-- taken so far are dropped; they only live a week anyway.
+DROP TABLE IF EXISTS idempotency_keys;
+CREATE TABLE idempotency_keys (
+  owner TEXT NOT NULL, -- "user <id>" or "share <token>"
+  household_id INTEGER NOT NULL,
+  key TEXT NOT NULL,
+  request TEXT NOT NULL, -- Method, path and a hash of the body the key was first sent with
+  status INTEGER, -- NULL while the first request is running
+  content_type TEXT,
+  body BLOB,
+  created INTEGER NOT NULL, -- Unix timestamp
+  PRIMARY KEY(owner, household_id, key)
+);
@@ -0,0 +1,11 @@
+-- Users invited into a household. They become members once they accept.
+CREATE TABLE household_invites (
+  household_id INTEGER NOT NULL,
+  user_id INTEGER NOT NULL,
+  invited_by INTEGER,
+  created TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now')),
+  PRIMARY KEY(household_id, user_id),
+  FOREIGN KEY(household_id) REFERENCES households(id) ON DELETE CASCADE,
+  FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE,
+  FOREIGN KEY(invited_by) REFERENCES users(id) ON DELETE SET NULL
+);
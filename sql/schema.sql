@@ -2,8 +2,8 @@ PRAGMA foreign_keys = ON;
 CREATE TABLE IF NOT EXISTS lists (
   id INTEGER PRIMARY KEY,
   name TEXT NOT NULL,
-  created TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now', 'localtime') ),
-  modified TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now', 'localtime') ) 
+  created TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now') ),
+  modified TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now') ) 
 );
 
 CREATE TABLE IF NOT EXISTS tasks (
@@ -11,8 +11,8 @@ CREATE TABLE IF NOT EXISTS tasks (
   task TEXT NOT NULL,
   completed INTEGER NOT NULL DEFAULT 0 CHECK(completed IN (0,1)),
   position INTEGER NOT NULL DEFAULT 0,
-  created TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now', 'localtime') ),
-  modified TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now', 'localtime') ),
+  created TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now') ),
+  modified TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now') ),
   list_id INTEGER, 
   FOREIGN KEY(list_id) REFERENCES lists(id)
   ON DELETE CASCADE
@@ -24,7 +24,7 @@ BEFORE UPDATE
     ON tasks
 BEGIN
     UPDATE tasks
-       SET modified = strftime('%Y-%m-%d %H:%M:%S:%s', 'now', 'localtime') 
+       SET modified = strftime('%Y-%m-%d %H:%M:%S:%s', 'now') 
      WHERE id = old.id;
 END;
 
@@ -33,7 +33,7 @@ BEFORE UPDATE
     ON tasks
 BEGIN
     UPDATE lists
-       SET modified = strftime('%Y-%m-%d %H:%M:%S:%s', 'now', 'localtime') 
+       SET modified = strftime('%Y-%m-%d %H:%M:%S:%s', 'now') 
      WHERE id = old.list_id;
 END;
 
@@ -44,8 +44,8 @@ CREATE TABLE IF NOT EXISTS recipes (
   instructions TEXT NOT NULL DEFAULT '',
   ingredients TEXT NOT NULL DEFAULT '',
   photo_url TEXT DEFAULT '',
-  created TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now', 'localtime')),
-  modified TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now', 'localtime'))
+  created TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now')),
+  modified TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now'))
 );
 
 -- Meal plan entries
@@ -54,7 +54,7 @@ CREATE TABLE IF NOT EXISTS meal_plan (
   date TEXT NOT NULL, -- YYYY-MM-DD format
   meal_text TEXT NOT NULL, -- Either recipe title or free-form text
   recipe_id INTEGER, -- NULL for free-form entries
-  created TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now', 'localtime')),
+  created TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now')),
   FOREIGN KEY(recipe_id) REFERENCES recipes(id) ON DELETE SET NULL
 );
 
@@ -62,7 +62,7 @@ CREATE TABLE IF NOT EXISTS meal_plan (
 CREATE TRIGGER IF NOT EXISTS update_recipes_modified
 BEFORE UPDATE ON recipes
 BEGIN
-    UPDATE recipes SET modified = strftime('%Y-%m-%d %H:%M:%S:%s', 'now', 'localtime') 
+    UPDATE recipes SET modified = strftime('%Y-%m-%d %H:%M:%S:%s', 'now') 
     WHERE id = old.id;
 END;
 
@@ -76,10 +76,131 @@ CREATE TABLE IF NOT EXISTS recipe_photos (
   mime_type TEXT NOT NULL,
   upload_order INTEGER NOT NULL DEFAULT 0,
   thumbnail_blob BLOB,
-  created TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now', 'localtime')),
+  created TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now')),
   FOREIGN KEY(recipe_id) REFERENCES recipes(id) ON DELETE CASCADE
 );
 
 -- Index for faster photo queries
 CREATE INDEX IF NOT EXISTS idx_recipe_photos_recipe_id ON recipe_photos(recipe_id);
 CREATE INDEX IF NOT EXISTS idx_recipe_photos_order ON recipe_photos(recipe_id, upload_order);
+
+-- Application settings as key/value pairs
+CREATE TABLE IF NOT EXISTS settings (
+  key TEXT PRIMARY KEY,
+  value TEXT NOT NULL
+);
+
+-- Free-form notes per meal plan day, e.g. "eat out"
+CREATE TABLE IF NOT EXISTS meal_plan_notes (
+  date TEXT PRIMARY KEY, -- YYYY-MM-DD format
+  note TEXT NOT NULL
+);
+
+-- Food in stock at home
+CREATE TABLE IF NOT EXISTS pantry_items (
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL,
+  quantity REAL, -- NULL when the amount isn't tracked
+  unit TEXT,
+  expires TEXT, -- YYYY-MM-DD format, NULL for items that keep
+  created TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now')),
+  modified TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now'))
+);
+
+CREATE TRIGGER IF NOT EXISTS update_pantry_items_modified
+BEFORE UPDATE ON pantry_items
+BEGIN
+    UPDATE pantry_items SET modified = strftime('%Y-%m-%d %H:%M:%S:%s', 'now')
+    WHERE id = old.id;
+END;
+
+-- Store categories learned from manual corrections, keyed by normalised ingredient name
+CREATE TABLE IF NOT EXISTS ingredient_categories (
+  name TEXT PRIMARY KEY,
+  category TEXT NOT NULL
+);
+
+-- Stores, each with the order its aisles are walked in
+CREATE TABLE IF NOT EXISTS stores (
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS store_aisles (
+  store_id INTEGER NOT NULL,
+  category TEXT NOT NULL,
+  position INTEGER NOT NULL,
+  PRIMARY KEY(store_id, category),
+  FOREIGN KEY(store_id) REFERENCES stores(id) ON DELETE CASCADE
+);
+
+-- A finished shopping trip, holding the tasks that were checked off on it
+CREATE TABLE IF NOT EXISTS shopping_trips (
+  id INTEGER PRIMARY KEY,
+  list_id INTEGER NOT NULL,
+  finished TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now')),
+  FOREIGN KEY(list_id) REFERENCES lists(id) ON DELETE CASCADE
+);
+
+-- Every price paid for an item, keyed by normalised ingredient name
+CREATE TABLE IF NOT EXISTS price_history (
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL,
+  price REAL NOT NULL,
+  store_id INTEGER,
+  task_id INTEGER, -- Shopping list task the price was entered on
+  recorded TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now')),
+  FOREIGN KEY(store_id) REFERENCES stores(id) ON DELETE SET NULL,
+  FOREIGN KEY(task_id) REFERENCES tasks(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_price_history_name ON price_history(name);
+
+CREATE TABLE IF NOT EXISTS users (
+  id INTEGER PRIMARY KEY,
+  username TEXT NOT NULL UNIQUE COLLATE NOCASE,
+  password_hash TEXT NOT NULL, -- Argon2 PHC string
+  is_admin INTEGER NOT NULL DEFAULT 0,
+  created TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now'))
+);
+
+-- Login sessions, keyed by the random token in the session cookie
+CREATE TABLE IF NOT EXISTS sessions (
+  token TEXT PRIMARY KEY,
+  user_id INTEGER NOT NULL,
+  expires INTEGER NOT NULL, -- Unix timestamp
+  created TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now')),
+  FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- A family sharing lists, recipes, the meal plan, the pantry and stores
+CREATE TABLE IF NOT EXISTS households (
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL,
+  shopping_list_id INTEGER, -- List whose checked off tasks move into the pantry
+  created TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now')),
+  FOREIGN KEY(shopping_list_id) REFERENCES lists(id) ON DELETE SET NULL
+);
+
+CREATE TABLE IF NOT EXISTS household_members (
+  household_id INTEGER NOT NULL,
+  user_id INTEGER NOT NULL,
+  PRIMARY KEY(household_id, user_id),
+  FOREIGN KEY(household_id) REFERENCES households(id) ON DELETE CASCADE,
+  FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- Links giving people without an account access to one list or recipe
+CREATE TABLE IF NOT EXISTS share_links (
+  token TEXT PRIMARY KEY,
+  household_id INTEGER NOT NULL,
+  list_id INTEGER,
+  recipe_id INTEGER,
+  permission TEXT NOT NULL CHECK(permission IN ('view', 'check_off', 'edit')),
+  expires INTEGER, -- Unix timestamp, NULL for links that don't expire
+  created TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now')),
+  CHECK((list_id IS NULL) <> (recipe_id IS NULL)),
+  FOREIGN KEY(household_id) REFERENCES households(id) ON DELETE CASCADE,
+  FOREIGN KEY(list_id) REFERENCES lists(id) ON DELETE CASCADE,
+  FOREIGN KEY(recipe_id) REFERENCES recipes(id) ON DELETE CASCADE
+);
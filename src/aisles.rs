@@ -0,0 +1,139 @@
+use crate::ingredients::{normalise_name, Ingredient};
+use crate::todo::Task;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Store categories as (key, label), in the default walking order through a store
+pub const CATEGORIES: [(&str, &str); 11] = [
+    ("produce", "Fruit & vegetables"),
+    ("bakery", "Bakery"),
+    ("meat", "Meat & fish"),
+    ("dairy", "Dairy & eggs"),
+    ("dry", "Dry goods"),
+    ("canned", "Canned & jars"),
+    ("spices", "Spices, oils & baking"),
+    ("frozen", "Frozen"),
+    ("drinks", "Drinks"),
+    ("household", "Household"),
+    (OTHER, "Other"),
+];
+
+/// Category for anything the dictionary doesn't know
+pub const OTHER: &str = "other";
+
+const BUNDLED_DICTIONARY: &str = include_str!("../data/aisle_dictionary.txt");
+
+/// Ingredient name to category overrides learned from manual corrections, keyed by normalised name
+pub type CategoryOverrides = HashMap<String, String>;
+
+pub fn is_category(key: &str) -> bool {
+    CATEGORIES.iter().any(|(k, _)| *k == key)
+}
+
+pub fn category_label(key: &str) -> &'static str {
+    CATEGORIES
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, label)| *label)
+        .unwrap_or("Other")
+}
+
+/// Parses the "category: name, name" dictionary format. Lines for unknown categories are skipped.
+pub fn parse_dictionary(text: &str) -> HashMap<String, String> {
+    let mut dictionary = HashMap::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((category, names)) = line.split_once(':') else {
+            continue;
+        };
+        let category = category.trim();
+        if !is_category(category) {
+            continue;
+        }
+        for name in names.split(',').map(normalise_name).filter(|name| !name.is_empty()) {
+            dictionary.insert(name, category.to_string());
+        }
+    }
+    dictionary
+}
+
+fn bundled_dictionary() -> &'static HashMap<String, String> {
+    static DICTIONARY: OnceLock<HashMap<String, String>> = OnceLock::new();
+    DICTIONARY.get_or_init(|| parse_dictionary(BUNDLED_DICTIONARY))
+}
+
+/// Normalised ingredient name of a shopping list task, e.g. "2 dl Milk" becomes "milk".
+/// Overrides are stored under this name.
+pub fn item_name(text: &str) -> String {
+    Ingredient::parse(text).normalised_name()
+}
+
+/// Category of a shopping list task. Learned overrides win over the bundled dictionary, and
+/// longer names win over shorter ones, so "bell pepper" is produce while "pepper" is a spice.
+pub fn categorise(text: &str, overrides: &CategoryOverrides) -> String {
+    let name = item_name(text);
+    if let Some(category) = overrides.get(&name) {
+        return category.clone();
+    }
+
+    let dictionary = bundled_dictionary();
+    let words: Vec<&str> = name.split(' ').collect();
+    // Trailing word sequences first, e.g. "red bell pepper", "bell pepper", "pepper"
+    let trailing = (0..words.len()).map(|start| words[start..].join(" "));
+    // Then any single word, last to first, e.g. "milk" in "milk chocolate"
+    let single = words.iter().rev().map(|word| word.to_string());
+    for candidate in trailing.chain(single) {
+        let candidate = normalise_name(&candidate);
+        if let Some(category) = overrides.get(&candidate).or_else(|| dictionary.get(&candidate)) {
+            return category.clone();
+        }
+    }
+    OTHER.to_string()
+}
+
+/// Full walking order for a store: its own aisle order first, then the remaining categories
+/// in the default order
+pub fn aisle_order(store_order: &[String]) -> Vec<String> {
+    let mut order: Vec<String> = store_order.iter().filter(|key| is_category(key)).cloned().collect();
+    for (key, _) in CATEGORIES {
+        if !order.iter().any(|k| k == key) {
+            order.push(key.to_string());
+        }
+    }
+    order
+}
+
+/// Tasks of one category in the grouped list view
+#[derive(Clone, Debug)]
+pub struct TaskGroup {
+    pub category: String,
+    pub label: String,
+    pub tasks: Vec<Task>,
+}
+
+/// Groups tasks by category in the given aisle order, keeping their list order within a group.
+/// Empty groups are left out.
+pub fn group_tasks(tasks: Vec<Task>, overrides: &CategoryOverrides, order: &[String]) -> Vec<TaskGroup> {
+    let mut groups: Vec<TaskGroup> = aisle_order(order)
+        .into_iter()
+        .map(|category| TaskGroup {
+            label: category_label(&category).to_string(),
+            category,
+            tasks: Vec::new(),
+        })
+        .collect();
+    for task in tasks {
+        let category = categorise(&task.text, overrides);
+        let index = groups
+            .iter()
+            .position(|group| group.category == category)
+            .or_else(|| groups.iter().position(|group| group.category == OTHER));
+        if let Some(index) = index {
+            groups[index].tasks.push(task);
+        }
+    }
+    groups.retain(|group| !group.tasks.is_empty());
+    groups
+}
@@ -0,0 +1,927 @@
+//! Versioned JSON API, nested under /api/v1 by `create_app`.
+//!
+//! Errors always have the body `{"error": {"code": "...", "message": "..."}}`, and
+//! collections are paginated with `?page=` (from 1) and `?per_page=`. Everything is scoped
+//! to the household the signed in user is working in.
+
+use crate::households::HouseholdState;
+use crate::todo::{List, MealPlanEntry, Recipe, RecipePhoto, Task};
+use crate::AppState;
+use axum::{
+    extract::{
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        Path, Query,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+const DEFAULT_PER_PAGE: usize = 50;
+const MAX_PER_PAGE: usize = 200;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/lists", get(list_lists).post(create_list))
+        .route("/lists/:id", get(get_list).put(update_list).delete(delete_list))
+        .route("/lists/:id/tasks", get(list_tasks).post(create_task))
+        .route("/tasks/:id", get(get_task).patch(update_task).delete(delete_task))
+        .route("/recipes", get(list_recipes).post(create_recipe))
+        .route("/recipes/:id", get(get_recipe).put(update_recipe).delete(delete_recipe))
+        .route("/recipes/:id/photos", get(list_recipe_photos))
+        .route("/photos/:id", get(get_photo).delete(delete_photo))
+        .route("/meal-plan", get(list_meal_plan).post(create_meal_plan_entry))
+        .route("/meal-plan/:id", get(get_meal_plan_entry).put(update_meal_plan_entry).delete(delete_meal_plan_entry))
+        .fallback(not_found)
+}
+
+/// OpenAPI description of the routes above, generated from the handler annotations and the
+/// request and response types. Served at /api/openapi.json.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "htmx-rs-todo API", description = "Lists, tasks, recipes and the meal plan"),
+    paths(
+        list_lists, get_list, create_list, update_list, delete_list,
+        list_tasks, get_task, create_task, update_task, delete_task,
+        list_recipes, get_recipe, create_recipe, update_recipe, delete_recipe,
+        list_recipe_photos, get_photo, delete_photo,
+        list_meal_plan, get_meal_plan_entry, create_meal_plan_entry, update_meal_plan_entry, delete_meal_plan_entry,
+    ),
+    components(schemas(ErrorBody, ErrorDetail)),
+    tags(
+        (name = "lists", description = "Todo and shopping lists"),
+        (name = "tasks", description = "Tasks on a list"),
+        (name = "recipes", description = "Recipes"),
+        (name = "photos", description = "Recipe photo metadata"),
+        (name = "meal-plan", description = "Planned meals"),
+    )
+)]
+pub struct ApiDoc;
+
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Error returned by every API handler, rendered as a JSON error body
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// Body of every error response
+#[derive(Serialize, ToSchema)]
+struct ErrorBody {
+    error: ErrorDetail,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ErrorDetail {
+    code: &'static str,
+    message: String,
+}
+
+impl ApiError {
+    pub fn bad_request(message: impl Into<String>) -> ApiError {
+        ApiError { status: StatusCode::BAD_REQUEST, code: "bad_request", message: message.into() }
+    }
+
+    pub fn not_found(what: &str) -> ApiError {
+        ApiError { status: StatusCode::NOT_FOUND, code: "not_found", message: format!("{} not found", what) }
+    }
+
+    pub fn unauthorized() -> ApiError {
+        ApiError { status: StatusCode::UNAUTHORIZED, code: "unauthorized", message: "Login required".to_string() }
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> ApiError {
+        ApiError { status: StatusCode::FORBIDDEN, code: "forbidden", message: message.into() }
+    }
+
+    /// The If-Match version is out of date
+    pub fn conflict(what: &str) -> ApiError {
+        ApiError {
+            status: StatusCode::CONFLICT,
+            code: "conflict",
+            message: format!("{} was changed since the version in If-Match", what),
+        }
+    }
+
+    pub fn validation(message: impl Into<String>) -> ApiError {
+        ApiError { status: StatusCode::UNPROCESSABLE_ENTITY, code: "validation_failed", message: message.into() }
+    }
+
+    pub fn internal(err: anyhow::Error) -> ApiError {
+        warn!("API request failed: {:#}", err);
+        ApiError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            code: "internal_error",
+            message: "Internal server error".to_string(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody { error: ErrorDetail { code: self.code, message: self.message } };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> ApiError {
+        match rejection {
+            JsonRejection::JsonDataError(err) => ApiError::validation(err.body_text()),
+            other => ApiError::bad_request(other.body_text()),
+        }
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> ApiError {
+        ApiError::bad_request(rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> ApiError {
+        ApiError::bad_request(rejection.body_text())
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+async fn not_found() -> ApiError {
+    ApiError::not_found("Route")
+}
+
+fn created<T: Serialize>(location: String, body: T) -> Response {
+    (StatusCode::CREATED, [(header::LOCATION, location)], Json(body)).into_response()
+}
+
+// Pagination
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageQuery {
+    /// Page number, starting at 1
+    pub page: Option<usize>,
+    /// Items per page, 50 by default and at most 200
+    pub per_page: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub page: usize,
+    pub per_page: usize,
+    pub total: usize,
+}
+
+pub fn paginate<T>(items: Vec<T>, query: &PageQuery) -> ApiResult<Page<T>> {
+    let page = query.page.unwrap_or(1);
+    let per_page = query.per_page.unwrap_or(DEFAULT_PER_PAGE);
+    if page == 0 {
+        return Err(ApiError::bad_request("page starts at 1"));
+    }
+    if per_page == 0 || per_page > MAX_PER_PAGE {
+        return Err(ApiError::bad_request(format!("per_page must be between 1 and {}", MAX_PER_PAGE)));
+    }
+
+    let total = items.len();
+    let items = items.into_iter().skip((page - 1) * per_page).take(per_page).collect();
+    Ok(Page { items, page, per_page, total })
+}
+
+// Lists
+
+#[derive(Deserialize, ToSchema)]
+pub struct ListInput {
+    pub name: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/lists",
+    tag = "lists",
+    params(
+        PageQuery,
+    ),
+    responses(
+        (status = 200, description = "Lists", body = Page<List>),
+        (status = 400, description = "Malformed request", body = ErrorBody),
+    )
+)]
+async fn list_lists(
+    HouseholdState(state): HouseholdState,
+    query: Result<Query<PageQuery>, QueryRejection>,
+) -> ApiResult<Json<Page<List>>> {
+    let Query(query) = query?;
+    let lists = state.db.get_lists().await.map_err(ApiError::internal)?;
+    Ok(Json(paginate(lists, &query)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/lists/{id}",
+    tag = "lists",
+    params(
+        ("id" = usize, Path, description = "List id"),
+    ),
+    responses(
+        (status = 200, description = "The list", body = List),
+        (status = 404, description = "List not found", body = ErrorBody),
+    )
+)]
+async fn get_list(
+    HouseholdState(state): HouseholdState,
+    id: Result<Path<usize>, PathRejection>,
+) -> ApiResult<Json<List>> {
+    let Path(id) = id?;
+    let list = state.db.get_list(id).await.map_err(|_| ApiError::not_found("List"))?;
+    Ok(Json(list))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/lists",
+    tag = "lists",
+    request_body = ListInput,
+    responses(
+        (status = 201, description = "List created", body = List),
+        (status = 400, description = "Malformed request", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    )
+)]
+async fn create_list(
+    HouseholdState(state): HouseholdState,
+    input: Result<Json<ListInput>, JsonRejection>,
+) -> ApiResult<Response> {
+    let Json(input) = input?;
+    let name = input.name.trim().to_string();
+    if name.is_empty() {
+        return Err(ApiError::validation("name must not be empty"));
+    }
+
+    let id = state.db.create_list(name).await.map_err(ApiError::internal)?;
+    let list = state.db.get_list(id).await.map_err(ApiError::internal)?;
+    Ok(created(format!("/api/v1/lists/{}", id), list))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/lists/{id}",
+    tag = "lists",
+    request_body = ListInput,
+    params(
+        ("id" = usize, Path, description = "List id"),
+    ),
+    responses(
+        (status = 200, description = "List renamed", body = List),
+        (status = 404, description = "List not found", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    )
+)]
+async fn update_list(
+    HouseholdState(state): HouseholdState,
+    id: Result<Path<usize>, PathRejection>,
+    input: Result<Json<ListInput>, JsonRejection>,
+) -> ApiResult<Json<List>> {
+    let Path(id) = id?;
+    let Json(input) = input?;
+    state.db.get_list(id).await.map_err(|_| ApiError::not_found("List"))?;
+    let name = input.name.trim().to_string();
+    if name.is_empty() {
+        return Err(ApiError::validation("name must not be empty"));
+    }
+
+    state.db.rename_list(id, name).await.map_err(ApiError::internal)?;
+    let list = state.db.get_list(id).await.map_err(ApiError::internal)?;
+    Ok(Json(list))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/lists/{id}",
+    tag = "lists",
+    params(
+        ("id" = usize, Path, description = "List id"),
+    ),
+    responses(
+        (status = 204, description = "List deleted"),
+        (status = 404, description = "List not found", body = ErrorBody),
+    )
+)]
+async fn delete_list(
+    HouseholdState(state): HouseholdState,
+    id: Result<Path<usize>, PathRejection>,
+) -> ApiResult<StatusCode> {
+    let Path(id) = id?;
+    state.db.get_list(id).await.map_err(|_| ApiError::not_found("List"))?;
+    state.db.delete_list(id).await.map_err(ApiError::internal)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Tasks
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewTask {
+    pub text: String,
+}
+
+/// Partial update, fields that are left out keep their value
+#[derive(Deserialize, ToSchema)]
+pub struct TaskUpdate {
+    pub text: Option<String>,
+    pub completed: Option<bool>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/lists/{id}/tasks",
+    tag = "tasks",
+    params(
+        ("id" = usize, Path, description = "List id"),
+        PageQuery,
+    ),
+    responses(
+        (status = 200, description = "Open tasks of the list", body = Page<Task>),
+        (status = 400, description = "Malformed request", body = ErrorBody),
+        (status = 404, description = "List not found", body = ErrorBody),
+    )
+)]
+async fn list_tasks(
+    HouseholdState(state): HouseholdState,
+    id: Result<Path<usize>, PathRejection>,
+    query: Result<Query<PageQuery>, QueryRejection>,
+) -> ApiResult<Json<Page<Task>>> {
+    let Path(id) = id?;
+    let Query(query) = query?;
+    state.db.get_list(id).await.map_err(|_| ApiError::not_found("List"))?;
+    let tasks = state.db.get_tasks(id).await.map_err(ApiError::internal)?;
+    Ok(Json(paginate(tasks, &query)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tasks/{id}",
+    tag = "tasks",
+    params(
+        ("id" = usize, Path, description = "Task id"),
+    ),
+    responses(
+        (status = 200, description = "The task", body = Task),
+        (status = 404, description = "Task not found", body = ErrorBody),
+    )
+)]
+async fn get_task(
+    HouseholdState(state): HouseholdState,
+    id: Result<Path<usize>, PathRejection>,
+) -> ApiResult<Json<Task>> {
+    let Path(id) = id?;
+    let task = state.db.get_task(id).await.map_err(|_| ApiError::not_found("Task"))?;
+    Ok(Json(task))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/lists/{id}/tasks",
+    tag = "tasks",
+    request_body = NewTask,
+    params(
+        ("id" = usize, Path, description = "List id"),
+    ),
+    responses(
+        (status = 201, description = "Task created", body = Task),
+        (status = 404, description = "List not found", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    )
+)]
+async fn create_task(
+    HouseholdState(state): HouseholdState,
+    id: Result<Path<usize>, PathRejection>,
+    input: Result<Json<NewTask>, JsonRejection>,
+) -> ApiResult<Response> {
+    let Path(list_id) = id?;
+    let Json(input) = input?;
+    state.db.get_list(list_id).await.map_err(|_| ApiError::not_found("List"))?;
+    let text = input.text.trim().to_string();
+    if text.is_empty() {
+        return Err(ApiError::validation("text must not be empty"));
+    }
+
+    let id = state.db.create_task(text, list_id).await.map_err(ApiError::internal)?;
+    let task = state.db.get_task(id).await.map_err(ApiError::internal)?;
+    Ok(created(format!("/api/v1/tasks/{}", id), task))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/tasks/{id}",
+    tag = "tasks",
+    request_body = TaskUpdate,
+    params(
+        ("id" = usize, Path, description = "Task id"),
+        ("If-Match" = Option<String>, Header, description = "Only update if the task is still at this version"),
+    ),
+    responses(
+        (status = 200, description = "Task updated", body = Task),
+        (status = 404, description = "Task not found", body = ErrorBody),
+        (status = 409, description = "Task changed since the If-Match version", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    )
+)]
+async fn update_task(
+    HouseholdState(state): HouseholdState,
+    id: Result<Path<usize>, PathRejection>,
+    headers: HeaderMap,
+    input: Result<Json<TaskUpdate>, JsonRejection>,
+) -> ApiResult<Json<Task>> {
+    let Path(id) = id?;
+    let Json(input) = input?;
+    let task = state.db.get_task(id).await.map_err(|_| ApiError::not_found("Task"))?;
+
+    let text = input.text.map(|text| text.trim().to_string());
+    if text.as_deref() == Some("") {
+        return Err(ApiError::validation("text must not be empty"));
+    }
+    let updated = state.db
+        .update_task(id, text, input.completed, crate::conflicts::if_match(&headers))
+        .await
+        .map_err(ApiError::internal)?;
+    if !updated {
+        return Err(ApiError::conflict("Task"));
+    }
+
+    let updated = crate::stock_pantry_if_bought(&state, &task).await.map_err(ApiError::internal)?;
+    Ok(Json(updated))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/tasks/{id}",
+    tag = "tasks",
+    params(
+        ("id" = usize, Path, description = "Task id"),
+    ),
+    responses(
+        (status = 204, description = "Task deleted"),
+        (status = 404, description = "Task not found", body = ErrorBody),
+    )
+)]
+async fn delete_task(
+    HouseholdState(state): HouseholdState,
+    id: Result<Path<usize>, PathRejection>,
+) -> ApiResult<StatusCode> {
+    let Path(id) = id?;
+    state.db.get_task(id).await.map_err(|_| ApiError::not_found("Task"))?;
+    state.db.delete_task(id).await.map_err(ApiError::internal)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Recipes
+
+#[derive(Deserialize, ToSchema)]
+pub struct RecipeInput {
+    pub title: String,
+    #[serde(default)]
+    pub instructions: String,
+    #[serde(default)]
+    pub ingredients: String,
+    #[serde(default)]
+    pub favourite: bool,
+    #[serde(default)]
+    pub rating: u8,
+    #[serde(default)]
+    pub tags: String,
+    pub servings: Option<u32>,
+}
+
+impl RecipeInput {
+    fn validate(&self) -> ApiResult<()> {
+        if self.title.trim().is_empty() {
+            return Err(ApiError::validation("title must not be empty"));
+        }
+        if self.rating > 5 {
+            return Err(ApiError::validation("rating must be between 0 and 5"));
+        }
+        if self.servings == Some(0) {
+            return Err(ApiError::validation("servings must be at least 1"));
+        }
+        Ok(())
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/recipes",
+    tag = "recipes",
+    params(
+        PageQuery,
+    ),
+    responses(
+        (status = 200, description = "Recipes", body = Page<Recipe>),
+        (status = 400, description = "Malformed request", body = ErrorBody),
+    )
+)]
+async fn list_recipes(
+    HouseholdState(state): HouseholdState,
+    query: Result<Query<PageQuery>, QueryRejection>,
+) -> ApiResult<Json<Page<Recipe>>> {
+    let Query(query) = query?;
+    let recipes = state.db.get_recipes().await.map_err(ApiError::internal)?;
+    Ok(Json(paginate(recipes, &query)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/recipes/{id}",
+    tag = "recipes",
+    params(
+        ("id" = usize, Path, description = "Recipe id"),
+    ),
+    responses(
+        (status = 200, description = "The recipe", body = Recipe),
+        (status = 404, description = "Recipe not found", body = ErrorBody),
+    )
+)]
+async fn get_recipe(
+    HouseholdState(state): HouseholdState,
+    id: Result<Path<usize>, PathRejection>,
+) -> ApiResult<Json<Recipe>> {
+    let Path(id) = id?;
+    let recipe = state.db.get_recipe(id).await.map_err(|_| ApiError::not_found("Recipe"))?;
+    Ok(Json(recipe))
+}
+
+/// Creates a recipe, or updates the one given with the version it is expected to be at
+async fn save_recipe(state: &AppState, existing: Option<(usize, Option<usize>)>, input: RecipeInput) -> ApiResult<Recipe> {
+    input.validate()?;
+    let title = input.title.trim().to_string();
+    let id = match existing {
+        Some((id, expected_version)) => {
+            let updated = state.db
+                .update_recipe(id, title, input.instructions, input.ingredients, input.servings, expected_version)
+                .await
+                .map_err(ApiError::internal)?;
+            if !updated {
+                return Err(ApiError::conflict("Recipe"));
+            }
+            id
+        }
+        None => state.db
+            .create_recipe(title, input.instructions, input.ingredients, input.servings)
+            .await
+            .map_err(ApiError::internal)?,
+    };
+    state.db
+        .update_recipe_preferences(id, input.favourite, input.rating, input.tags.trim().to_string())
+        .await
+        .map_err(ApiError::internal)?;
+    state.db.get_recipe(id).await.map_err(ApiError::internal)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/recipes",
+    tag = "recipes",
+    request_body = RecipeInput,
+    responses(
+        (status = 201, description = "Recipe created", body = Recipe),
+        (status = 400, description = "Malformed request", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    )
+)]
+async fn create_recipe(
+    HouseholdState(state): HouseholdState,
+    input: Result<Json<RecipeInput>, JsonRejection>,
+) -> ApiResult<Response> {
+    let Json(input) = input?;
+    let recipe = save_recipe(&state, None, input).await?;
+    Ok(created(format!("/api/v1/recipes/{}", recipe.id), recipe))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/recipes/{id}",
+    tag = "recipes",
+    request_body = RecipeInput,
+    params(
+        ("id" = usize, Path, description = "Recipe id"),
+        ("If-Match" = Option<String>, Header, description = "Only update if the recipe is still at this version"),
+    ),
+    responses(
+        (status = 200, description = "Recipe updated", body = Recipe),
+        (status = 404, description = "Recipe not found", body = ErrorBody),
+        (status = 409, description = "Recipe changed since the If-Match version", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    )
+)]
+async fn update_recipe(
+    HouseholdState(state): HouseholdState,
+    id: Result<Path<usize>, PathRejection>,
+    headers: HeaderMap,
+    input: Result<Json<RecipeInput>, JsonRejection>,
+) -> ApiResult<Json<Recipe>> {
+    let Path(id) = id?;
+    let Json(input) = input?;
+    state.db.get_recipe(id).await.map_err(|_| ApiError::not_found("Recipe"))?;
+    let expected = crate::conflicts::if_match(&headers);
+    Ok(Json(save_recipe(&state, Some((id, expected)), input).await?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/recipes/{id}",
+    tag = "recipes",
+    params(
+        ("id" = usize, Path, description = "Recipe id"),
+    ),
+    responses(
+        (status = 204, description = "Recipe deleted"),
+        (status = 404, description = "Recipe not found", body = ErrorBody),
+    )
+)]
+async fn delete_recipe(
+    HouseholdState(state): HouseholdState,
+    id: Result<Path<usize>, PathRejection>,
+) -> ApiResult<StatusCode> {
+    let Path(id) = id?;
+    state.db.get_recipe(id).await.map_err(|_| ApiError::not_found("Recipe"))?;
+    state.db.delete_recipe(id).await.map_err(ApiError::internal)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Recipe photos. Uploads go through the multipart HTML routes, the API exposes metadata only.
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/recipes/{id}/photos",
+    tag = "photos",
+    params(
+        ("id" = usize, Path, description = "Recipe id"),
+        PageQuery,
+    ),
+    responses(
+        (status = 200, description = "Photo metadata of the recipe", body = Page<RecipePhoto>),
+        (status = 400, description = "Malformed request", body = ErrorBody),
+        (status = 404, description = "Recipe not found", body = ErrorBody),
+    )
+)]
+async fn list_recipe_photos(
+    HouseholdState(state): HouseholdState,
+    id: Result<Path<usize>, PathRejection>,
+    query: Result<Query<PageQuery>, QueryRejection>,
+) -> ApiResult<Json<Page<RecipePhoto>>> {
+    let Path(id) = id?;
+    let Query(query) = query?;
+    state.db.get_recipe(id).await.map_err(|_| ApiError::not_found("Recipe"))?;
+    let photos = state.db.get_recipe_photos(id).await.map_err(ApiError::internal)?;
+    Ok(Json(paginate(photos, &query)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/photos/{id}",
+    tag = "photos",
+    params(
+        ("id" = usize, Path, description = "Photo id"),
+    ),
+    responses(
+        (status = 200, description = "Photo metadata", body = RecipePhoto),
+        (status = 404, description = "Photo not found", body = ErrorBody),
+    )
+)]
+async fn get_photo(
+    HouseholdState(state): HouseholdState,
+    id: Result<Path<usize>, PathRejection>,
+) -> ApiResult<Json<RecipePhoto>> {
+    let Path(id) = id?;
+    match state.db.get_recipe_photo_by_id(id).await.map_err(ApiError::internal)? {
+        Some(photo) => Ok(Json(photo)),
+        None => Err(ApiError::not_found("Photo")),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/photos/{id}",
+    tag = "photos",
+    params(
+        ("id" = usize, Path, description = "Photo id"),
+    ),
+    responses(
+        (status = 204, description = "Photo deleted"),
+        (status = 404, description = "Photo not found", body = ErrorBody),
+    )
+)]
+async fn delete_photo(
+    HouseholdState(state): HouseholdState,
+    id: Result<Path<usize>, PathRejection>,
+) -> ApiResult<StatusCode> {
+    let Path(id) = id?;
+    let photo = state.db
+        .get_recipe_photo_by_id(id)
+        .await
+        .map_err(ApiError::internal)?
+        .ok_or_else(|| ApiError::not_found("Photo"))?;
+
+    let _ = tokio::fs::remove_file(state.photos_dir.join(&photo.filename)).await;
+    state.db.delete_recipe_photo(id).await.map_err(ApiError::internal)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Meal plan
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MealPlanQuery {
+    /// First day, YYYY-MM-DD. Defaults to the start of the current week.
+    pub from: Option<String>,
+    /// Last day, inclusive. Defaults to the end of the `from` week.
+    pub to: Option<String>,
+    /// Page number, starting at 1
+    pub page: Option<usize>,
+    /// Items per page, 50 by default and at most 200
+    pub per_page: Option<usize>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewMealPlanEntry {
+    /// YYYY-MM-DD
+    pub date: String,
+    /// Defaults to the recipe title
+    pub meal_text: Option<String>,
+    pub recipe_id: Option<usize>,
+    pub servings: Option<u32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MealPlanEntryUpdate {
+    pub meal_text: String,
+    pub recipe_id: Option<usize>,
+}
+
+fn parse_date(value: &str, field: &str) -> ApiResult<chrono::NaiveDate> {
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| ApiError::validation(format!("{} must be a date in YYYY-MM-DD format", field)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/meal-plan",
+    tag = "meal-plan",
+    params(
+        MealPlanQuery,
+    ),
+    responses(
+        (status = 200, description = "Meal plan entries in the date range", body = Page<MealPlanEntry>),
+        (status = 400, description = "Malformed request", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    )
+)]
+async fn list_meal_plan(
+    HouseholdState(state): HouseholdState,
+    query: Result<Query<MealPlanQuery>, QueryRejection>,
+) -> ApiResult<Json<Page<MealPlanEntry>>> {
+    let Query(query) = query?;
+    let from = match &query.from {
+        Some(from) => parse_date(from, "from")?,
+        None => crate::get_current_week_start(&crate::current_locale(&state).await),
+    };
+    let to = match &query.to {
+        Some(to) => parse_date(to, "to")?,
+        None => from + chrono::Duration::days(6),
+    };
+    if to < from {
+        return Err(ApiError::validation("to must not be before from"));
+    }
+
+    let entries = state.db
+        .get_meal_plan_between(from.format("%Y-%m-%d").to_string(), to.format("%Y-%m-%d").to_string())
+        .await
+        .map_err(ApiError::internal)?;
+    let page = PageQuery { page: query.page, per_page: query.per_page };
+    Ok(Json(paginate(entries, &page)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/meal-plan/{id}",
+    tag = "meal-plan",
+    params(
+        ("id" = usize, Path, description = "Meal plan entry id"),
+    ),
+    responses(
+        (status = 200, description = "The meal plan entry", body = MealPlanEntry),
+        (status = 404, description = "Meal plan entry not found", body = ErrorBody),
+    )
+)]
+async fn get_meal_plan_entry(
+    HouseholdState(state): HouseholdState,
+    id: Result<Path<usize>, PathRejection>,
+) -> ApiResult<Json<MealPlanEntry>> {
+    let Path(id) = id?;
+    let entry = state.db.get_meal_plan_entry(id).await.map_err(|_| ApiError::not_found("Meal plan entry"))?;
+    Ok(Json(entry))
+}
+
+/// Meal text for an entry, falling back to the recipe title
+async fn meal_text_for(state: &AppState, meal_text: Option<String>, recipe_id: Option<usize>) -> ApiResult<String> {
+    let recipe = match recipe_id {
+        Some(recipe_id) => Some(state.db.get_recipe(recipe_id).await.map_err(|_| ApiError::validation("recipe_id does not exist"))?),
+        None => None,
+    };
+    let meal_text = meal_text.map(|text| text.trim().to_string()).filter(|text| !text.is_empty());
+    match (meal_text, recipe) {
+        (Some(text), _) => Ok(text),
+        (None, Some(recipe)) => Ok(recipe.title),
+        (None, None) => Err(ApiError::validation("meal_text or recipe_id is required")),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/meal-plan",
+    tag = "meal-plan",
+    request_body = NewMealPlanEntry,
+    responses(
+        (status = 201, description = "Meal planned", body = MealPlanEntry),
+        (status = 400, description = "Malformed request", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    )
+)]
+async fn create_meal_plan_entry(
+    HouseholdState(state): HouseholdState,
+    input: Result<Json<NewMealPlanEntry>, JsonRejection>,
+) -> ApiResult<Response> {
+    let Json(input) = input?;
+    let date = parse_date(&input.date, "date")?;
+    if input.servings == Some(0) {
+        return Err(ApiError::validation("servings must be at least 1"));
+    }
+    let meal_text = meal_text_for(&state, input.meal_text, input.recipe_id).await?;
+
+    let id = state.db
+        .create_meal_plan_entry(date.format("%Y-%m-%d").to_string(), meal_text, input.recipe_id, None, input.servings)
+        .await
+        .map_err(ApiError::internal)?;
+    let entry = state.db.get_meal_plan_entry(id).await.map_err(ApiError::internal)?;
+    Ok(created(format!("/api/v1/meal-plan/{}", id), entry))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/meal-plan/{id}",
+    tag = "meal-plan",
+    request_body = MealPlanEntryUpdate,
+    params(
+        ("id" = usize, Path, description = "Meal plan entry id"),
+    ),
+    responses(
+        (status = 200, description = "Meal plan entry updated", body = MealPlanEntry),
+        (status = 404, description = "Meal plan entry not found", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    )
+)]
+async fn update_meal_plan_entry(
+    HouseholdState(state): HouseholdState,
+    id: Result<Path<usize>, PathRejection>,
+    input: Result<Json<MealPlanEntryUpdate>, JsonRejection>,
+) -> ApiResult<Json<MealPlanEntry>> {
+    let Path(id) = id?;
+    let Json(input) = input?;
+    state.db.get_meal_plan_entry(id).await.map_err(|_| ApiError::not_found("Meal plan entry"))?;
+    let meal_text = meal_text_for(&state, Some(input.meal_text), input.recipe_id).await?;
+
+    state.db.update_meal_plan_entry(id, meal_text, input.recipe_id).await.map_err(ApiError::internal)?;
+    let entry = state.db.get_meal_plan_entry(id).await.map_err(ApiError::internal)?;
+    Ok(Json(entry))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/meal-plan/{id}",
+    tag = "meal-plan",
+    params(
+        ("id" = usize, Path, description = "Meal plan entry id"),
+    ),
+    responses(
+        (status = 204, description = "Meal plan entry deleted"),
+        (status = 404, description = "Meal plan entry not found", body = ErrorBody),
+    )
+)]
+async fn delete_meal_plan_entry(
+    HouseholdState(state): HouseholdState,
+    id: Result<Path<usize>, PathRejection>,
+) -> ApiResult<StatusCode> {
+    let Path(id) = id?;
+    state.db.get_meal_plan_entry(id).await.map_err(|_| ApiError::not_found("Meal plan entry"))?;
+    state.db.delete_meal_plan_entry(id).await.map_err(ApiError::internal)?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,200 @@
+use crate::api::ApiError;
+use crate::database::Database;
+use crate::todo::User;
+use crate::AppState;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Redirect, Response};
+use tracing::warn;
+use uuid::Uuid;
+
+pub const SESSION_COOKIE: &str = "session";
+
+/// How long a login lasts
+pub const SESSION_DAYS: i64 = 30;
+
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Paths reachable without logging in. Share links under /s/ check their own token.
+const PUBLIC_PATHS: [&str; 4] = ["/login", "/vendor/", "/s/", "/.well-known/caldav"];
+
+/// Realm CalDAV clients are asked to sign in to
+const DAV_REALM: &str = "Basic realm=\"htmx-rs-todo\", charset=\"UTF-8\"";
+
+pub fn hash_password(password: &str) -> anyhow::Result<String> {
+    let salt = SaltString::encode_b64(Uuid::new_v4().as_bytes())
+        .map_err(|err| anyhow::anyhow!("Encode salt: {}", err))?;
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|err| anyhow::anyhow!("Hash password: {}", err))?;
+    Ok(hash.to_string())
+}
+
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    match PasswordHash::new(hash) {
+        Ok(hash) => Argon2::default().verify_password(password.as_bytes(), &hash).is_ok(),
+        Err(err) => {
+            warn!("Stored password hash is invalid: {}", err);
+            false
+        }
+    }
+}
+
+/// Checks the username and password and stores a user with a hashed password, as a
+/// member of the database handle's household
+pub async fn create_user(db: &Database, username: &str, password: &str, is_admin: bool) -> anyhow::Result<usize> {
+    let username = username.trim();
+    if username.is_empty() {
+        anyhow::bail!("Username must not be empty");
+    }
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        anyhow::bail!("Password must be at least {} characters", MIN_PASSWORD_LENGTH);
+    }
+    let id = db.create_user(username.to_string(), hash_password(password)?, is_admin).await?;
+    db.add_household_member(db.household_id(), id).await?;
+    Ok(id)
+}
+
+/// User for a username and password, if they match
+pub async fn authenticate(db: &Database, username: &str, password: &str) -> anyhow::Result<Option<User>> {
+    Ok(db
+        .get_user_credentials(username.trim().to_string())
+        .await?
+        .filter(|(_, hash)| verify_password(password, hash))
+        .map(|(user, _)| user))
+}
+
+/// Unguessable token for session cookies and share links
+pub fn random_token() -> String {
+    // Two v4 UUIDs give 244 random bits
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Starts a session for the user and returns its token
+pub async fn start_session(db: &Database, user: &User) -> anyhow::Result<String> {
+    let token = random_token();
+    let now = chrono::Utc::now().timestamp();
+    let expires = now + SESSION_DAYS * 24 * 60 * 60;
+    db.create_session(token.clone(), user.id, expires, now).await?;
+    Ok(token)
+}
+
+pub fn session_cookie(token: &str) -> String {
+    format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}",
+        SESSION_COOKIE,
+        token,
+        SESSION_DAYS * 24 * 60 * 60
+    )
+}
+
+pub fn clear_session_cookie() -> String {
+    format!("{}=; Path=/; HttpOnly; SameSite=Lax; Max-Age=0", SESSION_COOKIE)
+}
+
+/// Session token from the request's cookies
+pub fn session_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, token)| token.to_string())
+        .filter(|token| !token.is_empty())
+}
+
+/// The user whose username and password came in an `Authorization: Basic` header
+pub async fn basic_auth_user(db: &Database, headers: &HeaderMap) -> Option<User> {
+    let encoded = headers.get(header::AUTHORIZATION)?.to_str().ok()?.strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(BASE64.decode(encoded.trim()).ok()?).ok()?;
+    let (username, password) = decoded.split_once(':')?;
+    match authenticate(db, username, password).await {
+        Ok(user) => user,
+        Err(err) => {
+            warn!("Failed to check the password of {}: {}", username, err);
+            None
+        }
+    }
+}
+
+/// Whether a path is served by the CalDAV server
+pub fn is_dav(path: &str) -> bool {
+    path == "/.well-known/caldav" || path == "/dav" || path.starts_with("/dav/")
+}
+
+fn is_public(path: &str) -> bool {
+    PUBLIC_PATHS
+        .iter()
+        .any(|public| path == *public || (public.ends_with('/') && path.starts_with(public)))
+}
+
+/// Local path to return to after logging in. Anything else, like "//evil.example", goes home.
+pub fn safe_next(next: Option<&str>) -> String {
+    match next {
+        Some(next) if next.starts_with('/') && !next.starts_with("//") && !next.contains('\\') => next.to_string(),
+        _ => "/".to_string(),
+    }
+}
+
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Middleware on `create_app` that lets only signed in users through. The user, and the
+/// household they are working in if any, are put in the request extensions.
+pub async fn require_login(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    if is_public(request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    // CalDAV clients can't fill in the login form, so they send the password every time
+    if is_dav(request.uri().path()) {
+        return match basic_auth_user(&state.db, request.headers()).await {
+            Some(user) => {
+                request.extensions_mut().insert(user);
+                next.run(request).await
+            }
+            None => (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, DAV_REALM)]).into_response(),
+        };
+    }
+
+    if let Some(token) = session_token(request.headers()) {
+        match state.db.get_session_user(token, chrono::Utc::now().timestamp()).await {
+            Ok(Some((user, household))) => {
+                request.extensions_mut().insert(user);
+                if let Some(household) = household {
+                    request.extensions_mut().insert(household);
+                }
+                return next.run(request).await;
+            }
+            Ok(None) => {}
+            Err(err) => warn!("Failed to look up session: {}", err),
+        }
+    }
+
+    if request.uri().path().starts_with("/api/") {
+        return ApiError::unauthorized().into_response();
+    }
+    if request.headers().contains_key("HX-Request") {
+        // Send HTMX to the login page instead of swapping it into the current one
+        return (StatusCode::UNAUTHORIZED, [("HX-Redirect", "/login")]).into_response();
+    }
+    if request.method() == Method::GET {
+        let path = request.uri().path_and_query().map(|p| p.as_str()).unwrap_or("/");
+        return Redirect::to(&format!("/login?next={}", encode_query_value(path))).into_response();
+    }
+    (StatusCode::UNAUTHORIZED, "Login required").into_response()
+}
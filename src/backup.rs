@@ -0,0 +1,222 @@
+use anyhow::{bail, Context};
+use rusqlite::{backup::{Backup, StepResult}, Connection, OpenFlags};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Name of the database in the data directory and in backups
+pub const DATABASE_FILE: &str = "todos.db";
+
+/// Name of the photos directory in the data directory and in backups
+pub const PHOTOS_DIR: &str = "photos";
+
+/// Name scheduled backups are given, around a UTC timestamp that sorts oldest first
+const SCHEDULED_PREFIX: &str = "todos-";
+const SCHEDULED_SUFFIX: &str = ".tar.zst";
+
+/// How often, and how far apart, copying the database is tried while a write holds it locked
+const COPY_ATTEMPTS: u32 = 240;
+const COPY_RETRY_PAUSE: Duration = Duration::from_millis(250);
+
+/// Writes a zstd-compressed tarball of the database and the photos in `data_dir` to `out`.
+/// The database is copied with SQLite's online backup API, so the server can keep running.
+pub fn create(data_dir: &Path, out: &Path) -> anyhow::Result<()> {
+    let database = data_dir.join(DATABASE_FILE);
+    if !database.is_file() {
+        bail!("No database at {:?}", database);
+    }
+
+    let snapshot = std::env::temp_dir().join(format!("todos-backup-{}.db", Uuid::new_v4()));
+    let result = snapshot_database(&database, &snapshot).and_then(|()| write_archive(&snapshot, data_dir, out));
+    let _ = std::fs::remove_file(&snapshot);
+    result
+}
+
+fn snapshot_database(database: &Path, snapshot: &Path) -> anyhow::Result<()> {
+    let source = Connection::open_with_flags(database, OpenFlags::SQLITE_OPEN_READ_ONLY).context("Open database")?;
+    let mut copy = Connection::open(snapshot).context("Create database snapshot")?;
+    let backup = Backup::new(&source, &mut copy).context("Start database copy")?;
+    // All pages in one step, so the copy is of a single moment. Busy while a write is going on.
+    for attempt in 1..=COPY_ATTEMPTS {
+        match backup.step(-1).context("Copy database")? {
+            StepResult::Done => return Ok(()),
+            result => {
+                if attempt == 1 {
+                    warn!("Database is busy ({:?}), retrying the copy for up to {:?}", result, COPY_RETRY_PAUSE * COPY_ATTEMPTS);
+                }
+                std::thread::sleep(COPY_RETRY_PAUSE);
+            }
+        }
+    }
+    bail!("Database stayed locked for {:?}, gave up copying it", COPY_RETRY_PAUSE * COPY_ATTEMPTS)
+}
+
+// Written next to `out` and renamed into place, so a failed backup doesn't leave half an archive
+fn write_archive(snapshot: &Path, data_dir: &Path, out: &Path) -> anyhow::Result<()> {
+    let partial = out.with_extension("partial");
+    let file = std::fs::File::create(&partial).with_context(|| format!("Create {:?}", partial))?;
+    let mut archive = tar::Builder::new(zstd::Encoder::new(file, 0)?);
+    archive.append_path_with_name(snapshot, DATABASE_FILE).context("Add database to backup")?;
+    let photos = data_dir.join(PHOTOS_DIR);
+    if photos.is_dir() {
+        archive.append_dir_all(PHOTOS_DIR, &photos).context("Add photos to backup")?;
+    }
+    archive.into_inner().and_then(|encoder| encoder.finish()).context("Finish backup")?;
+    std::fs::rename(&partial, out).with_context(|| format!("Move backup to {:?}", out))
+}
+
+/// Unpacks a backup made by [`create`] into `data_dir`. The archive is checked first: it may
+/// only hold the database and photos, and the database has to pass SQLite's integrity check.
+/// An existing database is only replaced with `force`, and the server should be stopped.
+pub fn restore(archive: &Path, data_dir: &Path, force: bool) -> anyhow::Result<()> {
+    let database = data_dir.join(DATABASE_FILE);
+    if database.exists() && !force {
+        bail!("{:?} already exists, pass --force to replace it", database);
+    }
+
+    std::fs::create_dir_all(data_dir).with_context(|| format!("Create {:?}", data_dir))?;
+    let staging = data_dir.join(format!(".restore-{}", Uuid::new_v4()));
+    let result = unpack(archive, &staging).and_then(|()| replace(&staging, data_dir));
+    let _ = std::fs::remove_dir_all(&staging);
+    result
+}
+
+fn unpack(archive: &Path, staging: &Path) -> anyhow::Result<()> {
+    let file = std::fs::File::open(archive).with_context(|| format!("Open {:?}", archive))?;
+    let mut entries = tar::Archive::new(zstd::Decoder::new(file)?);
+    std::fs::create_dir(staging).with_context(|| format!("Create {:?}", staging))?;
+    for entry in entries.entries().context("Read backup")? {
+        let mut entry = entry.context("Read backup")?;
+        let path = entry.path().context("Read backup")?.into_owned();
+        let kind = entry.header().entry_type();
+        if !(kind.is_file() || kind.is_dir()) || !allowed_path(&path) {
+            bail!("Unexpected {:?} in backup", path);
+        }
+        entry.unpack_in(staging).with_context(|| format!("Unpack {:?}", path))?;
+    }
+
+    let database = staging.join(DATABASE_FILE);
+    if !database.is_file() {
+        bail!("Backup has no {}", DATABASE_FILE);
+    }
+    let connection = Connection::open_with_flags(&database, OpenFlags::SQLITE_OPEN_READ_ONLY).context("Open restored database")?;
+    let check: String = connection
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .context("Check restored database")?;
+    if check != "ok" {
+        bail!("Restored database is damaged: {}", check);
+    }
+    Ok(())
+}
+
+// The database, or anything under the photos directory
+fn allowed_path(path: &Path) -> bool {
+    let mut components = path.components();
+    let first = match components.next() {
+        Some(Component::Normal(first)) => first,
+        _ => return false,
+    };
+    if first == DATABASE_FILE {
+        return components.next().is_none();
+    }
+    first == PHOTOS_DIR && components.all(|component| matches!(component, Component::Normal(_)))
+}
+
+// What is there is moved aside into the staging directory first and put back if anything
+// fails, so the data directory ends up either fully restored or as it was
+fn replace(staging: &Path, data_dir: &Path) -> anyhow::Result<()> {
+    let aside = staging.join(".replaced");
+    std::fs::create_dir(&aside).with_context(|| format!("Create {:?}", aside))?;
+    // Journal files of the old database would be applied to the new one
+    let mut names: Vec<String> = ["", "-wal", "-shm", "-journal"]
+        .iter()
+        .map(|suffix| format!("{}{}", DATABASE_FILE, suffix))
+        .collect();
+    names.push(PHOTOS_DIR.to_string());
+
+    let mut moved = Vec::new();
+    let result = move_aside(data_dir, &aside, &names, &mut moved).and_then(|()| move_in(staging, data_dir));
+    if result.is_err() {
+        for name in [DATABASE_FILE, PHOTOS_DIR] {
+            let restored = data_dir.join(name);
+            let _ = std::fs::remove_file(&restored).or_else(|_| std::fs::remove_dir_all(&restored));
+        }
+        for name in moved {
+            if let Err(err) = std::fs::rename(aside.join(&name), data_dir.join(&name)) {
+                warn!("Failed to move {:?} back into {:?}: {}", name, data_dir, err);
+            }
+        }
+    }
+    result
+}
+
+fn move_aside(data_dir: &Path, aside: &Path, names: &[String], moved: &mut Vec<String>) -> anyhow::Result<()> {
+    for name in names {
+        let path = data_dir.join(name);
+        if path.symlink_metadata().is_ok() {
+            std::fs::rename(&path, aside.join(name)).with_context(|| format!("Move {:?} aside", path))?;
+            moved.push(name.clone());
+        }
+    }
+    Ok(())
+}
+
+// The photos go first, so the database only shows up once they are all there
+fn move_in(staging: &Path, data_dir: &Path) -> anyhow::Result<()> {
+    let photos = data_dir.join(PHOTOS_DIR);
+    let restored = staging.join(PHOTOS_DIR);
+    if restored.is_dir() {
+        std::fs::rename(&restored, &photos).with_context(|| format!("Move photos to {:?}", photos))?;
+    } else {
+        std::fs::create_dir_all(&photos).with_context(|| format!("Create {:?}", photos))?;
+    }
+    let database = data_dir.join(DATABASE_FILE);
+    std::fs::rename(staging.join(DATABASE_FILE), &database).with_context(|| format!("Move database to {:?}", database))
+}
+
+/// Makes a backup of `data_dir` into `backup_dir` every `interval`, starting now, and keeps
+/// the newest `keep` of them
+pub async fn schedule(data_dir: PathBuf, backup_dir: PathBuf, interval: Duration, keep: usize) {
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        let (data_dir, backup_dir) = (data_dir.clone(), backup_dir.clone());
+        let made = tokio::task::spawn_blocking(move || scheduled_backup(&data_dir, &backup_dir, keep)).await;
+        match made {
+            Ok(Ok(path)) => info!("Backed up to {:?}", path),
+            Ok(Err(err)) => warn!("Scheduled backup failed: {:#}", err),
+            Err(err) => warn!("Scheduled backup failed: {}", err),
+        }
+    }
+}
+
+fn scheduled_backup(data_dir: &Path, backup_dir: &Path, keep: usize) -> anyhow::Result<PathBuf> {
+    std::fs::create_dir_all(backup_dir).with_context(|| format!("Create {:?}", backup_dir))?;
+    let name = format!("{}{}{}", SCHEDULED_PREFIX, chrono::Utc::now().format("%Y%m%dT%H%M%SZ"), SCHEDULED_SUFFIX);
+    let out = backup_dir.join(name);
+    create(data_dir, &out)?;
+    prune(backup_dir, keep)?;
+    Ok(out)
+}
+
+/// Deletes all but the newest `keep` scheduled backups in `backup_dir`. Other files are left alone.
+pub fn prune(backup_dir: &Path, keep: usize) -> anyhow::Result<()> {
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(backup_dir)
+        .with_context(|| format!("Read {:?}", backup_dir))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(SCHEDULED_PREFIX) && name.ends_with(SCHEDULED_SUFFIX))
+        })
+        .collect();
+    backups.sort();
+    let old = backups.len().saturating_sub(keep);
+    for path in &backups[..old] {
+        std::fs::remove_file(path).with_context(|| format!("Remove old backup {:?}", path))?;
+        info!("Removed old backup {:?}", path);
+    }
+    Ok(())
+}
@@ -0,0 +1,760 @@
+//! A minimal CalDAV server (RFC 4791), so reminder apps on phones can sync the lists. Each
+//! list is a calendar collection and each of its tasks a VTODO:
+//!
+//! ```text
+//! /dav/                              points at the principal
+//! /dav/principal/                    points at the calendar home
+//! /dav/calendars/                    a collection for every list of the user's households
+//! /dav/calendars/<list id>/          the list
+//! /dav/calendars/<list id>/<name>    a task, <id>.ics unless a client named it
+//! ```
+//!
+//! Clients sign in with HTTP basic auth, see `auth::require_login`. ETags come from when the
+//! task was last modified, plus its version to tell apart edits within the same second.
+
+use crate::todo::{List, Task, User};
+use crate::AppState;
+use anyhow::{bail, Context};
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderName, Method, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    routing::any,
+    Extension, Router,
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use chrono_tz::Tz;
+use roxmltree::Node;
+use tracing::{info, warn};
+
+const DAV: &str = "DAV:";
+const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+const CALENDARSERVER: &str = "http://calendarserver.org/ns/";
+
+const PRINCIPAL: &str = "/dav/principal/";
+const CALENDAR_HOME: &str = "/dav/calendars/";
+
+const METHODS: &str = "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, REPORT";
+const ICAL_TIME: &str = "%Y%m%dT%H%M%S";
+
+/// A task as a CalDAV resource
+#[derive(Clone, Debug)]
+pub struct DavTask {
+    pub task: Task,
+    pub name: String, // In the list's collection
+    pub uid: String,
+    pub created: String, // UTC, as stored
+    pub modified: String,
+}
+
+impl DavTask {
+    pub fn etag(&self) -> String {
+        format!("\"{}-{}\"", stored_epoch(&self.modified), self.task.version)
+    }
+}
+
+/// What a VTODO sent by a client sets on a task
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VTodo {
+    pub uid: Option<String>,
+    pub summary: String,
+    pub completed: bool,
+    pub completed_at: Option<i64>,
+    pub priority: Option<String>, // todo.txt style, see `ical_priority`
+}
+
+/// Routes of the server, merged into `create_app` since they start at `/dav/`
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/.well-known/caldav", any(well_known))
+        .route("/dav/", any(root))
+        .route("/dav/principal/", any(principal))
+        .route("/dav/calendars/", any(home))
+        .route("/dav/calendars/:list_id", any(calendar))
+        .route("/dav/calendars/:list_id/", any(calendar))
+        .route("/dav/calendars/:list_id/:name", any(task))
+}
+
+/// Sends CalDAV clients looking for the server (RFC 6764) to the root
+async fn well_known() -> Redirect {
+    Redirect::permanent("/dav/")
+}
+
+/// Seconds since the epoch at the end of a stored `YYYY-MM-DD HH:MM:SS:<epoch>` timestamp
+fn stored_epoch(stored: &str) -> i64 {
+    stored.rsplit(':').next().and_then(|epoch| epoch.parse().ok()).unwrap_or(0)
+}
+
+fn ical_time(epoch: i64) -> String {
+    DateTime::from_timestamp(epoch, 0).unwrap_or_default().format(&format!("{}Z", ICAL_TIME)).to_string()
+}
+
+/// A DATE-TIME or DATE value. Floating times are in the household timezone.
+fn parse_ical_time(value: &str, timezone: Tz) -> Option<i64> {
+    let value = value.trim();
+    if let Some(utc) = value.strip_suffix('Z') {
+        return NaiveDateTime::parse_from_str(utc, ICAL_TIME).ok().map(|time| time.and_utc().timestamp());
+    }
+    let local = NaiveDateTime::parse_from_str(value, ICAL_TIME)
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y%m%d").ok().map(|date| date.and_time(NaiveTime::MIN)))?;
+    timezone.from_local_datetime(&local).earliest().map(|time| time.timestamp())
+}
+
+/// iCalendar priority for a todo.txt one. A to I are 1 (the highest) to 9, the rest all 9.
+pub fn ical_priority(priority: &str) -> Option<u8> {
+    let letter = priority.bytes().next().filter(u8::is_ascii_uppercase)?;
+    Some((letter - b'A' + 1).min(9))
+}
+
+fn todo_priority(priority: u8) -> Option<String> {
+    (1..=9).contains(&priority).then(|| ((b'A' + priority - 1) as char).to_string())
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\").replace(';', "\\;").replace(',', "\\,").replace('\n', "\\n")
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+/// Splits a content line into lines of at most 75 octets, the later ones starting with a space
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded
+}
+
+/// The task as a VCALENDAR holding one VTODO. Its tags and `@context`s become CATEGORIES.
+pub fn to_ical(task: &DavTask) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//htmx-rs-todo//CalDAV//EN".to_string(),
+        "BEGIN:VTODO".to_string(),
+        format!("UID:{}", escape_text(&task.uid)),
+        format!("DTSTAMP:{}", ical_time(stored_epoch(&task.modified))),
+        format!("CREATED:{}", ical_time(stored_epoch(&task.created))),
+        format!("LAST-MODIFIED:{}", ical_time(stored_epoch(&task.modified))),
+        format!("SUMMARY:{}", escape_text(&task.task.text)),
+        format!("STATUS:{}", if task.task.completed { "COMPLETED" } else { "NEEDS-ACTION" }),
+    ];
+    if let (true, Some(completed_at)) = (task.task.completed, task.task.completed_at) {
+        lines.push(format!("COMPLETED:{}", ical_time(completed_at)));
+    }
+    if let Some(priority) = task.task.priority.as_deref().and_then(ical_priority) {
+        lines.push(format!("PRIORITY:{}", priority));
+    }
+    let tags = task.task.all_tags();
+    if !tags.is_empty() {
+        lines.push(format!("CATEGORIES:{}", tags.iter().map(|tag| escape_text(tag)).collect::<Vec<_>>().join(",")));
+    }
+    lines.push("END:VTODO".to_string());
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+/// Name and value of a content line, leaving out the parameters
+fn split_property(line: &str) -> Option<(String, &str)> {
+    let mut quoted = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ':' if !quoted => {
+                let name = line[..index].split(';').next().unwrap_or_default();
+                return Some((name.trim().to_ascii_uppercase(), &line[index + 1..]));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Reads the first VTODO of a calendar object. Its alarms and any other occurrences are
+/// left out.
+pub fn parse_vtodo(ical: &str, timezone: Tz) -> anyhow::Result<VTodo> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ical.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut components = Vec::new();
+    let mut todos = 0;
+    let mut status = None;
+    let mut vtodo = VTodo::default();
+    for line in &lines {
+        let Some((name, value)) = split_property(line) else {
+            continue;
+        };
+        match name.as_str() {
+            "BEGIN" => {
+                let component = value.trim().to_ascii_uppercase();
+                todos += (component == "VTODO") as usize;
+                components.push(component);
+            }
+            "END" => {
+                components.pop();
+            }
+            _ if todos != 1 || components.last().map(String::as_str) != Some("VTODO") => {}
+            "UID" => vtodo.uid = Some(unescape_text(value.trim())).filter(|uid| !uid.is_empty()),
+            "SUMMARY" => vtodo.summary = unescape_text(value).trim().to_string(),
+            "STATUS" => status = Some(value.trim().to_ascii_uppercase()),
+            "COMPLETED" => vtodo.completed_at = parse_ical_time(value, timezone),
+            "PRIORITY" => vtodo.priority = value.trim().parse().ok().and_then(todo_priority),
+            _ => {}
+        }
+    }
+    if todos == 0 {
+        bail!("No VTODO in the calendar data");
+    }
+    vtodo.completed = match status.as_deref() {
+        Some(status) => status == "COMPLETED",
+        None => vtodo.completed_at.is_some(),
+    };
+    if !vtodo.completed {
+        vtodo.completed_at = None;
+    }
+    Ok(vtodo)
+}
+
+/// Names clients may give tasks, which then need no escaping in hrefs
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 255
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "-_.@+~".contains(c))
+}
+
+/// Whether a name is of the `<id>.ics` form tasks without a client given name are served
+/// under. Clients can't pick these, or a task made later could end up sharing its name.
+fn is_reserved_name(name: &str) -> bool {
+    name.strip_suffix(".ics").is_some_and(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes.get(index + 1..index + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+        match (bytes[index], hex.and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn calendar_href(list_id: usize) -> String {
+    format!("{}{}/", CALENDAR_HOME, list_id)
+}
+
+fn task_href(list_id: usize, name: &str) -> String {
+    format!("{}{}", calendar_href(list_id), name)
+}
+
+fn href(path: &str) -> String {
+    format!("<d:href>{}</d:href>", escape_xml(path))
+}
+
+fn prefix(namespace: &str) -> Option<&'static str> {
+    match namespace {
+        DAV => Some("d"),
+        CALDAV => Some("c"),
+        CALENDARSERVER => Some("cs"),
+        _ => None,
+    }
+}
+
+/// A property of a resource, with the XML inside it
+struct Prop {
+    namespace: &'static str,
+    name: &'static str,
+    value: String,
+}
+
+impl Prop {
+    fn new(namespace: &'static str, name: &'static str, value: impl Into<String>) -> Prop {
+        Prop { namespace, name, value: value.into() }
+    }
+
+    fn to_xml(&self) -> String {
+        let prefix = prefix(self.namespace).unwrap_or("d");
+        format!("<{0}:{1}>{2}</{0}:{1}>", prefix, self.name, self.value)
+    }
+}
+
+/// An empty element for a property this server doesn't have
+fn missing_prop(namespace: &str, name: &str) -> String {
+    match prefix(namespace) {
+        Some(prefix) => format!("<{}:{}/>", prefix, name),
+        None => format!("<x:{} xmlns:x=\"{}\"/>", name, escape_xml(namespace)),
+    }
+}
+
+/// Properties a PROPFIND or REPORT asks for
+enum Wanted {
+    All,
+    Props(Vec<(String, String)>), // Namespace and name
+}
+
+impl Wanted {
+    fn from_xml(request: Node) -> Wanted {
+        match request.children().find(|node| node.tag_name().name() == "prop") {
+            Some(prop) => Wanted::Props(
+                prop.children()
+                    .filter(Node::is_element)
+                    .map(|node| (node.tag_name().namespace().unwrap_or_default().to_string(), node.tag_name().name().to_string()))
+                    .collect(),
+            ),
+            None => Wanted::All,
+        }
+    }
+}
+
+/// What a response in a multistatus describes
+enum Resource<'a> {
+    Root,
+    Principal(&'a User),
+    Home,
+    Calendar(&'a List, &'a [DavTask]),
+    Task(&'a DavTask),
+}
+
+/// Changes whenever a task on the list is added, changed or removed
+fn ctag(tasks: &[DavTask]) -> String {
+    let modified = tasks.iter().map(|task| stored_epoch(&task.modified)).max().unwrap_or(0);
+    let versions: usize = tasks.iter().map(|task| task.task.version).sum();
+    let ids: usize = tasks.iter().map(|task| task.task.id).sum();
+    format!("{}-{}-{}-{}", tasks.len(), modified, versions, ids)
+}
+
+fn props(resource: &Resource) -> Vec<Prop> {
+    let mut props = vec![
+        Prop::new(DAV, "current-user-principal", href(PRINCIPAL)),
+        Prop::new(
+            DAV,
+            "current-user-privilege-set",
+            ["read", "write", "write-content", "bind", "unbind"]
+                .iter()
+                .map(|privilege| format!("<d:privilege><d:{}/></d:privilege>", privilege))
+                .collect::<String>(),
+        ),
+    ];
+    match resource {
+        Resource::Root => props.push(Prop::new(DAV, "resourcetype", "<d:collection/>")),
+        Resource::Principal(user) => props.extend([
+            Prop::new(DAV, "resourcetype", "<d:principal/>"),
+            Prop::new(DAV, "displayname", escape_xml(&user.username)),
+            Prop::new(DAV, "principal-URL", href(PRINCIPAL)),
+            Prop::new(CALDAV, "calendar-home-set", href(CALENDAR_HOME)),
+        ]),
+        Resource::Home => props.extend([
+            Prop::new(DAV, "resourcetype", "<d:collection/>"),
+            Prop::new(DAV, "displayname", "Lists"),
+        ]),
+        Resource::Calendar(list, tasks) => props.extend([
+            Prop::new(DAV, "resourcetype", "<d:collection/><c:calendar/>"),
+            Prop::new(DAV, "displayname", escape_xml(&list.name)),
+            Prop::new(DAV, "owner", href(PRINCIPAL)),
+            Prop::new(
+                DAV,
+                "supported-report-set",
+                ["calendar-query", "calendar-multiget"]
+                    .iter()
+                    .map(|report| format!("<d:supported-report><d:report><c:{}/></d:report></d:supported-report>", report))
+                    .collect::<String>(),
+            ),
+            Prop::new(CALDAV, "supported-calendar-component-set", "<c:comp name=\"VTODO\"/>"),
+            Prop::new(CALENDARSERVER, "getctag", ctag(tasks)),
+        ]),
+        Resource::Task(task) => props.extend([
+            Prop::new(DAV, "resourcetype", ""),
+            Prop::new(DAV, "getetag", escape_xml(&task.etag())),
+            Prop::new(DAV, "getcontenttype", "text/calendar; charset=utf-8; component=VTODO"),
+            Prop::new(CALDAV, "calendar-data", escape_xml(&to_ical(task))),
+        ]),
+    }
+    props
+}
+
+/// One `response` of a multistatus, with a propstat for the properties there are and one
+/// for those there aren't
+fn response(path: &str, resource: &Resource, wanted: &Wanted) -> String {
+    let props = props(resource);
+    let (found, missing) = match wanted {
+        // Calendar data is only sent when asked for
+        Wanted::All => (props.iter().filter(|prop| prop.name != "calendar-data").map(Prop::to_xml).collect(), Vec::new()),
+        Wanted::Props(names) => {
+            let mut found = Vec::new();
+            let mut missing = Vec::new();
+            for (namespace, name) in names {
+                match props.iter().find(|prop| prop.namespace == namespace && prop.name == name) {
+                    Some(prop) => found.push(prop.to_xml()),
+                    None => missing.push(missing_prop(namespace, name)),
+                }
+            }
+            (found, missing)
+        }
+    };
+
+    let mut xml = format!("<d:response>{}", href(path));
+    if !found.is_empty() || missing.is_empty() {
+        xml += &format!("<d:propstat><d:prop>{}</d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat>", found.concat());
+    }
+    if !missing.is_empty() {
+        xml += &format!(
+            "<d:propstat><d:prop>{}</d:prop><d:status>HTTP/1.1 404 Not Found</d:status></d:propstat>",
+            missing.concat()
+        );
+    }
+    xml + "</d:response>"
+}
+
+fn multistatus(responses: Vec<String>) -> Response {
+    let body = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<d:multistatus xmlns:d=\"{}\" xmlns:c=\"{}\" xmlns:cs=\"{}\">{}</d:multistatus>",
+        DAV,
+        CALDAV,
+        CALENDARSERVER,
+        responses.concat()
+    );
+    (StatusCode::MULTI_STATUS, [(header::CONTENT_TYPE, "application/xml; charset=utf-8")], body).into_response()
+}
+
+fn options() -> Response {
+    (StatusCode::OK, [(HeaderName::from_static("dav"), "1, calendar-access"), (header::ALLOW, METHODS)]).into_response()
+}
+
+fn not_allowed() -> Response {
+    (StatusCode::METHOD_NOT_ALLOWED, [(header::ALLOW, METHODS)]).into_response()
+}
+
+fn server_error(err: anyhow::Error) -> Response {
+    warn!("CalDAV request failed: {:?}", err);
+    StatusCode::INTERNAL_SERVER_ERROR.into_response()
+}
+
+/// Answers a PROPFIND on the first resource, listing the rest, its members, only for a depth
+/// of 1 or infinity. Infinity, the default, is served as 1 since no collection here holds
+/// more collections.
+fn propfind(headers: &HeaderMap, body: &Bytes, resources: Vec<(String, Resource)>) -> Response {
+    let wanted = match std::str::from_utf8(body).map(str::trim) {
+        Ok("") => Wanted::All,
+        Ok(body) => match roxmltree::Document::parse(body) {
+            Ok(document) => Wanted::from_xml(document.root_element()),
+            Err(_) => return (StatusCode::BAD_REQUEST, "Malformed PROPFIND body").into_response(),
+        },
+        Err(_) => return (StatusCode::BAD_REQUEST, "PROPFIND body is not UTF-8").into_response(),
+    };
+    let members = headers.get("Depth").is_none_or(|depth| depth != "0");
+    let count = if members { resources.len() } else { 1 };
+    multistatus(resources.iter().take(count).map(|(path, resource)| response(path, resource, &wanted)).collect())
+}
+
+/// Every list of the user's households, each with the state scoped to its household
+async fn user_lists(state: &AppState, user: &User) -> Vec<(AppState, List)> {
+    let households = match state.db.get_user_households(user.id).await {
+        Ok(households) => households,
+        Err(err) => {
+            warn!("Failed to get households of {}: {}", user.username, err);
+            Vec::new()
+        }
+    };
+    let mut lists = Vec::new();
+    for household in households {
+        let scoped = AppState { db: state.db.for_household(household.id), photos_dir: state.photos_dir.clone() };
+        match scoped.db.get_lists().await {
+            Ok(found) => lists.extend(found.into_iter().map(|list| (scoped.clone(), list))),
+            Err(err) => warn!("Failed to get lists of household {}: {}", household.id, err),
+        }
+    }
+    lists
+}
+
+async fn find_list(state: &AppState, user: &User, list_id: usize) -> Option<(AppState, List)> {
+    user_lists(state, user).await.into_iter().find(|(_, list)| list.id == list_id)
+}
+
+async fn root(method: Method, headers: HeaderMap, body: Bytes) -> Response {
+    match method.as_str() {
+        "OPTIONS" => options(),
+        "PROPFIND" => propfind(&headers, &body, vec![("/dav/".to_string(), Resource::Root)]),
+        _ => not_allowed(),
+    }
+}
+
+async fn principal(Extension(user): Extension<User>, method: Method, headers: HeaderMap, body: Bytes) -> Response {
+    match method.as_str() {
+        "OPTIONS" => options(),
+        "PROPFIND" => propfind(&headers, &body, vec![(PRINCIPAL.to_string(), Resource::Principal(&user))]),
+        _ => not_allowed(),
+    }
+}
+
+async fn home(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    method: Method,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    match method.as_str() {
+        "OPTIONS" => options(),
+        "PROPFIND" => {
+            let mut calendars = Vec::new();
+            for (state, list) in user_lists(&state, &user).await {
+                match state.db.get_dav_tasks(list.id).await {
+                    Ok(tasks) => calendars.push((list, tasks)),
+                    Err(err) => return server_error(err),
+                }
+            }
+            let mut resources = vec![(CALENDAR_HOME.to_string(), Resource::Home)];
+            resources.extend(calendars.iter().map(|(list, tasks)| (calendar_href(list.id), Resource::Calendar(list, tasks))));
+            propfind(&headers, &body, resources)
+        }
+        _ => not_allowed(),
+    }
+}
+
+async fn calendar(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path(list_id): Path<usize>,
+    method: Method,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some((state, list)) = find_list(&state, &user, list_id).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let tasks = match state.db.get_dav_tasks(list_id).await {
+        Ok(tasks) => tasks,
+        Err(err) => return server_error(err),
+    };
+    match method.as_str() {
+        "OPTIONS" => options(),
+        "PROPFIND" => {
+            let mut resources = vec![(calendar_href(list_id), Resource::Calendar(&list, &tasks))];
+            resources.extend(tasks.iter().map(|task| (task_href(list_id, &task.name), Resource::Task(task))));
+            propfind(&headers, &body, resources)
+        }
+        "REPORT" => report(list_id, &tasks, &body),
+        _ => not_allowed(),
+    }
+}
+
+/// Whether a task passes the filter of a calendar-query. Only the filters reminder apps use
+/// are read, the others let every task through.
+fn query_matches(query: Node, task: &Task) -> bool {
+    let Some(filter) = query.children().find(|node| node.tag_name().name() == "filter") else {
+        return true;
+    };
+    filter.descendants().filter(Node::is_element).all(|filter| match (filter.tag_name().name(), filter.attribute("name")) {
+        ("comp-filter", Some(component)) => matches!(component, "VCALENDAR" | "VTODO"),
+        ("prop-filter", Some("COMPLETED")) => {
+            let undefined = filter.children().any(|node| node.tag_name().name() == "is-not-defined");
+            !undefined || !task.completed
+        }
+        ("prop-filter", Some("STATUS")) => match filter.children().find(|node| node.tag_name().name() == "text-match") {
+            Some(text_match) => {
+                let status = if task.completed { "COMPLETED" } else { "NEEDS-ACTION" };
+                let text = text_match.text().unwrap_or_default().trim().to_ascii_uppercase();
+                status.contains(&text) != (text_match.attribute("negate-condition") == Some("yes"))
+            }
+            None => true,
+        },
+        _ => true,
+    })
+}
+
+/// Answers a calendar-query or calendar-multiget on a list
+fn report(list_id: usize, tasks: &[DavTask], body: &Bytes) -> Response {
+    let Ok(body) = std::str::from_utf8(body) else {
+        return (StatusCode::BAD_REQUEST, "REPORT body is not UTF-8").into_response();
+    };
+    let Ok(document) = roxmltree::Document::parse(body) else {
+        return (StatusCode::BAD_REQUEST, "Malformed REPORT body").into_response();
+    };
+    let request = document.root_element();
+    let wanted = Wanted::from_xml(request);
+    let responses = match request.tag_name().name() {
+        "calendar-query" => tasks
+            .iter()
+            .filter(|task| query_matches(request, &task.task))
+            .map(|task| response(&task_href(list_id, &task.name), &Resource::Task(task), &wanted))
+            .collect(),
+        "calendar-multiget" => request
+            .children()
+            .filter(|node| node.tag_name().name() == "href")
+            .filter_map(|node| node.text())
+            .map(|path| {
+                let name = path.trim().strip_prefix(&calendar_href(list_id)).map(percent_decode);
+                match tasks.iter().find(|task| Some(&task.name) == name.as_ref()) {
+                    Some(task) => response(path.trim(), &Resource::Task(task), &wanted),
+                    None => format!("<d:response>{}<d:status>HTTP/1.1 404 Not Found</d:status></d:response>", href(path.trim())),
+                }
+            })
+            .collect(),
+        _ => return (StatusCode::FORBIDDEN, "Only calendar-query and calendar-multiget reports are supported").into_response(),
+    };
+    multistatus(responses)
+}
+
+/// Whether If-Match and If-None-Match let a request go ahead on a resource with `etag`, or
+/// on one that doesn't exist yet
+fn preconditions_hold(headers: &HeaderMap, etag: Option<&str>) -> bool {
+    let matches = |name: HeaderName| {
+        headers.get(name).and_then(|value| value.to_str().ok()).map(|value| {
+            value.split(',').map(|tag| tag.trim().trim_start_matches("W/")).any(|tag| {
+                (tag == "*" && etag.is_some()) || Some(tag) == etag
+            })
+        })
+    };
+    matches(header::IF_MATCH) != Some(false) && matches(header::IF_NONE_MATCH) != Some(true)
+}
+
+async fn task(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Path((list_id, name)): Path<(usize, String)>,
+    method: Method,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some((state, list)) = find_list(&state, &user, list_id).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let existing = match state.db.get_dav_task(list_id, name.clone()).await {
+        Ok(existing) => existing,
+        Err(err) => return server_error(err),
+    };
+    match (method.as_str(), existing) {
+        ("OPTIONS", _) => options(),
+        ("PUT", existing) => put_task(&state, &list, name, existing, &headers, &body).await,
+        ("GET" | "HEAD", Some(task)) => (
+            [(header::CONTENT_TYPE, "text/calendar; charset=utf-8".to_string()), (header::ETAG, task.etag())],
+            to_ical(&task),
+        )
+            .into_response(),
+        ("PROPFIND", Some(task)) => propfind(&headers, &body, vec![(task_href(list_id, &task.name), Resource::Task(&task))]),
+        ("DELETE", Some(task)) => {
+            if !preconditions_hold(&headers, Some(&task.etag())) {
+                return StatusCode::PRECONDITION_FAILED.into_response();
+            }
+            match state.db.delete_task(task.task.id).await {
+                Ok(()) => {
+                    info!("Deleted task {} over CalDAV", task.task.id);
+                    StatusCode::NO_CONTENT.into_response()
+                }
+                Err(err) => server_error(err),
+            }
+        }
+        ("GET" | "HEAD" | "PROPFIND" | "DELETE", None) => StatusCode::NOT_FOUND.into_response(),
+        _ => not_allowed(),
+    }
+}
+
+/// Creates or replaces a task from the VTODO in the body
+async fn put_task(
+    state: &AppState,
+    list: &List,
+    name: String,
+    existing: Option<DavTask>,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Response {
+    if !is_valid_name(&name) {
+        return (StatusCode::FORBIDDEN, "Names may only have letters, digits and -_.@+~").into_response();
+    }
+    if !preconditions_hold(headers, existing.as_ref().map(DavTask::etag).as_deref()) {
+        return StatusCode::PRECONDITION_FAILED.into_response();
+    }
+    let locale = crate::current_locale(state).await;
+    let parsed = std::str::from_utf8(body)
+        .context("Calendar data is not UTF-8")
+        .and_then(|ical| parse_vtodo(ical, locale.timezone));
+    let mut vtodo = match parsed {
+        Ok(vtodo) if vtodo.summary.is_empty() => return (StatusCode::BAD_REQUEST, "The VTODO has no SUMMARY").into_response(),
+        Ok(vtodo) => vtodo,
+        Err(err) => return (StatusCode::BAD_REQUEST, err.to_string()).into_response(),
+    };
+
+    let status = match existing {
+        Some(existing) => {
+            // Keep todo.txt priorities past I, which iCalendar can't tell apart
+            let kept = existing.task.priority.as_deref().and_then(ical_priority);
+            if kept.is_some() && kept == vtodo.priority.as_deref().and_then(ical_priority) {
+                vtodo.priority = existing.task.priority.clone();
+            }
+            match state.db.update_dav_task(existing.task.id, vtodo, existing.task.version).await {
+                Ok(true) => {}
+                Ok(false) => return StatusCode::PRECONDITION_FAILED.into_response(),
+                Err(err) => return server_error(err),
+            }
+            // The change is saved either way, so this only gets logged
+            if let Err(err) = crate::stock_pantry_if_bought(state, &existing.task).await {
+                warn!("Task {} changed, but couldn't be checked for the pantry: {}", existing.task.id, err);
+            }
+            StatusCode::NO_CONTENT
+        }
+        None if is_reserved_name(&name) => {
+            return (StatusCode::FORBIDDEN, "Names of the form <number>.ics are taken by the server").into_response();
+        }
+        None => {
+            let uid = vtodo.uid.clone().unwrap_or_else(|| name.clone());
+            match state.db.get_dav_tasks(list.id).await {
+                Ok(tasks) if tasks.iter().any(|task| task.uid == uid) => {
+                    return (StatusCode::FORBIDDEN, "Another task on the list has this UID").into_response();
+                }
+                Ok(_) => {}
+                Err(err) => return server_error(err),
+            }
+            match state.db.create_dav_task(list.id, name.clone(), uid, vtodo).await {
+                Ok(id) => info!("Task {} created over CalDAV", id),
+                Err(err) => return server_error(err),
+            }
+            StatusCode::CREATED
+        }
+    };
+    match state.db.get_dav_task(list.id, name).await {
+        Ok(Some(task)) => (status, [(header::ETAG, task.etag())]).into_response(),
+        _ => status.into_response(),
+    }
+}
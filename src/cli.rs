@@ -0,0 +1,238 @@
+//! What the scripting subcommands in main.rs (`task add`, `list ls` and so on) do, either
+//! on the database in the data directory or through a running server's JSON API.
+
+use crate::auth::SESSION_COOKIE;
+use crate::csrf::{CSRF_COOKIE, CSRF_HEADER};
+use crate::todo::{List, MealPlanEntry, Recipe, Task};
+use crate::AppState;
+use anyhow::{anyhow, bail, Context};
+use reqwest::{header, redirect, Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+
+/// Where the subcommands read and change the data
+pub enum Backend {
+    Local(AppState),
+    Remote(Remote),
+}
+
+/// A signed in session on a running server
+pub struct Remote {
+    client: reqwest::Client,
+    base: String,
+    cookie: String,
+}
+
+#[derive(Deserialize)]
+struct Page<T> {
+    items: Vec<T>,
+    total: usize,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct ErrorDetail {
+    message: String,
+}
+
+/// Value of the cookie `name` set by a response
+fn set_cookie(response: &reqwest::Response, name: &str) -> Option<String> {
+    response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| value.split(';').next()?.split_once('='))
+        .find(|(cookie, _)| *cookie == name)
+        .map(|(_, value)| value.to_string())
+        .filter(|value| !value.is_empty())
+}
+
+impl Remote {
+    /// Logs in to the server at `base`, e.g. `http://localhost:3000`, the way the login page does
+    pub async fn login(base: &str, username: &str, password: &str) -> anyhow::Result<Remote> {
+        let base = base.trim_end_matches('/').to_string();
+        let client = reqwest::Client::builder()
+            .redirect(redirect::Policy::none())
+            .build()
+            .context("Create HTTP client")?;
+
+        let page = client.get(format!("{}/login", base)).send().await.context("Connect to server")?;
+        let csrf = set_cookie(&page, CSRF_COOKIE).ok_or_else(|| anyhow!("Server sent no CSRF token"))?;
+        let response = client
+            .post(format!("{}/login", base))
+            .header(header::COOKIE, format!("{}={}", CSRF_COOKIE, csrf))
+            .header(CSRF_HEADER, &csrf)
+            .form(&[("username", username), ("password", password)])
+            .send()
+            .await
+            .context("Log in")?;
+        let session = match set_cookie(&response, SESSION_COOKIE) {
+            Some(session) => session,
+            None if response.status() == StatusCode::UNAUTHORIZED => bail!("Wrong username or password"),
+            None => bail!("Login failed with {}", response.status()),
+        };
+        Ok(Remote { client, base, cookie: format!("{}={}", SESSION_COOKIE, session) })
+    }
+
+    async fn send<T: DeserializeOwned>(&self, method: Method, path: &str, body: Option<serde_json::Value>) -> anyhow::Result<T> {
+        let mut request = self
+            .client
+            .request(method, format!("{}/api/v1{}", self.base, path))
+            .header(header::COOKIE, &self.cookie);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request.send().await.context("Send request")?;
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(match response.json::<ErrorBody>().await {
+                Ok(body) => anyhow!(body.error.message),
+                Err(_) => anyhow!("Server answered {}", status),
+            });
+        }
+        response.json().await.context("Read response")
+    }
+
+    /// Every page of a collection
+    async fn get_all<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<Vec<T>> {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let mut items = Vec::new();
+        for page in 1.. {
+            let url = format!("{}{}page={}&per_page=200", path, separator, page);
+            let page: Page<T> = self.send(Method::GET, &url, None).await?;
+            let done = page.items.is_empty();
+            items.extend(page.items);
+            if done || items.len() >= page.total {
+                break;
+            }
+        }
+        Ok(items)
+    }
+}
+
+impl Backend {
+    /// The database in the data directory, scoped to the household with this ID or, ignoring
+    /// case, name. Without one there has to be only a single household to work in.
+    pub async fn local(state: AppState, household: Option<&str>) -> anyhow::Result<Backend> {
+        let households = state.db.get_households().await?;
+        let found = match household {
+            Some(household) => {
+                let id = household.parse::<usize>().ok();
+                households
+                    .iter()
+                    .find(|found| Some(found.id) == id || found.name.eq_ignore_ascii_case(household.trim()))
+                    .ok_or_else(|| anyhow!("No household {:?}", household))?
+            }
+            None => match households.as_slice() {
+                [only] => only,
+                [] => bail!("There are no households yet"),
+                _ => {
+                    let names = households.iter().map(|household| household.name.as_str()).collect::<Vec<_>>();
+                    bail!("There are several households, pick one with --household: {}", names.join(", "))
+                }
+            },
+        };
+        Ok(Backend::Local(AppState { db: state.db.for_household(found.id), ..state }))
+    }
+
+    pub async fn lists(&self) -> anyhow::Result<Vec<List>> {
+        match self {
+            Backend::Local(state) => state.db.get_lists().await,
+            Backend::Remote(remote) => remote.get_all("/lists").await,
+        }
+    }
+
+    /// Tasks on a list that haven't been archived into a shopping trip
+    pub async fn tasks(&self, list_id: usize) -> anyhow::Result<Vec<Task>> {
+        match self {
+            Backend::Local(state) => state.db.get_tasks(list_id).await,
+            Backend::Remote(remote) => remote.get_all(&format!("/lists/{}/tasks", list_id)).await,
+        }
+    }
+
+    pub async fn add_task(&self, list_id: usize, text: &str) -> anyhow::Result<Task> {
+        let text = text.trim();
+        if text.is_empty() {
+            bail!("The task text is empty");
+        }
+        match self {
+            Backend::Local(state) => {
+                let id = state.db.create_task(text.to_string(), list_id).await?;
+                state.db.get_task(id).await
+            }
+            Backend::Remote(remote) => {
+                remote.send(Method::POST, &format!("/lists/{}/tasks", list_id), Some(json!({ "text": text }))).await
+            }
+        }
+    }
+
+    /// Checks a task off, stocking the pantry if it is on the household's shopping list
+    pub async fn complete_task(&self, task_id: usize) -> anyhow::Result<Task> {
+        match self {
+            Backend::Local(state) => {
+                let before = state.db.get_task(task_id).await.map_err(|_| anyhow!("Task {} not found", task_id))?;
+                state.db.set_task_completed(task_id, true, None).await?;
+                crate::stock_pantry_if_bought(state, &before).await
+            }
+            Backend::Remote(remote) => {
+                remote.send(Method::PATCH, &format!("/tasks/{}", task_id), Some(json!({ "completed": true }))).await
+            }
+        }
+    }
+
+    pub async fn recipes(&self) -> anyhow::Result<Vec<Recipe>> {
+        match self {
+            Backend::Local(state) => state.db.get_recipes().await,
+            Backend::Remote(remote) => remote.get_all("/recipes").await,
+        }
+    }
+
+    /// Plans a meal on `date`, YYYY-MM-DD. The text defaults to the recipe's title.
+    pub async fn add_meal(&self, date: &str, meal_text: Option<&str>, recipe: Option<&Recipe>) -> anyhow::Result<MealPlanEntry> {
+        chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").with_context(|| format!("{:?} is not a YYYY-MM-DD date", date))?;
+        let meal_text = meal_text.map(str::trim).filter(|text| !text.is_empty());
+        match self {
+            Backend::Local(state) => {
+                let meal_text = match (meal_text, recipe) {
+                    (Some(text), _) => text.to_string(),
+                    (None, Some(recipe)) => recipe.title.clone(),
+                    (None, None) => bail!("Give the meal or a recipe"),
+                };
+                let id = state.db
+                    .create_meal_plan_entry(date.to_string(), meal_text, recipe.map(|recipe| recipe.id), None, None)
+                    .await?;
+                state.db.get_meal_plan_entry(id).await
+            }
+            Backend::Remote(remote) => {
+                let body = json!({ "date": date, "meal_text": meal_text, "recipe_id": recipe.map(|recipe| recipe.id) });
+                remote.send(Method::POST, "/meal-plan", Some(body)).await
+            }
+        }
+    }
+
+    /// The list with this ID or, ignoring case, name
+    pub async fn find_list(&self, id_or_name: &str) -> anyhow::Result<List> {
+        let lists = self.lists().await?;
+        let id = id_or_name.parse::<usize>().ok();
+        lists
+            .into_iter()
+            .find(|list| Some(list.id) == id || list.name.eq_ignore_ascii_case(id_or_name.trim()))
+            .ok_or_else(|| anyhow!("No list {:?}", id_or_name))
+    }
+
+    /// The recipe with this ID or, ignoring case, title
+    pub async fn find_recipe(&self, id_or_title: &str) -> anyhow::Result<Recipe> {
+        let recipes = self.recipes().await?;
+        let id = id_or_title.parse::<usize>().ok();
+        recipes
+            .into_iter()
+            .find(|recipe| Some(recipe.id) == id || recipe.title.eq_ignore_ascii_case(id_or_title.trim()))
+            .ok_or_else(|| anyhow!("No recipe {:?}", id_or_title))
+    }
+}
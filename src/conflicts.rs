@@ -0,0 +1,75 @@
+use crate::events::View;
+use crate::template::{ReorderConflictTemplate, TaskConflictTemplate};
+use crate::AppState;
+use askama::Template;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+
+/// The version an If-Match header asks for, from `"3"`, `W/"3"` or `3`. No header, `*` or
+/// anything else unreadable puts no condition on the edit.
+pub fn if_match(headers: &HeaderMap) -> Option<usize> {
+    let value = headers.get(header::IF_MATCH)?.to_str().ok()?.trim();
+    value.trim_start_matches("W/").trim_matches('"').parse().ok()
+}
+
+/// The ETag of a version, the value to send back in If-Match
+pub fn etag(version: usize) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{}\"", version)).expect("Digits are a valid header value")
+}
+
+/// Answers checking off a task that didn't go through because it changed since the page
+/// showed it. If the task is already how the user wanted it there's nothing to resolve,
+/// otherwise a 409 with both versions replaces the row.
+pub async fn task_conflict(state: &AppState, task_id: usize, wanted: bool, view: View) -> Response {
+    let task = match state.db.get_task(task_id).await {
+        Ok(task) => task,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+    if wanted == task.completed {
+        return match view.render(state, task).await {
+            Ok(row) => Html(row).into_response(),
+            Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        };
+    }
+
+    let (row_url, set_url) = match &view {
+        View::Index => (format!("/task/{}", task.id), format!("/task/{}/completed", task.id)),
+        View::Shop(None) => (format!("/task/{}?view=shop", task.id), format!("/task/{}/completed?view=shop", task.id)),
+        View::Shop(Some(share)) => (
+            format!("/s/{}/task/{}", share.token, task.id),
+            format!("/s/{}/task/{}/completed", share.token, task.id),
+        ),
+    };
+    let template = TaskConflictTemplate {
+        shop: matches!(view, View::Shop(_)),
+        mine: !task.completed,
+        task,
+        row_url,
+        set_url,
+    };
+    match template.render() {
+        Ok(html) => (StatusCode::CONFLICT, Html(html)).into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// Answers a reorder that didn't go through because the list was reordered since the page
+/// showed it, with both orders of the open tasks
+pub async fn reorder_conflict(state: &AppState, list_id: usize, order: &[u64]) -> Response {
+    let list = match state.db.get_list(list_id).await {
+        Ok(list) => list,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let theirs: Vec<_> = match state.db.get_tasks(list_id).await {
+        Ok(tasks) => tasks.into_iter().filter(|task| !task.completed).collect(),
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let mine = order
+        .iter()
+        .filter_map(|&id| theirs.iter().find(|task| task.id as u64 == id).cloned())
+        .collect();
+    match (ReorderConflictTemplate { mine, theirs }).render() {
+        Ok(html) => (StatusCode::CONFLICT, [(header::ETAG, etag(list.version))], Html(html)).into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
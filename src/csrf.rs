@@ -0,0 +1,174 @@
+use crate::api::ApiError;
+use crate::auth;
+use axum::body::{self, Body, Bytes};
+use axum::extract::{FromRequest, Multipart, Request};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Form;
+use tracing::warn;
+
+pub const CSRF_COOKIE: &str = "csrf";
+
+/// Header HTMX sends the token in, set with `hx-headers` on each page's body
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+
+/// Hidden field plain forms send the token in
+pub const CSRF_FIELD: &str = "csrf_token";
+
+tokio::task_local! {
+    static TOKEN: String;
+}
+
+/// CSRF token of the request being handled, for templates to put in forms and headers
+pub fn token() -> String {
+    TOKEN.try_with(|token| token.clone()).unwrap_or_default()
+}
+
+fn csrf_cookie(token: &str) -> String {
+    format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}",
+        CSRF_COOKIE,
+        token,
+        auth::SESSION_DAYS * 24 * 60 * 60
+    )
+}
+
+fn cookie_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == CSRF_COOKIE)
+        .map(|(_, token)| token.to_string())
+        .filter(|token| !token.is_empty())
+}
+
+/// Compares in constant time, so the token can't be guessed byte by byte
+fn tokens_match(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected.bytes().zip(given.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Host and port of an `Origin` or `Referer` URL
+fn url_authority(url: &str) -> Option<&str> {
+    let (_, rest) = url.split_once("://")?;
+    Some(rest.split(['/', '?', '#']).next().unwrap_or(rest))
+}
+
+/// Whether the request comes from one of our own pages. Requests without an Origin or
+/// Referer, like those from scripts, are let through and still need the token.
+fn is_same_origin(headers: &HeaderMap) -> bool {
+    let source = match headers.get(header::ORIGIN).or_else(|| headers.get(header::REFERER)) {
+        Some(source) => source.to_str().unwrap_or_default(),
+        None => return true,
+    };
+    let Some(authority) = url_authority(source) else {
+        return false; // Includes "null" origins from sandboxed frames
+    };
+    [header::HOST.as_str(), "X-Forwarded-Host"]
+        .iter()
+        .filter_map(|name| headers.get(*name))
+        .filter_map(|host| host.to_str().ok())
+        .any(|host| host.eq_ignore_ascii_case(authority))
+}
+
+/// Token sent in the header or, for plain forms, in the body. Returns the body as well,
+/// since reading it for the hidden field uses it up.
+async fn request_token(headers: &HeaderMap, body: Body) -> Result<(Option<String>, Bytes), Response> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let bytes = body::to_bytes(body, crate::MAX_BODY_BYTES)
+        .await
+        .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE.into_response())?;
+
+    // Lets the extractors read the buffered body
+    let request = || {
+        let mut request = Request::new(Body::from(bytes.clone()));
+        *request.method_mut() = Method::POST;
+        request.headers_mut().extend(
+            headers.get(header::CONTENT_TYPE).map(|value| (header::CONTENT_TYPE, value.clone())),
+        );
+        request
+    };
+
+    if content_type.starts_with("application/x-www-form-urlencoded") {
+        let Form(fields) = Form::<Vec<(String, String)>>::from_request(request(), &())
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let token = fields.into_iter().find(|(name, _)| name == CSRF_FIELD).map(|(_, token)| token);
+        return Ok((token, bytes));
+    }
+
+    if content_type.starts_with("multipart/form-data") {
+        let mut multipart = Multipart::from_request(request(), &())
+            .await
+            .map_err(IntoResponse::into_response)?;
+        while let Ok(Some(field)) = multipart.next_field().await {
+            if field.name() == Some(CSRF_FIELD) {
+                return Ok((field.text().await.ok(), bytes));
+            }
+        }
+    }
+    Ok((None, bytes))
+}
+
+fn reject(path: &str, reason: &str) -> Response {
+    warn!("Rejected cross-site request to {}: {}", path, reason);
+    if path.starts_with("/api/") {
+        return ApiError::forbidden(reason).into_response();
+    }
+    (StatusCode::FORBIDDEN, reason.to_string()).into_response()
+}
+
+/// Middleware on `create_app` that guards against cross-site request forgery. Every
+/// browser gets a random token in a cookie, which templates read with [`token`].
+/// Requests that change data must come from our own origin and, except for the JSON
+/// API, send the token back in the `X-CSRF-Token` header or a `csrf_token` field.
+pub async fn protect(request: Request, next: Next) -> Response {
+    let cookie = cookie_token(request.headers());
+    let token = cookie.clone().unwrap_or_else(auth::random_token);
+
+    let mutating = !matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    let request = if mutating {
+        let path = request.uri().path().to_string();
+        if !is_same_origin(request.headers()) {
+            return reject(&path, "Cross-origin request");
+        }
+
+        // JSON can't be sent from another site without CORS, which we don't allow, and
+        // neither can CalDAV's PUT, DELETE, PROPFIND and REPORT
+        if path.starts_with("/api/") || auth::is_dav(&path) {
+            request
+        } else {
+            let (parts, body) = request.into_parts();
+            let (given, body) = match parts.headers.get(CSRF_HEADER) {
+                Some(given) => (given.to_str().ok().map(str::to_string), body),
+                None => match request_token(&parts.headers, body).await {
+                    Ok((given, bytes)) => (given, Body::from(bytes)),
+                    Err(response) => return response,
+                },
+            };
+            match (&cookie, given) {
+                (Some(expected), Some(given)) if tokens_match(expected, &given) => {}
+                _ => return reject(&path, "Missing or invalid CSRF token"),
+            }
+            Request::from_parts(parts, body)
+        }
+    } else {
+        request
+    };
+
+    let mut response = TOKEN.scope(token.clone(), next.run(request)).await;
+    if cookie.is_none() {
+        if let Ok(value) = HeaderValue::from_str(&csrf_cookie(&token)) {
+            response.headers_mut().append(header::SET_COOKIE, value);
+        }
+    }
+    response
+}
@@ -1,12 +1,46 @@
 use rusqlite;
 use anyhow::Context;
+use tokio::sync::broadcast;
 use tokio_rusqlite::Connection;
-use crate::todo::{Task, List, Recipe, MealPlanEntry, RecipePhoto};
+use crate::caldav::{DavTask, VTodo};
+use crate::events::{Change, Event, EVENT_BUFFER};
+use crate::export::{DayNoteExport, Export, HouseholdExport, ImportSummary, ListExport, MealExport, PhotoExport, RecipeExport, TaskExport};
+use crate::idempotency::{IdempotencyKey, KeyClaim, StoredResponse, KEY_SECONDS};
+use crate::prices::TripPurchase;
+use crate::todotxt::{project_name, ImportedTask};
+use crate::todo::{Task, List, Recipe, MealPlanEntry, PantryItem, RecipePhoto, Store, User, Household, ShareLink, SharePermission, ShareTarget};
 use tracing::{info, warn};
 
+// Schema changes applied on top of schema.sql, in order. The index of the last
+// applied migration is tracked in SQLite's user_version pragma.
+const MIGRATIONS: &[&str] = &[
+    include_str!("../sql/migrations/0001_recipe_preferences.sql"),
+    include_str!("../sql/migrations/0002_utc_timestamps.sql"),
+    include_str!("../sql/migrations/0003_meal_plan_leftovers.sql"),
+    include_str!("../sql/migrations/0004_servings.sql"),
+    include_str!("../sql/migrations/0005_shopping_trips.sql"),
+    include_str!("../sql/migrations/0006_task_prices.sql"),
+    include_str!("../sql/migrations/0007_households.sql"),
+    include_str!("../sql/migrations/0008_versions.sql"),
+    include_str!("../sql/migrations/0009_completed_at.sql"),
+    include_str!("../sql/migrations/0010_todo_txt.sql"),
+    include_str!("../sql/migrations/0011_caldav.sql"),
+    include_str!("../sql/migrations/0012_task_stocked.sql"),
+    include_str!("../sql/migrations/0013_household_settings.sql"),
+    include_str!("../sql/migrations/0014_idempotency_owner.sql"),
+    include_str!("../sql/migrations/0015_household_invites.sql"),
+];
+
+/// Household that existing data was moved into, and that new handles start out in
+pub const DEFAULT_HOUSEHOLD: usize = 1;
+
+/// Handle to the database, scoped to one household. Queries on lists, recipes, the meal
+/// plan, the pantry and stores only see and touch that household's rows.
 #[derive(Clone)]
 pub struct Database {
     connection : Connection,
+    household_id: usize,
+    events: broadcast::Sender<Event>,
 }
 
 impl Database {
@@ -35,14 +69,62 @@ impl Database {
                 Ok(())
             })
             .await?;
-        Ok(Database { connection: dbconn })
+
+        dbconn
+            .call(|conn| {
+                let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+                for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+                    info!("Applying database migration {}", index + 1);
+                    let tx = conn.transaction()?;
+                    tx.execute_batch(migration)?;
+                    tx.pragma_update(None, "user_version", index + 1)?;
+                    tx.commit()?;
+                }
+                Ok(())
+            })
+            .await
+            .context("Apply database migrations")?;
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        Ok(Database { connection: dbconn, household_id: DEFAULT_HOUSEHOLD, events })
+    }
+
+    /// The same database, scoped to another household
+    pub fn for_household(&self, household_id: usize) -> Database {
+        Database { household_id, ..self.clone() }
+    }
+
+    pub fn household_id(&self) -> usize {
+        self.household_id
+    }
+
+    /// Changes made through any handle to this database, in every household
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.events.subscribe()
+    }
+
+    fn publish(&self, change: Change) {
+        // Sending only fails when nobody is listening
+        let _ = self.events.send(Event { household_id: self.household_id, change });
+    }
+
+    /// Tells subscribers which list the task changed on
+    async fn publish_task(&self, task_id: usize) {
+        if let Ok(task) = self.get_task(task_id).await {
+            self.publish(Change::Task { list_id: task.list_id, task_id });
+        }
     }
 
     pub async fn delete_task(&self, id: usize) -> anyhow::Result<()> {
+        // Looked up first, since subscribers need to know which list lost the task
+        let list_id = self.get_task(id).await.map(|task| task.list_id);
+        let household = self.household_id;
         self.connection
             .call(
                 move |conn| {
-                    match conn.execute("DELETE FROM tasks WHERE id=(?1)", &[&id]) {
+                    match conn.execute(
+                        "DELETE FROM tasks WHERE id=(?1) AND list_id IN (SELECT id FROM lists WHERE household_id = ?2)",
+                        [&id, &household],
+                    ) {
                         Ok(_) => Ok(()),
                         Err(err) => {
                             warn!("Delete task failed: {}", err);
@@ -52,14 +134,19 @@ impl Database {
                 },
             )
             .await
-            .context("Delete task")
+            .context("Delete task")?;
+        if let Ok(list_id) = list_id {
+            self.publish(Change::Task { list_id, task_id: id });
+        }
+        Ok(())
     }
 
     pub async fn delete_list(&self, id: usize) -> anyhow::Result<()> {
+        let household = self.household_id;
         self.connection
             .call(
                 move |conn| {
-                    match conn.execute("DELETE FROM lists WHERE id=(?1)", &[&id]) {
+                    match conn.execute("DELETE FROM lists WHERE id=(?1) AND household_id = ?2", [&id, &household]) {
                         Ok(_) => Ok(()),
                         Err(err) => {
                             warn!("Delete list failed: {}", err);
@@ -70,12 +157,17 @@ impl Database {
             )
             .await
             .context("Delete list")
+            .inspect(|_| self.publish(Change::Lists))
     }
 
     pub async fn get_task(&self, id: usize) -> anyhow::Result<Task> {
+        let household = self.household_id;
         Ok(self.connection
             .call(move |conn| {
-                let t = conn.query_row("SELECT id, task, completed, list_id, position FROM tasks WHERE id=(?1)", &[&id], 
+                let t = conn.query_row(
+                    "SELECT id, task, completed, list_id, position, price, store_id, version, completed_at, priority, tags FROM tasks
+                     WHERE id=(?1) AND list_id IN (SELECT id FROM lists WHERE household_id = ?2)",
+                    [&id, &household],
                 |row| {
                     Ok(Task {
                         id: row.get(0).expect("Failed to get id, corrupt database?"),
@@ -83,6 +175,12 @@ impl Database {
                         completed: row.get(2).expect("Failed to get completed, corrupt database?"),
                         list_id: row.get(3).expect("Failed to get list_id, corrupt database?"),
                         position: row.get(4).ok(),
+                        price: row.get(5).ok().flatten(),
+                        store_id: row.get(6).ok().flatten(),
+                        version: row.get(7).expect("Failed to get version, corrupt database?"),
+                        completed_at: row.get(8).expect("Failed to get completed_at, corrupt database?"),
+                        priority: row.get(9).expect("Failed to get priority, corrupt database?"),
+                        tags: tags_from_column(row.get(10).expect("Failed to get tags, corrupt database?")),
                     })
                 });
                 Ok(t)
@@ -91,22 +189,29 @@ impl Database {
     }
 
     pub async fn get_tasks(&self, list_id: usize) -> anyhow::Result<Vec<Task>> {
+        let household = self.household_id;
         Ok(self.connection
             .call(move |conn| {
                 let mut stmt = conn.prepare(
-                    "SELECT tasks.id, tasks.task, tasks.completed, tasks.list_id, tasks.position 
+                    "SELECT tasks.id, tasks.task, tasks.completed, tasks.list_id, tasks.position, tasks.price, tasks.store_id, tasks.version, tasks.completed_at, tasks.priority, tasks.tags
                     FROM tasks 
                     INNER JOIN lists ON lists.id=tasks.list_id 
-                    WHERE lists.id=(:list_id) 
+                    WHERE lists.id=(:list_id) AND lists.household_id=(:household) AND tasks.trip_id IS NULL
                     ORDER BY tasks.position ASC NULLS LAST, tasks.completed ASC, tasks.modified DESC;",
                 )?;
-                let rows = stmt.query_map(&[(":list_id", &list_id)], |row| {
+                let rows = stmt.query_map(&[(":list_id", &list_id), (":household", &household)], |row| {
                     Ok(Task {
                         id: row.get(0).expect("Failed to get id, corrupt database?"),
                         text: row.get(1).expect("Failed to get task, corrupt database?"),
                         completed: row.get(2).expect("Failed to get completed, corrupt database?"),
                         list_id: row.get(3).expect("Failed to get list_id, corrupt database?"),
                         position: row.get(4).ok(),
+                        price: row.get(5).ok().flatten(),
+                        store_id: row.get(6).ok().flatten(),
+                        version: row.get(7).expect("Failed to get version, corrupt database?"),
+                        completed_at: row.get(8).expect("Failed to get completed_at, corrupt database?"),
+                        priority: row.get(9).expect("Failed to get priority, corrupt database?"),
+                        tags: tags_from_column(row.get(10).expect("Failed to get tags, corrupt database?")),
                     })
                 })?;
                 let mut tasks = Vec::new();
@@ -127,9 +232,17 @@ impl Database {
     ) -> anyhow::Result<usize> {
         info!("Inserting task item with list_id {}", list_id);
 
+        let household = self.household_id;
         let id = self
             .connection
             .call(move |conn| {
+                // The list has to belong to the household
+                conn.query_row(
+                    "SELECT id FROM lists WHERE id = ?1 AND household_id = ?2",
+                    [&list_id, &household],
+                    |row| row.get::<_, usize>(0),
+                )?;
+
                 // Get the next position for this list
                 let next_position: i32 = conn.query_row(
                     "SELECT COALESCE(MAX(position), -1) + 1 FROM tasks WHERE list_id = ?1",
@@ -153,25 +266,284 @@ impl Database {
             })
             .await
             .context("Create task on db.")?;
+        self.publish(Change::Task { list_id, task_id: id });
         info!("Task item with id {} created", id);
         Ok(id)
     }
 
-    pub async fn toggle_task_completed(&self, task_id: usize) -> anyhow::Result<()>{
-        
-        self
+    /// The list's tasks with what CalDAV needs of them, in the order they are shown
+    pub async fn get_dav_tasks(&self, list_id: usize) -> anyhow::Result<Vec<DavTask>> {
+        self.dav_tasks(list_id, None).await
+    }
+
+    /// The list's task served under `name`
+    pub async fn get_dav_task(&self, list_id: usize, name: String) -> anyhow::Result<Option<DavTask>> {
+        Ok(self.dav_tasks(list_id, Some(name)).await?.into_iter().next())
+    }
+
+    /// With `name`, only the task of that name
+    async fn dav_tasks(&self, list_id: usize, name: Option<String>) -> anyhow::Result<Vec<DavTask>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT tasks.id, tasks.task, tasks.completed, tasks.list_id, tasks.position, tasks.price, tasks.store_id, tasks.version, tasks.completed_at, tasks.priority, tasks.tags,
+                            COALESCE(tasks.caldav_name, tasks.id || '.ics'), COALESCE(tasks.caldav_uid, 'task-' || tasks.id || '@htmx-rs-todo'),
+                            tasks.created, tasks.modified
+                     FROM tasks
+                     INNER JOIN lists ON lists.id = tasks.list_id
+                     WHERE lists.id = ?1 AND lists.household_id = ?2 AND tasks.trip_id IS NULL
+                     AND (?3 IS NULL OR COALESCE(tasks.caldav_name, tasks.id || '.ics') = ?3)
+                     ORDER BY tasks.position ASC NULLS LAST, tasks.id ASC",
+                )?;
+                let rows = stmt.query_map(rusqlite::params![&list_id, &household, &name], |row| {
+                    Ok(DavTask {
+                        task: Task {
+                            id: row.get(0).expect("Failed to get id, corrupt database?"),
+                            text: row.get(1).expect("Failed to get task, corrupt database?"),
+                            completed: row.get(2).expect("Failed to get completed, corrupt database?"),
+                            list_id: row.get(3).expect("Failed to get list_id, corrupt database?"),
+                            position: row.get(4).ok(),
+                            price: row.get(5).ok().flatten(),
+                            store_id: row.get(6).ok().flatten(),
+                            version: row.get(7).expect("Failed to get version, corrupt database?"),
+                            completed_at: row.get(8).expect("Failed to get completed_at, corrupt database?"),
+                            priority: row.get(9).expect("Failed to get priority, corrupt database?"),
+                            tags: tags_from_column(row.get(10).expect("Failed to get tags, corrupt database?")),
+                        },
+                        name: row.get(11)?,
+                        uid: row.get(12)?,
+                        created: row.get::<_, Option<String>>(13)?.unwrap_or_default(),
+                        modified: row.get::<_, Option<String>>(14)?.unwrap_or_default(),
+                    })
+                })?;
+                let mut tasks = Vec::new();
+                for r in rows {
+                    tasks.push(r?);
+                }
+                Ok(tasks)
+            })
+            .await
+            .context("Get CalDAV tasks")
+    }
+
+    /// Adds a task a CalDAV client sent, under the name and UID the client gave it
+    pub async fn create_dav_task(&self, list_id: usize, name: String, uid: String, vtodo: VTodo) -> anyhow::Result<usize> {
+        let household = self.household_id;
+        let completed_at = match vtodo.completed {
+            true => vtodo.completed_at.or(Some(chrono::Utc::now().timestamp())),
+            false => None,
+        };
+        let id = self
+            .connection
+            .call(move |conn| {
+                conn.query_row(
+                    "SELECT id FROM lists WHERE id = ?1 AND household_id = ?2",
+                    [&list_id, &household],
+                    |row| row.get::<_, usize>(0),
+                )?;
+                conn.execute(
+                    "INSERT INTO tasks (task, completed, completed_at, priority, list_id, position, caldav_name, caldav_uid)
+                     VALUES (?1, ?2, ?3, ?4, ?5, (SELECT COALESCE(MAX(position), -1) + 1 FROM tasks WHERE list_id = ?5), ?6, ?7)",
+                    rusqlite::params![&vtodo.summary, &vtodo.completed, &completed_at, &vtodo.priority, &list_id, &name, &uid],
+                )?;
+                Ok(conn.last_insert_rowid() as usize)
+            })
+            .await
+            .context("Create CalDAV task")?;
+        self.publish(Change::Task { list_id, task_id: id });
+        Ok(id)
+    }
+
+    /// Replaces a task with what a CalDAV client sent, if it is still at `expected_version`.
+    /// Returns whether it was changed.
+    pub async fn update_dav_task(&self, id: usize, vtodo: VTodo, expected_version: usize) -> anyhow::Result<bool> {
+        let household = self.household_id;
+        let now = chrono::Utc::now().timestamp();
+        let updated = self
+            .connection
+            .call(move |conn| {
+                let updated = conn.execute(
+                    "UPDATE tasks SET task = ?1, completed = ?2, priority = ?4,
+                         completed_at = CASE WHEN NOT ?2 THEN NULL ELSE COALESCE(?3, CASE WHEN completed THEN completed_at END, ?5) END,
+                         version = version + 1
+                     WHERE id = ?6 AND list_id IN (SELECT id FROM lists WHERE household_id = ?7) AND version = ?8",
+                    rusqlite::params![&vtodo.summary, &vtodo.completed, &vtodo.completed_at, &vtodo.priority, &now, &id, &household, &expected_version],
+                )?;
+                Ok(updated > 0)
+            })
+            .await
+            .context("Update CalDAV task")?;
+        if updated {
+            self.publish_task(id).await;
+        }
+        Ok(updated)
+    }
+
+    /// When each task on the list was created, in the stored UTC format
+    pub async fn get_tasks_created(&self, list_id: usize) -> anyhow::Result<std::collections::HashMap<usize, String>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT tasks.id, tasks.created FROM tasks
+                     INNER JOIN lists ON lists.id = tasks.list_id
+                     WHERE lists.id = ?1 AND lists.household_id = ?2 AND tasks.created IS NOT NULL",
+                )?;
+                let rows = stmt.query_map([&list_id, &household], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                let mut created = std::collections::HashMap::new();
+                for r in rows {
+                    let (id, at) = r?;
+                    created.insert(id, at);
+                }
+                Ok(created)
+            })
+            .await
+            .context("Get task creation times")
+    }
+
+    /// Adds tasks read from a todo.txt file to the end of a list, keeping when they were
+    /// created and checked off. A task with a `+project` goes on the household's list of
+    /// that name instead, which is made if there isn't one. All in one transaction, so a
+    /// failed import changes nothing. Returns how many tasks were added.
+    pub async fn import_todo_txt(&self, list_id: usize, tasks: Vec<(Option<String>, ImportedTask)>) -> anyhow::Result<usize> {
+        let household = self.household_id;
+        let (added, created, touched) = self
+            .connection
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                let name: String = tx.query_row(
+                    "SELECT name FROM lists WHERE id = ?1 AND household_id = ?2",
+                    [&list_id, &household],
+                    |row| row.get(0),
+                )?;
+                let mut lists: Vec<(usize, String)> = {
+                    let mut stmt = tx.prepare("SELECT id, name FROM lists WHERE household_id = ?1 ORDER BY id ASC")?;
+                    let rows = stmt.query_map([&household], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                    rows.collect::<rusqlite::Result<_>>()?
+                };
+                let mut created = false;
+                let mut touched: Vec<usize> = Vec::new();
+                for (project, task) in &tasks {
+                    let target = match project {
+                        Some(project) if !project.eq_ignore_ascii_case(&project_name(&name)) => {
+                            match lists.iter().find(|(_, list)| project_name(list).eq_ignore_ascii_case(project)) {
+                                Some((id, _)) => *id,
+                                None => {
+                                    tx.execute("INSERT INTO lists (name, household_id) VALUES (?1, ?2)", rusqlite::params![project, &household])?;
+                                    let id = tx.last_insert_rowid() as usize;
+                                    lists.push((id, project.clone()));
+                                    created = true;
+                                    id
+                                }
+                            }
+                        }
+                        _ => list_id,
+                    };
+                    tx.execute(
+                        "INSERT INTO tasks (task, completed, completed_at, priority, tags, list_id, position)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, (SELECT COALESCE(MAX(position), -1) + 1 FROM tasks WHERE list_id = ?6))",
+                        rusqlite::params![&task.text, &task.completed, &task.completed_at, &task.priority, &task.tags.join(" "), &target],
+                    )?;
+                    // Set after the insert, whose trigger stamps it with the current time
+                    if let Some(created) = &task.created {
+                        tx.execute("UPDATE tasks SET created = ?1 WHERE id = ?2", rusqlite::params![created, &tx.last_insert_rowid()])?;
+                    }
+                    if !touched.contains(&target) {
+                        touched.push(target);
+                    }
+                }
+                tx.commit()?;
+                Ok((tasks.len(), created, touched))
+            })
+            .await
+            .context("Import todo.txt")?;
+        if created {
+            self.publish(Change::Lists);
+        }
+        for list_id in touched {
+            self.publish(Change::Tasks { list_id });
+        }
+        Ok(added)
+    }
+
+    /// Checks a task off, or back on, recording when it was checked off. Setting the state it
+    /// is already in changes nothing, so sending it twice is harmless. With `expected_version`
+    /// the task is only changed if it is still at that version. Returns whether the task is
+    /// now `completed`.
+    pub async fn set_task_completed(
+        &self,
+        task_id: usize,
+        completed: bool,
+        expected_version: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        let household = self.household_id;
+        let now = chrono::Utc::now().timestamp();
+        let updated = self
             .connection
             .call(move |conn| {
                 match conn.execute(
-                    "UPDATE tasks 
-                               SET completed = ((completed | 1) - (completed & 1)) 
-                               WHERE id=(?1)",
-                    &[&task_id],
+                    "UPDATE tasks
+                     SET completed_at = CASE WHEN NOT ?1 THEN NULL WHEN completed THEN completed_at ELSE ?2 END,
+                         version = version + (completed <> ?1),
+                         completed = ?1
+                     WHERE id = ?3 AND list_id IN (SELECT id FROM lists WHERE household_id = ?4)
+                     AND (?5 IS NULL OR version = ?5 OR completed = ?1)",
+                    rusqlite::params![&completed, &now, &task_id, &household, &expected_version],
                 ) {
                     Ok(updated) => {
                         info!("{} rows were updated", updated);
-                        Ok(())
+                        Ok(updated > 0)
+                    }
+                    Err(err) => {
+                        warn!("Failed to update task: {}", err);
+                        Err(err.into())
                     }
+                }
+            })
+            .await
+            .context("Set task completed")?;
+        if updated {
+            self.publish_task(task_id).await;
+        }
+        Ok(updated)
+    }
+
+    /// Flips whether a task is checked off, see `set_task_completed`. Returns whether it was
+    /// changed.
+    pub async fn toggle_task_completed(&self, task_id: usize, expected_version: Option<usize>) -> anyhow::Result<bool> {
+        let task = match self.get_task(task_id).await {
+            Ok(task) => task,
+            Err(_) => return Ok(false),
+        };
+        // The version read here makes sure nothing changed it in between
+        self.set_task_completed(task_id, !task.completed, expected_version.or(Some(task.version))).await
+    }
+
+    /// Changes a task's text and/or completed state, leaving out what is None. With
+    /// `expected_version` the task is only changed if it is still at that version.
+    /// Returns whether it was changed.
+    pub async fn update_task(
+        &self,
+        id: usize,
+        text: Option<String>,
+        completed: Option<bool>,
+        expected_version: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        let household = self.household_id;
+        let now = chrono::Utc::now().timestamp();
+        let updated = self
+            .connection
+            .call(move |conn| {
+                match conn.execute(
+                    "UPDATE tasks SET task = COALESCE(?1, task), completed = COALESCE(?2, completed),
+                         completed_at = CASE WHEN ?2 IS NULL OR (?2 AND completed) THEN completed_at WHEN ?2 THEN ?6 END,
+                         version = version + 1
+                     WHERE id = ?3 AND list_id IN (SELECT id FROM lists WHERE household_id = ?4)
+                     AND (?5 IS NULL OR version = ?5)",
+                    rusqlite::params![&text, &completed, &id, &household, &expected_version, &now],
+                ) {
+                    Ok(updated) => Ok(updated > 0),
                     Err(err) => {
                         warn!("Failed to update task: {}", err);
                         Err(err.into())
@@ -179,18 +551,148 @@ impl Database {
                 }
             })
             .await
-            .context("Toggle task complete")
+            .context("Update task")?;
+        if updated {
+            self.publish_task(id).await;
+        }
+        Ok(updated)
+    }
+
+    /// Archives the checked off tasks of a list into a new shopping trip.
+    /// Returns the trip id, or None if nothing was checked off.
+    pub async fn finish_shopping_trip(&self, list_id: usize) -> anyhow::Result<Option<usize>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                let checked: usize = tx.query_row(
+                    "SELECT COUNT(*) FROM tasks WHERE list_id = ?1 AND completed = 1 AND trip_id IS NULL
+                     AND list_id IN (SELECT id FROM lists WHERE household_id = ?2)",
+                    [&list_id, &household],
+                    |row| row.get(0),
+                )?;
+                if checked == 0 {
+                    return Ok(None);
+                }
+                tx.execute("INSERT INTO shopping_trips (list_id) VALUES (?1)", [&list_id])?;
+                let trip_id = tx.last_insert_rowid() as usize;
+                tx.execute(
+                    "UPDATE tasks SET trip_id = ?1 WHERE list_id = ?2 AND completed = 1 AND trip_id IS NULL",
+                    [&trip_id, &list_id],
+                )?;
+                tx.commit()?;
+                info!("Archived {} tasks into shopping trip {}", checked, trip_id);
+                Ok(Some(trip_id))
+            })
+            .await
+            .context("Finish shopping trip")
+            .inspect(|_| self.publish(Change::Tasks { list_id }))
+    }
+
+    /// Sets the price paid for a task and records it in the price history under `name`.
+    /// A price of None clears it from both.
+    pub async fn set_task_price(
+        &self,
+        id: usize,
+        name: String,
+        price: Option<f64>,
+        store_id: Option<usize>,
+    ) -> anyhow::Result<()> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                let updated = tx.execute(
+                    "UPDATE tasks SET price = ?1, store_id = ?2, version = version + 1
+                     WHERE id = ?3 AND list_id IN (SELECT id FROM lists WHERE household_id = ?4)",
+                    rusqlite::params![&price, &store_id, &id, &household],
+                )?;
+                if updated == 0 {
+                    return Ok(());
+                }
+                // One history entry per task, so correcting a price doesn't count it twice
+                tx.execute("DELETE FROM price_history WHERE task_id = ?1", [&id])?;
+                if let Some(price) = price {
+                    tx.execute(
+                        "INSERT INTO price_history (name, price, store_id, task_id, household_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+                        rusqlite::params![&name, &price, &store_id, &id, &household],
+                    )?;
+                }
+                tx.commit()?;
+                Ok(())
+            })
+            .await
+            .context("Set task price")?;
+        self.publish_task(id).await;
+        Ok(())
+    }
+
+    /// The most recent price paid for each item
+    pub async fn get_last_prices(&self) -> anyhow::Result<std::collections::HashMap<String, f64>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT name, price FROM price_history
+                     WHERE id IN (SELECT MAX(id) FROM price_history WHERE household_id = ?1 GROUP BY name)"
+                )?;
+                let rows = stmt.query_map([&household], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                let mut prices = std::collections::HashMap::new();
+                for r in rows {
+                    let (name, price) = r?;
+                    prices.insert(name, price);
+                }
+                Ok(prices)
+            })
+            .await
+            .context("Get last prices")
+    }
+
+    /// Priced items of every finished shopping trip. Trips without prices appear once with no price.
+    pub async fn get_trip_purchases(&self) -> anyhow::Result<Vec<TripPurchase>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT trips.id, trips.finished, stores.name, tasks.price
+                     FROM shopping_trips trips
+                     JOIN lists ON lists.id = trips.list_id
+                     LEFT JOIN tasks ON tasks.trip_id = trips.id AND tasks.price IS NOT NULL
+                     LEFT JOIN stores ON stores.id = tasks.store_id
+                     WHERE lists.household_id = ?1
+                     ORDER BY trips.finished ASC"
+                )?;
+                let rows = stmt.query_map([&household], |row| {
+                    Ok(TripPurchase {
+                        trip_id: row.get(0)?,
+                        finished: row.get(1)?,
+                        store: row.get(2)?,
+                        price: row.get(3)?,
+                    })
+                })?;
+                let mut purchases = Vec::new();
+                for r in rows {
+                    purchases.push(r?);
+                }
+                Ok(purchases)
+            })
+            .await
+            .context("Get trip purchases")
     }
 
     pub async fn create_list(
         &self,
         name: String,
         ) -> anyhow::Result<usize> {
+        let household = self.household_id;
         let id = self
             .connection
             .call(move |conn| {
                 // Create the list
-                match conn.execute("INSERT INTO lists (name) values (?1)", &[&name]) {
+                match conn.execute(
+                    "INSERT INTO lists (name, household_id) values (?1, ?2)",
+                    rusqlite::params![&name, &household],
+                ) {
                     Ok(_) => {},
                     Err(err) => {
                         warn!("Create list failed: {}", err);
@@ -201,21 +703,44 @@ impl Database {
             })
             .await
             .context("Create list on db.")?;
+        self.publish(Change::Lists);
         Ok(id)
     }
 
+    pub async fn rename_list(&self, id: usize, name: String) -> anyhow::Result<()> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                match conn.execute(
+                    "UPDATE lists SET name = ?1 WHERE id = ?2 AND household_id = ?3",
+                    rusqlite::params![&name, &id, &household],
+                ) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Rename list failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Rename list")
+            .inspect(|_| self.publish(Change::Lists))
+    }
+
     pub async fn get_list(&self, id: usize) -> anyhow::Result<List> 
     {
+        let household = self.household_id;
         let list = self
             .connection
             .call(move |conn| {
                 Ok(conn.query_row(
-                    "SELECT * FROM lists WHERE id=(?1)",
-                    &[&id],
+                    "SELECT id, name, version FROM lists WHERE id=(?1) AND household_id = ?2",
+                    [&id, &household],
                     |row| {
                         Ok(List {
                             id: row.get(0).expect("Failed to get row value, corrupt database?"),
                             name: row.get(1).expect("Failed to get row value, corrupt database?"),
+                            version: row.get(2).expect("Failed to get row value, corrupt database?"),
                         })
                     },
                 ))
@@ -226,15 +751,17 @@ impl Database {
     }
 
     pub async fn get_lists(&self) -> anyhow::Result<Vec<List>> {
+        let household = self.household_id;
         Ok(self.connection
             .call(move |conn| {
                 let mut stmt = conn.prepare(
-                    "SELECT lists.id, lists.name FROM lists;",
+                    "SELECT lists.id, lists.name, lists.version FROM lists WHERE household_id = ?1;",
                 )?;
-                let rows = stmt.query_map([], |row| {
+                let rows = stmt.query_map([&household], |row| {
                     Ok(List {
                         id: row.get(0).expect("Failed to get row value, corrupt database?"),
                         name: row.get(1).expect("Failed to get row value, corrupt database?"),
+                        version: row.get(2).expect("Failed to get row value, corrupt database?"),
                     })
                 })?;
                 let mut lists = Vec::new();
@@ -247,14 +774,25 @@ impl Database {
             .context("Failed to get lists")?)
     }
 
-    pub async fn reorder(&self, list_id: usize, order: Vec<u64>) -> anyhow::Result<()> {
+    /// Puts the list's tasks in `order`. With `expected_version` the list is only reordered
+    /// if it is still at that version. Returns whether it was reordered.
+    pub async fn reorder(&self, list_id: usize, order: Vec<u64>, expected_version: Option<usize>) -> anyhow::Result<bool> {
         if order.is_empty() {
-            return Ok(());
+            return Ok(true);
         }
 
-        self.connection
+        let household = self.household_id;
+        let reordered = self.connection
             .call(move |conn| {
                 let tx = conn.transaction()?;
+                let bumped = tx.execute(
+                    "UPDATE lists SET version = version + 1
+                     WHERE id = ?1 AND household_id = ?2 AND (?3 IS NULL OR version = ?3)",
+                    rusqlite::params![&list_id, &household, &expected_version],
+                )?;
+                if bumped == 0 {
+                    return Ok(false);
+                }
                 
                 // Update positions for the reordered tasks
                 for (position, task_id) in order.iter().enumerate() {
@@ -279,10 +817,14 @@ impl Database {
                 tx.execute(&query, rusqlite::params_from_iter(params))?;
                 
                 tx.commit()?;
-                Ok(())
+                Ok(true)
             })
             .await
-            .context("Reorder tasks")
+            .context("Reorder tasks")?;
+        if reordered {
+            self.publish(Change::Tasks { list_id });
+        }
+        Ok(reordered)
     }
 
     // Recipe operations
@@ -291,15 +833,17 @@ impl Database {
         title: String,
         instructions: String,
         ingredients: String,
+        servings: Option<u32>,
     ) -> anyhow::Result<usize> {
         info!("Creating recipe: {}", title);
 
+        let household = self.household_id;
         let id = self
             .connection
             .call(move |conn| {
                 match conn.execute(
-                    "INSERT INTO recipes (title, instructions, ingredients) VALUES (?1, ?2, ?3)",
-                    rusqlite::params![&title, &instructions, &ingredients],
+                    "INSERT INTO recipes (title, instructions, ingredients, servings, household_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    rusqlite::params![&title, &instructions, &ingredients, &servings, &household],
                 ) {
                     Ok(_) => Ok(conn.last_insert_rowid() as usize),
                     Err(err) => {
@@ -310,24 +854,22 @@ impl Database {
             })
             .await
             .context("Create recipe")?;
+        self.publish(Change::Recipes);
         
         info!("Recipe created with id {}", id);
         Ok(id)
     }
 
     pub async fn get_recipe(&self, id: usize) -> anyhow::Result<Recipe> {
+        let household = self.household_id;
         self.connection
             .call(move |conn| {
                 let recipe = conn.query_row(
-                    "SELECT id, title, instructions, ingredients FROM recipes WHERE id = ?1",
-                    &[&id],
+                    "SELECT id, title, instructions, ingredients, favourite, rating, tags, servings, version FROM recipes
+                     WHERE id = ?1 AND household_id = ?2",
+                    [&id, &household],
                     |row| {
-                        Ok(Recipe {
-                            id: row.get(0)?,
-                            title: row.get(1)?,
-                            instructions: row.get(2)?,
-                            ingredients: row.get(3)?,
-                        })
+                        recipe_from_row(row)
                     },
                 )?;
                 Ok(recipe)
@@ -337,18 +879,15 @@ impl Database {
     }
 
     pub async fn get_recipes(&self) -> anyhow::Result<Vec<Recipe>> {
+        let household = self.household_id;
         self.connection
             .call(move |conn| {
                 let mut stmt = conn.prepare(
-                    "SELECT id, title, instructions, ingredients FROM recipes ORDER BY modified DESC"
+                    "SELECT id, title, instructions, ingredients, favourite, rating, tags, servings, version FROM recipes
+                     WHERE household_id = ?1 ORDER BY modified DESC"
                 )?;
-                let rows = stmt.query_map([], |row| {
-                    Ok(Recipe {
-                        id: row.get(0)?,
-                        title: row.get(1)?,
-                        instructions: row.get(2)?,
-                        ingredients: row.get(3)?,
-                    })
+                let rows = stmt.query_map([&household], |row| {
+                    recipe_from_row(row)
                 })?;
                 let mut recipes = Vec::new();
                 for r in rows {
@@ -360,20 +899,26 @@ impl Database {
             .context("Get recipes")
     }
 
+    /// With `expected_version` the recipe is only changed if it is still at that version.
+    /// Returns whether it was changed.
     pub async fn update_recipe(
         &self,
         id: usize,
         title: String,
         instructions: String,
         ingredients: String,
-    ) -> anyhow::Result<()> {
+        servings: Option<u32>,
+        expected_version: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        let household = self.household_id;
         self.connection
             .call(move |conn| {
                 match conn.execute(
-                    "UPDATE recipes SET title = ?1, instructions = ?2, ingredients = ?3 WHERE id = ?4",
-                    rusqlite::params![&title, &instructions, &ingredients, &id],
+                    "UPDATE recipes SET title = ?1, instructions = ?2, ingredients = ?3, servings = ?4, version = version + 1
+                     WHERE id = ?5 AND household_id = ?6 AND (?7 IS NULL OR version = ?7)",
+                    rusqlite::params![&title, &instructions, &ingredients, &servings, &id, &household, &expected_version],
                 ) {
-                    Ok(_) => Ok(()),
+                    Ok(updated) => Ok(updated > 0),
                     Err(err) => {
                         warn!("Update recipe failed: {}", err);
                         Err(err.into())
@@ -382,12 +927,40 @@ impl Database {
             })
             .await
             .context("Update recipe")
+            .inspect(|&updated| if updated { self.publish(Change::Recipes) })
+    }
+
+    pub async fn update_recipe_preferences(
+        &self,
+        id: usize,
+        favourite: bool,
+        rating: u8,
+        tags: String,
+    ) -> anyhow::Result<()> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                match conn.execute(
+                    "UPDATE recipes SET favourite = ?1, rating = ?2, tags = ?3 WHERE id = ?4 AND household_id = ?5",
+                    rusqlite::params![&favourite, &rating.min(5), &tags, &id, &household],
+                ) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Update recipe preferences failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Update recipe preferences")
+            .inspect(|_| self.publish(Change::Recipes))
     }
 
     pub async fn delete_recipe(&self, id: usize) -> anyhow::Result<()> {
+        let household = self.household_id;
         self.connection
             .call(move |conn| {
-                match conn.execute("DELETE FROM recipes WHERE id = ?1", &[&id]) {
+                match conn.execute("DELETE FROM recipes WHERE id = ?1 AND household_id = ?2", [&id, &household]) {
                     Ok(_) => Ok(()),
                     Err(err) => {
                         warn!("Delete recipe failed: {}", err);
@@ -397,6 +970,7 @@ impl Database {
             })
             .await
             .context("Delete recipe")
+            .inspect(|_| self.publish(Change::Recipes))
     }
 
     // Meal plan operations
@@ -405,15 +979,20 @@ impl Database {
         date: String,
         meal_text: String,
         recipe_id: Option<usize>,
+        leftovers_of: Option<usize>,
+        servings: Option<u32>,
     ) -> anyhow::Result<usize> {
         info!("Creating meal plan entry for {}: {}", date, meal_text);
 
+        let household = self.household_id;
         let id = self
             .connection
             .call(move |conn| {
                 match conn.execute(
-                    "INSERT INTO meal_plan (date, meal_text, recipe_id) VALUES (?1, ?2, ?3)",
-                    rusqlite::params![&date, &meal_text, &recipe_id],
+                    // Recipes of other households are dropped
+                    "INSERT INTO meal_plan (date, meal_text, recipe_id, leftovers_of, servings, household_id)
+                     VALUES (?1, ?2, (SELECT id FROM recipes WHERE id = ?3 AND household_id = ?6), ?4, ?5, ?6)",
+                    rusqlite::params![&date, &meal_text, &recipe_id, &leftovers_of, &servings, &household],
                 ) {
                     Ok(_) => Ok(conn.last_insert_rowid() as usize),
                     Err(err) => {
@@ -424,26 +1003,52 @@ impl Database {
             })
             .await
             .context("Create meal plan entry")?;
+        self.publish(Change::MealPlan);
         
         info!("Meal plan entry created with id {}", id);
         Ok(id)
     }
 
+    /// Plans a recipe on a day with nothing planned yet. `None` if the day already has an entry.
+    pub async fn create_meal_plan_entry_if_free(
+        &self,
+        date: String,
+        meal_text: String,
+        recipe_id: usize,
+    ) -> anyhow::Result<Option<usize>> {
+        info!("Creating meal plan entry for {} if free: {}", date, meal_text);
+
+        let household = self.household_id;
+        let id = self
+            .connection
+            .call(move |conn| {
+                let inserted = conn.execute(
+                    "INSERT INTO meal_plan (date, meal_text, recipe_id, household_id)
+                     SELECT ?1, ?2, (SELECT id FROM recipes WHERE id = ?3 AND household_id = ?4), ?4
+                     WHERE NOT EXISTS (SELECT 1 FROM meal_plan WHERE date = ?1 AND household_id = ?4)",
+                    rusqlite::params![&date, &meal_text, &recipe_id, &household],
+                )?;
+                Ok((inserted > 0).then(|| conn.last_insert_rowid() as usize))
+            })
+            .await
+            .context("Create meal plan entry if free")?;
+        if id.is_some() {
+            self.publish(Change::MealPlan);
+        }
+        Ok(id)
+    }
+
     pub async fn get_meal_plan_for_week(&self, start_date: String) -> anyhow::Result<Vec<MealPlanEntry>> {
+        let household = self.household_id;
         self.connection
             .call(move |conn| {
                 let mut stmt = conn.prepare(
-                    "SELECT id, date, meal_text, recipe_id FROM meal_plan 
-                     WHERE date >= ?1 AND date < date(?1, '+7 days') 
+                    "SELECT id, date, meal_text, recipe_id, leftovers_of, servings FROM meal_plan 
+                     WHERE date >= ?1 AND date < date(?1, '+7 days') AND household_id = ?2
                      ORDER BY date ASC"
                 )?;
-                let rows = stmt.query_map(&[&start_date], |row| {
-                    Ok(MealPlanEntry {
-                        id: row.get(0)?,
-                        date: row.get(1)?,
-                        meal_text: row.get(2)?,
-                        recipe_id: row.get(3)?,
-                    })
+                let rows = stmt.query_map(rusqlite::params![&start_date, &household], |row| {
+                    meal_plan_entry_from_row(row)
                 })?;
                 let mut entries = Vec::new();
                 for r in rows {
@@ -455,57 +1060,194 @@ impl Database {
             .context("Get meal plan for week")
     }
 
-    pub async fn delete_meal_plan_entry(&self, id: usize) -> anyhow::Result<()> {
+    /// Entries from `from` up to and including `to`, both YYYY-MM-DD
+    pub async fn get_meal_plan_between(&self, from: String, to: String) -> anyhow::Result<Vec<MealPlanEntry>> {
+        let household = self.household_id;
         self.connection
             .call(move |conn| {
-                match conn.execute("DELETE FROM meal_plan WHERE id = ?1", &[&id]) {
-                    Ok(_) => Ok(()),
-                    Err(err) => {
-                        warn!("Delete meal plan entry failed: {}", err);
-                        Err(err.into())
-                    }
+                let mut stmt = conn.prepare(
+                    "SELECT id, date, meal_text, recipe_id, leftovers_of, servings FROM meal_plan
+                     WHERE date >= ?1 AND date <= ?2 AND household_id = ?3
+                     ORDER BY date ASC, id ASC"
+                )?;
+                let rows = stmt.query_map(rusqlite::params![&from, &to, &household], meal_plan_entry_from_row)?;
+                let mut entries = Vec::new();
+                for r in rows {
+                    entries.push(r?);
                 }
+                Ok(entries)
             })
             .await
-            .context("Delete meal plan entry")
+            .context("Get meal plan between dates")
     }
 
-    pub async fn update_meal_plan_entry(
-        &self,
-        id: usize,
-        meal_text: String,
-        recipe_id: Option<usize>,
-    ) -> anyhow::Result<()> {
+    /// Entries from `days` days before `date` up to, but not including, `date`
+    pub async fn get_meal_plan_before(&self, date: String, days: u32) -> anyhow::Result<Vec<MealPlanEntry>> {
+        let household = self.household_id;
         self.connection
             .call(move |conn| {
-                match conn.execute(
-                    "UPDATE meal_plan SET meal_text = ?1, recipe_id = ?2 WHERE id = ?3",
-                    rusqlite::params![&meal_text, &recipe_id, &id],
-                ) {
-                    Ok(_) => Ok(()),
-                    Err(err) => {
-                        warn!("Update meal plan entry failed: {}", err);
-                        Err(err.into())
-                    }
+                let mut stmt = conn.prepare(
+                    "SELECT id, date, meal_text, recipe_id, leftovers_of, servings FROM meal_plan 
+                     WHERE date >= date(?1, ?2) AND date < ?1 AND household_id = ?3
+                     ORDER BY date DESC, id DESC"
+                )?;
+                let offset = format!("-{} days", days);
+                let rows = stmt.query_map(rusqlite::params![&date, &offset, &household], |row| {
+                    meal_plan_entry_from_row(row)
+                })?;
+                let mut entries = Vec::new();
+                for r in rows {
+                    entries.push(r?);
                 }
+                Ok(entries)
             })
             .await
-            .context("Update meal plan entry")
+            .context("Get meal plan before date")
     }
 
-    pub async fn get_meal_plan_entry(&self, id: usize) -> anyhow::Result<MealPlanEntry> {
+    /// Dates from `date` onwards that already have a meal or a note
+    pub async fn get_planned_dates_from(&self, date: String) -> anyhow::Result<Vec<String>> {
+        let household = self.household_id;
         self.connection
             .call(move |conn| {
-                let entry = conn.query_row(
-                    "SELECT id, date, meal_text, recipe_id FROM meal_plan WHERE id = ?1",
-                    &[&id],
-                    |row| {
-                        Ok(MealPlanEntry {
-                            id: row.get(0)?,
-                            date: row.get(1)?,
-                            meal_text: row.get(2)?,
-                            recipe_id: row.get(3)?,
-                        })
+                let mut stmt = conn.prepare(
+                    "SELECT date FROM meal_plan WHERE date >= ?1 AND household_id = ?2
+                     UNION
+                     SELECT date FROM meal_plan_notes WHERE date >= ?1 AND household_id = ?2
+                     ORDER BY date ASC"
+                )?;
+                let rows = stmt.query_map(rusqlite::params![&date, &household], |row| row.get(0))?;
+                let mut dates = Vec::new();
+                for r in rows {
+                    dates.push(r?);
+                }
+                Ok(dates)
+            })
+            .await
+            .context("Get planned dates")
+    }
+
+    pub async fn get_meal_plan_notes_for_week(&self, start_date: String) -> anyhow::Result<std::collections::HashMap<String, String>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT date, note FROM meal_plan_notes 
+                     WHERE date >= ?1 AND date < date(?1, '+7 days') AND household_id = ?2"
+                )?;
+                let rows = stmt.query_map(rusqlite::params![&start_date, &household], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                let mut notes = std::collections::HashMap::new();
+                for r in rows {
+                    let (date, note) = r?;
+                    notes.insert(date, note);
+                }
+                Ok(notes)
+            })
+            .await
+            .context("Get meal plan notes for week")
+    }
+
+    /// Saves the note for a day, or removes it when the note is empty
+    pub async fn set_meal_plan_note(&self, date: String, note: String) -> anyhow::Result<()> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let result = if note.trim().is_empty() {
+                    conn.execute(
+                        "DELETE FROM meal_plan_notes WHERE date = ?1 AND household_id = ?2",
+                        rusqlite::params![&date, &household],
+                    )
+                } else {
+                    conn.execute(
+                        "INSERT INTO meal_plan_notes (household_id, date, note) VALUES (?1, ?2, ?3)
+                         ON CONFLICT(household_id, date) DO UPDATE SET note = excluded.note",
+                        rusqlite::params![&household, &date, note.trim()],
+                    )
+                };
+                match result {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Set meal plan note failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Set meal plan note")
+            .inspect(|_| self.publish(Change::MealPlan))
+    }
+
+    /// Every date each recipe has been planned for, used to avoid repeats.
+    pub async fn get_recipe_plan_history(&self) -> anyhow::Result<Vec<(usize, String)>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT recipe_id, date FROM meal_plan WHERE recipe_id IS NOT NULL AND household_id = ?1 ORDER BY date ASC"
+                )?;
+                let rows = stmt.query_map([&household], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                let mut history = Vec::new();
+                for r in rows {
+                    history.push(r?);
+                }
+                Ok(history)
+            })
+            .await
+            .context("Get recipe plan history")
+    }
+
+    pub async fn delete_meal_plan_entry(&self, id: usize) -> anyhow::Result<()> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                match conn.execute("DELETE FROM meal_plan WHERE id = ?1 AND household_id = ?2", [&id, &household]) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Delete meal plan entry failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Delete meal plan entry")
+            .inspect(|_| self.publish(Change::MealPlan))
+    }
+
+    pub async fn update_meal_plan_entry(
+        &self,
+        id: usize,
+        meal_text: String,
+        recipe_id: Option<usize>,
+    ) -> anyhow::Result<()> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                match conn.execute(
+                    "UPDATE meal_plan SET meal_text = ?1, recipe_id = (SELECT id FROM recipes WHERE id = ?2 AND household_id = ?4)
+                     WHERE id = ?3 AND household_id = ?4",
+                    rusqlite::params![&meal_text, &recipe_id, &id, &household],
+                ) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Update meal plan entry failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Update meal plan entry")
+            .inspect(|_| self.publish(Change::MealPlan))
+    }
+
+    pub async fn get_meal_plan_entry(&self, id: usize) -> anyhow::Result<MealPlanEntry> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let entry = conn.query_row(
+                    "SELECT id, date, meal_text, recipe_id, leftovers_of, servings FROM meal_plan WHERE id = ?1 AND household_id = ?2",
+                    [&id, &household],
+                    |row| {
+                        meal_plan_entry_from_row(row)
                     },
                 )?;
                 Ok(entry)
@@ -514,171 +1256,1364 @@ impl Database {
             .context("Get meal plan entry")
     }
 
-    // Recipe photo operations
-    pub async fn create_recipe_photo(
+    // Settings operations. Each household has its own.
+    pub async fn get_settings(&self) -> anyhow::Result<std::collections::HashMap<String, String>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare("SELECT key, value FROM settings WHERE household_id = ?1")?;
+                let rows = stmt.query_map([&household], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                let mut settings = std::collections::HashMap::new();
+                for r in rows {
+                    let (key, value) = r?;
+                    settings.insert(key, value);
+                }
+                Ok(settings)
+            })
+            .await
+            .context("Get settings")
+    }
+
+    pub async fn set_setting(&self, key: String, value: String) -> anyhow::Result<()> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                match conn.execute(
+                    "INSERT INTO settings (household_id, key, value) VALUES (?1, ?2, ?3)
+                     ON CONFLICT(household_id, key) DO UPDATE SET value = excluded.value",
+                    rusqlite::params![&household, &key, &value],
+                ) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Set setting failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Set setting")
+    }
+
+    /// Sets a setting for every household that hasn't picked one itself
+    pub async fn set_default_setting(&self, key: String, value: String) -> anyhow::Result<()> {
+        self.connection
+            .call(move |conn| {
+                match conn.execute(
+                    "INSERT OR IGNORE INTO settings (household_id, key, value) SELECT id, ?1, ?2 FROM households",
+                    rusqlite::params![&key, &value],
+                ) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Set default setting failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Set default setting")
+    }
+
+    // Pantry operations
+    pub async fn create_pantry_item(
         &self,
-        recipe_id: usize,
-        filename: String,
-        original_name: String,
-        file_size: i64,
-        mime_type: String,
-        upload_order: i32,
-        thumbnail_blob: Option<Vec<u8>>,
+        name: String,
+        quantity: Option<f64>,
+        unit: Option<String>,
+        expires: Option<String>,
     ) -> anyhow::Result<usize> {
-        info!("Creating recipe photo: {} for recipe {}", original_name, recipe_id);
+        info!("Adding {} to the pantry", name);
 
-        let id = self
-            .connection
+        let household = self.household_id;
+        self.connection
             .call(move |conn| {
                 match conn.execute(
-                    "INSERT INTO recipe_photos (recipe_id, filename, original_name, file_size, mime_type, upload_order, thumbnail_blob) 
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-                    rusqlite::params![&recipe_id, &filename, &original_name, &file_size, &mime_type, &upload_order, &thumbnail_blob],
+                    "INSERT INTO pantry_items (name, quantity, unit, expires, household_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    rusqlite::params![&name, &quantity, &unit, &expires, &household],
                 ) {
                     Ok(_) => Ok(conn.last_insert_rowid() as usize),
                     Err(err) => {
-                        warn!("Create recipe photo failed: {}", err);
+                        warn!("Create pantry item failed: {}", err);
                         Err(err.into())
                     }
                 }
             })
             .await
-            .context("Create recipe photo")?;
-        
-        info!("Recipe photo created with id {}", id);
-        Ok(id)
+            .context("Create pantry item")
+            .inspect(|_| self.publish(Change::Pantry))
     }
 
-    pub async fn get_recipe_photos(&self, recipe_id: usize) -> anyhow::Result<Vec<RecipePhoto>> {
+    pub async fn get_pantry_items(&self) -> anyhow::Result<Vec<PantryItem>> {
+        let household = self.household_id;
         self.connection
             .call(move |conn| {
                 let mut stmt = conn.prepare(
-                    "SELECT id, recipe_id, filename, original_name, file_size, mime_type, upload_order, thumbnail_blob 
-                     FROM recipe_photos WHERE recipe_id = ?1 ORDER BY upload_order ASC"
+                    "SELECT id, name, quantity, unit, expires FROM pantry_items WHERE household_id = ?1
+                     ORDER BY expires IS NULL, expires ASC, name COLLATE NOCASE ASC"
                 )?;
-                let rows = stmt.query_map(&[&recipe_id], |row| {
-                    Ok(RecipePhoto {
-                        id: row.get(0)?,
-                        recipe_id: row.get(1)?,
-                        filename: row.get(2)?,
-                        original_name: row.get(3)?,
-                        file_size: row.get(4)?,
-                        mime_type: row.get(5)?,
-                        upload_order: row.get(6)?,
-                        thumbnail_blob: row.get(7)?,
-                    })
-                })?;
-                let mut photos = Vec::new();
+                let rows = stmt.query_map([&household], pantry_item_from_row)?;
+                let mut items = Vec::new();
                 for r in rows {
-                    photos.push(r?);
+                    items.push(r?);
                 }
-                Ok(photos)
+                Ok(items)
             })
             .await
-            .context("Get recipe photos")
+            .context("Get pantry items")
     }
 
-    pub async fn get_recipe_first_photo(&self, recipe_id: usize) -> anyhow::Result<Option<RecipePhoto>> {
+    /// Records that a checked off task went into the pantry. False if it already had.
+    pub async fn mark_task_stocked(&self, task_id: usize) -> anyhow::Result<bool> {
+        let household = self.household_id;
+        let now = chrono::Utc::now().timestamp();
         self.connection
             .call(move |conn| {
-                let result = conn.query_row(
-                    "SELECT id, recipe_id, filename, original_name, file_size, mime_type, upload_order, thumbnail_blob 
-                     FROM recipe_photos WHERE recipe_id = ?1 ORDER BY upload_order ASC LIMIT 1",
-                    &[&recipe_id],
-                    |row| {
-                        Ok(RecipePhoto {
-                            id: row.get(0)?,
-                            recipe_id: row.get(1)?,
-                            filename: row.get(2)?,
-                            original_name: row.get(3)?,
-                            file_size: row.get(4)?,
-                            mime_type: row.get(5)?,
-                            upload_order: row.get(6)?,
-                            thumbnail_blob: row.get(7)?,
-                        })
-                    },
-                );
-                match result {
-                    Ok(photo) => Ok(Some(photo)),
-                    Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-                    Err(err) => Err(err.into()),
+                match conn.execute(
+                    "UPDATE tasks SET stocked_at = ?1
+                     WHERE id = ?2 AND completed AND stocked_at IS NULL
+                     AND list_id IN (SELECT id FROM lists WHERE household_id = ?3)",
+                    rusqlite::params![&now, &task_id, &household],
+                ) {
+                    Ok(updated) => Ok(updated > 0),
+                    Err(err) => {
+                        warn!("Mark task stocked failed: {}", err);
+                        Err(err.into())
+                    }
                 }
             })
             .await
-            .context("Get recipe first photo")
+            .context("Mark task stocked")
     }
 
-    pub async fn delete_recipe_photo(&self, id: usize) -> anyhow::Result<()> {
+    pub async fn update_pantry_item_quantity(&self, id: usize, quantity: Option<f64>) -> anyhow::Result<()> {
+        let household = self.household_id;
         self.connection
             .call(move |conn| {
-                match conn.execute("DELETE FROM recipe_photos WHERE id = ?1", &[&id]) {
+                match conn.execute(
+                    "UPDATE pantry_items SET quantity = ?1 WHERE id = ?2 AND household_id = ?3",
+                    rusqlite::params![&quantity, &id, &household],
+                ) {
                     Ok(_) => Ok(()),
                     Err(err) => {
-                        warn!("Delete recipe photo failed: {}", err);
+                        warn!("Update pantry item failed: {}", err);
                         Err(err.into())
                     }
                 }
             })
             .await
-            .context("Delete recipe photo")
+            .context("Update pantry item quantity")
+            .inspect(|_| self.publish(Change::Pantry))
     }
 
-    pub async fn delete_recipe_photos_by_recipe(&self, recipe_id: usize) -> anyhow::Result<()> {
+    pub async fn delete_pantry_item(&self, id: usize) -> anyhow::Result<()> {
+        let household = self.household_id;
         self.connection
             .call(move |conn| {
-                match conn.execute("DELETE FROM recipe_photos WHERE recipe_id = ?1", &[&recipe_id]) {
+                match conn.execute("DELETE FROM pantry_items WHERE id = ?1 AND household_id = ?2", [&id, &household]) {
                     Ok(_) => Ok(()),
                     Err(err) => {
-                        warn!("Delete recipe photos failed: {}", err);
+                        warn!("Delete pantry item failed: {}", err);
                         Err(err.into())
                     }
                 }
             })
             .await
-            .context("Delete recipe photos by recipe")
+            .context("Delete pantry item")
+            .inspect(|_| self.publish(Change::Pantry))
     }
 
-    pub async fn get_next_photo_order(&self, recipe_id: usize) -> anyhow::Result<i32> {
+    // Aisle category operations
+    pub async fn get_category_overrides(&self) -> anyhow::Result<std::collections::HashMap<String, String>> {
+        let household = self.household_id;
         self.connection
             .call(move |conn| {
-                let order: i32 = conn.query_row(
-                    "SELECT COALESCE(MAX(upload_order), -1) + 1 FROM recipe_photos WHERE recipe_id = ?1",
-                    &[&recipe_id],
-                    |row| row.get(0)
-                ).unwrap_or(0);
-                Ok(order)
+                let mut stmt = conn.prepare("SELECT name, category FROM ingredient_categories WHERE household_id = ?1")?;
+                let rows = stmt.query_map([&household], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                let mut overrides = std::collections::HashMap::new();
+                for r in rows {
+                    let (name, category) = r?;
+                    overrides.insert(name, category);
+                }
+                Ok(overrides)
             })
             .await
-            .context("Get next photo order")
+            .context("Get category overrides")
     }
 
-    pub async fn get_recipe_photo_by_id(&self, photo_id: usize) -> anyhow::Result<Option<RecipePhoto>> {
+    pub async fn set_category_override(&self, name: String, category: String) -> anyhow::Result<()> {
+        let household = self.household_id;
         self.connection
             .call(move |conn| {
-                let result = conn.query_row(
-                    "SELECT id, recipe_id, filename, original_name, file_size, mime_type, upload_order, thumbnail_blob 
-                     FROM recipe_photos WHERE id = ?1",
-                    &[&photo_id],
-                    |row| {
-                        Ok(RecipePhoto {
-                            id: row.get(0)?,
-                            recipe_id: row.get(1)?,
-                            filename: row.get(2)?,
-                            original_name: row.get(3)?,
-                            file_size: row.get(4)?,
-                            mime_type: row.get(5)?,
-                            upload_order: row.get(6)?,
-                            thumbnail_blob: row.get(7)?,
-                        })
-                    },
-                );
-                match result {
-                    Ok(photo) => Ok(Some(photo)),
-                    Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-                    Err(err) => Err(err.into()),
+                match conn.execute(
+                    "INSERT INTO ingredient_categories (household_id, name, category) VALUES (?1, ?2, ?3)
+                     ON CONFLICT(household_id, name) DO UPDATE SET category = excluded.category",
+                    rusqlite::params![&household, &name, &category],
+                ) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Set category override failed: {}", err);
+                        Err(err.into())
+                    }
                 }
             })
             .await
-            .context("Get recipe photo by id")
+            .context("Set category override")
+            .inspect(|_| self.publish(Change::Stores))
+    }
+
+    pub async fn create_store(&self, name: String) -> anyhow::Result<usize> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                match conn.execute(
+                    "INSERT INTO stores (name, household_id) VALUES (?1, ?2)",
+                    rusqlite::params![&name, &household],
+                ) {
+                    Ok(_) => Ok(conn.last_insert_rowid() as usize),
+                    Err(err) => {
+                        warn!("Create store failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Create store")
+            .inspect(|_| self.publish(Change::Stores))
+    }
+
+    pub async fn get_stores(&self) -> anyhow::Result<Vec<Store>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT id, name FROM stores WHERE household_id = ?1 ORDER BY name COLLATE NOCASE ASC"
+                )?;
+                let rows = stmt.query_map([&household], |row| Ok(Store { id: row.get(0)?, name: row.get(1)? }))?;
+                let mut stores = Vec::new();
+                for r in rows {
+                    stores.push(r?);
+                }
+                Ok(stores)
+            })
+            .await
+            .context("Get stores")
     }
+
+    pub async fn delete_store(&self, id: usize) -> anyhow::Result<()> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                match conn.execute("DELETE FROM stores WHERE id = ?1 AND household_id = ?2", [&id, &household]) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Delete store failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Delete store")
+            .inspect(|_| self.publish(Change::Stores))
+    }
+
+    /// Categories in the order they are walked in the store
+    pub async fn get_store_aisles(&self, store_id: usize) -> anyhow::Result<Vec<String>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT category FROM store_aisles
+                     WHERE store_id = ?1 AND store_id IN (SELECT id FROM stores WHERE household_id = ?2)
+                     ORDER BY position ASC"
+                )?;
+                let rows = stmt.query_map([&store_id, &household], |row| row.get(0))?;
+                let mut aisles = Vec::new();
+                for r in rows {
+                    aisles.push(r?);
+                }
+                Ok(aisles)
+            })
+            .await
+            .context("Get store aisles")
+    }
+
+    pub async fn set_store_aisles(&self, store_id: usize, categories: Vec<String>) -> anyhow::Result<()> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                let own_store: bool = tx.query_row(
+                    "SELECT EXISTS(SELECT 1 FROM stores WHERE id = ?1 AND household_id = ?2)",
+                    [&store_id, &household],
+                    |row| row.get(0),
+                )?;
+                if !own_store {
+                    return Ok(());
+                }
+                tx.execute("DELETE FROM store_aisles WHERE store_id = ?1", [&store_id])?;
+                for (position, category) in categories.iter().enumerate() {
+                    tx.execute(
+                        "INSERT INTO store_aisles (store_id, category, position) VALUES (?1, ?2, ?3)",
+                        rusqlite::params![&store_id, category, &position],
+                    )?;
+                }
+                tx.commit()?;
+                Ok(())
+            })
+            .await
+            .context("Set store aisles")
+            .inspect(|_| self.publish(Change::Stores))
+    }
+
+    // Recipe photo operations
+    pub async fn create_recipe_photo(
+        &self,
+        recipe_id: usize,
+        filename: String,
+        original_name: String,
+        file_size: i64,
+        mime_type: String,
+        upload_order: i32,
+        thumbnail_blob: Option<Vec<u8>>,
+    ) -> anyhow::Result<usize> {
+        info!("Creating recipe photo: {} for recipe {}", original_name, recipe_id);
+
+        let household = self.household_id;
+        let id = self
+            .connection
+            .call(move |conn| {
+                // The recipe has to belong to the household
+                conn.query_row(
+                    "SELECT id FROM recipes WHERE id = ?1 AND household_id = ?2",
+                    [&recipe_id, &household],
+                    |row| row.get::<_, usize>(0),
+                )?;
+                match conn.execute(
+                    "INSERT INTO recipe_photos (recipe_id, filename, original_name, file_size, mime_type, upload_order, thumbnail_blob) 
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    rusqlite::params![&recipe_id, &filename, &original_name, &file_size, &mime_type, &upload_order, &thumbnail_blob],
+                ) {
+                    Ok(_) => Ok(conn.last_insert_rowid() as usize),
+                    Err(err) => {
+                        warn!("Create recipe photo failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Create recipe photo")?;
+        self.publish(Change::Recipes);
+        
+        info!("Recipe photo created with id {}", id);
+        Ok(id)
+    }
+
+    pub async fn get_recipe_photos(&self, recipe_id: usize) -> anyhow::Result<Vec<RecipePhoto>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT id, recipe_id, filename, original_name, file_size, mime_type, upload_order, thumbnail_blob 
+                     FROM recipe_photos
+                     WHERE recipe_id = ?1 AND recipe_id IN (SELECT id FROM recipes WHERE household_id = ?2)
+                     ORDER BY upload_order ASC"
+                )?;
+                let rows = stmt.query_map([&recipe_id, &household], |row| {
+                    Ok(RecipePhoto {
+                        id: row.get(0)?,
+                        recipe_id: row.get(1)?,
+                        filename: row.get(2)?,
+                        original_name: row.get(3)?,
+                        file_size: row.get(4)?,
+                        mime_type: row.get(5)?,
+                        upload_order: row.get(6)?,
+                        thumbnail_blob: row.get(7)?,
+                    })
+                })?;
+                let mut photos = Vec::new();
+                for r in rows {
+                    photos.push(r?);
+                }
+                Ok(photos)
+            })
+            .await
+            .context("Get recipe photos")
+    }
+
+    pub async fn get_recipe_first_photo(&self, recipe_id: usize) -> anyhow::Result<Option<RecipePhoto>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let result = conn.query_row(
+                    "SELECT id, recipe_id, filename, original_name, file_size, mime_type, upload_order, thumbnail_blob 
+                     FROM recipe_photos
+                     WHERE recipe_id = ?1 AND recipe_id IN (SELECT id FROM recipes WHERE household_id = ?2)
+                     ORDER BY upload_order ASC LIMIT 1",
+                    [&recipe_id, &household],
+                    |row| {
+                        Ok(RecipePhoto {
+                            id: row.get(0)?,
+                            recipe_id: row.get(1)?,
+                            filename: row.get(2)?,
+                            original_name: row.get(3)?,
+                            file_size: row.get(4)?,
+                            mime_type: row.get(5)?,
+                            upload_order: row.get(6)?,
+                            thumbnail_blob: row.get(7)?,
+                        })
+                    },
+                );
+                match result {
+                    Ok(photo) => Ok(Some(photo)),
+                    Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                    Err(err) => Err(err.into()),
+                }
+            })
+            .await
+            .context("Get recipe first photo")
+    }
+
+    pub async fn delete_recipe_photo(&self, id: usize) -> anyhow::Result<()> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                match conn.execute(
+                    "DELETE FROM recipe_photos WHERE id = ?1 AND recipe_id IN (SELECT id FROM recipes WHERE household_id = ?2)",
+                    [&id, &household],
+                ) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Delete recipe photo failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Delete recipe photo")
+            .inspect(|_| self.publish(Change::Recipes))
+    }
+
+    pub async fn delete_recipe_photos_by_recipe(&self, recipe_id: usize) -> anyhow::Result<()> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                match conn.execute(
+                    "DELETE FROM recipe_photos WHERE recipe_id = ?1 AND recipe_id IN (SELECT id FROM recipes WHERE household_id = ?2)",
+                    [&recipe_id, &household],
+                ) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Delete recipe photos failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Delete recipe photos by recipe")
+            .inspect(|_| self.publish(Change::Recipes))
+    }
+
+    pub async fn get_next_photo_order(&self, recipe_id: usize) -> anyhow::Result<i32> {
+        self.connection
+            .call(move |conn| {
+                let order: i32 = conn.query_row(
+                    "SELECT COALESCE(MAX(upload_order), -1) + 1 FROM recipe_photos WHERE recipe_id = ?1",
+                    &[&recipe_id],
+                    |row| row.get(0)
+                ).unwrap_or(0);
+                Ok(order)
+            })
+            .await
+            .context("Get next photo order")
+    }
+
+    pub async fn get_recipe_photo_by_id(&self, photo_id: usize) -> anyhow::Result<Option<RecipePhoto>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let result = conn.query_row(
+                    "SELECT id, recipe_id, filename, original_name, file_size, mime_type, upload_order, thumbnail_blob 
+                     FROM recipe_photos WHERE id = ?1 AND recipe_id IN (SELECT id FROM recipes WHERE household_id = ?2)",
+                    [&photo_id, &household],
+                    |row| {
+                        Ok(RecipePhoto {
+                            id: row.get(0)?,
+                            recipe_id: row.get(1)?,
+                            filename: row.get(2)?,
+                            original_name: row.get(3)?,
+                            file_size: row.get(4)?,
+                            mime_type: row.get(5)?,
+                            upload_order: row.get(6)?,
+                            thumbnail_blob: row.get(7)?,
+                        })
+                    },
+                );
+                match result {
+                    Ok(photo) => Ok(Some(photo)),
+                    Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                    Err(err) => Err(err.into()),
+                }
+            })
+            .await
+            .context("Get recipe photo by id")
+    }
+
+    /// Stores a share link for one of the household's lists or recipes
+    pub async fn create_share_link(
+        &self,
+        token: String,
+        target: ShareTarget,
+        permission: SharePermission,
+        expires: Option<i64>,
+    ) -> anyhow::Result<()> {
+        let household = self.household_id;
+        let (list_id, recipe_id) = match target {
+            ShareTarget::List(id) => (Some(id), None),
+            ShareTarget::Recipe(id) => (None, Some(id)),
+        };
+        self.connection
+            .call(move |conn| {
+                let own_target: bool = conn.query_row(
+                    "SELECT EXISTS(SELECT 1 FROM lists WHERE id = ?1 AND household_id = ?3)
+                         OR EXISTS(SELECT 1 FROM recipes WHERE id = ?2 AND household_id = ?3)",
+                    rusqlite::params![&list_id, &recipe_id, &household],
+                    |row| row.get(0),
+                )?;
+                if !own_target {
+                    return Err(rusqlite::Error::QueryReturnedNoRows.into());
+                }
+                match conn.execute(
+                    "INSERT INTO share_links (token, household_id, list_id, recipe_id, permission, expires)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    rusqlite::params![&token, &household, &list_id, &recipe_id, permission.code(), &expires],
+                ) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Create share link failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Create share link")
+    }
+
+    /// The household's share links, expired ones included
+    pub async fn get_share_links(&self) -> anyhow::Result<Vec<ShareLink>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(&format!(
+                    "{} WHERE share_links.household_id = ?1 ORDER BY share_links.created DESC, share_links.rowid DESC",
+                    SHARE_LINK_SELECT
+                ))?;
+                let rows = stmt.query_map([&household], share_link_from_row)?;
+                let mut links = Vec::new();
+                for r in rows {
+                    links.push(r?);
+                }
+                Ok(links)
+            })
+            .await
+            .context("Get share links")
+    }
+
+    /// Unexpired share link for a token, from any household
+    pub async fn get_share_link(&self, token: String, now: i64) -> anyhow::Result<Option<ShareLink>> {
+        self.connection
+            .call(move |conn| {
+                let result = conn.query_row(
+                    &format!(
+                        "{} WHERE share_links.token = ?1 AND (share_links.expires IS NULL OR share_links.expires > ?2)",
+                        SHARE_LINK_SELECT
+                    ),
+                    rusqlite::params![&token, &now],
+                    share_link_from_row,
+                );
+                match result {
+                    Ok(link) => Ok(Some(link)),
+                    Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                    Err(err) => Err(err.into()),
+                }
+            })
+            .await
+            .context("Get share link")
+    }
+
+    pub async fn delete_share_link(&self, token: String) -> anyhow::Result<()> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                match conn.execute(
+                    "DELETE FROM share_links WHERE token = ?1 AND household_id = ?2",
+                    rusqlite::params![&token, &household],
+                ) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Delete share link failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Delete share link")
+    }
+
+    /// Photo stored under the file name, if its recipe belongs to the household
+    pub async fn get_recipe_photo_by_filename(&self, filename: String) -> anyhow::Result<Option<RecipePhoto>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let result = conn.query_row(
+                    "SELECT id, recipe_id, filename, original_name, file_size, mime_type, upload_order, thumbnail_blob
+                     FROM recipe_photos WHERE filename = ?1 AND recipe_id IN (SELECT id FROM recipes WHERE household_id = ?2)",
+                    rusqlite::params![&filename, &household],
+                    |row| {
+                        Ok(RecipePhoto {
+                            id: row.get(0)?,
+                            recipe_id: row.get(1)?,
+                            filename: row.get(2)?,
+                            original_name: row.get(3)?,
+                            file_size: row.get(4)?,
+                            mime_type: row.get(5)?,
+                            upload_order: row.get(6)?,
+                            thumbnail_blob: row.get(7)?,
+                        })
+                    },
+                );
+                match result {
+                    Ok(photo) => Ok(Some(photo)),
+                    Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                    Err(err) => Err(err.into()),
+                }
+            })
+            .await
+            .context("Get recipe photo by filename")
+    }
+
+    pub async fn create_user(&self, username: String, password_hash: String, is_admin: bool) -> anyhow::Result<usize> {
+        self.connection
+            .call(move |conn| {
+                match conn.execute(
+                    "INSERT INTO users (username, password_hash, is_admin) VALUES (?1, ?2, ?3)",
+                    rusqlite::params![&username, &password_hash, &is_admin],
+                ) {
+                    Ok(_) => Ok(conn.last_insert_rowid() as usize),
+                    Err(err) => {
+                        warn!("Create user failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Create user")
+    }
+
+    /// User and password hash for a login attempt, usernames are case insensitive
+    pub async fn get_user_credentials(&self, username: String) -> anyhow::Result<Option<(User, String)>> {
+        self.connection
+            .call(move |conn| {
+                let result = conn.query_row(
+                    "SELECT id, username, is_admin, password_hash FROM users WHERE username = ?1",
+                    [&username],
+                    |row| Ok((user_from_row(row)?, row.get(3)?)),
+                );
+                match result {
+                    Ok(credentials) => Ok(Some(credentials)),
+                    Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                    Err(err) => Err(err.into()),
+                }
+            })
+            .await
+            .context("Get user credentials")
+    }
+
+    /// Stores a new session, clearing out expired ones while at it
+    pub async fn create_session(&self, token: String, user_id: usize, expires: i64, now: i64) -> anyhow::Result<()> {
+        self.connection
+            .call(move |conn| {
+                conn.execute("DELETE FROM sessions WHERE expires <= ?1", [&now])?;
+                match conn.execute(
+                    "INSERT INTO sessions (token, user_id, expires) VALUES (?1, ?2, ?3)",
+                    rusqlite::params![&token, &user_id, &expires],
+                ) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Create session failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Create session")
+    }
+
+    /// User of an unexpired session, with the household the session is working in. That is
+    /// the one picked for the session if the user is still a member, otherwise their first.
+    pub async fn get_session_user(&self, token: String, now: i64) -> anyhow::Result<Option<(User, Option<Household>)>> {
+        self.connection
+            .call(move |conn| {
+                let result = conn.query_row(
+                    "SELECT users.id, users.username, users.is_admin, sessions.household_id FROM sessions
+                     JOIN users ON users.id = sessions.user_id
+                     WHERE sessions.token = ?1 AND sessions.expires > ?2",
+                    rusqlite::params![&token, &now],
+                    |row| Ok((user_from_row(row)?, row.get::<_, Option<usize>>(3)?)),
+                );
+                let (user, picked) = match result {
+                    Ok(found) => found,
+                    Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+                    Err(err) => return Err(err.into()),
+                };
+                let household = conn.query_row(
+                    "SELECT households.id, households.name FROM households
+                     JOIN household_members ON household_members.household_id = households.id
+                     WHERE household_members.user_id = ?1
+                     ORDER BY households.id = ?2 DESC, households.id ASC LIMIT 1",
+                    rusqlite::params![&user.id, &picked.unwrap_or(0)],
+                    household_from_row,
+                );
+                match household {
+                    Ok(household) => Ok(Some((user, Some(household)))),
+                    Err(rusqlite::Error::QueryReturnedNoRows) => Ok(Some((user, None))),
+                    Err(err) => Err(err.into()),
+                }
+            })
+            .await
+            .context("Get session user")
+    }
+
+    pub async fn set_session_household(&self, token: String, household_id: usize) -> anyhow::Result<()> {
+        self.connection
+            .call(move |conn| {
+                match conn.execute(
+                    "UPDATE sessions SET household_id = ?1 WHERE token = ?2",
+                    rusqlite::params![&household_id, &token],
+                ) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Set session household failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Set session household")
+    }
+
+    pub async fn get_user_by_username(&self, username: String) -> anyhow::Result<Option<User>> {
+        Ok(self
+            .get_user_credentials(username)
+            .await?
+            .map(|(user, _)| user))
+    }
+
+    pub async fn create_household(&self, name: String) -> anyhow::Result<usize> {
+        self.connection
+            .call(move |conn| {
+                match conn.execute("INSERT INTO households (name) VALUES (?1)", [&name]) {
+                    Ok(_) => Ok(conn.last_insert_rowid() as usize),
+                    Err(err) => {
+                        warn!("Create household failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Create household")
+    }
+
+    pub async fn add_household_member(&self, household_id: usize, user_id: usize) -> anyhow::Result<()> {
+        self.connection
+            .call(move |conn| {
+                match conn.execute(
+                    "INSERT OR IGNORE INTO household_members (household_id, user_id) VALUES (?1, ?2)",
+                    [&household_id, &user_id],
+                ) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Add household member failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Add household member")
+    }
+
+    /// Invites a user into the current household, unless they are in it already
+    pub async fn invite_household_member(&self, user_id: usize, invited_by: usize) -> anyhow::Result<()> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                match conn.execute(
+                    "INSERT OR IGNORE INTO household_invites (household_id, user_id, invited_by)
+                     SELECT ?1, ?2, ?3
+                     WHERE NOT EXISTS (SELECT 1 FROM household_members WHERE household_id = ?1 AND user_id = ?2)",
+                    [&household, &user_id, &invited_by],
+                ) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Invite household member failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Invite household member")
+    }
+
+    /// Households the user is invited to
+    pub async fn get_household_invites(&self, user_id: usize) -> anyhow::Result<Vec<Household>> {
+        self.connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT households.id, households.name FROM households
+                     JOIN household_invites ON household_invites.household_id = households.id
+                     WHERE household_invites.user_id = ?1
+                     ORDER BY households.name COLLATE NOCASE ASC"
+                )?;
+                let rows = stmt.query_map([&user_id], household_from_row)?;
+                let mut households = Vec::new();
+                for r in rows {
+                    households.push(r?);
+                }
+                Ok(households)
+            })
+            .await
+            .context("Get household invites")
+    }
+
+    /// Users invited into the current household who haven't accepted yet
+    pub async fn get_invited_members(&self) -> anyhow::Result<Vec<User>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT users.id, users.username, users.is_admin FROM users
+                     JOIN household_invites ON household_invites.user_id = users.id
+                     WHERE household_invites.household_id = ?1
+                     ORDER BY users.username COLLATE NOCASE ASC"
+                )?;
+                let rows = stmt.query_map([&household], user_from_row)?;
+                let mut users = Vec::new();
+                for r in rows {
+                    users.push(r?);
+                }
+                Ok(users)
+            })
+            .await
+            .context("Get invited members")
+    }
+
+    /// Accepts or declines the user's invite into a household. Returns whether there was one.
+    pub async fn answer_household_invite(&self, household_id: usize, user_id: usize, accept: bool) -> anyhow::Result<bool> {
+        self.connection
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                let invited = tx.execute(
+                    "DELETE FROM household_invites WHERE household_id = ?1 AND user_id = ?2",
+                    [&household_id, &user_id],
+                )? > 0;
+                if invited && accept {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO household_members (household_id, user_id) VALUES (?1, ?2)",
+                        [&household_id, &user_id],
+                    )?;
+                }
+                tx.commit()?;
+                Ok(invited)
+            })
+            .await
+            .context("Answer household invite")
+    }
+
+    /// Every household on the server
+    pub async fn get_households(&self) -> anyhow::Result<Vec<Household>> {
+        self.connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare("SELECT id, name FROM households ORDER BY id ASC")?;
+                let rows = stmt.query_map([], household_from_row)?;
+                let mut households = Vec::new();
+                for r in rows {
+                    households.push(r?);
+                }
+                Ok(households)
+            })
+            .await
+            .context("Get households")
+    }
+
+    /// Households the user is a member of
+    pub async fn get_user_households(&self, user_id: usize) -> anyhow::Result<Vec<Household>> {
+        self.connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT households.id, households.name FROM households
+                     JOIN household_members ON household_members.household_id = households.id
+                     WHERE household_members.user_id = ?1
+                     ORDER BY households.name COLLATE NOCASE ASC"
+                )?;
+                let rows = stmt.query_map([&user_id], household_from_row)?;
+                let mut households = Vec::new();
+                for r in rows {
+                    households.push(r?);
+                }
+                Ok(households)
+            })
+            .await
+            .context("Get user households")
+    }
+
+    /// Members of the current household
+    pub async fn get_household_members(&self) -> anyhow::Result<Vec<User>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT users.id, users.username, users.is_admin FROM users
+                     JOIN household_members ON household_members.user_id = users.id
+                     WHERE household_members.household_id = ?1
+                     ORDER BY users.username COLLATE NOCASE ASC"
+                )?;
+                let rows = stmt.query_map([&household], user_from_row)?;
+                let mut users = Vec::new();
+                for r in rows {
+                    users.push(r?);
+                }
+                Ok(users)
+            })
+            .await
+            .context("Get household members")
+    }
+
+    /// List whose checked off tasks move into the household's pantry
+    pub async fn get_pantry_shopping_list(&self) -> anyhow::Result<Option<usize>> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                let result = conn.query_row(
+                    "SELECT shopping_list_id FROM households WHERE id = ?1",
+                    [&household],
+                    |row| row.get(0),
+                );
+                match result {
+                    Ok(list_id) => Ok(list_id),
+                    Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                    Err(err) => Err(err.into()),
+                }
+            })
+            .await
+            .context("Get pantry shopping list")
+    }
+
+    pub async fn set_pantry_shopping_list(&self, list_id: Option<usize>) -> anyhow::Result<()> {
+        let household = self.household_id;
+        self.connection
+            .call(move |conn| {
+                // Only the household's own lists can be picked
+                match conn.execute(
+                    "UPDATE households
+                     SET shopping_list_id = (SELECT id FROM lists WHERE id = ?1 AND household_id = ?2)
+                     WHERE id = ?2",
+                    rusqlite::params![&list_id, &household],
+                ) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Set pantry shopping list failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Set pantry shopping list")
+            .inspect(|_| self.publish(Change::Pantry))
+    }
+
+    pub async fn delete_session(&self, token: String) -> anyhow::Result<()> {
+        self.connection
+            .call(move |conn| {
+                match conn.execute("DELETE FROM sessions WHERE token = ?1", [&token]) {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        warn!("Delete session failed: {}", err);
+                        Err(err.into())
+                    }
+                }
+            })
+            .await
+            .context("Delete session")
+    }
+
+    /// Takes an idempotency key for `request`, unless it was taken before. Forgets keys
+    /// older than `KEY_SECONDS` while at it.
+    pub async fn claim_idempotency_key(&self, key: IdempotencyKey, request: String, now: i64) -> anyhow::Result<KeyClaim> {
+        self.connection
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                tx.execute("DELETE FROM idempotency_keys WHERE created <= ?1", [&(now - KEY_SECONDS)])?;
+                let inserted = tx.execute(
+                    "INSERT OR IGNORE INTO idempotency_keys (owner, household_id, key, request, created)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    rusqlite::params![&key.owner, &key.household_id, &key.key, &request, &now],
+                )?;
+                let claim = if inserted > 0 {
+                    KeyClaim::New
+                } else {
+                    let (first_request, status, content_type, body): (String, Option<u16>, Option<String>, Option<Vec<u8>>) =
+                        tx.query_row(
+                            "SELECT request, status, content_type, body FROM idempotency_keys
+                             WHERE owner = ?1 AND household_id = ?2 AND key = ?3",
+                            rusqlite::params![&key.owner, &key.household_id, &key.key],
+                            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+                        )?;
+                    match status {
+                        _ if first_request != request => KeyClaim::OtherRequest,
+                        Some(status) => KeyClaim::Done(StoredResponse { status, content_type, body: body.unwrap_or_default() }),
+                        None => KeyClaim::Running,
+                    }
+                };
+                tx.commit()?;
+                Ok(claim)
+            })
+            .await
+            .context("Claim idempotency key")
+    }
+
+    /// Keeps the response to the request that claimed `key`
+    pub async fn store_idempotent_response(&self, key: IdempotencyKey, response: StoredResponse) -> anyhow::Result<()> {
+        self.connection
+            .call(move |conn| {
+                conn.execute(
+                    "UPDATE idempotency_keys SET status = ?1, content_type = ?2, body = ?3
+                     WHERE owner = ?4 AND household_id = ?5 AND key = ?6",
+                    rusqlite::params![&response.status, &response.content_type, &response.body, &key.owner, &key.household_id, &key.key],
+                )?;
+                Ok(())
+            })
+            .await
+            .context("Store idempotent response")
+    }
+
+    /// Forgets a key whose request failed, so it can be tried again
+    pub async fn release_idempotency_key(&self, key: IdempotencyKey) -> anyhow::Result<()> {
+        self.connection
+            .call(move |conn| {
+                conn.execute(
+                    "DELETE FROM idempotency_keys WHERE owner = ?1 AND household_id = ?2 AND key = ?3",
+                    rusqlite::params![&key.owner, &key.household_id, &key.key],
+                )?;
+                Ok(())
+            })
+            .await
+            .context("Release idempotency key")
+    }
+
+    /// Everything [`crate::export::Export`] holds, for all households
+    pub async fn export_all(&self) -> anyhow::Result<Export> {
+        self.connection
+            .call(|conn| {
+                let mut stmt = conn.prepare("SELECT id, name, shopping_list_id FROM households ORDER BY id")?;
+                let households: Vec<(usize, String, Option<usize>)> = stmt
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                    .collect::<Result<_, _>>()?;
+                let mut exported = Vec::new();
+                for (household_id, name, shopping_list_id) in households {
+                    exported.push(HouseholdExport {
+                        name,
+                        shopping_list_id,
+                        lists: export_lists(conn, household_id)?,
+                        recipes: export_recipes(conn, household_id)?,
+                        meal_plan: export_meal_plan(conn, household_id)?,
+                        notes: export_notes(conn, household_id)?,
+                    });
+                }
+                Ok(Export { format: crate::export::FORMAT.to_string(), version: crate::export::FORMAT_VERSION, households: exported })
+            })
+            .await
+            .context("Export")
+    }
+
+    /// Adds an export in one transaction, mapping its IDs to new ones
+    pub async fn import_all(&self, export: Export) -> anyhow::Result<ImportSummary> {
+        self.connection
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                let mut summary = ImportSummary::default();
+                for household in export.households {
+                    import_household(&tx, household, &mut summary)?;
+                }
+                tx.commit()?;
+                Ok(summary)
+            })
+            .await
+            .context("Import")
+    }
+}
+
+fn export_lists(conn: &rusqlite::Connection, household_id: usize) -> rusqlite::Result<Vec<ListExport>> {
+    let mut stmt = conn.prepare("SELECT id, name FROM lists WHERE household_id = ?1 ORDER BY id")?;
+    let lists: Vec<(usize, String)> = stmt.query_map([household_id], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<Result<_, _>>()?;
+    // Tasks archived into a shopping trip stay behind with the trip
+    let mut tasks = conn.prepare(
+        "SELECT task, completed, position, completed_at, price, priority, tags FROM tasks
+         WHERE list_id = ?1 AND trip_id IS NULL ORDER BY position, id",
+    )?;
+    let mut exported = Vec::new();
+    for (id, name) in lists {
+        let tasks = tasks
+            .query_map([id], |row| {
+                Ok(TaskExport {
+                    text: row.get(0)?,
+                    completed: row.get(1)?,
+                    position: row.get(2)?,
+                    completed_at: row.get(3)?,
+                    price: row.get(4)?,
+                    priority: row.get(5)?,
+                    tags: tags_from_column(row.get(6)?),
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        exported.push(ListExport { id, name, tasks });
+    }
+    Ok(exported)
+}
+
+fn export_recipes(conn: &rusqlite::Connection, household_id: usize) -> rusqlite::Result<Vec<RecipeExport>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, instructions, ingredients, favourite, rating, tags, servings
+         FROM recipes WHERE household_id = ?1 ORDER BY id",
+    )?;
+    let mut photos = conn.prepare(
+        "SELECT filename, original_name, file_size, mime_type, upload_order
+         FROM recipe_photos WHERE recipe_id = ?1 ORDER BY upload_order, id",
+    )?;
+    let mut recipes: Vec<RecipeExport> = stmt
+        .query_map([household_id], |row| {
+            Ok(RecipeExport {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                instructions: row.get(2)?,
+                ingredients: row.get(3)?,
+                favourite: row.get(4)?,
+                rating: row.get(5)?,
+                tags: row.get(6)?,
+                servings: row.get(7)?,
+                photos: Vec::new(),
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+    for recipe in &mut recipes {
+        recipe.photos = photos
+            .query_map([recipe.id], |row| {
+                Ok(PhotoExport {
+                    filename: row.get(0)?,
+                    original_name: row.get(1)?,
+                    file_size: row.get(2)?,
+                    mime_type: row.get(3)?,
+                    upload_order: row.get(4)?,
+                    thumbnail: None,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+    }
+    Ok(recipes)
+}
+
+fn export_meal_plan(conn: &rusqlite::Connection, household_id: usize) -> rusqlite::Result<Vec<MealExport>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, date, meal_text, recipe_id, leftovers_of, servings
+         FROM meal_plan WHERE household_id = ?1 ORDER BY date, id",
+    )?;
+    let meals = stmt
+        .query_map([household_id], |row| {
+            Ok(MealExport {
+                id: row.get(0)?,
+                date: row.get(1)?,
+                meal_text: row.get(2)?,
+                recipe_id: row.get(3)?,
+                leftovers_of: row.get(4)?,
+                servings: row.get(5)?,
+            })
+        })?
+        .collect();
+    meals
+}
+
+fn export_notes(conn: &rusqlite::Connection, household_id: usize) -> rusqlite::Result<Vec<DayNoteExport>> {
+    let mut stmt = conn.prepare("SELECT date, note FROM meal_plan_notes WHERE household_id = ?1 ORDER BY date")?;
+    let notes = stmt
+        .query_map([household_id], |row| Ok(DayNoteExport { date: row.get(0)?, note: row.get(1)? }))?
+        .collect();
+    notes
+}
+
+/// Adds one household's part of an export, into the household with the same name if there
+/// is one. References between the records are followed to their new IDs; ones to records
+/// that aren't in the export are dropped.
+fn import_household(tx: &rusqlite::Transaction, household: HouseholdExport, summary: &mut ImportSummary) -> rusqlite::Result<()> {
+    let existing = tx.query_row(
+        "SELECT id FROM households WHERE name = ?1 ORDER BY id LIMIT 1",
+        [&household.name],
+        |row| row.get(0),
+    );
+    let household_id = match existing {
+        Ok(id) => id,
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            tx.execute("INSERT INTO households (name) VALUES (?1)", [&household.name])?;
+            summary.households += 1;
+            tx.last_insert_rowid() as usize
+        }
+        Err(err) => return Err(err),
+    };
+
+    let mut lists = std::collections::HashMap::new();
+    for list in household.lists {
+        tx.execute("INSERT INTO lists (name, household_id) VALUES (?1, ?2)", rusqlite::params![&list.name, &household_id])?;
+        let list_id = tx.last_insert_rowid() as usize;
+        lists.insert(list.id, list_id);
+        summary.lists += 1;
+        for task in list.tasks {
+            tx.execute(
+                "INSERT INTO tasks (task, completed, position, completed_at, price, priority, tags, list_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                rusqlite::params![&task.text, &task.completed, &task.position, &task.completed_at, &task.price, &task.priority, &task.tags.join(" "), &list_id],
+            )?;
+            summary.tasks += 1;
+        }
+    }
+    if let Some(&list_id) = household.shopping_list_id.and_then(|id| lists.get(&id)) {
+        tx.execute(
+            "UPDATE households SET shopping_list_id = ?1 WHERE id = ?2 AND shopping_list_id IS NULL",
+            [&list_id, &household_id],
+        )?;
+    }
+
+    let mut recipes = std::collections::HashMap::new();
+    for recipe in household.recipes {
+        tx.execute(
+            "INSERT INTO recipes (title, instructions, ingredients, favourite, rating, tags, servings, household_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                &recipe.title, &recipe.instructions, &recipe.ingredients, &recipe.favourite,
+                &recipe.rating, &recipe.tags, &recipe.servings, &household_id
+            ],
+        )?;
+        let recipe_id = tx.last_insert_rowid() as usize;
+        recipes.insert(recipe.id, recipe_id);
+        summary.recipes += 1;
+        for photo in recipe.photos {
+            tx.execute(
+                "INSERT INTO recipe_photos (recipe_id, filename, original_name, file_size, mime_type, upload_order, thumbnail_blob)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                rusqlite::params![
+                    &recipe_id, &photo.filename, &photo.original_name, &photo.file_size,
+                    &photo.mime_type, &photo.upload_order, &photo.thumbnail
+                ],
+            )?;
+            summary.photos += 1;
+        }
+    }
+
+    // Leftovers can point at any other meal, so they are linked once all are in
+    let mut meals = std::collections::HashMap::new();
+    for meal in &household.meal_plan {
+        let recipe_id = meal.recipe_id.and_then(|id| recipes.get(&id));
+        tx.execute(
+            "INSERT INTO meal_plan (date, meal_text, recipe_id, servings, household_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![&meal.date, &meal.meal_text, &recipe_id, &meal.servings, &household_id],
+        )?;
+        meals.insert(meal.id, tx.last_insert_rowid() as usize);
+        summary.meals += 1;
+    }
+    for meal in &household.meal_plan {
+        if let (Some(&id), Some(&leftovers_of)) = (meals.get(&meal.id), meal.leftovers_of.and_then(|id| meals.get(&id))) {
+            tx.execute("UPDATE meal_plan SET leftovers_of = ?1 WHERE id = ?2", [&leftovers_of, &id])?;
+        }
+    }
+
+    // A day's note already there is kept
+    for note in household.notes {
+        tx.execute(
+            "INSERT OR IGNORE INTO meal_plan_notes (household_id, date, note) VALUES (?1, ?2, ?3)",
+            rusqlite::params![&household_id, &note.date, &note.note],
+        )?;
+    }
+    Ok(())
+}
+
+const SHARE_LINK_SELECT: &str = "SELECT share_links.token, share_links.household_id, share_links.list_id,
+        share_links.recipe_id, COALESCE(lists.name, recipes.title), share_links.permission, share_links.expires
+    FROM share_links
+    LEFT JOIN lists ON lists.id = share_links.list_id
+    LEFT JOIN recipes ON recipes.id = share_links.recipe_id";
+
+/// Tags as kept in the space separated tags column of tasks
+fn tags_from_column(tags: String) -> Vec<String> {
+    tags.split_whitespace().map(str::to_string).collect()
+}
+
+fn share_link_from_row(row: &rusqlite::Row) -> rusqlite::Result<ShareLink> {
+    let target = match (row.get(2)?, row.get(3)?) {
+        (Some(list_id), _) => ShareTarget::List(list_id),
+        (None, Some(recipe_id)) => ShareTarget::Recipe(recipe_id),
+        (None, None) => return Err(rusqlite::Error::InvalidColumnType(2, "list_id".to_string(), rusqlite::types::Type::Null)),
+    };
+    let permission: String = row.get(5)?;
+    Ok(ShareLink {
+        token: row.get(0)?,
+        household_id: row.get(1)?,
+        target,
+        title: row.get(4)?,
+        permission: SharePermission::from_code(&permission).unwrap_or(SharePermission::View),
+        expires: row.get(6)?,
+    })
+}
+
+fn household_from_row(row: &rusqlite::Row) -> rusqlite::Result<Household> {
+    Ok(Household {
+        id: row.get(0)?,
+        name: row.get(1)?,
+    })
+}
+
+fn user_from_row(row: &rusqlite::Row) -> rusqlite::Result<User> {
+    Ok(User {
+        id: row.get(0)?,
+        username: row.get(1)?,
+        is_admin: row.get(2)?,
+    })
+}
+
+fn recipe_from_row(row: &rusqlite::Row) -> rusqlite::Result<Recipe> {
+    Ok(Recipe {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        instructions: row.get(2)?,
+        ingredients: row.get(3)?,
+        favourite: row.get(4)?,
+        rating: row.get(5)?,
+        tags: row.get(6)?,
+        servings: row.get(7)?,
+        version: row.get(8)?,
+    })
+}
+
+fn meal_plan_entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<MealPlanEntry> {
+    Ok(MealPlanEntry {
+        id: row.get(0)?,
+        date: row.get(1)?,
+        meal_text: row.get(2)?,
+        recipe_id: row.get(3)?,
+        leftovers_of: row.get(4)?,
+        servings: row.get(5)?,
+    })
+}
+
+fn pantry_item_from_row(row: &rusqlite::Row) -> rusqlite::Result<PantryItem> {
+    Ok(PantryItem {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        quantity: row.get(2)?,
+        unit: row.get(3)?,
+        expires: row.get(4)?,
+    })
 }
@@ -0,0 +1,119 @@
+use crate::households::HouseholdState;
+use crate::template::{ShopItemTemplate, ShopRow, TaskTemplate};
+use crate::todo::{ShareLink, Task};
+use crate::AppState;
+use askama::Template;
+use axum::extract::Query;
+use axum::http::StatusCode;
+use axum::response::sse::{Event as SseEvent, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+
+/// How many events a slow subscriber can fall behind before it misses some
+pub const EVENT_BUFFER: usize = 256;
+
+/// What a `Database` call changed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Change {
+    Task { list_id: usize, task_id: usize }, // Created, updated or deleted
+    Tasks { list_id: usize },                // Several at once, like a reorder
+    Lists,
+    Recipes,
+    MealPlan,
+    Pantry,
+    Stores, // Stores, their aisle order and the categories of items
+}
+
+/// A change in a household, sent to everyone subscribed to the database
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Event {
+    pub household_id: usize,
+    pub change: Change,
+}
+
+/// How the page listening for changes shows a list
+#[derive(Clone)]
+pub enum View {
+    Index,
+    Shop(Option<ShareLink>),
+}
+
+impl View {
+    pub(crate) async fn render(&self, state: &AppState, task: Task) -> askama::Result<String> {
+        match self {
+            View::Index => TaskTemplate { task }.render(),
+            View::Shop(share) => {
+                let prices = state.db.get_last_prices().await.unwrap_or_default();
+                ShopItemTemplate { row: ShopRow::new(task, &prices), share: share.clone() }.render()
+            }
+        }
+    }
+
+    /// Removes the task's row from wherever it is on the page
+    fn remove(&self, task_id: usize) -> String {
+        let tag = match self {
+            View::Index => "tr",
+            View::Shop(_) => "div",
+        };
+        format!(r#"<{tag} id="task-{task_id}" hx-swap-oob="delete"></{tag}>"#)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct EventsQuery {
+    list_id: usize,
+    view: Option<String>, // "shop" sends shopping mode items instead of table rows
+}
+
+/// Server-Sent Events for the changes to a list, for htmx's SSE extension. A `task`
+/// event moves the task's row into place, or removes it; a `list` event asks the
+/// page to reload the list.
+pub async fn events(HouseholdState(state): HouseholdState, Query(query): Query<EventsQuery>) -> Response {
+    if state.db.get_list(query.list_id).await.is_err() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let view = if query.view.as_deref() == Some("shop") { View::Shop(None) } else { View::Index };
+    list_events(state, query.list_id, view)
+}
+
+/// Stream of the list's changes, as seen in `view`
+pub fn list_events(state: AppState, list_id: usize, view: View) -> Response {
+    let receiver = state.db.subscribe();
+    let stream = futures::stream::unfold((receiver, state, view), move |(mut receiver, state, view)| async move {
+        loop {
+            let message = match receiver.recv().await {
+                Ok(event) => message(&state, list_id, &view, event).await,
+                // Missed some changes, so start over from the current list
+                Err(RecvError::Lagged(_)) => Some(SseEvent::default().event("list").data("")),
+                Err(RecvError::Closed) => return None,
+            };
+            if let Some(message) = message {
+                return Some((Ok::<_, Infallible>(message), (receiver, state, view)));
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
+
+async fn message(state: &AppState, list_id: usize, view: &View, event: Event) -> Option<SseEvent> {
+    if event.household_id != state.db.household_id() {
+        return None;
+    }
+    match event.change {
+        Change::Task { list_id: changed, task_id } if changed == list_id => {
+            let mut html = view.remove(task_id);
+            if let Ok(task) = state.db.get_task(task_id).await {
+                match view.render(state, task).await {
+                    Ok(row) => html.push_str(&row),
+                    Err(err) => warn!("Failed to render task {}: {}", task_id, err),
+                }
+            }
+            Some(SseEvent::default().event("task").data(html))
+        }
+        Change::Tasks { list_id: changed } if changed == list_id => Some(SseEvent::default().event("list").data("")),
+        _ => None,
+    }
+}
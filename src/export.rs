@@ -0,0 +1,174 @@
+use crate::database::Database;
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Written into every export, to tell them apart from other JSON
+pub const FORMAT: &str = "htmx-rs-todo";
+
+/// Version of the export format. Imports of newer versions are refused.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Name of the JSON file in an export directory, next to a photos directory
+pub const EXPORT_FILE: &str = "export.json";
+
+/// Lists, recipes and meal plans of every household, for moving them to another server.
+/// The IDs are the exporting server's and only link the records to each other; importing
+/// gives everything new IDs. Users, shares and shopping history aren't included.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Export {
+    pub format: String,
+    pub version: u32,
+    pub households: Vec<HouseholdExport>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HouseholdExport {
+    pub name: String,
+    pub shopping_list_id: Option<usize>, // List whose checked off tasks move into the pantry
+    pub lists: Vec<ListExport>,
+    pub recipes: Vec<RecipeExport>,
+    pub meal_plan: Vec<MealExport>,
+    pub notes: Vec<DayNoteExport>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListExport {
+    pub id: usize,
+    pub name: String,
+    pub tasks: Vec<TaskExport>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TaskExport {
+    pub text: String,
+    pub completed: bool,
+    pub position: i32,
+    pub completed_at: Option<i64>,
+    pub price: Option<f64>,
+    #[serde(default)]
+    pub priority: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecipeExport {
+    pub id: usize,
+    pub title: String,
+    pub instructions: String,
+    pub ingredients: String,
+    pub favourite: bool,
+    pub rating: u8,
+    pub tags: String,
+    pub servings: Option<u32>,
+    pub photos: Vec<PhotoExport>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PhotoExport {
+    pub filename: String, // In the photos directory next to the JSON
+    pub original_name: String,
+    pub file_size: i64,
+    pub mime_type: String,
+    pub upload_order: i32,
+    #[serde(skip)]
+    pub thumbnail: Option<Vec<u8>>, // Made again on import
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MealExport {
+    pub id: usize,
+    pub date: String, // YYYY-MM-DD
+    pub meal_text: String,
+    pub recipe_id: Option<usize>,
+    pub leftovers_of: Option<usize>,
+    pub servings: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DayNoteExport {
+    pub date: String, // YYYY-MM-DD
+    pub note: String,
+}
+
+/// How much an import added
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    pub households: usize, // Created, the others were merged into ones with the same name
+    pub lists: usize,
+    pub tasks: usize,
+    pub recipes: usize,
+    pub photos: usize,
+    pub meals: usize,
+}
+
+/// Writes the export JSON and copies of the recipe photos into `out_dir`
+pub async fn export(db: &Database, photos_dir: &Path, out_dir: &Path) -> anyhow::Result<Export> {
+    let export = db.export_all().await?;
+    let out_photos = out_dir.join(crate::backup::PHOTOS_DIR);
+    std::fs::create_dir_all(&out_photos).with_context(|| format!("Create {:?}", out_photos))?;
+    for photo in export.households.iter().flat_map(|household| &household.recipes).flat_map(|recipe| &recipe.photos) {
+        let from = photos_dir.join(&photo.filename);
+        if let Err(err) = std::fs::copy(&from, out_photos.join(&photo.filename)) {
+            warn!("Photo {:?} left out of export: {}", from, err);
+        }
+    }
+    let json = serde_json::to_vec_pretty(&export).context("Serialise export")?;
+    std::fs::write(out_dir.join(EXPORT_FILE), json).context("Write export")?;
+    Ok(export)
+}
+
+/// Adds an export from `dir` to the database. Households are merged into ones with the same
+/// name; everything else is added under new IDs, so nothing already there is overwritten.
+pub async fn import(db: &Database, photos_dir: &Path, dir: &Path) -> anyhow::Result<ImportSummary> {
+    let json = std::fs::read(dir.join(EXPORT_FILE)).with_context(|| format!("Read {:?}", dir.join(EXPORT_FILE)))?;
+    let mut export: Export = serde_json::from_slice(&json).context("Parse export")?;
+    if export.format != FORMAT {
+        bail!("Not an export of this app");
+    }
+    if export.version > FORMAT_VERSION {
+        bail!("Export is format version {}, this server reads up to {}", export.version, FORMAT_VERSION);
+    }
+    for household in &export.households {
+        let dates = household.meal_plan.iter().map(|meal| &meal.date).chain(household.notes.iter().map(|note| &note.date));
+        for date in dates {
+            if chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+                bail!("Meal plan of {} has a date {:?} that isn't YYYY-MM-DD", household.name, date);
+            }
+        }
+    }
+
+    // Photos get new names too, so they can't replace one already there
+    let mut copied = Vec::new();
+    for recipe in export.households.iter_mut().flat_map(|household| &mut household.recipes) {
+        let photos = std::mem::take(&mut recipe.photos);
+        for mut photo in photos {
+            let from = dir.join(crate::backup::PHOTOS_DIR).join(&photo.filename);
+            let data = match std::fs::read(&from) {
+                Ok(data) if from.file_name() == Some(photo.filename.as_ref()) => data,
+                _ => {
+                    warn!("Photo {:?} is missing from the export, skipping it", photo.filename);
+                    continue;
+                }
+            };
+            let extension = Path::new(&photo.filename).extension().and_then(|ext| ext.to_str()).unwrap_or("jpg");
+            photo.filename = format!("{}.{}", Uuid::new_v4(), extension);
+            photo.thumbnail = crate::generate_thumbnail(&data, 200).ok();
+            let to = photos_dir.join(&photo.filename);
+            std::fs::write(&to, data).with_context(|| format!("Write {:?}", to))?;
+            copied.push(to);
+            recipe.photos.push(photo);
+        }
+    }
+
+    let imported = db.import_all(export).await;
+    if imported.is_err() {
+        for path in copied {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    imported
+}
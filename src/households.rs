@@ -0,0 +1,190 @@
+use crate::api::ApiError;
+use crate::auth;
+use crate::template::{HouseholdsTemplate, HtmlTemplate};
+use crate::todo::{AddMemberForm, Household, HouseholdForm, InviteAnswerForm, SwitchHouseholdForm, User};
+use crate::AppState;
+use axum::extract::{Extension, FromRequestParts, OriginalUri, State};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::Form;
+use tracing::{info, warn};
+
+/// App state with the database scoped to the household the signed in user is working in.
+/// Handlers reading or changing household data take this instead of `State<AppState>`.
+pub struct HouseholdState(pub AppState);
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for HouseholdState {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<Household>() {
+            Some(household) => Ok(HouseholdState(AppState {
+                db: state.db.for_household(household.id),
+                ..state.clone()
+            })),
+            None => Err(no_household(parts)),
+        }
+    }
+}
+
+/// Sends users who are not in any household to the page for creating one
+fn no_household(parts: &Parts) -> Response {
+    // Routers nested under /api/v1 see a shortened path
+    let path = match parts.extensions.get::<OriginalUri>() {
+        Some(OriginalUri(uri)) => uri.path(),
+        None => parts.uri.path(),
+    };
+    if path.starts_with("/api/") {
+        return ApiError::forbidden("Not a member of any household").into_response();
+    }
+    if parts.headers.contains_key("HX-Request") {
+        return (StatusCode::FORBIDDEN, [("HX-Redirect", "/households")]).into_response();
+    }
+    Redirect::to("/households").into_response()
+}
+
+async fn render_page(
+    state: &AppState,
+    user: &User,
+    household: Option<&Household>,
+    error: Option<String>,
+) -> HouseholdsTemplate {
+    let (members, invited) = match household {
+        Some(household) => {
+            let db = state.db.for_household(household.id);
+            (db.get_household_members().await.unwrap_or_default(), db.get_invited_members().await.unwrap_or_default())
+        }
+        None => (vec![], vec![]),
+    };
+    HouseholdsTemplate {
+        households: state.db.get_user_households(user.id).await.unwrap_or_default(),
+        invites: state.db.get_household_invites(user.id).await.unwrap_or_default(),
+        current_id: household.map(|household| household.id),
+        members,
+        invited,
+        error,
+    }
+}
+
+pub async fn households_page(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    household: Option<Extension<Household>>,
+) -> impl IntoResponse {
+    let household = household.map(|Extension(household)| household);
+    HtmlTemplate(render_page(&state, &user, household.as_ref(), None).await).into_response()
+}
+
+/// Creates a household with the user in it and switches to it
+pub async fn create_household(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    headers: HeaderMap,
+    form: Form<HouseholdForm>,
+) -> impl IntoResponse {
+    let name = form.name.trim().to_string();
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Name is required").into_response();
+    }
+
+    let created = async {
+        let household_id = state.db.create_household(name).await?;
+        state.db.add_household_member(household_id, user.id).await?;
+        Ok::<_, anyhow::Error>(household_id)
+    };
+    let household_id = match created.await {
+        Ok(household_id) => household_id,
+        Err(err) => {
+            warn!("Failed to create household: {}", err);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    info!("User {} created household {}", user.username, household_id);
+    switch_session(&state, &headers, household_id).await
+}
+
+pub async fn switch_household(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    headers: HeaderMap,
+    form: Form<SwitchHouseholdForm>,
+) -> impl IntoResponse {
+    let households = state.db.get_user_households(user.id).await.unwrap_or_default();
+    if !households.iter().any(|household| household.id == form.household_id) {
+        return (StatusCode::FORBIDDEN, "Not a member of that household").into_response();
+    }
+    switch_session(&state, &headers, form.household_id).await
+}
+
+async fn switch_session(state: &AppState, headers: &HeaderMap, household_id: usize) -> Response {
+    let Some(token) = auth::session_token(headers) else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    match state.db.set_session_household(token, household_id).await {
+        Ok(_) => Redirect::to("/").into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// Invites an existing user into the current household. They join once they accept.
+pub async fn invite_member(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    household: Option<Extension<Household>>,
+    form: Form<AddMemberForm>,
+) -> impl IntoResponse {
+    let Some(Extension(household)) = household else {
+        return Redirect::to("/households").into_response();
+    };
+
+    match state.db.get_user_by_username(form.username.trim().to_string()).await {
+        Ok(Some(member)) => match state.db.for_household(household.id).invite_household_member(member.id, user.id).await {
+            Ok(_) => {
+                info!("User {} invited {} to household {}", user.username, member.username, household.id);
+                Redirect::to("/households").into_response()
+            }
+            Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        },
+        Ok(None) => {
+            let error = format!("There is no user called {}", form.username.trim());
+            let template = render_page(&state, &user, Some(&household), Some(error)).await;
+            (StatusCode::NOT_FOUND, HtmlTemplate(template)).into_response()
+        }
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// Joins a household the user was invited to
+pub async fn accept_invite(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    form: Form<InviteAnswerForm>,
+) -> impl IntoResponse {
+    answer_invite(&state, &user, form.household_id, true).await
+}
+
+/// Turns down an invite into a household
+pub async fn decline_invite(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    form: Form<InviteAnswerForm>,
+) -> impl IntoResponse {
+    answer_invite(&state, &user, form.household_id, false).await
+}
+
+async fn answer_invite(state: &AppState, user: &User, household_id: usize, accept: bool) -> Response {
+    match state.db.answer_household_invite(household_id, user.id, accept).await {
+        Ok(true) => {
+            let answer = if accept { "joined" } else { "declined to join" };
+            info!("User {} {} household {}", user.username, answer, household_id);
+            Redirect::to("/households").into_response()
+        }
+        Ok(false) => (StatusCode::NOT_FOUND, "No invite to that household").into_response(),
+        Err(err) => {
+            warn!("Failed to answer invite to household {}: {}", household_id, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
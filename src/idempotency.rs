@@ -0,0 +1,151 @@
+use crate::todo::{Household, User};
+use crate::AppState;
+use axum::body::{self, Body};
+use axum::extract::{Request, State};
+use axum::http::{header, request::Parts, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use blake2::{Blake2s256, Digest};
+use tracing::warn;
+
+/// Header a client sends a unique key in, so that sending the request again doesn't repeat it
+pub const IDEMPOTENCY_HEADER: &str = "Idempotency-Key";
+
+/// Header set on a response that is a copy of the one sent for the first request with the key
+pub const REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
+/// How long a key is remembered, longer than a phone is likely to be offline for
+pub const KEY_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+/// Longest key accepted, a UUID is 36 characters
+const MAX_KEY_LENGTH: usize = 255;
+
+/// A key as sent by one user, or over one share link, in one household. Nobody else's
+/// requests with the same key see its response.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IdempotencyKey {
+    pub owner: String, // "user <id>" or "share <token>"
+    pub household_id: usize,
+    pub key: String,
+}
+
+/// A response kept to answer repeats of the request with
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// What is known about a key when a request with it comes in
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeyClaim {
+    New,                  // First time, the request should run
+    Running,              // The first request with it hasn't finished yet
+    Done(StoredResponse), // Already answered
+    OtherRequest,         // Used for a different method or path
+}
+
+impl IntoResponse for StoredResponse {
+    fn into_response(self) -> Response {
+        let mut response = Response::new(Body::from(self.body));
+        *response.status_mut() = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+        if let Some(content_type) = self.content_type.and_then(|value| HeaderValue::from_str(&value).ok()) {
+            response.headers_mut().insert(header::CONTENT_TYPE, content_type);
+        }
+        response.headers_mut().insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+        response
+    }
+}
+
+/// Who sent a request, and in which household. Share links stand in for the guests using
+/// them. `None` for links that don't exist, which the handler turns away.
+async fn key_owner(state: &AppState, request: &Parts) -> Option<(String, usize)> {
+    if let Some(rest) = request.uri.path().strip_prefix("/s/") {
+        let token = rest.split('/').next().unwrap_or_default().to_string();
+        let link = state.db.get_share_link(token.clone(), chrono::Utc::now().timestamp()).await.ok()??;
+        return Some((format!("share {}", token), link.household_id));
+    }
+    let user = request.extensions.get::<User>()?;
+    let household = request.extensions.get::<Household>().map_or(0, |household| household.id);
+    Some((format!("user {}", user.id), household))
+}
+
+/// Method, path and a hash of the body, so a key sent again with something else is noticed
+fn fingerprint(request: &Parts, body: &[u8]) -> String {
+    let hash: String = Blake2s256::digest(body).iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{} {} {}", request.method, request.uri.path(), hash)
+}
+
+/// Middleware for routes a client may send again without knowing if the first one arrived,
+/// like the offline queue of the service worker does. Requests with an `Idempotency-Key`
+/// run once; repeats get the first response back. Failed requests aren't kept, so they can
+/// be tried again.
+pub async fn idempotent(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(key) = request.headers().get(IDEMPOTENCY_HEADER) else {
+        return next.run(request).await;
+    };
+    let key = match key.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => key.to_string(),
+        _ => return (StatusCode::BAD_REQUEST, "Invalid Idempotency-Key").into_response(),
+    };
+    let (parts, body) = request.into_parts();
+    let Some((owner, household_id)) = key_owner(&state, &parts).await else {
+        return next.run(Request::from_parts(parts, body)).await;
+    };
+    let key = IdempotencyKey { owner, household_id, key };
+
+    let body = match body::to_bytes(body, crate::MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(_) => return StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+    };
+    let what = fingerprint(&parts, &body);
+    let request = Request::from_parts(parts, Body::from(body));
+
+    let now = chrono::Utc::now().timestamp();
+    match state.db.claim_idempotency_key(key.clone(), what, now).await {
+        Ok(KeyClaim::New) => {}
+        Ok(KeyClaim::Done(stored)) => return stored.into_response(),
+        Ok(KeyClaim::Running) => {
+            return (StatusCode::CONFLICT, "A request with this Idempotency-Key is still running").into_response()
+        }
+        Ok(KeyClaim::OtherRequest) => {
+            return (StatusCode::UNPROCESSABLE_ENTITY, "Idempotency-Key was used for another request").into_response()
+        }
+        Err(err) => {
+            warn!("Failed to claim idempotency key: {}", err);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    }
+
+    let response = next.run(request).await;
+    if !response.status().is_success() {
+        release(&state, key).await;
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match body::to_bytes(body, crate::MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(err) => {
+            warn!("Failed to read response to keep: {}", err);
+            release(&state, key).await;
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let stored = StoredResponse {
+        status: parts.status.as_u16(),
+        content_type: parts.headers.get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok()).map(str::to_string),
+        body: body.to_vec(),
+    };
+    if let Err(err) = state.db.store_idempotent_response(key, stored).await {
+        warn!("Failed to keep response for idempotency key: {}", err);
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+async fn release(state: &AppState, key: IdempotencyKey) {
+    if let Err(err) = state.db.release_idempotency_key(key).await {
+        warn!("Failed to release idempotency key: {}", err);
+    }
+}
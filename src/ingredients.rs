@@ -0,0 +1,203 @@
+use std::fmt;
+
+/// Units recognised after a leading quantity. Anything else is treated as part of the name.
+const UNITS: &[&str] = &[
+    "g", "gram", "grams", "kg", "mg",
+    "ml", "cl", "dl", "l", "liter", "liters", "litre", "litres",
+    "tsp", "teaspoon", "teaspoons", "tbsp", "tablespoon", "tablespoons",
+    "cup", "cups", "oz", "lb", "lbs", "pinch", "pinches",
+    "clove", "cloves", "can", "cans", "pack", "packs", "pcs",
+    // Norwegian
+    "ts", "ss", "stk", "boks", "pk", "fedd", "neve",
+];
+
+/// One line of a recipe's ingredient text, split into quantity, unit and name
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ingredient {
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
+    pub name: String,
+}
+
+impl Ingredient {
+    /// Parses lines like "1 1/2 cups milk", "200g butter", "½ tsp salt" or "2 eggs".
+    /// Lines without a leading quantity keep their full text as the name.
+    pub fn parse(line: &str) -> Ingredient {
+        let line = line.trim();
+        let mut tokens: Vec<&str> = line.split_whitespace().collect();
+
+        let mut quantity = None;
+        let mut unit = None;
+
+        if let Some(first) = tokens.first().copied() {
+            // A number with the unit attached, e.g. "200g"
+            let split_at = first.find(|c: char| c.is_alphabetic()).unwrap_or(first.len());
+            let (number, attached_unit) = first.split_at(split_at);
+            if let Some(value) = parse_number(number) {
+                if attached_unit.is_empty() {
+                    quantity = Some(value);
+                    tokens.remove(0);
+                    // Mixed numbers, e.g. "1 1/2"
+                    if let Some(fraction) = tokens.first().and_then(|t| parse_fraction(t)) {
+                        quantity = Some(value + fraction);
+                        tokens.remove(0);
+                    }
+                } else if is_unit(attached_unit) {
+                    quantity = Some(value);
+                    unit = Some(attached_unit.to_string());
+                    tokens.remove(0);
+                }
+            }
+        }
+
+        if quantity.is_some() && unit.is_none() {
+            if let Some(candidate) = tokens.first().copied() {
+                if is_unit(candidate) {
+                    unit = Some(candidate.to_string());
+                    tokens.remove(0);
+                }
+            }
+        }
+
+        if quantity.is_none() {
+            return Ingredient { quantity: None, unit: None, name: line.to_string() };
+        }
+
+        Ingredient { quantity, unit, name: tokens.join(" ") }
+    }
+
+    /// Multiplies the quantity, if there is one
+    pub fn scaled(&self, factor: f64) -> Ingredient {
+        Ingredient {
+            quantity: self.quantity.map(|q| q * factor),
+            ..self.clone()
+        }
+    }
+
+    /// Normalised name used to match the same ingredient across recipes
+    pub fn normalised_name(&self) -> String {
+        normalise_name(&self.name)
+    }
+
+    /// Key used when summing ingredients: same unit and same normalised name
+    fn aggregation_key(&self) -> Option<(String, String)> {
+        self.quantity?;
+        let unit = normalise_name(self.unit.as_deref().unwrap_or(""));
+        Some((unit, self.normalised_name()))
+    }
+}
+
+impl fmt::Display for Ingredient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.quantity, &self.unit) {
+            (Some(quantity), Some(unit)) => write!(f, "{} {} {}", format_quantity(quantity), unit, self.name),
+            (Some(quantity), None) => write!(f, "{} {}", format_quantity(quantity), self.name),
+            _ => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// Lowercases, trims punctuation and drops a plural "s" so "Eggs," and "egg" match
+pub fn normalise_name(name: &str) -> String {
+    let name = name
+        .trim()
+        .trim_matches(|c: char| c.is_ascii_punctuation())
+        .to_lowercase();
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    match name.strip_suffix('s') {
+        Some(singular) if singular.len() > 2 && !singular.ends_with('s') => singular.to_string(),
+        _ => name,
+    }
+}
+
+/// Parses a bare amount such as "2", "1,5", "1 1/2" or "½"
+pub fn parse_quantity(text: &str) -> Option<f64> {
+    let ingredient = Ingredient::parse(text);
+    if ingredient.unit.is_none() && ingredient.name.is_empty() {
+        ingredient.quantity
+    } else {
+        None
+    }
+}
+
+/// Parses each non-empty line of a recipe's ingredient text
+pub fn parse_lines(text: &str) -> Vec<Ingredient> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(Ingredient::parse)
+        .collect()
+}
+
+/// Sums ingredients with the same unit and name, keeping the order they first appear in.
+/// Lines without a quantity are only de-duplicated.
+pub fn aggregate(ingredients: Vec<Ingredient>) -> Vec<Ingredient> {
+    let mut result: Vec<Ingredient> = Vec::new();
+    for ingredient in ingredients {
+        let existing = match ingredient.aggregation_key() {
+            Some(key) => result.iter_mut().find(|i| i.aggregation_key().as_ref() == Some(&key)),
+            None => result.iter_mut().find(|i| i.quantity.is_none() && i.name == ingredient.name),
+        };
+        match existing {
+            Some(existing) => {
+                if let (Some(total), Some(quantity)) = (existing.quantity.as_mut(), ingredient.quantity) {
+                    *total += quantity;
+                }
+            }
+            None => result.push(ingredient),
+        }
+    }
+    result
+}
+
+fn is_unit(token: &str) -> bool {
+    let token = token.trim_end_matches('.').to_lowercase();
+    UNITS.contains(&token.as_str())
+}
+
+fn parse_number(token: &str) -> Option<f64> {
+    if token.is_empty() {
+        return None;
+    }
+    if let Some(value) = parse_fraction(token) {
+        return Some(value);
+    }
+    // A whole number followed by a unicode fraction, e.g. "1½"
+    let (whole, fraction) = token.split_at(token.char_indices().last()?.0);
+    if let (Ok(whole), Some(fraction)) = (whole.parse::<f64>(), unicode_fraction(fraction)) {
+        return Some(whole + fraction);
+    }
+    token.replace(',', ".").parse::<f64>().ok().filter(|value| value.is_finite() && *value >= 0.0)
+}
+
+fn parse_fraction(token: &str) -> Option<f64> {
+    if let Some(value) = unicode_fraction(token) {
+        return Some(value);
+    }
+    let (numerator, denominator) = token.split_once('/')?;
+    let numerator: f64 = numerator.parse().ok()?;
+    let denominator: f64 = denominator.parse().ok()?;
+    (denominator != 0.0).then(|| numerator / denominator)
+}
+
+fn unicode_fraction(token: &str) -> Option<f64> {
+    match token {
+        "½" => Some(0.5),
+        "⅓" => Some(1.0 / 3.0),
+        "⅔" => Some(2.0 / 3.0),
+        "¼" => Some(0.25),
+        "¾" => Some(0.75),
+        "⅛" => Some(0.125),
+        _ => None,
+    }
+}
+
+/// Formats a quantity without trailing zeros, rounded to two decimals
+pub fn format_quantity(quantity: f64) -> String {
+    let rounded = (quantity * 100.0).round() / 100.0;
+    if rounded.fract() == 0.0 {
+        format!("{}", rounded as i64)
+    } else {
+        format!("{:.2}", rounded).trim_end_matches('0').to_string()
+    }
+}
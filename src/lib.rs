@@ -1,27 +1,50 @@
+pub mod aisles;
+pub mod api;
+pub mod auth;
+pub mod caldav;
+pub mod cli;
+pub mod backup;
+pub mod conflicts;
+pub mod csrf;
 pub mod database;
+pub mod events;
+pub mod export;
+pub mod households;
+pub mod idempotency;
+pub mod ingredients;
+pub mod locale;
+pub mod pantry;
+pub mod planner;
+pub mod prices;
+pub mod shares;
 pub mod template;
 pub mod todo;
+pub mod todotxt;
 
 use axum::{
-    extract::{Path, Query, State, Json, Multipart, DefaultBodyLimit, RawForm},
+    extract::{Path, Query, State, Json, Multipart, DefaultBodyLimit, RawForm, Extension},
+    handler::Handler,
     http::{StatusCode, HeaderMap},
     response::{IntoResponse, Response, Html, Redirect},
     routing::{delete, get, post},
+    middleware,
     Form,
 };
 use futures::stream::once;
 use std::convert::Infallible;
 use database::Database;
+use households::HouseholdState;
 use reqwest::header;
 use serde::Deserialize;
 use anyhow::Context;
 use template::*;
 use askama::Template;
-use todo::{ListForm, Task, TaskForm, MealForm, RecipeToMealPlanForm, WeekDay};
+use todo::{ListForm, Task, TaskForm, ToggleForm, CompletedForm, MealForm, RecipeForm, RecipeToMealPlanForm, WeekDay, MealSuggestionSlot, AcceptSuggestionForm, SettingsForm, DayNoteForm, PantryItemForm, PantryShoppingListForm, PlanRecipeForm, StoreForm, TaskCategoryForm, TaskPriceForm, LoginForm, User, ShareLink};
 use tracing::{info, warn};
 use std::path::PathBuf;
 use uuid::Uuid;
 use image::ImageFormat;
+use locale::Locale;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -32,6 +55,8 @@ pub struct AppState {
 #[derive(Deserialize)]
 struct ListQuery {
     list_id: Option<usize>,
+    group: Option<String>, // "aisle" groups open tasks by store category
+    store: Option<usize>,  // Store whose aisle order is used when grouping
 }
 
 #[derive(Deserialize)]
@@ -39,21 +64,40 @@ struct ReorderPayload {
     order: Vec<u64>,
 }
 
+/// Largest request body, to leave room for photo uploads
+pub const MAX_BODY_BYTES: usize = 50 * 1024 * 1024;
+
 const HTMX_JS_GZIP: &[u8] = include_bytes!("../vendor/htmx.js.gz");
 const SORTABLE_JS_GZIP: &[u8] = include_bytes!("../vendor/Sortable.js.gz");
+const SSE_JS_GZIP: &[u8] = include_bytes!("../vendor/sse.js.gz");
 const PICO_CSS_GZIP: &[u8] = include_bytes!("../vendor/pico.css.gz");
+const OFFLINE_JS_GZIP: &[u8] = include_bytes!("../vendor/offline.js.gz");
+const SERVICE_WORKER_GZIP: &[u8] = include_bytes!("../vendor/sw.js.gz");
+const MANIFEST_GZIP: &[u8] = include_bytes!("../vendor/manifest.webmanifest.gz");
+const ICON_SVG_GZIP: &[u8] = include_bytes!("../vendor/icon.svg.gz");
 
 pub fn create_app(state: AppState) -> axum::Router {
+    // Adding and checking off tasks can be replayed by the offline queue
+    let idempotent = middleware::from_fn_with_state(state.clone(), idempotency::idempotent);
     axum::Router::new()
         .route("/", get(index))
+        .route("/login", get(login_page).post(login))
+        .route("/logout", post(logout))
         .route("/manage", get(manage).post(create_list))
         .route("/list/:id", delete(delete_list))
-        .route("/task/:id", delete(delete_task).post(toggle_task))
-        .route("/:list_id/task", post(create_task))
+        .route("/list/:id/shop", get(shopping_mode))
+        .route("/list/:id/shop/finish", post(finish_shopping_trip))
+        .route("/list/:id/todo.txt", get(export_todo_txt).post(import_todo_txt))
+        .route("/task/:id", get(task_row).delete(delete_task).post(toggle_task.layer(idempotent.clone())))
+        .route("/task/:id/completed", post(set_task_completed.layer(idempotent.clone())))
+        .route("/task/:id/category", post(set_task_category))
+        .route("/task/:id/price", post(set_task_price))
+        .route("/:list_id/task", post(create_task.layer(idempotent.clone())))
         .route("/create_list", post(create_list))
         .route("/reorder", post(reorder))
         .route("/recipes", get(recipes_page))
         .route("/recipes/new", get(new_recipe_form).post(create_recipe))
+        .route("/recipes/cookable", get(cookable_recipes_page))
         .route("/recipes/:id", get(view_recipe))
         .route("/recipes/:id/edit", get(edit_recipe_form).post(update_recipe))
         .route("/recipes/:id/delete", post(delete_recipe))
@@ -67,11 +111,54 @@ pub fn create_app(state: AppState) -> axum::Router {
         .route("/meal-plan", get(meal_plan_page))
         .route("/meal-plan/:date/add", get(add_meal_form).post(add_meal))
         .route("/meal-plan/:id/delete", post(delete_meal))
+        .route("/meal-plan/:date/note", post(update_day_note))
         .route("/meal-plan/:start_date/add-ingredients", get(weekly_ingredients_form).post(add_weekly_ingredients))
+        .route("/meal-plan/:start_date/suggest", get(meal_suggestions_page))
+        .route("/meal-plan/:date/suggestion", get(reroll_meal_suggestion).post(accept_meal_suggestion))
+        .route("/pantry", get(pantry_page).post(add_pantry_item))
+        .route("/pantry/:id/delete", post(delete_pantry_item))
+        .route("/pantry/expiring", get(expiring_pantry_page))
+        .route("/pantry/expiring/plan", post(plan_recipe_for_expiring))
+        .route("/pantry/shopping-list", post(update_pantry_shopping_list))
+        .route("/stores", get(stores_page).post(create_store))
+        .route("/stores/:id", post(update_store_aisles))
+        .route("/stores/:id/delete", post(delete_store))
+        .route("/reports/spending", get(spending_report))
+        .route("/settings", get(settings_page).post(update_settings))
+        .route("/households", get(households::households_page).post(households::create_household))
+        .route("/households/switch", post(households::switch_household))
+        .route("/households/members", post(households::invite_member))
+        .route("/households/invites/accept", post(households::accept_invite))
+        .route("/households/invites/decline", post(households::decline_invite))
+        .route("/shares", get(shares::shares_page).post(shares::create_share))
+        .route("/shares/:token/delete", post(shares::delete_share))
+        .route("/s/:token", get(shares::shared_page))
+        .route("/s/:token/task", post(shares::add_shared_task.layer(idempotent.clone())))
+        .route(
+            "/s/:token/task/:id",
+            get(shares::shared_task_row)
+                .post(shares::toggle_shared_task.layer(idempotent.clone()))
+                .delete(shares::delete_shared_task),
+        )
+        .route("/s/:token/task/:id/completed", post(shares::set_shared_task_completed.layer(idempotent)))
+        .route("/s/:token/edit", get(shares::shared_recipe_form).post(shares::update_shared_recipe))
+        .route("/s/:token/photos/:filename", get(shares::shared_photo))
+        .route("/s/:token/events", get(shares::shared_events))
+        .route("/events", get(events::events))
         .route("/vendor/htmx.js", get(htmx))
         .route("/vendor/Sortable.js", get(sortable))
+        .route("/vendor/sse.js", get(sse))
         .route("/vendor/pico.min.css", get(picocss))
-        .layer(DefaultBodyLimit::max(50 * 1024 * 1024)) // 50MB limit for photo uploads
+        .route("/vendor/offline.js", get(offline_js))
+        .route("/vendor/sw.js", get(service_worker))
+        .route("/vendor/manifest.webmanifest", get(manifest))
+        .route("/vendor/icon.svg", get(icon))
+        .route("/api/openapi.json", get(api::openapi_json))
+        .nest("/api/v1", api::router())
+        .merge(caldav::router())
+        .layer(DefaultBodyLimit::max(MAX_BODY_BYTES))
+        .layer(middleware::from_fn_with_state(state.clone(), auth::require_login))
+        .layer(middleware::from_fn(csrf::protect))
         .with_state(state)
 }
 
@@ -135,7 +222,7 @@ async fn determine_selected_list(list_query: &ListQuery, state: &AppState) -> us
 }
 
 // Handler functions moved from main.rs
-async fn index(list_query: Query<ListQuery>, State(state): State<AppState>) -> impl IntoResponse {
+async fn index(list_query: Query<ListQuery>, HouseholdState(state): HouseholdState) -> impl IntoResponse {
     let selected_list = determine_selected_list(&list_query, &state).await;
 
     let lists = state.db.get_lists().await.expect("Get list options");
@@ -148,23 +235,80 @@ async fn index(list_query: Query<ListQuery>, State(state): State<AppState>) -> i
             "Got incomplete tasks: {:?} from list with id {:?}",
             incomplete, selected_list
         );
-        let template = IndexTemplate { selected_list, lists, tasks};
+        let aisles = if list_query.group.as_deref() == Some("aisle") {
+            Some(aisle_view(&state, incomplete, list_query.store).await)
+        } else {
+            None
+        };
+        let template = IndexTemplate { selected_list, lists, tasks, aisles };
         HtmlTemplate(template).into_response()
     } else {
         warn!("Failed to get tasks for list_id={}", selected_list);
         let tasks: Vec<Task> = vec![];
-        let template = IndexTemplate { selected_list, lists, tasks};
+        let template = IndexTemplate { selected_list, lists, tasks, aisles: None };
         HtmlTemplate(template).into_response()
     }
 }
 
-async fn delete_task(State(state): State<AppState>, Path(id): Path<u32>) -> StatusCode {
+async fn aisle_view(state: &AppState, tasks: Vec<Task>, store: Option<usize>) -> AisleView {
+    let overrides = state.db.get_category_overrides().await.unwrap_or_default();
+    let stores = state.db.get_stores().await.unwrap_or_default();
+    let selected_store = store.filter(|id| stores.iter().any(|s| s.id == *id));
+    let order = match selected_store {
+        Some(id) => state.db.get_store_aisles(id).await.unwrap_or_default(),
+        None => vec![],
+    };
+    AisleView {
+        groups: aisles::group_tasks(tasks, &overrides, &order),
+        stores,
+        selected_store,
+        categories: aisle_categories(),
+    }
+}
+
+fn aisle_categories() -> Vec<(String, String)> {
+    aisles::CATEGORIES
+        .iter()
+        .map(|(key, label)| (key.to_string(), label.to_string()))
+        .collect()
+}
+
+/// Moves a task to another store category and remembers it for the same item in future
+async fn set_task_category(
+    HouseholdState(state): HouseholdState,
+    Path(id): Path<usize>,
+    form: Form<TaskCategoryForm>,
+) -> impl IntoResponse {
+    if !aisles::is_category(&form.category) {
+        return (StatusCode::BAD_REQUEST, "Unknown category").into_response();
+    }
+    let task = match state.db.get_task(id).await {
+        Ok(task) => task,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    let name = aisles::item_name(&task.text);
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Task has no item name").into_response();
+    }
+    match state.db.set_category_override(name, form.category.clone()).await {
+        Ok(_) => {
+            // Regroup the list
+            let mut headers = HeaderMap::new();
+            headers.insert("HX-Refresh", "true".parse().unwrap());
+            (StatusCode::OK, headers, "").into_response()
+        }
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+async fn delete_task(HouseholdState(state): HouseholdState, Path(id): Path<u32>) -> StatusCode {
     state.db.delete_task(id as usize).await.expect("Delete task");
     info!("Deleted task with id {}", id);
     StatusCode::OK
 }
 
-async fn delete_list(State(state): State<AppState>, Path(id): Path<u32>) -> impl IntoResponse {
+async fn delete_list(HouseholdState(state): HouseholdState, Path(id): Path<u32>) -> impl IntoResponse {
     state.db.delete_list(id as usize).await.expect("Delete list");
     info!("Deleted list with id {}", id);
 
@@ -174,34 +318,242 @@ async fn delete_list(State(state): State<AppState>, Path(id): Path<u32>) -> impl
     (StatusCode::SEE_OTHER, headers, "").into_response()
 }
 
+#[derive(Deserialize)]
+struct ToggleQuery {
+    view: Option<String>, // "shop" returns the shopping mode item instead of a table row
+}
+
+impl ToggleQuery {
+    fn view(&self) -> events::View {
+        if self.view.as_deref() == Some("shop") { events::View::Shop(None) } else { events::View::Index }
+    }
+}
+
+/// The task's row as it is now, to drop a conflicting change
+async fn task_row(
+    HouseholdState(state): HouseholdState,
+    Path(id): Path<usize>,
+    Query(params): Query<ToggleQuery>,
+) -> Response {
+    let task = match state.db.get_task(id).await {
+        Ok(task) => task,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+    match params.view().render(&state, task).await {
+        Ok(row) => Html(row).into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// Checks a task off or back on. Takes the state to set rather than flipping it, so a
+/// request sent twice does the same as sending it once.
+async fn set_task_completed(
+    HouseholdState(state): HouseholdState,
+    Path(id): Path<usize>,
+    Query(params): Query<ToggleQuery>,
+    headers: HeaderMap,
+    Form(form): Form<CompletedForm>,
+) -> Response {
+    complete_task(&state, id, form.completed, conflicts::if_match(&headers), params.view()).await
+}
+
+/// Flips whether a task is checked off, for clients from before `set_task_completed`
 async fn toggle_task(
-    State(state): State<AppState>,
-    Path(id): Path<u32>,
-) -> impl IntoResponse {
-    let id = id as usize;
-    info!("Toggling task with id {}", id);
-    if let Ok(_) = state.db.toggle_task_completed(id).await {
-        if let Ok(task) = state.db.get_task(id).await {
-            return HtmlTemplate(TaskTemplate { task }).into_response();
-        } else {
-            warn!("Toggled task with id {}, but failed to retrieve it!", id);
-            StatusCode::OK.into_response()
+    HouseholdState(state): HouseholdState,
+    Path(id): Path<usize>,
+    Query(params): Query<ToggleQuery>,
+    headers: HeaderMap,
+    form: Option<Form<ToggleForm>>,
+) -> Response {
+    let task = match state.db.get_task(id).await {
+        Ok(task) => task,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let completed = form.and_then(|form| form.completed).unwrap_or(!task.completed);
+    let expected_version = conflicts::if_match(&headers).or(Some(task.version));
+    complete_task(&state, id, completed, expected_version, params.view()).await
+}
+
+/// Sets whether a task is checked off and answers with it as shown in `view`, or with
+/// both versions if it was changed elsewhere since `expected_version`
+async fn complete_task(
+    state: &AppState,
+    id: usize,
+    completed: bool,
+    expected_version: Option<usize>,
+    view: events::View,
+) -> Response {
+    info!("Setting task {} completed to {}", id, completed);
+    let before = match state.db.get_task(id).await {
+        Ok(task) => task,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+    match state.db.set_task_completed(id, completed, expected_version).await {
+        Ok(true) => {}
+        Ok(false) => return conflicts::task_conflict(state, id, completed, view).await,
+        Err(_) => {
+            warn!("Failed to set task {} completed", id);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
         }
+    }
+    let task = match stock_pantry_if_bought(state, &before).await {
+        Ok(task) => task,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    match view {
+        events::View::Index => HtmlTemplate(TaskTemplate { task }).into_response(),
+        events::View::Shop(share) => shop_item(state, task, share).await,
+    }
+}
+
+/// A task as a row of the shopping mode view
+async fn shop_item(state: &AppState, task: Task, share: Option<ShareLink>) -> Response {
+    let prices = state.db.get_last_prices().await.unwrap_or_default();
+    let row = ShopRow::new(task, &prices);
+    HtmlTemplate(ShopItemTemplate { row, share }).into_response()
+}
+
+async fn shopping_mode(HouseholdState(state): HouseholdState, Path(id): Path<usize>) -> impl IntoResponse {
+    shop_page(&state, id, None).await
+}
+
+async fn shop_page(state: &AppState, id: usize, share: Option<ShareLink>) -> Response {
+    let list = match state.db.get_list(id).await {
+        Ok(list) => list,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let tasks = state.db.get_tasks(id).await.unwrap_or_default();
+    let prices = state.db.get_last_prices().await.unwrap_or_default();
+    let (in_cart, to_buy): (Vec<Task>, Vec<Task>) = tasks.into_iter().partition(|task| task.completed);
+
+    let template = ShopTemplate {
+        list,
+        estimate: prices::estimate(&to_buy.iter().map(|task| task.text.as_str()).collect::<Vec<_>>(), &prices),
+        cart_total: in_cart.iter().filter_map(|task| task.price).sum(),
+        to_buy: to_buy.into_iter().map(|task| ShopRow::new(task, &prices)).collect(),
+        in_cart: in_cart.into_iter().map(|task| ShopRow::new(task, &prices)).collect(),
+        stores: state.db.get_stores().await.unwrap_or_default(),
+        share,
+    };
+    HtmlTemplate(template).into_response()
+}
+
+/// Saves the price paid for a task, which also becomes the item's latest known price
+async fn set_task_price(
+    HouseholdState(state): HouseholdState,
+    Path(id): Path<usize>,
+    form: Form<TaskPriceForm>,
+) -> impl IntoResponse {
+    let price = if form.price.trim().is_empty() {
+        None
     } else {
-        warn!("Failed to toggle task with id {}", id);
-        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        match prices::parse_price(&form.price) {
+            Some(price) => Some(price),
+            None => return (StatusCode::BAD_REQUEST, "Invalid price").into_response(),
+        }
+    };
+    if let Some(store_id) = form.store_id {
+        if !state.db.get_stores().await.unwrap_or_default().iter().any(|store| store.id == store_id) {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+    }
+    let task = match state.db.get_task(id).await {
+        Ok(task) => task,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    let name = ingredients::Ingredient::parse(&task.text).normalised_name();
+    match state.db.set_task_price(id, name, price, form.store_id).await {
+        Ok(_) => StatusCode::OK.into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+async fn spending_report(HouseholdState(state): HouseholdState) -> impl IntoResponse {
+    let locale = current_locale(&state).await;
+    let purchases = state.db.get_trip_purchases().await.unwrap_or_default();
+    let months = prices::monthly_spending(&purchases, &locale);
+    HtmlTemplate(SpendingTemplate { months }).into_response()
+}
+
+/// Archives everything in the cart, leaving only what is still to buy on the list
+async fn finish_shopping_trip(HouseholdState(state): HouseholdState, Path(id): Path<usize>) -> impl IntoResponse {
+    if state.db.get_list(id).await.is_err() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    match state.db.finish_shopping_trip(id).await {
+        Ok(_) => Redirect::to(&format!("/list/{}/shop", id)).into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// The list as a todo.txt file
+async fn export_todo_txt(HouseholdState(state): HouseholdState, Path(id): Path<usize>) -> Response {
+    let list = match state.db.get_list(id).await {
+        Ok(list) => list,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+    match todotxt::export_list(&state, id).await {
+        Ok(todo_txt) => {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, "text/plain; charset=utf-8".parse().unwrap());
+            let disposition = format!("attachment; filename=\"{}.txt\"", todotxt::project_name(&list.name).replace('"', ""));
+            match disposition.parse() {
+                Ok(value) => headers.insert(header::CONTENT_DISPOSITION, value),
+                Err(_) => headers.insert(header::CONTENT_DISPOSITION, "attachment; filename=\"todo.txt\"".parse().unwrap()),
+            };
+            (headers, todo_txt).into_response()
+        }
+        Err(err) => {
+            warn!("Failed to export list {} as todo.txt: {:?}", id, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Adds the tasks of an uploaded todo.txt file to the list
+async fn import_todo_txt(
+    HouseholdState(state): HouseholdState,
+    Path(id): Path<usize>,
+    mut multipart: Multipart,
+) -> Response {
+    if state.db.get_list(id).await.is_err() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let mut todo_txt = None;
+    while let Ok(Some(field)) = multipart.next_field().await {
+        if field.name() == Some("file") {
+            todo_txt = field.bytes().await.ok();
+        }
+    }
+    let todo_txt = match todo_txt.map(|bytes| String::from_utf8(bytes.to_vec())) {
+        Some(Ok(todo_txt)) => todo_txt,
+        _ => return (StatusCode::BAD_REQUEST, "Upload a todo.txt file").into_response(),
+    };
+    match todotxt::import_list(&state, id, &todo_txt).await {
+        Ok(added) => {
+            info!("Imported {} tasks from todo.txt into list {}", added, id);
+            Redirect::to(&format!("/?list_id={}", id)).into_response()
+        }
+        Err(err) => {
+            warn!("Failed to import todo.txt into list {}: {:?}", id, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
     }
 }
 
 async fn create_task(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path(list_id): Path<u32>,
     form: Form<TaskForm>,
 ) -> impl IntoResponse {
     let text = form.text.clone();
     info!("Inserting task item with list_id {}", list_id);
 
-    let id = state.db.create_task(text, list_id as usize).await.expect("Create task on db");
+    let id = match state.db.create_task(text, list_id as usize).await {
+        Ok(id) => id,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
 
     info!("Task item with id {} created", id);
 
@@ -211,13 +563,19 @@ async fn create_task(
         completed: false,
         list_id: list_id as usize,
         position: None, // Will be set by database
+        price: None,
+        store_id: None,
+        version: 1,
+        completed_at: None,
+        priority: None,
+        tags: vec![],
     };
 
     // could just return one task if we fix the template to only add an item!
-    HtmlTemplate(TaskTemplate { task })
+    HtmlTemplate(TaskTemplate { task }).into_response()
 }
 
-async fn create_list(State(state): State<AppState>, form: Form<ListForm>) -> Response {
+async fn create_list(HouseholdState(state): HouseholdState, form: Form<ListForm>) -> Response {
     let name = form.name.clone();
 
     if let Ok(id) = state.db.create_list(name.clone()).await.context("Create list") {
@@ -229,7 +587,7 @@ async fn create_list(State(state): State<AppState>, form: Form<ListForm>) -> Res
     }
 }
 
-async fn manage(list_query: Query<ListQuery>, State(state): State<AppState>) -> impl IntoResponse {
+async fn manage(list_query: Query<ListQuery>, HouseholdState(state): HouseholdState) -> impl IntoResponse {
     let selected_list = determine_selected_list(&list_query, &state).await;
 
     let lists = state.db.get_lists().await.expect("Get list options");
@@ -251,6 +609,13 @@ async fn sortable() -> impl IntoResponse {
     (headers, SORTABLE_JS_GZIP)
 }
 
+async fn sse() -> impl IntoResponse {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, "application/javascript".parse().unwrap());
+    headers.insert(header::CONTENT_ENCODING, "gzip".parse().unwrap());
+    (headers, SSE_JS_GZIP)
+}
+
 async fn picocss() -> impl IntoResponse {
     let mut headers = HeaderMap::new();
     headers.insert(header::CONTENT_TYPE, "text/css".parse().unwrap());
@@ -258,19 +623,56 @@ async fn picocss() -> impl IntoResponse {
     (headers, PICO_CSS_GZIP)
 }
 
-async fn reorder(State(state): State<AppState>,
+async fn offline_js() -> impl IntoResponse {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, "application/javascript".parse().unwrap());
+    headers.insert(header::CONTENT_ENCODING, "gzip".parse().unwrap());
+    (headers, OFFLINE_JS_GZIP)
+}
+
+/// The service worker, allowed to look after the whole site from under /vendor
+async fn service_worker() -> impl IntoResponse {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, "application/javascript".parse().unwrap());
+    headers.insert(header::CONTENT_ENCODING, "gzip".parse().unwrap());
+    headers.insert(header::CACHE_CONTROL, "no-cache".parse().unwrap());
+    headers.insert("Service-Worker-Allowed", "/".parse().unwrap());
+    (headers, SERVICE_WORKER_GZIP)
+}
+
+async fn manifest() -> impl IntoResponse {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, "application/manifest+json".parse().unwrap());
+    headers.insert(header::CONTENT_ENCODING, "gzip".parse().unwrap());
+    (headers, MANIFEST_GZIP)
+}
+
+async fn icon() -> impl IntoResponse {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, "image/svg+xml".parse().unwrap());
+    headers.insert(header::CONTENT_ENCODING, "gzip".parse().unwrap());
+    (headers, ICON_SVG_GZIP)
+}
+
+async fn reorder(HouseholdState(state): HouseholdState,
                  Query(params): Query<ListQuery>, 
+                 headers: HeaderMap,
                  Json(payload): Json<ReorderPayload>,
-                 ) -> StatusCode {
+                 ) -> Response {
     println!("List {:?} reordered to {:?}", params.list_id, payload.order);
-    match state.db.reorder(params.list_id.unwrap(), payload.order).await {
-        Ok(()) => StatusCode::OK,
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    let list_id = params.list_id.unwrap();
+    match state.db.reorder(list_id, payload.order.clone(), conflicts::if_match(&headers)).await {
+        Ok(true) => match state.db.get_list(list_id).await {
+            Ok(list) => [(header::ETAG, conflicts::etag(list.version))].into_response(),
+            Err(_) => StatusCode::OK.into_response(),
+        },
+        Ok(false) => conflicts::reorder_conflict(&state, list_id, &payload.order).await,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
     }
 }
 
 // Recipe handlers
-async fn recipes_page(State(state): State<AppState>) -> impl IntoResponse {
+async fn recipes_page(HouseholdState(state): HouseholdState) -> impl IntoResponse {
     let recipes = state.db.get_recipes().await.unwrap_or_default();
     
     // Get first photo for each recipe
@@ -290,16 +692,16 @@ async fn recipes_page(State(state): State<AppState>) -> impl IntoResponse {
 async fn new_recipe_form() -> impl IntoResponse {
     let template = RecipeFormTemplate { 
         recipe: None, 
-        is_edit: false 
+        is_edit: false,
+        share: None,
+        conflict: None,
     };
     HtmlTemplate(template).into_response()
 }
 
 // Helper function to parse recipe form data
-async fn parse_recipe_multipart(mut multipart: Multipart) -> Result<(String, String, String, Vec<PhotoData>), StatusCode> {
-    let mut title = String::new();
-    let mut instructions = String::new();
-    let mut ingredients = String::new();
+async fn parse_recipe_multipart(mut multipart: Multipart) -> Result<(RecipeForm, Vec<PhotoData>), StatusCode> {
+    let mut form = RecipeForm::default();
     let mut photos = Vec::new();
     let limits = PhotoUploadLimits::default();
 
@@ -309,17 +711,42 @@ async fn parse_recipe_multipart(mut multipart: Multipart) -> Result<(String, Str
         match name.as_str() {
             "title" => {
                 if let Ok(value) = field.text().await {
-                    title = value;
+                    form.title = value;
                 }
             }
             "instructions" => {
                 if let Ok(value) = field.text().await {
-                    instructions = value;
+                    form.instructions = value;
                 }
             }
             "ingredients" => {
                 if let Ok(value) = field.text().await {
-                    ingredients = value;
+                    form.ingredients = value;
+                }
+            }
+            "favourite" => {
+                if let Ok(value) = field.text().await {
+                    form.favourite = matches!(value.as_str(), "on" | "true" | "1");
+                }
+            }
+            "rating" => {
+                if let Ok(value) = field.text().await {
+                    form.rating = value.trim().parse::<u8>().unwrap_or(0).min(5);
+                }
+            }
+            "tags" => {
+                if let Ok(value) = field.text().await {
+                    form.tags = value;
+                }
+            }
+            "servings" => {
+                if let Ok(value) = field.text().await {
+                    form.servings = value.trim().parse::<u32>().ok().filter(|n| *n > 0);
+                }
+            }
+            "version" => {
+                if let Ok(value) = field.text().await {
+                    form.version = value.trim().parse().ok();
                 }
             }
             "photos" => {
@@ -345,29 +772,33 @@ async fn parse_recipe_multipart(mut multipart: Multipart) -> Result<(String, Str
         }
     }
 
-    if title.is_empty() {
+    if form.title.is_empty() {
         return Err(StatusCode::BAD_REQUEST);
     }
 
-    Ok((title, instructions, ingredients, photos))
+    Ok((form, photos))
 }
 
 async fn create_recipe(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     multipart: Multipart,
 ) -> impl IntoResponse {
     // Parse multipart form data
-    let (title, instructions, ingredients, photos) = match parse_recipe_multipart(multipart).await {
+    let (form, photos) = match parse_recipe_multipart(multipart).await {
         Ok(data) => data,
         Err(_) => return (StatusCode::BAD_REQUEST, "Invalid form data or missing title").into_response(),
     };
 
     // Create recipe in database
-    let recipe_id = match state.db.create_recipe(title, instructions, ingredients).await {
+    let recipe_id = match state.db.create_recipe(form.title, form.instructions, form.ingredients, form.servings).await {
         Ok(id) => id,
         Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
     };
 
+    if state.db.update_recipe_preferences(recipe_id, form.favourite, form.rating, form.tags).await.is_err() {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
     // Process uploaded photos
     let mut uploaded_count = 0;
     let limits = PhotoUploadLimits::default();
@@ -392,13 +823,17 @@ async fn create_recipe(
 }
 
 async fn view_recipe(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path(id): Path<u32>,
 ) -> impl IntoResponse {
-    match state.db.get_recipe(id as usize).await {
+    recipe_page(&state, id as usize, None).await
+}
+
+async fn recipe_page(state: &AppState, id: usize, share: Option<ShareLink>) -> Response {
+    match state.db.get_recipe(id).await {
         Ok(recipe) => {
-            let photos = state.db.get_recipe_photos(id as usize).await.unwrap_or_default();
-            let template = RecipeDetailTemplate { recipe, photos };
+            let photos = state.db.get_recipe_photos(id).await.unwrap_or_default();
+            let template = RecipeDetailTemplate { recipe, photos, share };
             HtmlTemplate(template).into_response()
         }
         Err(_) => StatusCode::NOT_FOUND.into_response()
@@ -406,14 +841,20 @@ async fn view_recipe(
 }
 
 async fn edit_recipe_form(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path(id): Path<u32>,
 ) -> impl IntoResponse {
-    match state.db.get_recipe(id as usize).await {
+    recipe_form_page(&state, id as usize, None).await
+}
+
+async fn recipe_form_page(state: &AppState, id: usize, share: Option<ShareLink>) -> Response {
+    match state.db.get_recipe(id).await {
         Ok(recipe) => {
             let template = RecipeFormTemplate { 
                 recipe: Some(recipe), 
-                is_edit: true 
+                is_edit: true,
+                share,
+                conflict: None,
             };
             HtmlTemplate(template).into_response()
         }
@@ -422,31 +863,73 @@ async fn edit_recipe_form(
 }
 
 async fn update_recipe(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path(id): Path<u32>,
     multipart: Multipart,
 ) -> impl IntoResponse {
+    save_recipe_edit(&state, id as usize, multipart, None).await
+}
+
+/// Saves the edit recipe form and goes back to the recipe, or to the share it was opened
+/// through. If the recipe was saved elsewhere since the form was opened, shows the form
+/// again with both versions.
+async fn save_recipe_edit(state: &AppState, id: usize, multipart: Multipart, share: Option<ShareLink>) -> Response {
     // Parse multipart form data (ignoring photos for updates)
-    let (title, instructions, ingredients, _photos) = match parse_recipe_multipart(multipart).await {
+    let (form, _photos) = match parse_recipe_multipart(multipart).await {
         Ok(data) => data,
         Err(_) => return (StatusCode::BAD_REQUEST, "Invalid form data or missing title").into_response(),
     };
 
-    match state.db.update_recipe(
-        id as usize,
-        title,
-        instructions,
-        ingredients,
-    ).await {
+    let updated = state.db.update_recipe(
+        id,
+        form.title.clone(),
+        form.instructions.clone(),
+        form.ingredients.clone(),
+        form.servings,
+        form.version,
+    ).await;
+    let updated = match updated {
+        Ok(true) => state.db.update_recipe_preferences(id, form.favourite, form.rating, form.tags).await,
+        Ok(false) => return recipe_conflict(state, id, form, share).await,
+        Err(err) => Err(err),
+    };
+
+    match updated {
         Ok(_) => {
-            Redirect::to(&format!("/recipes/{}", id)).into_response()
+            let done = match &share {
+                Some(link) => format!("/s/{}", link.token),
+                None => format!("/recipes/{}", id),
+            };
+            Redirect::to(&done).into_response()
         }
         Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response()
     }
 }
 
+/// The edit form holding the user's changes, against the current version, next to the
+/// version that was saved in the meantime
+async fn recipe_conflict(state: &AppState, id: usize, form: RecipeForm, share: Option<ShareLink>) -> Response {
+    let theirs = match state.db.get_recipe(id).await {
+        Ok(recipe) => recipe,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let mine = todo::Recipe {
+        id,
+        title: form.title,
+        instructions: form.instructions,
+        ingredients: form.ingredients,
+        favourite: form.favourite,
+        rating: form.rating,
+        tags: form.tags,
+        servings: form.servings,
+        version: theirs.version,
+    };
+    let template = RecipeFormTemplate { recipe: Some(mine), is_edit: true, share, conflict: Some(theirs) };
+    (StatusCode::CONFLICT, HtmlTemplate(template)).into_response()
+}
+
 async fn delete_recipe(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path(id): Path<u32>,
 ) -> impl IntoResponse {
     match state.db.delete_recipe(id as usize).await {
@@ -460,7 +943,7 @@ async fn delete_recipe(
 }
 
 async fn recipe_to_list_form(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path(id): Path<u32>,
 ) -> impl IntoResponse {
     let recipe = match state.db.get_recipe(id as usize).await {
@@ -478,7 +961,7 @@ async fn recipe_to_list_form(
 }
 
 async fn add_recipe_to_list(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path(id): Path<u32>,
     RawForm(body): RawForm,
 ) -> impl IntoResponse {
@@ -507,7 +990,7 @@ async fn add_recipe_to_list(
 }
 
 async fn recipe_to_meal_plan_form(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path(id): Path<u32>,
 ) -> impl IntoResponse {
     let recipe = match state.db.get_recipe(id as usize).await {
@@ -515,7 +998,12 @@ async fn recipe_to_meal_plan_form(
         Err(_) => return StatusCode::NOT_FOUND.into_response()
     };
     
-    let template = RecipeToMealPlanTemplate { recipe };
+    let locale = current_locale(&state).await;
+    let template = RecipeToMealPlanTemplate {
+        recipe,
+        language_tag: locale.language_tag().to_string(),
+        today: locale.today().format("%Y-%m-%d").to_string(),
+    };
     match template.render() {
         Ok(html) => Html(html).into_response(),
         Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response()
@@ -523,7 +1011,7 @@ async fn recipe_to_meal_plan_form(
 }
 
 async fn add_recipe_to_meal_plan(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path(id): Path<u32>,
     form: Form<RecipeToMealPlanForm>,
 ) -> impl IntoResponse {
@@ -543,6 +1031,8 @@ async fn add_recipe_to_meal_plan(
         form.date.clone(),
         meal_text,
         Some(id as usize),
+        None,
+        form.servings,
     ).await {
         Ok(_) => Redirect::to("/meal-plan").into_response(),
         Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response()
@@ -550,42 +1040,55 @@ async fn add_recipe_to_meal_plan(
 }
 
 // Meal plan helpers
-fn get_week_start_monday() -> chrono::NaiveDate {
-    use chrono::{Utc, Datelike, Duration};
-    let today = Utc::now().date_naive();
-    let days_since_monday = today.weekday().num_days_from_monday();
-    today - Duration::days(days_since_monday as i64)
+async fn current_locale(state: &AppState) -> Locale {
+    match state.db.get_settings().await {
+        Ok(settings) => Locale::from_settings(&settings),
+        Err(err) => {
+            warn!("Failed to load settings, using default locale: {}", err);
+            Locale::default()
+        }
+    }
 }
 
-fn parse_week_start_date(week_param: Option<String>) -> chrono::NaiveDate {
+fn get_current_week_start(locale: &Locale) -> chrono::NaiveDate {
+    locale.week_start_for(locale.today())
+}
+
+fn parse_week_start_date(week_param: Option<String>, locale: &Locale) -> chrono::NaiveDate {
     use chrono::NaiveDate;
     match week_param {
         Some(week_str) => {
             NaiveDate::parse_from_str(&week_str, "%Y-%m-%d")
-                .unwrap_or_else(|_| get_week_start_monday())
+                .unwrap_or_else(|_| get_current_week_start(locale))
         }
-        None => get_week_start_monday(),
+        None => get_current_week_start(locale),
     }
 }
 
-fn build_week_structure(start_date: chrono::NaiveDate, meals_by_date: std::collections::HashMap<String, Vec<crate::todo::MealPlanEntry>>) -> Vec<WeekDay> {
-    use chrono::Duration;
-    let day_names = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+fn build_week_structure(
+    start_date: chrono::NaiveDate,
+    meals_by_date: std::collections::HashMap<String, Vec<crate::todo::MealPlanEntry>>,
+    mut notes_by_date: std::collections::HashMap<String, String>,
+    locale: &Locale,
+) -> Vec<WeekDay> {
+    use chrono::{Datelike, Duration};
     let mut week_days = Vec::new();
     let mut meals_map = meals_by_date;
     
     for i in 0..7 {
         let date = start_date + Duration::days(i);
         let date_str_db = date.format("%Y-%m-%d").to_string(); // Keep for database queries
-        let date_str_display = date.format("%d.%m.%y").to_string(); // New format for display
-        let day_name = day_names[i as usize].to_string();
+        let date_str_display = locale.format_date(date);
+        let day_name = locale.day_name(date.weekday());
         let meals = meals_map.remove(&date_str_db).unwrap_or_default();
+        let note = notes_by_date.remove(&date_str_db).unwrap_or_default();
         
         week_days.push(WeekDay {
             day_name,
             date: date_str_display,
             db_date: date_str_db,
             meals,
+            note,
         });
     }
     
@@ -593,19 +1096,18 @@ fn build_week_structure(start_date: chrono::NaiveDate, meals_by_date: std::colle
 }
 
 // Meal plan handlers
-async fn meal_plan_page(Query(params): Query<WeekQuery>, State(state): State<AppState>) -> impl IntoResponse {
-    use chrono::{Duration, Datelike};
+async fn meal_plan_page(Query(params): Query<WeekQuery>, HouseholdState(state): HouseholdState) -> impl IntoResponse {
+    use chrono::Duration;
     use std::collections::HashMap;
     
-    let start_date = parse_week_start_date(params.week);
+    let locale = current_locale(&state).await;
+    let start_date = parse_week_start_date(params.week, &locale);
     let start_date_str = start_date.format("%Y-%m-%d").to_string();
     let prev_week = (start_date - Duration::days(7)).format("%Y-%m-%d").to_string();
     let next_week = (start_date + Duration::days(7)).format("%Y-%m-%d").to_string();
     
     // Calculate week number and year
-    let iso_week = start_date.iso_week();
-    let week_number = iso_week.week();
-    let week_year = iso_week.year();
+    let (week_number, week_year) = locale.week_number(start_date);
     
     // Get all meals for this week and group by date
     let meal_plan = state.db.get_meal_plan_for_week(start_date_str.clone()).await.unwrap_or_default();
@@ -614,7 +1116,8 @@ async fn meal_plan_page(Query(params): Query<WeekQuery>, State(state): State<App
         meals_by_date.entry(meal.date.clone()).or_default().push(meal);
     }
     
-    let week_days = build_week_structure(start_date, meals_by_date);
+    let notes_by_date = state.db.get_meal_plan_notes_for_week(start_date_str.clone()).await.unwrap_or_default();
+    let week_days = build_week_structure(start_date, meals_by_date, notes_by_date, &locale);
     
     let template = MealPlanTemplate { 
         start_date: start_date_str,
@@ -627,45 +1130,83 @@ async fn meal_plan_page(Query(params): Query<WeekQuery>, State(state): State<App
     HtmlTemplate(template).into_response()
 }
 
+// How far back the add meal form looks for meals to eat leftovers from
+const LEFTOVERS_LOOKBACK_DAYS: u32 = 7;
+
 async fn add_meal_form(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path(date): Path<String>,
 ) -> impl IntoResponse {
+    use chrono::Datelike;
+
     // Parse and format the date for display
+    let locale = current_locale(&state).await;
     let display_date = match chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
-        Ok(parsed_date) => parsed_date.format("%d.%m.%y").to_string(),
+        Ok(parsed_date) => locale.format_date(parsed_date),
         Err(_) => date.clone(), // Fallback to original if parsing fails
     };
 
-    match state.db.get_recipes().await {
-        Ok(recipes) => {
-            let template = AddMealFormTemplate { 
-                date,
-                display_date,
-                recipes
-            };
-            HtmlTemplate(template).into_response()
-        }
-        Err(_) => {
-            let template = AddMealFormTemplate { 
-                date,
-                display_date,
-                recipes: vec![]
+    // Earlier meals that were actually cooked, labelled with their day
+    let leftover_candidates = state.db.get_meal_plan_before(date.clone(), LEFTOVERS_LOOKBACK_DAYS).await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|entry| entry.leftovers_of.is_none())
+        .map(|entry| {
+            let label = match chrono::NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d") {
+                Ok(day) => format!("{} {}: {}", locale.day_name(day.weekday()), locale.format_date(day), entry.meal_text),
+                Err(_) => entry.meal_text.clone(),
             };
-            HtmlTemplate(template).into_response()
-        }
-    }
+            (entry.id, label)
+        })
+        .collect();
+
+    let recipes = state.db.get_recipes().await.unwrap_or_default();
+    let template = AddMealFormTemplate { 
+        date,
+        display_date,
+        recipes,
+        leftover_candidates,
+    };
+    HtmlTemplate(template).into_response()
 }
 
 async fn add_meal(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path(date): Path<String>,
     form: Form<MealForm>,
 ) -> impl IntoResponse {
+    if chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d").is_err() {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    let mut meal_text = form.meal_text.trim().to_string();
+    let mut recipe_id = form.recipe_id;
+
+    // Leftovers point at the meal that was cooked, so they never pull in a recipe's ingredients
+    let leftovers_of = match form.leftovers_of {
+        Some(entry_id) => {
+            let original = match state.db.get_meal_plan_entry(entry_id).await {
+                Ok(entry) => entry,
+                Err(_) => return StatusCode::NOT_FOUND.into_response()
+            };
+            if original.date >= date {
+                return (StatusCode::BAD_REQUEST, "Leftovers must come from an earlier meal").into_response();
+            }
+            if meal_text.is_empty() {
+                meal_text = format!("Leftovers: {}", original.meal_text);
+            }
+            recipe_id = None;
+            Some(original.leftovers_of.unwrap_or(original.id))
+        }
+        None => None,
+    };
+
     match state.db.create_meal_plan_entry(
         date,
-        form.meal_text.clone(),
-        form.recipe_id,
+        meal_text,
+        recipe_id,
+        leftovers_of,
+        None,
     ).await {
         Ok(_) => {
             Redirect::to("/meal-plan").into_response()
@@ -674,8 +1215,23 @@ async fn add_meal(
     }
 }
 
+async fn update_day_note(
+    HouseholdState(state): HouseholdState,
+    Path(date): Path<String>,
+    form: Form<DayNoteForm>,
+) -> impl IntoResponse {
+    if chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d").is_err() {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    match state.db.set_meal_plan_note(date, form.note.clone()).await {
+        Ok(_) => StatusCode::OK.into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    }
+}
+
 async fn delete_meal(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path(id): Path<u32>,
 ) -> impl IntoResponse {
     match state.db.delete_meal_plan_entry(id as usize).await {
@@ -694,38 +1250,49 @@ struct WeekQuery {
 }
 
 async fn weekly_ingredients_form(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path(start_date): Path<String>,
 ) -> impl IntoResponse {
     use chrono::NaiveDate;
     
     // Parse start date and calculate week range
+    let locale = current_locale(&state).await;
     let parsed_start = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
-        .unwrap_or_else(|_| chrono::Utc::now().date_naive());
+        .unwrap_or_else(|_| locale.today());
     
-    // Format the date in dd.MM.yy format for display
-    let formatted_start_date = parsed_start.format("%d.%m.%y").to_string();
+    // Format the date for display
+    let formatted_start_date = locale.format_date(parsed_start);
     
     // Get all meal plan entries for this week that have recipes
     let meal_entries = state.db.get_meal_plan_for_week(start_date.clone()).await.unwrap_or_default();
     
-    // Collect unique ingredients from all recipes
-    let mut all_ingredients = Vec::new();
+    // Collect ingredients from all recipes, scaled to the planned servings
+    let mut scaled_ingredients = Vec::new();
     for entry in meal_entries {
+        // Leftovers were already bought for the original meal
+        if entry.leftovers_of.is_some() {
+            continue;
+        }
         if let Some(recipe_id) = entry.recipe_id {
             if let Ok(recipe) = state.db.get_recipe(recipe_id).await {
-                for ingredient in recipe.ingredients.split('\n') {
-                    let ingredient = ingredient.trim();
-                    if !ingredient.is_empty() && !all_ingredients.contains(&ingredient.to_string()) {
-                        all_ingredients.push(ingredient.to_string());
-                    }
+                let factor = entry.servings_factor(&recipe);
+                for ingredient in ingredients::parse_lines(&recipe.ingredients) {
+                    scaled_ingredients.push(ingredient.scaled(factor));
                 }
             }
         }
     }
+
+    // Sum up the same ingredient used by several meals
+    let all_ingredients: Vec<String> = ingredients::aggregate(scaled_ingredients)
+        .iter()
+        .map(|ingredient| ingredient.to_string())
+        .collect();
     
     let lists = state.db.get_lists().await.unwrap_or_default();
+    let prices = state.db.get_last_prices().await.unwrap_or_default();
     let template = WeeklyIngredientsTemplate {
+        estimate: prices::estimate(&all_ingredients, &prices),
         start_date,
         display_date: formatted_start_date,
         ingredients: all_ingredients,
@@ -739,7 +1306,7 @@ async fn weekly_ingredients_form(
 }
 
 async fn add_weekly_ingredients(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path(_start_date): Path<String>,
     RawForm(body): RawForm,
 ) -> impl IntoResponse {
@@ -761,8 +1328,478 @@ async fn add_weekly_ingredients(
     Redirect::to("/meal-plan").into_response()
 }
 
+#[derive(Deserialize)]
+struct SuggestionQuery {
+    exclude: Option<String>,
+    repeat_days: Option<i64>,
+}
+
+impl SuggestionQuery {
+    fn planner_config(&self) -> planner::PlannerConfig {
+        let mut config = planner::PlannerConfig::default();
+        if let Some(days) = self.repeat_days {
+            config.avoid_repeat_days = days.max(0);
+        }
+        config
+    }
+
+    fn excluded_ids(&self) -> Vec<usize> {
+        self.exclude
+            .as_deref()
+            .unwrap_or("")
+            .split(',')
+            .filter_map(|id| id.trim().parse().ok())
+            .collect()
+    }
+}
+
+fn suggestion_slot(date: chrono::NaiveDate, recipe: Option<todo::Recipe>, mut exclude: Vec<usize>, accepted: bool, locale: &Locale) -> MealSuggestionSlot {
+    use chrono::Datelike;
+    if let Some(recipe) = &recipe {
+        exclude.push(recipe.id);
+    }
+    MealSuggestionSlot {
+        day_name: locale.day_name(date.weekday()),
+        date: locale.format_date(date),
+        db_date: date.format("%Y-%m-%d").to_string(),
+        recipe,
+        exclude: exclude.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(","),
+        accepted,
+    }
+}
+
+// Suggest recipes for every day of the week that has nothing planned yet
+async fn meal_suggestions_page(
+    HouseholdState(state): HouseholdState,
+    Path(start_date): Path<String>,
+    Query(params): Query<SuggestionQuery>,
+) -> impl IntoResponse {
+    use chrono::Duration;
+
+    let locale = current_locale(&state).await;
+    let start = parse_week_start_date(Some(start_date), &locale);
+    let start_date = start.format("%Y-%m-%d").to_string();
+
+    let planned_dates: Vec<String> = state.db.get_meal_plan_for_week(start_date.clone()).await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|entry| entry.date)
+        .collect();
+    let empty_dates: Vec<chrono::NaiveDate> = (0..7)
+        .map(|i| start + Duration::days(i))
+        .filter(|date| !planned_dates.contains(&date.format("%Y-%m-%d").to_string()))
+        .collect();
+
+    let recipes = state.db.get_recipes().await.unwrap_or_default();
+    let history = planner::build_history(state.db.get_recipe_plan_history().await.unwrap_or_default());
+    let suggestions = planner::suggest_for_dates(&recipes, &history, &empty_dates, &params.planner_config());
+
+    let slots = empty_dates
+        .iter()
+        .zip(suggestions)
+        .map(|(date, suggestion)| suggestion_slot(*date, suggestion.map(|s| s.recipe), vec![], false, &locale))
+        .collect();
+
+    let template = MealSuggestionsTemplate {
+        display_date: locale.format_date(start),
+        start_date,
+        slots,
+    };
+    HtmlTemplate(template).into_response()
+}
+
+// Replace a single suggestion with the next best recipe not offered yet
+async fn reroll_meal_suggestion(
+    HouseholdState(state): HouseholdState,
+    Path(date): Path<String>,
+    Query(params): Query<SuggestionQuery>,
+) -> impl IntoResponse {
+    let date = match chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
+        Ok(date) => date,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+
+    let exclude = params.excluded_ids();
+    let recipes = state.db.get_recipes().await.unwrap_or_default();
+    let history = planner::build_history(state.db.get_recipe_plan_history().await.unwrap_or_default());
+    let suggestion = planner::suggest_for_date(&recipes, &history, date, &exclude, &params.planner_config());
+
+    let locale = current_locale(&state).await;
+    let slot = suggestion_slot(date, suggestion.map(|s| s.recipe), exclude, false, &locale);
+    HtmlTemplate(MealSuggestionTemplate { slot }).into_response()
+}
+
+async fn accept_meal_suggestion(
+    HouseholdState(state): HouseholdState,
+    Path(date): Path<String>,
+    form: Form<AcceptSuggestionForm>,
+) -> impl IntoResponse {
+    let parsed_date = match chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
+        Ok(date) => date,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+
+    let recipe = match state.db.get_recipe(form.recipe_id).await {
+        Ok(recipe) => recipe,
+        Err(_) => return StatusCode::NOT_FOUND.into_response()
+    };
+
+    // A second click, or another tab, shows what the day already has instead of planning it twice
+    let planned = match state.db.create_meal_plan_entry_if_free(date.clone(), recipe.title.clone(), recipe.id).await {
+        Ok(Some(_)) => Some(recipe),
+        Ok(None) => match state.db.get_meal_plan_between(date.clone(), date).await {
+            Ok(entries) => match entries.first().and_then(|entry| entry.recipe_id) {
+                Some(recipe_id) => state.db.get_recipe(recipe_id).await.ok(),
+                None => None,
+            },
+            Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        },
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    let locale = current_locale(&state).await;
+    let slot = suggestion_slot(parsed_date, planned, vec![], true, &locale);
+    HtmlTemplate(MealSuggestionTemplate { slot }).into_response()
+}
+
+// Pantry handlers
+async fn pantry_shopping_list(state: &AppState) -> Option<usize> {
+    state.db.get_pantry_shopping_list().await.unwrap_or_default()
+}
+
+/// Reads back a task after `before` was changed. If that checked it off on the pantry
+/// shopping list, it moves into the pantry. Used wherever tasks get checked off.
+pub(crate) async fn stock_pantry_if_bought(state: &AppState, before: &Task) -> anyhow::Result<Task> {
+    let task = state.db.get_task(before.id).await?;
+    if task.completed && !before.completed && pantry_shopping_list(state).await == Some(task.list_id) {
+        if let Err(err) = stock_pantry_from_task(state, &task).await {
+            warn!("Failed to move task {} into the pantry: {}", task.id, err);
+        }
+    }
+    Ok(task)
+}
+
+/// Adds a checked off shopping task to the pantry, topping up a matching item if there is one.
+/// A task is only ever added once, however often it is unchecked and checked off again.
+async fn stock_pantry_from_task(state: &AppState, task: &Task) -> anyhow::Result<()> {
+    if !state.db.mark_task_stocked(task.id).await? {
+        info!("Task {} is already in the pantry", task.id);
+        return Ok(());
+    }
+    let bought = ingredients::Ingredient::parse(&task.text);
+    let items = state.db.get_pantry_items().await?;
+    match pantry::find_same_item(&bought, &items) {
+        Some(item) => {
+            if let (Some(current), Some(quantity)) = (item.quantity, bought.quantity) {
+                state.db.update_pantry_item_quantity(item.id, Some(current + quantity)).await?;
+            }
+        }
+        None => {
+            state.db.create_pantry_item(bought.name, bought.quantity, bought.unit, None).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn pantry_page(HouseholdState(state): HouseholdState) -> impl IntoResponse {
+    let items = state.db.get_pantry_items().await.unwrap_or_default();
+    let lists = state.db.get_lists().await.unwrap_or_default();
+    let shopping_list_id = pantry_shopping_list(&state).await;
+    let template = PantryTemplate { items, lists, shopping_list_id };
+    HtmlTemplate(template).into_response()
+}
+
+async fn add_pantry_item(HouseholdState(state): HouseholdState, form: Form<PantryItemForm>) -> impl IntoResponse {
+    let name = form.name.trim().to_string();
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Name is required").into_response();
+    }
+
+    let quantity = if form.quantity.trim().is_empty() {
+        None
+    } else {
+        match ingredients::parse_quantity(&form.quantity) {
+            Some(quantity) => Some(quantity),
+            None => return (StatusCode::BAD_REQUEST, "Invalid quantity").into_response(),
+        }
+    };
+    let unit = Some(form.unit.trim().to_string()).filter(|unit| !unit.is_empty());
+    let expires = if form.expires.trim().is_empty() {
+        None
+    } else {
+        match chrono::NaiveDate::parse_from_str(form.expires.trim(), "%Y-%m-%d") {
+            Ok(date) => Some(date.format("%Y-%m-%d").to_string()),
+            Err(_) => return (StatusCode::BAD_REQUEST, "Invalid expiry date").into_response(),
+        }
+    };
+
+    match state.db.create_pantry_item(name, quantity, unit, expires).await {
+        Ok(_) => Redirect::to("/pantry").into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+async fn delete_pantry_item(HouseholdState(state): HouseholdState, Path(id): Path<usize>) -> impl IntoResponse {
+    match state.db.delete_pantry_item(id).await {
+        Ok(_) => Redirect::to("/pantry").into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+async fn update_pantry_shopping_list(HouseholdState(state): HouseholdState, form: Form<PantryShoppingListForm>) -> impl IntoResponse {
+    if let Some(list_id) = form.list_id {
+        if state.db.get_list(list_id).await.is_err() {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+    }
+
+    match state.db.set_pantry_shopping_list(form.list_id).await {
+        Ok(_) => Redirect::to("/pantry").into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct ExpiringQuery {
+    days: Option<i64>,
+}
+
+async fn expiring_pantry_page(Query(params): Query<ExpiringQuery>, HouseholdState(state): HouseholdState) -> impl IntoResponse {
+    let days = params.days.unwrap_or(pantry::DEFAULT_EXPIRY_DAYS).clamp(0, 365);
+    let locale = current_locale(&state).await;
+    let today = locale.today();
+
+    let items = state.db.get_pantry_items().await.unwrap_or_default();
+    let recipes = state.db.get_recipes().await.unwrap_or_default();
+    let template = ExpiringPantryTemplate {
+        days,
+        items: pantry::expiring_items(items, &recipes, today, days),
+    };
+    HtmlTemplate(template).into_response()
+}
+
+/// Plans a recipe into the next day without meals, so expiring items get used
+async fn plan_recipe_for_expiring(HouseholdState(state): HouseholdState, form: Form<PlanRecipeForm>) -> impl IntoResponse {
+    let recipe = match state.db.get_recipe(form.recipe_id).await {
+        Ok(recipe) => recipe,
+        Err(_) => return StatusCode::NOT_FOUND.into_response()
+    };
+
+    let locale = current_locale(&state).await;
+    let today = locale.today();
+    let planned = state.db
+        .get_planned_dates_from(today.format("%Y-%m-%d").to_string())
+        .await
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .collect();
+    let Some(date) = pantry::next_free_date(today, &planned) else {
+        return (StatusCode::CONFLICT, "No free day in the meal plan").into_response();
+    };
+
+    let db_date = date.format("%Y-%m-%d").to_string();
+    match state.db.create_meal_plan_entry(db_date, recipe.title, Some(recipe.id), None, None).await {
+        Ok(_) => {
+            let week_start = locale.week_start_for(date).format("%Y-%m-%d");
+            Redirect::to(&format!("/meal-plan?week={}", week_start)).into_response()
+        }
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    }
+}
+
+async fn cookable_recipes_page(HouseholdState(state): HouseholdState) -> impl IntoResponse {
+    let recipes = state.db.get_recipes().await.unwrap_or_default();
+    let items = state.db.get_pantry_items().await.unwrap_or_default();
+    let template = CookableRecipesTemplate {
+        pantry_empty: items.is_empty(),
+        recipes: pantry::rank_cookable(recipes, &items),
+    };
+    HtmlTemplate(template).into_response()
+}
+
+// Store handlers
+async fn stores_page(HouseholdState(state): HouseholdState) -> impl IntoResponse {
+    let mut stores = Vec::new();
+    for store in state.db.get_stores().await.unwrap_or_default() {
+        let order = state.db.get_store_aisles(store.id).await.unwrap_or_default();
+        let aisles = aisles::aisle_order(&order)
+            .into_iter()
+            .map(|key| {
+                let label = aisles::category_label(&key).to_string();
+                (key, label)
+            })
+            .collect();
+        stores.push((store, aisles));
+    }
+    HtmlTemplate(StoresTemplate { stores }).into_response()
+}
+
+async fn create_store(HouseholdState(state): HouseholdState, form: Form<StoreForm>) -> impl IntoResponse {
+    let name = form.name.trim().to_string();
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Name is required").into_response();
+    }
+    match state.db.create_store(name).await {
+        Ok(_) => Redirect::to("/stores").into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// Saves a store's aisle order, posted as one position number per category key
+async fn update_store_aisles(
+    HouseholdState(state): HouseholdState,
+    Path(id): Path<usize>,
+    Form(positions): Form<std::collections::HashMap<String, String>>,
+) -> impl IntoResponse {
+    if !state.db.get_stores().await.unwrap_or_default().iter().any(|store| store.id == id) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let mut order = Vec::new();
+    for (category, position) in positions {
+        if !aisles::is_category(&category) {
+            continue;
+        }
+        match position.trim().parse::<u32>() {
+            Ok(position) => order.push((position, category)),
+            Err(_) => return (StatusCode::BAD_REQUEST, "Invalid aisle position").into_response(),
+        }
+    }
+    // Ties keep the default category order
+    order.sort_by_key(|(position, category)| {
+        (*position, aisles::CATEGORIES.iter().position(|(key, _)| key == category))
+    });
+
+    let categories = order.into_iter().map(|(_, category)| category).collect();
+    match state.db.set_store_aisles(id, categories).await {
+        Ok(_) => Redirect::to("/stores").into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+async fn delete_store(HouseholdState(state): HouseholdState, Path(id): Path<usize>) -> impl IntoResponse {
+    match state.db.delete_store(id).await {
+        Ok(_) => Redirect::to("/stores").into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct LoginQuery {
+    next: Option<String>,
+}
+
+async fn login_page(Query(params): Query<LoginQuery>) -> impl IntoResponse {
+    let template = LoginTemplate {
+        username: String::new(),
+        next: auth::safe_next(params.next.as_deref()),
+        error: None,
+    };
+    HtmlTemplate(template).into_response()
+}
+
+async fn login(State(state): State<AppState>, form: Form<LoginForm>) -> impl IntoResponse {
+    let next = auth::safe_next(form.next.as_deref());
+    let failed = |error: &str| {
+        let template = LoginTemplate {
+            username: form.username.clone(),
+            next: next.clone(),
+            error: Some(error.to_string()),
+        };
+        (StatusCode::UNAUTHORIZED, HtmlTemplate(template)).into_response()
+    };
+
+    let user = match auth::authenticate(&state.db, &form.username, &form.password).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            info!("Failed login for {:?}", form.username);
+            return failed("Wrong username or password");
+        }
+        Err(err) => {
+            warn!("Login failed: {}", err);
+            return failed("Could not log in, please try again");
+        }
+    };
+
+    match auth::start_session(&state.db, &user).await {
+        Ok(token) => {
+            info!("User {} logged in", user.username);
+            ([(header::SET_COOKIE, auth::session_cookie(&token))], Redirect::to(&next)).into_response()
+        }
+        Err(err) => {
+            warn!("Failed to start session: {}", err);
+            failed("Could not log in, please try again")
+        }
+    }
+}
+
+async fn logout(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    if let Some(token) = auth::session_token(&headers) {
+        if let Err(err) = state.db.delete_session(token).await {
+            warn!("Failed to delete session: {}", err);
+        }
+    }
+    ([(header::SET_COOKIE, auth::clear_session_cookie())], Redirect::to("/login")).into_response()
+}
+
+#[derive(Deserialize)]
+struct SettingsQuery {
+    saved: Option<bool>,
+}
+
+async fn settings_page(
+    Query(params): Query<SettingsQuery>,
+    HouseholdState(state): HouseholdState,
+    Extension(user): Extension<User>,
+) -> impl IntoResponse {
+    let locale = current_locale(&state).await;
+    let template = SettingsTemplate {
+        username: user.username,
+        timezone: locale.timezone.name().to_string(),
+        timezones: chrono_tz::TZ_VARIANTS.iter().map(|tz| tz.name().to_string()).collect(),
+        language: locale.language.code().to_string(),
+        week_start: if locale.week_starts_on_sunday() { "sunday" } else { "monday" }.to_string(),
+        date_format: locale.date_format.clone(),
+        languages: locale::Language::ALL
+            .iter()
+            .map(|language| (language.code().to_string(), language.name().to_string()))
+            .collect(),
+        date_formats: locale::DATE_FORMATS
+            .iter()
+            .map(|(pattern, example)| (pattern.to_string(), example.to_string()))
+            .collect(),
+        saved: params.saved.unwrap_or(false),
+    };
+    HtmlTemplate(template).into_response()
+}
+
+async fn update_settings(HouseholdState(state): HouseholdState, form: Form<SettingsForm>) -> impl IntoResponse {
+    let valid = locale::Language::from_code(&form.language).is_some()
+        && locale::parse_week_start(&form.week_start).is_some()
+        && locale::DATE_FORMATS.iter().any(|(pattern, _)| *pattern == form.date_format)
+        && locale::parse_timezone(&form.timezone).is_some();
+    if !valid {
+        return (StatusCode::BAD_REQUEST, "Invalid settings").into_response();
+    }
+
+    let settings = [
+        (locale::LANGUAGE_SETTING, &form.language),
+        (locale::WEEK_START_SETTING, &form.week_start),
+        (locale::DATE_FORMAT_SETTING, &form.date_format),
+        (locale::TIMEZONE_SETTING, &form.timezone),
+    ];
+    for (key, value) in settings {
+        if state.db.set_setting(key.to_string(), value.clone()).await.is_err() {
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    }
+
+    Redirect::to("/settings?saved=true").into_response()
+}
+
 // Photo handling utilities
-fn generate_thumbnail(image_data: &[u8], max_size: u32) -> anyhow::Result<Vec<u8>> {
+pub(crate) fn generate_thumbnail(image_data: &[u8], max_size: u32) -> anyhow::Result<Vec<u8>> {
     use anyhow::Context;
     
     let img = image::load_from_memory(image_data)
@@ -943,7 +1980,7 @@ async fn save_photo_to_disk_and_db(
 
 // Simplified unified photo upload handler
 async fn upload_photos_unified(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path(recipe_id): Path<u32>,
     headers: HeaderMap,
     body: bytes::Bytes,
@@ -1001,10 +2038,19 @@ async fn upload_photos_unified(
 
 // Serve full-size photos
 async fn serve_photo(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path(filename): Path<String>,
 ) -> impl IntoResponse {
-    let file_path = state.photos_dir.join(&filename);
+    // Only photos of the household's recipes, which also keeps the path inside photos_dir
+    match state.db.get_recipe_photo_by_filename(filename).await {
+        Ok(Some(photo)) => photo_file(&state, &photo).await,
+        _ => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn photo_file(state: &AppState, photo: &todo::RecipePhoto) -> Response {
+    let filename = &photo.filename;
+    let file_path = state.photos_dir.join(filename);
     
     match tokio::fs::read(&file_path).await {
         Ok(data) => {
@@ -1020,7 +2066,7 @@ async fn serve_photo(
             
             let mut headers = HeaderMap::new();
             headers.insert("content-type", content_type.parse().unwrap());
-            headers.insert("cache-control", "public, max-age=86400".parse().unwrap());
+            headers.insert("cache-control", "private, max-age=86400".parse().unwrap());
             
             (headers, data).into_response()
         }
@@ -1030,7 +2076,7 @@ async fn serve_photo(
 
 // Serve thumbnails from database
 async fn serve_thumbnail(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path(photo_id): Path<u32>,
 ) -> impl IntoResponse {
     match state.db.get_recipe_photo_by_id(photo_id as usize).await {
@@ -1038,7 +2084,7 @@ async fn serve_thumbnail(
             if let Some(thumbnail_data) = photo.thumbnail_blob {
                 let mut headers = HeaderMap::new();
                 headers.insert("content-type", "image/jpeg".parse().unwrap());
-                headers.insert("cache-control", "public, max-age=86400".parse().unwrap());
+                headers.insert("cache-control", "private, max-age=86400".parse().unwrap());
                 
                 (headers, thumbnail_data).into_response()
             } else {
@@ -1078,7 +2124,7 @@ async fn serve_default_photo(
 
 // Delete individual photo
 async fn delete_recipe_photo(
-    State(state): State<AppState>,
+    HouseholdState(state): HouseholdState,
     Path((recipe_id, photo_id)): Path<(u32, u32)>,
 ) -> impl IntoResponse {
     let photo_id = photo_id as usize;
@@ -0,0 +1,161 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Utc, Weekday};
+use chrono_tz::Tz;
+use std::collections::HashMap;
+
+/// Languages available for day names
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    English,
+    Norwegian,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::Norwegian];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Norwegian => "nb",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Norwegian => "Norsk (bokmål)",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Language> {
+        Language::ALL.into_iter().find(|language| language.code() == code)
+    }
+
+    fn day_names(&self) -> [&'static str; 7] {
+        // Indexed by days from Monday
+        match self {
+            Language::English => ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"],
+            Language::Norwegian => ["Mandag", "Tirsdag", "Onsdag", "Torsdag", "Fredag", "Lørdag", "Søndag"],
+        }
+    }
+}
+
+/// Date formats that can be picked on the settings page, as (strftime pattern, example)
+pub const DATE_FORMATS: [(&str, &str); 4] = [
+    ("%d.%m.%y", "31.12.24"),
+    ("%d.%m.%Y", "31.12.2024"),
+    ("%Y-%m-%d", "2024-12-31"),
+    ("%m/%d/%Y", "12/31/2024"),
+];
+
+// Keys in the settings table
+pub const LANGUAGE_SETTING: &str = "language";
+pub const WEEK_START_SETTING: &str = "week_start";
+pub const DATE_FORMAT_SETTING: &str = "date_format";
+pub const TIMEZONE_SETTING: &str = "timezone";
+
+/// Format of the created/modified columns, which are stored in UTC
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Controls how days and dates are presented, and which day a week starts on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Locale {
+    pub language: Language,
+    pub week_start: Weekday,
+    pub date_format: String,
+    /// Household timezone, used to decide what "today" is
+    pub timezone: Tz,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self {
+            language: Language::English,
+            week_start: Weekday::Mon,
+            date_format: DATE_FORMATS[0].0.to_string(),
+            timezone: Tz::UTC,
+        }
+    }
+}
+
+impl Locale {
+    /// Builds a locale from the settings table, falling back to defaults for missing or unknown values
+    pub fn from_settings(settings: &HashMap<String, String>) -> Locale {
+        let mut locale = Locale::default();
+        if let Some(language) = settings.get(LANGUAGE_SETTING).and_then(|code| Language::from_code(code)) {
+            locale.language = language;
+        }
+        if let Some(week_start) = settings.get(WEEK_START_SETTING).and_then(|day| parse_week_start(day)) {
+            locale.week_start = week_start;
+        }
+        if let Some(format) = settings.get(DATE_FORMAT_SETTING) {
+            if DATE_FORMATS.iter().any(|(pattern, _)| pattern == format) {
+                locale.date_format = format.clone();
+            }
+        }
+        if let Some(timezone) = settings.get(TIMEZONE_SETTING).and_then(|name| parse_timezone(name)) {
+            locale.timezone = timezone;
+        }
+        locale
+    }
+
+    /// The current date in the household timezone
+    pub fn today(&self) -> NaiveDate {
+        self.today_at(Utc::now())
+    }
+
+    pub fn today_at(&self, now: DateTime<Utc>) -> NaiveDate {
+        now.with_timezone(&self.timezone).date_naive()
+    }
+
+    /// Converts a stored UTC timestamp to local time in the household timezone
+    pub fn local_timestamp(&self, stored: &str) -> Option<NaiveDateTime> {
+        let utc = NaiveDateTime::parse_from_str(stored.get(..19)?, TIMESTAMP_FORMAT).ok()?;
+        Some(utc.and_utc().with_timezone(&self.timezone).naive_local())
+    }
+
+    pub fn day_name(&self, weekday: Weekday) -> String {
+        self.language.day_names()[weekday.num_days_from_monday() as usize].to_string()
+    }
+
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        date.format(&self.date_format).to_string()
+    }
+
+    /// First day of the week containing `date`
+    pub fn week_start_for(&self, date: NaiveDate) -> NaiveDate {
+        let offset = (7 + date.weekday().num_days_from_monday() - self.week_start.num_days_from_monday()) % 7;
+        date - Duration::days(offset as i64)
+    }
+
+    /// Week number shown for a week starting on `start`. Sunday-start weeks use
+    /// the ISO week of the Monday that follows.
+    pub fn week_number(&self, start: NaiveDate) -> (u32, i32) {
+        let iso_week = (start + Duration::days(3)).iso_week();
+        (iso_week.week(), iso_week.year())
+    }
+
+    /// BCP 47 tag for client-side formatting, e.g. toLocaleDateString
+    pub fn language_tag(&self) -> &'static str {
+        match self.language {
+            Language::English => "en-GB",
+            Language::Norwegian => "nb-NO",
+        }
+    }
+
+    pub fn week_starts_on_sunday(&self) -> bool {
+        self.week_start == Weekday::Sun
+    }
+}
+
+pub fn parse_week_start(day: &str) -> Option<Weekday> {
+    match day {
+        "monday" => Some(Weekday::Mon),
+        "sunday" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// Parses an IANA timezone name such as "Europe/Oslo"
+pub fn parse_timezone(name: &str) -> Option<Tz> {
+    name.trim().parse().ok()
+}
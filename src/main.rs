@@ -1,9 +1,10 @@
-use htmx_rs_todo::{database::Database, AppState, create_app};
+use htmx_rs_todo::{auth, backup, cli::{Backend, Remote}, database::Database, export, locale, AppState, create_app};
 use anyhow::Context;
 use tokio::net::TcpListener;
 use tracing_subscriber;
 use tracing::info;
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use std::io::IsTerminal;
 
 /// Crappy todo app to test out HTMX with Rust as the backend
 #[derive(Debug, Parser)]
@@ -20,16 +21,280 @@ struct Cli {
     /// Listening address
     #[arg(short, long, default_value = "127.0.0.1")]
     address: String,
+
+    /// IANA timezone used for "today", e.g. Europe/Oslo. Saved for households that haven't
+    /// picked one on the settings page yet
+    #[arg(long)]
+    timezone: Option<String>,
+
+    /// Directory to write scheduled backups to while serving. No backups are made without it
+    #[arg(long)]
+    backup_dir: Option<std::path::PathBuf>,
+
+    /// Hours between scheduled backups
+    #[arg(long, default_value_t = 24)]
+    backup_interval_hours: u64,
+
+    /// Number of scheduled backups to keep, older ones are deleted
+    #[arg(long, default_value_t = 7)]
+    backup_keep: usize,
+
+    /// Run the task, list, recipe and meal subcommands against the server at this URL, e.g.
+    /// http://localhost:3000, instead of on the database. The password is read from
+    /// TODO_PASSWORD or asked for.
+    #[arg(long)]
+    server: Option<String>,
+
+    /// User to log in to --server as
+    #[arg(long)]
+    username: Option<String>,
+
+    /// Household, by ID or name, the task, list, recipe and meal subcommands work in on the
+    /// database. Needed once there is more than one
+    #[arg(long)]
+    household: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Create an admin user, prompting for the password
+    CreateAdmin {
+        username: String,
+    },
+    /// Write the database and photos to a .tar.zst archive
+    Backup {
+        #[arg(long)]
+        out: std::path::PathBuf,
+    },
+    /// Unpack a backup into --data-dir. Stop the server first
+    Restore {
+        archive: std::path::PathBuf,
+        /// Replace an existing database
+        #[arg(long)]
+        force: bool,
+    },
+    /// Write lists, recipes and meal plans of every household as JSON, with the photos, to a directory
+    Export {
+        #[arg(long)]
+        out: std::path::PathBuf,
+    },
+    /// Add an export to this server's data, under new IDs
+    Import {
+        dir: std::path::PathBuf,
+    },
+    /// Add, show and check off tasks
+    Task {
+        #[command(subcommand)]
+        command: TaskCommand,
+    },
+    /// Show the lists
+    List {
+        #[command(subcommand)]
+        command: ListCommand,
+    },
+    /// Show the recipes
+    Recipe {
+        #[command(subcommand)]
+        command: RecipeCommand,
+    },
+    /// Plan meals
+    Meal {
+        #[command(subcommand)]
+        command: MealCommand,
+    },
+}
+
+impl Command {
+    /// Whether it can run against --server
+    fn is_scripting(&self) -> bool {
+        matches!(self, Command::Task { .. } | Command::List { .. } | Command::Recipe { .. } | Command::Meal { .. })
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum TaskCommand {
+    /// Add a task, printing its ID
+    Add {
+        /// List ID or name
+        #[arg(long)]
+        list: String,
+        #[arg(required = true)]
+        text: Vec<String>,
+    },
+    /// Print open tasks as ID, [ ] or [x], text and list
+    Ls {
+        /// List ID or name, all lists if left out
+        #[arg(long)]
+        list: Option<String>,
+        /// Include checked off tasks
+        #[arg(long)]
+        all: bool,
+    },
+    /// Check off tasks
+    Done {
+        #[arg(required = true)]
+        ids: Vec<usize>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ListCommand {
+    /// Print lists as ID and name
+    Ls,
+}
+
+#[derive(Debug, Subcommand)]
+enum RecipeCommand {
+    /// Print recipes as ID and title
+    Ls,
+}
+
+#[derive(Debug, Subcommand)]
+enum MealCommand {
+    /// Plan a meal, printing its ID
+    Add {
+        /// Day, YYYY-MM-DD
+        #[arg(long)]
+        date: String,
+        /// Recipe ID or title, whose title is the meal if no text is given
+        #[arg(long)]
+        recipe: Option<String>,
+        text: Vec<String>,
+    },
+}
+
+/// The first line of stdin when it isn't a terminal, for scripts passing a password in
+fn piped_password() -> anyhow::Result<Option<String>> {
+    if std::io::stdin().is_terminal() {
+        return Ok(None);
+    }
+    let mut password = String::new();
+    std::io::stdin().read_line(&mut password).context("Read password from stdin")?;
+    Ok(Some(password.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+/// Asks for a password without echoing it, or reads the first line of stdin when it isn't a terminal
+fn prompt_password() -> anyhow::Result<String> {
+    if let Some(password) = piped_password()? {
+        return Ok(password);
+    }
+    rpassword::prompt_password("Password: ").context("Read password")
+}
+
+/// Runs a task, list, recipe or meal subcommand, printing tab separated lines for scripts
+async fn run_scripting(backend: &Backend, command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::Task { command: TaskCommand::Add { list, text } } => {
+            let list = backend.find_list(&list).await?;
+            let task = backend.add_task(list.id, &text.join(" ")).await?;
+            println!("{}", task.id);
+        }
+        Command::Task { command: TaskCommand::Ls { list, all } } => {
+            let lists = match list {
+                Some(list) => vec![backend.find_list(&list).await?],
+                None => backend.lists().await?,
+            };
+            for list in lists {
+                for task in backend.tasks(list.id).await? {
+                    if all || !task.completed {
+                        let check = if task.completed { "[x]" } else { "[ ]" };
+                        println!("{}\t{}\t{}\t{}", task.id, check, task.text, list.name);
+                    }
+                }
+            }
+        }
+        Command::Task { command: TaskCommand::Done { ids } } => {
+            for id in ids {
+                let task = backend.complete_task(id).await.with_context(|| format!("Check off task {}", id))?;
+                println!("{}\t[x]\t{}", task.id, task.text);
+            }
+        }
+        Command::List { command: ListCommand::Ls } => {
+            for list in backend.lists().await? {
+                println!("{}\t{}", list.id, list.name);
+            }
+        }
+        Command::Recipe { command: RecipeCommand::Ls } => {
+            for recipe in backend.recipes().await? {
+                println!("{}\t{}", recipe.id, recipe.title);
+            }
+        }
+        Command::Meal { command: MealCommand::Add { date, recipe, text } } => {
+            let recipe = match recipe {
+                Some(recipe) => Some(backend.find_recipe(&recipe).await?),
+                None => None,
+            };
+            let text = text.join(" ");
+            let meal = backend.add_meal(&date, Some(&text), recipe.as_ref()).await?;
+            println!("{}", meal.id);
+        }
+        _ => anyhow::bail!("Not a task, list, recipe or meal subcommand"),
+    }
+    Ok(())
+}
+
+/// Asks for a new password twice without echoing it. When stdin isn't a terminal the
+/// password is read from its first line instead, for scripted setups.
+fn prompt_new_password() -> anyhow::Result<String> {
+    if let Some(password) = piped_password()? {
+        return Ok(password);
+    }
+
+    let password = rpassword::prompt_password("Password: ").context("Read password")?;
+    let repeated = rpassword::prompt_password("Repeat password: ").context("Read password")?;
+    if password != repeated {
+        anyhow::bail!("Passwords do not match");
+    }
+    Ok(password)
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
-    tracing_subscriber::fmt::init();
+    // Scripts read the output of the task, list, recipe and meal subcommands
+    if cli.command.as_ref().is_some_and(Command::is_scripting) {
+        tracing_subscriber::fmt().with_writer(std::io::stderr).with_max_level(tracing::Level::WARN).init();
+    } else {
+        tracing_subscriber::fmt::init();
+    }
+
+    // Both work on the files, before the database is opened and migrated
+    match &cli.command {
+        Some(Command::Backup { out }) => {
+            backup::create(&cli.data_dir, out).context("Back up")?;
+            println!("Backed up {:?} to {:?}", cli.data_dir, out);
+            return Ok(());
+        }
+        Some(Command::Restore { archive, force }) => {
+            backup::restore(archive, &cli.data_dir, *force).context("Restore")?;
+            println!("Restored {:?} into {:?}", archive, cli.data_dir);
+            return Ok(());
+        }
+        _ => {}
+    }
 
-    let dbpath = cli.data_dir.join("todos.db");
-    let photos_dir = cli.data_dir.join("photos");
+    if let Some(server) = &cli.server {
+        let Some(command) = cli.command.filter(Command::is_scripting) else {
+            anyhow::bail!("--server only works with the task, list, recipe and meal subcommands");
+        };
+        if cli.household.is_some() {
+            anyhow::bail!("--household only works on the database, --server uses the household picked on its households page");
+        }
+        let username = cli.username.context("--server needs --username")?;
+        let password = match std::env::var("TODO_PASSWORD") {
+            Ok(password) => password,
+            Err(_) => prompt_password()?,
+        };
+        let remote = Remote::login(server, &username, &password).await?;
+        return run_scripting(&Backend::Remote(remote), command).await;
+    }
+
+    let dbpath = cli.data_dir.join(backup::DATABASE_FILE);
+    let photos_dir = cli.data_dir.join(backup::PHOTOS_DIR);
 
     info!("Opening database at {:?}", dbpath);
     info!("Photos directory: {:?}", photos_dir);
@@ -41,6 +306,51 @@ async fn main() -> anyhow::Result<()> {
 
     let db = Database::new(dbpath).await.context("Create db")?;
 
+    if let Some(timezone) = cli.timezone {
+        let tz = locale::parse_timezone(&timezone)
+            .with_context(|| format!("Unknown timezone {:?}", timezone))?;
+        info!("Using timezone {}", tz.name());
+        db.set_default_setting(locale::TIMEZONE_SETTING.to_string(), tz.name().to_string()).await?;
+    }
+
+    match cli.command {
+        Some(command) if command.is_scripting() => {
+            let backend = Backend::local(AppState { db, photos_dir }, cli.household.as_deref()).await?;
+            return run_scripting(&backend, command).await;
+        }
+        Some(Command::CreateAdmin { username }) => {
+            let password = prompt_new_password()?;
+            auth::create_user(&db, &username, &password, true)
+                .await
+                .with_context(|| format!("Create user {:?}", username))?;
+            println!("Created admin user {}", username.trim());
+            return Ok(());
+        }
+        Some(Command::Export { out }) => {
+            let exported = export::export(&db, &photos_dir, &out).await.context("Export")?;
+            println!("Exported {} households to {:?}", exported.households.len(), out);
+            return Ok(());
+        }
+        Some(Command::Import { dir }) => {
+            let summary = export::import(&db, &photos_dir, &dir).await.context("Import")?;
+            println!(
+                "Imported {} lists, {} tasks, {} recipes, {} photos and {} meals, creating {} households",
+                summary.lists, summary.tasks, summary.recipes, summary.photos, summary.meals, summary.households
+            );
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    if let Some(backup_dir) = cli.backup_dir {
+        if cli.backup_interval_hours == 0 || cli.backup_keep == 0 {
+            anyhow::bail!("--backup-interval-hours and --backup-keep must be at least 1");
+        }
+        let interval = std::time::Duration::from_secs(cli.backup_interval_hours * 60 * 60);
+        info!("Backing up to {:?} every {} hours, keeping {}", backup_dir, cli.backup_interval_hours, cli.backup_keep);
+        tokio::spawn(backup::schedule(cli.data_dir.clone(), backup_dir, interval, cli.backup_keep));
+    }
+
     let state = AppState { db, photos_dir };
 
     let app = create_app(state);
@@ -0,0 +1,125 @@
+use crate::ingredients::{self, Ingredient};
+use crate::todo::{PantryItem, Recipe};
+use chrono::{Duration, NaiveDate};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+/// Default window for the expiry dashboard, in days
+pub const DEFAULT_EXPIRY_DAYS: i64 = 3;
+
+/// How far ahead to look for a day without meals when planning a recipe
+pub const FREE_SLOT_LOOKAHEAD_DAYS: i64 = 60;
+
+/// A pantry item close to its expiry date and the recipes that could use it up
+#[derive(Clone, Debug)]
+pub struct ExpiringItem {
+    pub item: PantryItem,
+    pub days_left: i64, // Negative once expired
+    pub recipes: Vec<Recipe>,
+}
+
+/// A recipe with its ingredients split by whether they are in the pantry
+#[derive(Clone, Debug)]
+pub struct CookableRecipe {
+    pub recipe: Recipe,
+    pub in_stock: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+impl CookableRecipe {
+    /// Share of the recipe's ingredients that are in stock, in percent
+    pub fn percent_in_stock(&self) -> usize {
+        let total = self.in_stock.len() + self.missing.len();
+        if total == 0 {
+            return 0;
+        }
+        self.in_stock.len() * 100 / total
+    }
+}
+
+/// True if the pantry name appears as whole words in the ingredient name,
+/// so "flour" in stock covers "plain flour" but not "flourless"
+pub fn name_matches(ingredient_name: &str, pantry_name: &str) -> bool {
+    let ingredient_name = ingredients::normalise_name(ingredient_name);
+    let pantry_name = ingredients::normalise_name(pantry_name);
+    if pantry_name.is_empty() {
+        return false;
+    }
+    format!(" {} ", ingredient_name).contains(&format!(" {} ", pantry_name))
+}
+
+/// Pantry items that can be used for the ingredient. Items with a tracked quantity of zero are used up.
+pub fn find_in_stock<'a>(ingredient: &Ingredient, pantry: &'a [PantryItem]) -> Option<&'a PantryItem> {
+    pantry
+        .iter()
+        .filter(|item| !matches!(item.quantity, Some(quantity) if quantity <= 0.0))
+        .find(|item| name_matches(&ingredient.name, &item.name))
+}
+
+/// Existing pantry item to add a bought ingredient to: same name and unit, and no
+/// expiry date, since dated items are kept apart so each batch keeps its own date
+pub fn find_same_item<'a>(ingredient: &Ingredient, pantry: &'a [PantryItem]) -> Option<&'a PantryItem> {
+    let unit = ingredients::normalise_name(ingredient.unit.as_deref().unwrap_or(""));
+    pantry.iter().find(|item| {
+        item.expires.is_none()
+            && item.as_ingredient().normalised_name() == ingredient.normalised_name()
+            && ingredients::normalise_name(item.unit.as_deref().unwrap_or("")) == unit
+    })
+}
+
+pub fn match_recipe(recipe: Recipe, pantry: &[PantryItem]) -> CookableRecipe {
+    let mut in_stock = Vec::new();
+    let mut missing = Vec::new();
+    for ingredient in ingredients::parse_lines(&recipe.ingredients) {
+        if find_in_stock(&ingredient, pantry).is_some() {
+            in_stock.push(ingredient.to_string());
+        } else {
+            missing.push(ingredient.to_string());
+        }
+    }
+    CookableRecipe { recipe, in_stock, missing }
+}
+
+/// Recipes with at least one ingredient in stock, the ones with the most
+/// ingredients at home first and, among those, the fewest missing
+pub fn rank_cookable(recipes: Vec<Recipe>, pantry: &[PantryItem]) -> Vec<CookableRecipe> {
+    let mut cookable: Vec<CookableRecipe> = recipes
+        .into_iter()
+        .map(|recipe| match_recipe(recipe, pantry))
+        .filter(|cookable| !cookable.in_stock.is_empty())
+        .collect();
+    cookable.sort_by_key(|c| (Reverse(c.in_stock.len()), c.missing.len(), c.recipe.title.to_lowercase()));
+    cookable
+}
+
+pub fn uses_item(recipe: &Recipe, item: &PantryItem) -> bool {
+    ingredients::parse_lines(&recipe.ingredients)
+        .iter()
+        .any(|ingredient| name_matches(&ingredient.name, &item.name))
+}
+
+/// Items that expire within `days` days of `today`, including those already expired,
+/// soonest first, each with the recipes that use it
+pub fn expiring_items(items: Vec<PantryItem>, recipes: &[Recipe], today: NaiveDate, days: i64) -> Vec<ExpiringItem> {
+    let mut expiring: Vec<ExpiringItem> = items
+        .into_iter()
+        .filter_map(|item| {
+            let expires = NaiveDate::parse_from_str(item.expires.as_deref()?, "%Y-%m-%d").ok()?;
+            let days_left = (expires - today).num_days();
+            if days_left > days {
+                return None;
+            }
+            let recipes = recipes.iter().filter(|recipe| uses_item(recipe, &item)).cloned().collect();
+            Some(ExpiringItem { item, days_left, recipes })
+        })
+        .collect();
+    expiring.sort_by_key(|e| (e.days_left, e.item.name.to_lowercase()));
+    expiring
+}
+
+/// First day from `today` without any meal or note, within the lookahead window
+pub fn next_free_date(today: NaiveDate, planned: &HashSet<NaiveDate>) -> Option<NaiveDate> {
+    (0..FREE_SLOT_LOOKAHEAD_DAYS)
+        .map(|offset| today + Duration::days(offset))
+        .find(|date| !planned.contains(date))
+}
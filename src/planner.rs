@@ -0,0 +1,121 @@
+use crate::todo::Recipe;
+use chrono::{Datelike, NaiveDate, Weekday};
+use std::collections::HashMap;
+
+/// Tuning knobs for the meal plan suggestions
+#[derive(Clone, Debug)]
+pub struct PlannerConfig {
+    /// A recipe is not suggested if it is planned within this many days of the slot
+    pub avoid_repeat_days: i64,
+    /// Tag that is preferred on weekdays (Monday to Friday)
+    pub weekday_tag: String,
+}
+
+impl Default for PlannerConfig {
+    fn default() -> Self {
+        Self {
+            avoid_repeat_days: 14,
+            weekday_tag: "quick".to_string(),
+        }
+    }
+}
+
+// Score weights. Recency is counted in days since the recipe was last planned.
+const BASE_SCORE: i64 = 10;
+const RATING_WEIGHT: i64 = 10;
+const FAVOURITE_BONUS: i64 = 25;
+const WEEKDAY_TAG_BONUS: i64 = 20;
+const MAX_RECENCY_BONUS: i64 = 60;
+
+/// Dates each recipe has been planned for, keyed by recipe id
+pub type PlanHistory = HashMap<usize, Vec<NaiveDate>>;
+
+#[derive(Clone, Debug)]
+pub struct Suggestion {
+    pub date: NaiveDate,
+    pub recipe: Recipe,
+    pub score: i64,
+}
+
+pub fn build_history(entries: Vec<(usize, String)>) -> PlanHistory {
+    let mut history = PlanHistory::new();
+    for (recipe_id, date) in entries {
+        if let Ok(date) = NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
+            history.entry(recipe_id).or_default().push(date);
+        }
+    }
+    history
+}
+
+fn is_weekday(date: NaiveDate) -> bool {
+    !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// Scores a recipe for the given date, or returns None if it would repeat too soon.
+pub fn score_recipe(recipe: &Recipe, date: NaiveDate, planned: &[NaiveDate], config: &PlannerConfig) -> Option<i64> {
+    if planned.iter().any(|d| (*d - date).num_days().abs() < config.avoid_repeat_days) {
+        return None;
+    }
+
+    let mut score = BASE_SCORE + RATING_WEIGHT * recipe.rating as i64;
+    if recipe.favourite {
+        score += FAVOURITE_BONUS;
+    }
+    if is_weekday(date) && recipe.has_tag(&config.weekday_tag) {
+        score += WEEKDAY_TAG_BONUS;
+    }
+
+    let days_since_last = planned
+        .iter()
+        .filter(|d| **d < date)
+        .map(|d| (date - *d).num_days())
+        .min()
+        .unwrap_or(MAX_RECENCY_BONUS);
+    score += days_since_last.min(MAX_RECENCY_BONUS);
+
+    Some(score)
+}
+
+/// Picks the best recipe for a single date, skipping recipes in `exclude`.
+/// Ties are broken by recipe id so the result is stable between requests.
+pub fn suggest_for_date(
+    recipes: &[Recipe],
+    history: &PlanHistory,
+    date: NaiveDate,
+    exclude: &[usize],
+    config: &PlannerConfig,
+) -> Option<Suggestion> {
+    recipes
+        .iter()
+        .filter(|recipe| !exclude.contains(&recipe.id))
+        .filter_map(|recipe| {
+            let planned = history.get(&recipe.id).map(|v| v.as_slice()).unwrap_or(&[]);
+            score_recipe(recipe, date, planned, config).map(|score| (recipe, score))
+        })
+        .max_by(|(a, score_a), (b, score_b)| score_a.cmp(score_b).then(b.id.cmp(&a.id)))
+        .map(|(recipe, score)| Suggestion {
+            date,
+            recipe: recipe.clone(),
+            score,
+        })
+}
+
+/// Fills each date with a suggestion. Earlier suggestions count as planned, so
+/// the same recipe is not proposed twice within `avoid_repeat_days`.
+pub fn suggest_for_dates(
+    recipes: &[Recipe],
+    history: &PlanHistory,
+    dates: &[NaiveDate],
+    config: &PlannerConfig,
+) -> Vec<Option<Suggestion>> {
+    let mut history = history.clone();
+    let mut suggestions = Vec::new();
+    for date in dates {
+        let suggestion = suggest_for_date(recipes, &history, *date, &[], config);
+        if let Some(suggestion) = &suggestion {
+            history.entry(suggestion.recipe.id).or_default().push(*date);
+        }
+        suggestions.push(suggestion);
+    }
+    suggestions
+}
@@ -0,0 +1,100 @@
+use crate::ingredients::Ingredient;
+use crate::locale::Locale;
+use std::collections::HashMap;
+
+/// Last price paid for each item, keyed by normalised ingredient name
+pub type LastPrices = HashMap<String, f64>;
+
+/// Estimated cost of a set of items from their last known prices
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Estimate {
+    pub total: f64,
+    pub priced: usize, // Items with a known price
+    pub items: usize,
+}
+
+impl Estimate {
+    pub fn is_complete(&self) -> bool {
+        self.priced == self.items
+    }
+}
+
+/// One priced item, or an unpriced trip, from the archived shopping trips
+#[derive(Clone, Debug)]
+pub struct TripPurchase {
+    pub trip_id: usize,
+    pub finished: String, // Stored UTC timestamp
+    pub store: Option<String>,
+    pub price: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonthlySpend {
+    pub month: String, // YYYY-MM in the household timezone
+    pub trips: usize,
+    pub total: f64,
+    pub by_store: Vec<(String, f64)>, // Largest first, "Unknown store" for items without one
+}
+
+/// Parses a price like "12.50" or "12,50". Negative or non-numeric prices are rejected.
+pub fn parse_price(text: &str) -> Option<f64> {
+    text.trim()
+        .replace(',', ".")
+        .parse::<f64>()
+        .ok()
+        .filter(|price| price.is_finite() && *price >= 0.0)
+}
+
+pub fn last_price(item: &str, prices: &LastPrices) -> Option<f64> {
+    prices.get(&Ingredient::parse(item).normalised_name()).copied()
+}
+
+pub fn estimate<S: AsRef<str>>(items: &[S], prices: &LastPrices) -> Estimate {
+    let mut estimate = Estimate { items: items.len(), ..Default::default() };
+    for price in items.iter().filter_map(|item| last_price(item.as_ref(), prices)) {
+        estimate.total += price;
+        estimate.priced += 1;
+    }
+    estimate
+}
+
+/// Spending per month, newest first
+pub fn monthly_spending(purchases: &[TripPurchase], locale: &Locale) -> Vec<MonthlySpend> {
+    let mut months: Vec<MonthlySpend> = Vec::new();
+    let mut trips_seen: HashMap<String, Vec<usize>> = HashMap::new();
+    for purchase in purchases {
+        let Some(finished) = locale.local_timestamp(&purchase.finished) else {
+            continue;
+        };
+        let month = finished.format("%Y-%m").to_string();
+        let index = match months.iter().position(|m| m.month == month) {
+            Some(index) => index,
+            None => {
+                months.push(MonthlySpend { month: month.clone(), trips: 0, total: 0.0, by_store: Vec::new() });
+                months.len() - 1
+            }
+        };
+        let entry = &mut months[index];
+
+        let seen = trips_seen.entry(month).or_default();
+        if !seen.contains(&purchase.trip_id) {
+            seen.push(purchase.trip_id);
+            entry.trips += 1;
+        }
+
+        if let Some(price) = purchase.price {
+            entry.total += price;
+            let store = purchase.store.clone().unwrap_or_else(|| "Unknown store".to_string());
+            match entry.by_store.iter_mut().find(|(name, _)| *name == store) {
+                Some((_, total)) => *total += price,
+                None => entry.by_store.push((store, price)),
+            }
+        }
+    }
+
+    for month in months.iter_mut() {
+        month.by_store.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    }
+    months.sort_by(|a, b| b.month.cmp(&a.month));
+    months
+}
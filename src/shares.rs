@@ -0,0 +1,274 @@
+use crate::auth;
+use crate::conflicts;
+use crate::events::{self, View};
+use crate::households::HouseholdState;
+use crate::template::{HtmlTemplate, SharesTemplate};
+use crate::todo::{CompletedForm, ShareForm, ShareLink, SharePermission, ShareTarget, TaskForm, ToggleForm};
+use crate::AppState;
+use axum::extract::{Multipart, Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::Form;
+use tracing::{info, warn};
+
+/// Longest a share link can be made to last
+const MAX_SHARE_DAYS: i64 = 365;
+
+pub async fn shares_page(HouseholdState(state): HouseholdState) -> impl IntoResponse {
+    let locale = crate::current_locale(&state).await;
+    let now = chrono::Utc::now().timestamp();
+    let links = state
+        .db
+        .get_share_links()
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|link| {
+            let expires = match link.expires {
+                None => "Never".to_string(),
+                Some(expires) if expires <= now => "Expired".to_string(),
+                Some(expires) => chrono::DateTime::from_timestamp(expires, 0)
+                    .map(|time| locale.format_date(time.with_timezone(&locale.timezone).date_naive()))
+                    .unwrap_or_default(),
+            };
+            (link, expires)
+        })
+        .collect();
+    let template = SharesTemplate {
+        links,
+        lists: state.db.get_lists().await.unwrap_or_default(),
+        recipes: state.db.get_recipes().await.unwrap_or_default(),
+        permissions: SharePermission::ALL
+            .iter()
+            .map(|permission| (permission.code().to_string(), permission.label().to_string()))
+            .collect(),
+    };
+    HtmlTemplate(template).into_response()
+}
+
+fn parse_target(target: &str) -> Option<ShareTarget> {
+    let (kind, id) = target.split_once(':')?;
+    let id = id.parse().ok()?;
+    match kind {
+        "list" => Some(ShareTarget::List(id)),
+        "recipe" => Some(ShareTarget::Recipe(id)),
+        _ => None,
+    }
+}
+
+pub async fn create_share(HouseholdState(state): HouseholdState, form: Form<ShareForm>) -> impl IntoResponse {
+    let (Some(target), Some(permission)) = (parse_target(&form.target), SharePermission::from_code(&form.permission)) else {
+        return (StatusCode::BAD_REQUEST, "Invalid share").into_response();
+    };
+    let expires = if form.expires_days.trim().is_empty() {
+        None
+    } else {
+        match form.expires_days.trim().parse::<i64>() {
+            Ok(days) if (1..=MAX_SHARE_DAYS).contains(&days) => Some(chrono::Utc::now().timestamp() + days * 24 * 60 * 60),
+            _ => return (StatusCode::BAD_REQUEST, "Invalid number of days").into_response(),
+        }
+    };
+
+    match state.db.create_share_link(auth::random_token(), target, permission, expires).await {
+        Ok(_) => {
+            info!("Shared {:?} with permission {}", target, permission.code());
+            Redirect::to("/shares").into_response()
+        }
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+pub async fn delete_share(HouseholdState(state): HouseholdState, Path(token): Path<String>) -> impl IntoResponse {
+    match state.db.delete_share_link(token).await {
+        Ok(_) => Redirect::to("/shares").into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// The share link for a token, with the state scoped to the household that made it.
+/// Unknown and expired tokens are not found.
+async fn open_share(state: &AppState, token: String) -> Result<(AppState, ShareLink), Response> {
+    match state.db.get_share_link(token, chrono::Utc::now().timestamp()).await {
+        Ok(Some(link)) => {
+            let state = AppState { db: state.db.for_household(link.household_id), ..state.clone() };
+            Ok((state, link))
+        }
+        Ok(None) => Err(StatusCode::NOT_FOUND.into_response()),
+        Err(err) => {
+            warn!("Failed to look up share link: {}", err);
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+    }
+}
+
+/// Shared lists open in shopping mode, shared recipes on their page
+pub async fn shared_page(State(state): State<AppState>, Path(token): Path<String>) -> Response {
+    let (state, link) = match open_share(&state, token).await {
+        Ok(share) => share,
+        Err(response) => return response,
+    };
+    match link.target {
+        ShareTarget::List(list_id) => crate::shop_page(&state, list_id, Some(link)).await,
+        ShareTarget::Recipe(recipe_id) => crate::recipe_page(&state, recipe_id, Some(link)).await,
+    }
+}
+
+/// Opens a share of a list that allows `needed`, and checks the task is on that list
+async fn open_list_share(
+    state: &AppState,
+    token: String,
+    task_id: Option<usize>,
+    needed: SharePermission,
+) -> Result<(AppState, ShareLink, usize), Response> {
+    let (state, link) = open_share(state, token).await?;
+    let ShareTarget::List(list_id) = link.target else {
+        return Err(StatusCode::NOT_FOUND.into_response());
+    };
+    if link.permission < needed {
+        return Err(StatusCode::FORBIDDEN.into_response());
+    }
+    if let Some(task_id) = task_id {
+        match state.db.get_task(task_id).await {
+            Ok(task) if task.list_id == list_id => {}
+            _ => return Err(StatusCode::NOT_FOUND.into_response()),
+        }
+    }
+    Ok((state, link, list_id))
+}
+
+pub async fn set_shared_task_completed(
+    State(state): State<AppState>,
+    Path((token, id)): Path<(String, usize)>,
+    headers: HeaderMap,
+    Form(form): Form<CompletedForm>,
+) -> Response {
+    let (state, link, _) = match open_list_share(&state, token, Some(id), SharePermission::CheckOff).await {
+        Ok(share) => share,
+        Err(response) => return response,
+    };
+    crate::complete_task(&state, id, form.completed, conflicts::if_match(&headers), View::Shop(Some(link))).await
+}
+
+/// Flips whether a shared task is checked off, for pages from before `set_shared_task_completed`
+pub async fn toggle_shared_task(
+    State(state): State<AppState>,
+    Path((token, id)): Path<(String, usize)>,
+    headers: HeaderMap,
+    form: Option<Form<ToggleForm>>,
+) -> Response {
+    let (state, link, _) = match open_list_share(&state, token, Some(id), SharePermission::CheckOff).await {
+        Ok(share) => share,
+        Err(response) => return response,
+    };
+    let task = match state.db.get_task(id).await {
+        Ok(task) => task,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let completed = form.and_then(|form| form.completed).unwrap_or(!task.completed);
+    let expected_version = conflicts::if_match(&headers).or(Some(task.version));
+    crate::complete_task(&state, id, completed, expected_version, View::Shop(Some(link))).await
+}
+
+/// A shared task's item as it is now, to drop a conflicting change
+pub async fn shared_task_row(State(state): State<AppState>, Path((token, id)): Path<(String, usize)>) -> Response {
+    let (state, link, _) = match open_list_share(&state, token, Some(id), SharePermission::View).await {
+        Ok(share) => share,
+        Err(response) => return response,
+    };
+    match state.db.get_task(id).await {
+        Ok(task) => crate::shop_item(&state, task, Some(link)).await,
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+pub async fn add_shared_task(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    form: Form<TaskForm>,
+) -> Response {
+    let (state, link, list_id) = match open_list_share(&state, token, None, SharePermission::Edit).await {
+        Ok(share) => share,
+        Err(response) => return response,
+    };
+    let text = form.text.trim().to_string();
+    if text.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Text is required").into_response();
+    }
+    let created = match state.db.create_task(text, list_id).await {
+        Ok(id) => state.db.get_task(id).await,
+        Err(err) => Err(err),
+    };
+    match created {
+        Ok(task) => crate::shop_item(&state, task, Some(link)).await,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+pub async fn delete_shared_task(State(state): State<AppState>, Path((token, id)): Path<(String, usize)>) -> Response {
+    let (state, _, _) = match open_list_share(&state, token, Some(id), SharePermission::Edit).await {
+        Ok(share) => share,
+        Err(response) => return response,
+    };
+    match state.db.delete_task(id).await {
+        Ok(_) => Html("").into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// Opens a share of a recipe that allows editing
+async fn open_recipe_edit_share(state: &AppState, token: String) -> Result<(AppState, ShareLink, usize), Response> {
+    let (state, link) = open_share(state, token).await?;
+    let ShareTarget::Recipe(recipe_id) = link.target else {
+        return Err(StatusCode::NOT_FOUND.into_response());
+    };
+    if !link.can_edit() {
+        return Err(StatusCode::FORBIDDEN.into_response());
+    }
+    Ok((state, link, recipe_id))
+}
+
+pub async fn shared_recipe_form(State(state): State<AppState>, Path(token): Path<String>) -> Response {
+    match open_recipe_edit_share(&state, token).await {
+        Ok((state, link, recipe_id)) => crate::recipe_form_page(&state, recipe_id, Some(link)).await,
+        Err(response) => response,
+    }
+}
+
+pub async fn update_shared_recipe(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    multipart: Multipart,
+) -> Response {
+    match open_recipe_edit_share(&state, token).await {
+        Ok((state, link, recipe_id)) => {
+            crate::save_recipe_edit(&state, recipe_id, multipart, Some(link)).await
+        }
+        Err(response) => response,
+    }
+}
+
+/// Live changes to a shared list, for the shopping mode page it opens in
+pub async fn shared_events(State(state): State<AppState>, Path(token): Path<String>) -> Response {
+    match open_share(&state, token).await {
+        Ok((state, link)) => match link.target {
+            ShareTarget::List(list_id) => events::list_events(state, list_id, View::Shop(Some(link))),
+            ShareTarget::Recipe(_) => StatusCode::NOT_FOUND.into_response(),
+        },
+        Err(response) => response,
+    }
+}
+
+/// Photos of a shared recipe
+pub async fn shared_photo(
+    State(state): State<AppState>,
+    Path((token, filename)): Path<(String, String)>,
+) -> Response {
+    let (state, link) = match open_share(&state, token).await {
+        Ok(share) => share,
+        Err(response) => return response,
+    };
+    match state.db.get_recipe_photo_by_filename(filename).await {
+        Ok(Some(photo)) if link.target == ShareTarget::Recipe(photo.recipe_id) => crate::photo_file(&state, &photo).await,
+        _ => StatusCode::NOT_FOUND.into_response(),
+    }
+}
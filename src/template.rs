@@ -1,4 +1,7 @@
-use crate::todo::{List, Task, Recipe, RecipePhoto, RecipeWithPhoto, WeekDay};
+use crate::pantry::{CookableRecipe, ExpiringItem};
+use crate::prices::{self, Estimate, LastPrices, MonthlySpend};
+use crate::aisles::TaskGroup;
+use crate::todo::{List, Task, Recipe, RecipePhoto, RecipeWithPhoto, WeekDay, MealSuggestionSlot, PantryItem, Store, Household, User, ShareLink};
 use askama::Template;
 use axum::http::StatusCode;
 use axum::response::{Html, IntoResponse, Response};
@@ -9,6 +12,15 @@ pub struct IndexTemplate {
     pub selected_list: usize,
     pub lists: Vec<List>,
     pub tasks: Vec<Task>,
+    pub aisles: Option<AisleView>,  // Set when open tasks are grouped by store category
+}
+
+#[derive(Clone, Debug)]
+pub struct AisleView {
+    pub groups: Vec<TaskGroup>,
+    pub stores: Vec<Store>,
+    pub selected_store: Option<usize>,
+    pub categories: Vec<(String, String)>,  // (key, label)
 }
 
 #[derive(Template)]
@@ -45,6 +57,25 @@ pub struct TaskTemplate {
     pub task: Task,
 }
 
+/// A task row showing a toggle that clashed with a change made elsewhere
+#[derive(Template)]
+#[template(path = "task_conflict.html")]
+pub struct TaskConflictTemplate {
+    pub task: Task,      // Theirs, as it is now
+    pub mine: bool,      // Whether the user was checking it off
+    pub row_url: String, // Where the row reloads from
+    pub set_url: String, // Where the row is checked off
+    pub shop: bool,      // Shopping mode item instead of a table row
+}
+
+/// Both orders of a list's open tasks, after a reorder clashed with one made elsewhere
+#[derive(Template)]
+#[template(path = "reorder_conflict.html")]
+pub struct ReorderConflictTemplate {
+    pub mine: Vec<Task>,
+    pub theirs: Vec<Task>,
+}
+
 #[derive(Template)]
 #[allow(dead_code)]
 #[template(path = "lists.html")]
@@ -74,6 +105,7 @@ pub struct RecipesTemplate {
 pub struct RecipeDetailTemplate {
     pub recipe: Recipe,
     pub photos: Vec<RecipePhoto>,
+    pub share: Option<ShareLink>,  // Set when opened through a share link
 }
 
 #[derive(Template)]
@@ -81,6 +113,8 @@ pub struct RecipeDetailTemplate {
 pub struct RecipeFormTemplate {
     pub recipe: Option<Recipe>,
     pub is_edit: bool,
+    pub share: Option<ShareLink>,  // Set when opened through a share link
+    pub conflict: Option<Recipe>,  // Saved elsewhere since the form was opened, the form holds the user's version
 }
 
 // Meal plan templates
@@ -95,12 +129,27 @@ pub struct MealPlanTemplate {
     pub week_days: Vec<WeekDay>,
 }
 
+#[derive(Template)]
+#[template(path = "meal_suggestions.html")]
+pub struct MealSuggestionsTemplate {
+    pub start_date: String,
+    pub display_date: String,
+    pub slots: Vec<MealSuggestionSlot>,
+}
+
+#[derive(Template)]
+#[template(path = "meal_suggestion.html")]
+pub struct MealSuggestionTemplate {
+    pub slot: MealSuggestionSlot,
+}
+
 #[derive(Template)]
 #[template(path = "add_meal_form.html")]
 pub struct AddMealFormTemplate {
     pub date: String,           // Database format (YYYY-MM-DD) for forms
     pub display_date: String,   // Display format (dd.MM.yy) for UI
     pub recipes: Vec<Recipe>,
+    pub leftover_candidates: Vec<(usize, String)>, // (meal plan entry id, label)
 }
 
 #[derive(Template)]
@@ -114,6 +163,8 @@ pub struct RecipeToListTemplate {
 #[template(path = "add_recipe_to_meal_plan.html")]
 pub struct RecipeToMealPlanTemplate {
     pub recipe: Recipe,
+    pub language_tag: String,     // BCP 47 tag for the quick day buttons
+    pub today: String,            // Database format (YYYY-MM-DD) in the household timezone
 }
 
 #[derive(Template)]
@@ -123,6 +174,139 @@ pub struct WeeklyIngredientsTemplate {
     pub display_date: String,       // Display format (dd.MM.yy) for UI
     pub ingredients: Vec<String>,
     pub lists: Vec<List>,
+    pub estimate: Estimate,  // Cost of the ingredients from their last known prices
+}
+
+#[derive(Template)]
+#[template(path = "login.html")]
+pub struct LoginTemplate {
+    pub username: String,
+    pub next: String, // Local path to return to
+    pub error: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "settings.html")]
+pub struct SettingsTemplate {
+    pub username: String, // Signed in user
+    pub language: String,
+    pub week_start: String,
+    pub date_format: String,
+    pub languages: Vec<(String, String)>,     // (code, name)
+    pub date_formats: Vec<(String, String)>,  // (pattern, example)
+    pub timezone: String,
+    pub timezones: Vec<String>,
+    pub saved: bool,
+}
+
+#[derive(Template)]
+#[template(path = "pantry.html")]
+pub struct PantryTemplate {
+    pub items: Vec<PantryItem>,
+    pub lists: Vec<List>,
+    pub shopping_list_id: Option<usize>,  // List whose checked off tasks move into the pantry
+}
+
+#[derive(Template)]
+#[template(path = "cookable_recipes.html")]
+pub struct CookableRecipesTemplate {
+    pub recipes: Vec<CookableRecipe>,
+    pub pantry_empty: bool,
+}
+
+#[derive(Template)]
+#[template(path = "pantry_expiring.html")]
+pub struct ExpiringPantryTemplate {
+    pub days: i64,
+    pub items: Vec<ExpiringItem>,
+}
+
+#[derive(Template)]
+#[template(path = "shop.html")]
+pub struct ShopTemplate {
+    pub list: List,
+    pub to_buy: Vec<ShopRow>,
+    pub in_cart: Vec<ShopRow>,  // Checked off, but not yet archived by finishing the trip
+    pub stores: Vec<Store>,
+    pub estimate: Estimate,     // Expected cost of what is left to buy
+    pub cart_total: f64,        // Prices entered for what is in the cart
+    pub share: Option<ShareLink>,  // Set when opened through a share link
+}
+
+#[derive(Template)]
+#[template(path = "shop_item.html")]
+pub struct ShopItemTemplate {
+    pub row: ShopRow,
+    pub share: Option<ShareLink>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ShopRow {
+    pub task: Task,
+    pub estimate: Option<f64>,  // Last known price of the item
+}
+
+impl ShopRow {
+    pub fn new(task: Task, prices: &LastPrices) -> ShopRow {
+        let estimate = prices::last_price(&task.text, prices);
+        ShopRow { task, estimate }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "spending.html")]
+pub struct SpendingTemplate {
+    pub months: Vec<MonthlySpend>,
+}
+
+#[derive(Template)]
+#[template(path = "stores.html")]
+pub struct StoresTemplate {
+    pub stores: Vec<(Store, Vec<(String, String)>)>,  // Each store with its aisles as (key, label) in walking order
+}
+
+#[derive(Template)]
+#[template(path = "households.html")]
+pub struct HouseholdsTemplate {
+    pub households: Vec<Household>,  // Households the user is a member of
+    pub invites: Vec<Household>,     // Households the user is invited to
+    pub current_id: Option<usize>,
+    pub members: Vec<User>,          // Members of the current household
+    pub invited: Vec<User>,          // Users invited to the current household
+    pub error: Option<String>,
+}
+
+impl IndexTemplate {
+    /// This page, to reload the list from
+    fn page_url(&self) -> String {
+        match &self.aisles {
+            Some(view) => match view.selected_store {
+                Some(store) => format!("/?list_id={}&group=aisle&store={}", self.selected_list, store),
+                None => format!("/?list_id={}&group=aisle", self.selected_list),
+            },
+            None => format!("/?list_id={}", self.selected_list),
+        }
+    }
+
+    /// Version of the list's order, for If-Match when reordering
+    fn list_version(&self) -> usize {
+        self.lists.iter().find(|list| list.id == self.selected_list).map(|list| list.version).unwrap_or_default()
+    }
+}
+
+impl HouseholdsTemplate {
+    fn is_current(&self, id: &usize) -> bool {
+        self.current_id == Some(*id)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "shares.html")]
+pub struct SharesTemplate {
+    pub links: Vec<(ShareLink, String)>,     // Each link with when it expires
+    pub lists: Vec<List>,
+    pub recipes: Vec<Recipe>,
+    pub permissions: Vec<(String, String)>,  // (code, label)
 }
 
 // Custom filter for auto-linking URLs
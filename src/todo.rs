@@ -1,18 +1,149 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 pub struct Task {
     pub id: usize,
     pub text: String,
     pub completed: bool,
     pub list_id: usize,
     pub position: Option<i32>,
+    pub price: Option<f64>,        // Price paid, set while shopping
+    pub store_id: Option<usize>,   // Store it was bought in
+    pub version: usize,            // Bumped on every edit, for If-Match
+    pub completed_at: Option<i64>, // Unix timestamp of when it was checked off
+    pub priority: Option<String>,  // todo.txt priority, "A" to "Z"
+    #[serde(default)]
+    pub tags: Vec<String>,         // todo.txt @contexts it was imported with, without the @
 }
 
-#[derive(Clone, Debug)]
+impl Task {
+    /// Its tags and the todo.txt `@context`s typed into its text, without the @
+    pub fn all_tags(&self) -> Vec<String> {
+        let mut tags = self.tags.clone();
+        for context in self.text.split_whitespace().filter_map(|word| word.strip_prefix('@')) {
+            if !context.is_empty() && !tags.iter().any(|tag| tag == context) {
+                tags.push(context.to_string());
+            }
+        }
+        tags
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 pub struct List {
     pub id: usize,
     pub name: String,
+    pub version: usize, // Bumped when its tasks are reordered
+}
+
+/// A signed in user, without the password hash
+#[derive(Clone, Debug)]
+pub struct User {
+    pub id: usize,
+    pub username: String,
+    pub is_admin: bool,
+}
+
+/// A group of users sharing lists, recipes, the meal plan, the pantry and stores
+#[derive(Clone, Debug)]
+pub struct Household {
+    pub id: usize,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct HouseholdForm {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct SwitchHouseholdForm {
+    pub household_id: usize,
+}
+
+#[derive(Deserialize)]
+pub struct AddMemberForm {
+    pub username: String,
+}
+
+#[derive(Deserialize)]
+pub struct InviteAnswerForm {
+    pub household_id: usize,
+}
+
+/// What a share link lets its holder do, each level including the ones before it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SharePermission {
+    View,
+    CheckOff,
+    Edit,
+}
+
+impl SharePermission {
+    pub const ALL: [SharePermission; 3] = [SharePermission::View, SharePermission::CheckOff, SharePermission::Edit];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            SharePermission::View => "view",
+            SharePermission::CheckOff => "check_off",
+            SharePermission::Edit => "edit",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SharePermission::View => "View only",
+            SharePermission::CheckOff => "Check off items",
+            SharePermission::Edit => "Edit",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<SharePermission> {
+        SharePermission::ALL.into_iter().find(|permission| permission.code() == code)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShareTarget {
+    List(usize),
+    Recipe(usize),
+}
+
+/// A link giving people without an account access to one list or recipe
+#[derive(Clone, Debug)]
+pub struct ShareLink {
+    pub token: String,
+    pub household_id: usize,
+    pub target: ShareTarget,
+    pub title: String, // Name of the list or title of the recipe
+    pub permission: SharePermission,
+    pub expires: Option<i64>, // Unix timestamp
+}
+
+impl ShareLink {
+    pub fn can_check_off(&self) -> bool {
+        self.permission >= SharePermission::CheckOff
+    }
+
+    pub fn can_edit(&self) -> bool {
+        self.permission >= SharePermission::Edit
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ShareForm {
+    pub target: String, // "list:<id>" or "recipe:<id>"
+    pub permission: String,
+    #[serde(default)]
+    pub expires_days: String, // Empty for links that don't expire
+}
+
+#[derive(Deserialize)]
+pub struct LoginForm {
+    pub username: String,
+    pub password: String,
+    pub next: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -20,32 +151,83 @@ pub struct TaskForm {
     pub text: String,
 }
 
+#[derive(Deserialize)]
+pub struct CompletedForm {
+    pub completed: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ToggleForm {
+    pub completed: Option<bool>, // What the user was changing it to, to tell if a conflict matters
+}
+
 #[derive(Deserialize)]
 pub struct ListForm {
     pub name: String,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 pub struct Recipe {
     pub id: usize,
     pub title: String,
     pub instructions: String,
     pub ingredients: String,
+    pub favourite: bool,
+    pub rating: u8, // 0 = unrated, otherwise 1-5
+    pub tags: String, // Comma separated, e.g. "quick, vegetarian"
+    pub servings: Option<u32>,
+    pub version: usize, // Bumped on every edit, for If-Match
 }
 
-#[derive(Clone, Debug)]
+impl Recipe {
+    pub fn tag_list(&self) -> Vec<String> {
+        self.tags
+            .split(',')
+            .map(|tag| tag.trim().to_lowercase())
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tag_list().iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 pub struct MealPlanEntry {
     pub id: usize,
     pub date: String, // YYYY-MM-DD
     pub meal_text: String,
     pub recipe_id: Option<usize>,
+    pub leftovers_of: Option<usize>, // Earlier entry this day eats leftovers from
+    pub servings: Option<u32>,       // Planned servings, defaults to the recipe's servings
 }
 
-#[derive(Deserialize)]
+impl MealPlanEntry {
+    /// Factor to scale the recipe's ingredient quantities by for this meal
+    pub fn servings_factor(&self, recipe: &Recipe) -> f64 {
+        match (self.servings, recipe.servings) {
+            (Some(planned), Some(serves)) => planned as f64 / serves as f64,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
 pub struct RecipeForm {
     pub title: String,
     pub instructions: String,
     pub ingredients: String,
+    #[serde(default)]
+    pub favourite: bool,
+    #[serde(default)]
+    pub rating: u8,
+    #[serde(default)]
+    pub tags: String,
+    #[serde(default)]
+    pub servings: Option<u32>,
+    #[serde(default)]
+    pub version: Option<usize>, // The version being edited, None on a new recipe
 }
 
 #[derive(Deserialize)]
@@ -53,6 +235,14 @@ pub struct MealForm {
     pub meal_text: String,
     #[serde(default, deserialize_with = "deserialize_optional_usize")]
     pub recipe_id: Option<usize>,
+    #[serde(default, deserialize_with = "deserialize_optional_usize")]
+    pub leftovers_of: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct DayNoteForm {
+    #[serde(default)]
+    pub note: String,
 }
 
 fn deserialize_optional_usize<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
@@ -67,7 +257,94 @@ where
     }
 }
 
+// Like deserialize_optional_usize, but zero also counts as "not given"
+fn deserialize_optional_servings<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s: Option<String> = Option::deserialize(deserializer)?;
+    match s {
+        Some(s) if s.trim().is_empty() => Ok(None),
+        Some(s) => s.trim().parse::<u32>().map(|n| Some(n).filter(|n| *n > 0)).map_err(serde::de::Error::custom),
+        None => Ok(None),
+    }
+}
+
 #[derive(Clone, Debug)]
+pub struct PantryItem {
+    pub id: usize,
+    pub name: String,
+    pub quantity: Option<f64>, // None when the amount isn't tracked
+    pub unit: Option<String>,
+    pub expires: Option<String>, // YYYY-MM-DD
+}
+
+impl PantryItem {
+    pub fn as_ingredient(&self) -> crate::ingredients::Ingredient {
+        crate::ingredients::Ingredient {
+            quantity: self.quantity,
+            unit: self.unit.clone(),
+            name: self.name.clone(),
+        }
+    }
+
+    /// Quantity and unit for display, e.g. "1.5 l", or empty when untracked
+    pub fn amount(&self) -> String {
+        match (self.quantity, &self.unit) {
+            (Some(quantity), Some(unit)) => format!("{} {}", crate::ingredients::format_quantity(quantity), unit),
+            (Some(quantity), None) => crate::ingredients::format_quantity(quantity),
+            _ => String::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PantryItemForm {
+    pub name: String,
+    #[serde(default)]
+    pub quantity: String,
+    #[serde(default)]
+    pub unit: String,
+    #[serde(default)]
+    pub expires: String, // YYYY-MM-DD, empty for items that keep
+}
+
+#[derive(Deserialize)]
+pub struct PantryShoppingListForm {
+    #[serde(default, deserialize_with = "deserialize_optional_usize")]
+    pub list_id: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Store {
+    pub id: usize,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct StoreForm {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct TaskCategoryForm {
+    pub category: String,
+}
+
+#[derive(Deserialize)]
+pub struct TaskPriceForm {
+    #[serde(default)]
+    pub price: String, // Empty clears the price
+    #[serde(default, deserialize_with = "deserialize_optional_usize")]
+    pub store_id: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct PlanRecipeForm {
+    pub recipe_id: usize,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
 pub struct RecipePhoto {
     pub id: usize,
     pub recipe_id: usize,
@@ -76,6 +353,7 @@ pub struct RecipePhoto {
     pub file_size: i64,
     pub mime_type: String,
     pub upload_order: i32,
+    #[serde(skip)]
     pub thumbnail_blob: Option<Vec<u8>>,
 }
 
@@ -96,6 +374,8 @@ pub struct RecipeToListForm {
 pub struct RecipeToMealPlanForm {
     pub date: String,
     pub meal_text: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_servings")]
+    pub servings: Option<u32>,
 }
 
 #[derive(Deserialize)]
@@ -111,4 +391,28 @@ pub struct WeekDay {
     pub date: String,          // Display format (dd.MM.yy)
     pub db_date: String,       // Database format (YYYY-MM-DD) for URLs
     pub meals: Vec<MealPlanEntry>,
+    pub note: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct MealSuggestionSlot {
+    pub day_name: String,
+    pub date: String,          // Display format (dd.MM.yy)
+    pub db_date: String,       // Database format (YYYY-MM-DD) for URLs
+    pub recipe: Option<Recipe>,
+    pub exclude: String,       // Comma separated recipe ids already offered for this slot
+    pub accepted: bool,
+}
+
+#[derive(Deserialize)]
+pub struct AcceptSuggestionForm {
+    pub recipe_id: usize,
+}
+
+#[derive(Deserialize)]
+pub struct SettingsForm {
+    pub language: String,
+    pub week_start: String,
+    pub date_format: String,
+    pub timezone: String,
 }
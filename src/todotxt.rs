@@ -0,0 +1,201 @@
+//! Lists in the todo.txt format (https://github.com/todotxt/todo.txt), one task a line:
+//!
+//! ```text
+//! (A) 2026-10-01 Call the plumber @phone +Chores
+//! x 2026-10-03 2026-10-01 Buy batteries +Chores pri:B
+//! ```
+//!
+//! `x` marks a task done, `(A)` its priority, then come the completion date (done tasks
+//! only) and the creation date. A `+project` names the list the task is on and `@context`s
+//! are kept as the task's tags. Dates are in the household timezone.
+//!
+//! Tags are written after the text, so a context in the middle of a line, as in
+//! `Call @phone the plumber`, comes back at its end. Word order is all that is lost.
+
+use crate::locale::Locale;
+use crate::todo::{List, Task};
+use crate::AppState;
+use chrono::{NaiveDate, TimeZone};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// One line of a todo.txt file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TodoTxtTask {
+    pub completed: bool,
+    pub priority: Option<char>,
+    pub completed_on: Option<NaiveDate>,
+    pub created_on: Option<NaiveDate>,
+    pub text: String,            // Without the project and contexts
+    pub contexts: Vec<String>,   // Without the @
+    pub project: Option<String>, // The last +project of the line
+}
+
+/// A task from a todo.txt file, ready to be added to a list
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedTask {
+    pub text: String,
+    pub completed: bool,
+    pub priority: Option<String>,
+    pub tags: Vec<String>,
+    pub created: Option<String>, // UTC, in the format of the created column
+    pub completed_at: Option<i64>,
+}
+
+fn parse_priority(word: &str) -> Option<char> {
+    match word.as_bytes() {
+        [b'(', priority @ b'A'..=b'Z', b')'] => Some(*priority as char),
+        _ => None,
+    }
+}
+
+fn parse_date(word: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(word, DATE_FORMAT).ok()
+}
+
+/// Name of the `+project` for a list. Projects can't have spaces.
+pub fn project_name(list: &str) -> String {
+    list.split_whitespace().collect::<Vec<_>>().join("-")
+}
+
+impl TodoTxtTask {
+    /// Reads a line, `None` when it is blank
+    pub fn parse(line: &str) -> Option<TodoTxtTask> {
+        let mut rest = line.trim();
+        if rest.is_empty() {
+            return None;
+        }
+        let mut task = TodoTxtTask::default();
+
+        // Takes the next word if `parse` accepts it
+        fn take<T>(rest: &mut &str, parse: impl Fn(&str) -> Option<T>) -> Option<T> {
+            let (word, after) = rest.split_once(' ').unwrap_or((rest, ""));
+            let value = parse(word)?;
+            *rest = after.trim_start();
+            Some(value)
+        }
+
+        task.completed = take(&mut rest, |word| (word == "x").then_some(())).is_some();
+        task.priority = take(&mut rest, parse_priority);
+        let first = take(&mut rest, parse_date);
+        // Only done tasks have two dates, any other belongs to the text
+        let second = match task.completed {
+            true => first.and_then(|_| take(&mut rest, parse_date)),
+            false => None,
+        };
+        // A done task with one date has only the completion date
+        (task.completed_on, task.created_on) = match (task.completed, first, second) {
+            (true, Some(done), created) => (Some(done), created),
+            (false, created, _) => (None, created),
+            (true, None, _) => (None, None),
+        };
+
+        let mut words: Vec<&str> = rest.split(' ').collect();
+        if let Some(index) = words.iter().rposition(|word| word.len() > 1 && word.starts_with('+')) {
+            task.project = Some(words.remove(index)[1..].to_string());
+        }
+        // Taken out wherever they are in the line; `to_line` puts them back at the end
+        words.retain(|word| match word.strip_prefix('@') {
+            Some(context) if !context.is_empty() => {
+                task.contexts.push(context.to_string());
+                false
+            }
+            _ => true,
+        });
+        // Done tasks keep their priority in a pri: tag
+        if let Some(index) = words.iter().rposition(|word| word.starts_with("pri:")) {
+            if let Some(priority) = parse_priority(&format!("({})", &words[index][4..])) {
+                words.remove(index);
+                task.priority = Some(priority);
+            }
+        }
+        task.text = words.join(" ").trim().to_string();
+        Some(task)
+    }
+
+    /// The line for the task, with `+project` added if it is set
+    pub fn to_line(&self) -> String {
+        let mut words = Vec::new();
+        if self.completed {
+            words.push("x".to_string());
+        } else if let Some(priority) = self.priority {
+            words.push(format!("({})", priority));
+        }
+        // The completion date is only allowed together with the creation date
+        match (self.completed, self.completed_on, self.created_on) {
+            (true, Some(done), Some(created)) => {
+                words.push(done.format(DATE_FORMAT).to_string());
+                words.push(created.format(DATE_FORMAT).to_string());
+            }
+            (false, _, Some(created)) => words.push(created.format(DATE_FORMAT).to_string()),
+            _ => {}
+        }
+        words.push(self.text.clone());
+        words.extend(self.contexts.iter().map(|context| format!("@{}", context)));
+        if let Some(project) = &self.project {
+            words.push(format!("+{}", project));
+        }
+        if let (true, Some(priority)) = (self.completed, self.priority) {
+            words.push(format!("pri:{}", priority));
+        }
+        words.join(" ")
+    }
+
+    fn from_task(task: &Task, created: Option<&String>, list: &List, locale: &Locale) -> TodoTxtTask {
+        TodoTxtTask {
+            completed: task.completed,
+            priority: task.priority.as_deref().and_then(|priority| priority.chars().next()),
+            completed_on: task
+                .completed_at
+                .and_then(|at| chrono::DateTime::from_timestamp(at, 0))
+                .map(|at| at.with_timezone(&locale.timezone).date_naive()),
+            created_on: created.and_then(|created| locale.local_timestamp(created)).map(|created| created.date()),
+            text: task.text.clone(),
+            contexts: task.tags.clone(),
+            project: Some(project_name(&list.name)),
+        }
+    }
+
+    fn imported(self, locale: &Locale) -> ImportedTask {
+        // Days start at midnight in the household timezone
+        let start_of = |date: NaiveDate| {
+            locale.timezone.from_local_datetime(&date.and_time(chrono::NaiveTime::MIN)).earliest().map(|start| start.to_utc())
+        };
+        ImportedTask {
+            text: self.text,
+            completed: self.completed,
+            priority: self.priority.map(String::from),
+            tags: self.contexts,
+            created: self
+                .created_on
+                .and_then(start_of)
+                .map(|start| format!("{}:{}", start.format("%Y-%m-%d %H:%M:%S"), start.timestamp())),
+            completed_at: self.completed_on.and_then(start_of).map(|start| start.timestamp()),
+        }
+    }
+}
+
+/// A list as a todo.txt file, in the order it is shown
+pub async fn export_list(state: &AppState, list_id: usize) -> anyhow::Result<String> {
+    let list = state.db.get_list(list_id).await?;
+    let tasks = state.db.get_tasks(list_id).await?;
+    let created = state.db.get_tasks_created(list_id).await?;
+    let locale = crate::current_locale(state).await;
+    Ok(tasks
+        .iter()
+        .map(|task| TodoTxtTask::from_task(task, created.get(&task.id), &list, &locale).to_line() + "\n")
+        .collect())
+}
+
+/// Adds the tasks in a todo.txt file to the list. Tasks with a `+project` go on the list of
+/// that name instead, which is made if there isn't one. Returns how many were added.
+pub async fn import_list(state: &AppState, list_id: usize, todo_txt: &str) -> anyhow::Result<usize> {
+    let locale = crate::current_locale(state).await;
+    let tasks = todo_txt
+        .lines()
+        .filter_map(TodoTxtTask::parse)
+        .filter(|task| !task.text.is_empty())
+        .map(|mut task| (task.project.take(), task.imported(&locale)))
+        .collect();
+    state.db.import_todo_txt(list_id, tasks).await
+}
@@ -0,0 +1,130 @@
+use htmx_rs_todo::aisles::{self, CategoryOverrides};
+use htmx_rs_todo::todo::Task;
+
+mod common;
+use common::*;
+
+fn task(id: usize, text: &str) -> Task {
+    Task { id, text: text.to_string(), completed: false, list_id: 1, position: None, price: None, store_id: None, version: 1, completed_at: None, priority: None, tags: vec![] }
+}
+
+#[test]
+fn test_categorise_from_bundled_dictionary() {
+    let none = CategoryOverrides::new();
+
+    assert_eq!(aisles::categorise("2 l Milk", &none), "dairy");
+    assert_eq!(aisles::categorise("3 eggs", &none), "dairy");
+    assert_eq!(aisles::categorise("500 g kjøttdeig", &none), "meat");
+    assert_eq!(aisles::categorise("Tomatoes", &none), "produce");
+    assert_eq!(aisles::categorise("1 can chopped tomatoes", &none), "canned");
+    assert_eq!(aisles::categorise("1 red bell pepper", &none), "produce");
+    assert_eq!(aisles::categorise("pepper", &none), "spices");
+    assert_eq!(aisles::categorise("Birthday candles", &none), "other");
+}
+
+#[test]
+fn test_overrides_take_precedence() {
+    let overrides = CategoryOverrides::from([("oat milk".to_string(), "drinks".to_string())]);
+
+    assert_eq!(aisles::categorise("1 l oat milk", &overrides), "drinks");
+    assert_eq!(aisles::categorise("Milk", &overrides), "dairy");
+}
+
+#[test]
+fn test_parse_dictionary_skips_unknown_categories() {
+    let dictionary = aisles::parse_dictionary("# comment\nproduce: Apples, pear\nsweets: candy\n\nnot a line");
+
+    assert_eq!(dictionary.get("apple").map(String::as_str), Some("produce"));
+    assert_eq!(dictionary.get("pear").map(String::as_str), Some("produce"));
+    assert!(!dictionary.contains_key("candy"));
+}
+
+#[test]
+fn test_group_tasks_in_store_order() {
+    let tasks = vec![task(1, "milk"), task(2, "bread"), task(3, "apples"), task(4, "cheese"), task(5, "widget")];
+
+    let default_groups = aisles::group_tasks(tasks.clone(), &CategoryOverrides::new(), &[]);
+    let labels: Vec<&str> = default_groups.iter().map(|g| g.category.as_str()).collect();
+    assert_eq!(labels, vec!["produce", "bakery", "dairy", "other"]);
+    assert_eq!(default_groups[2].tasks.iter().map(|t| t.id).collect::<Vec<_>>(), vec![1, 4]);
+
+    let store_order = vec!["dairy".to_string(), "bakery".to_string()];
+    let store_groups = aisles::group_tasks(tasks, &CategoryOverrides::new(), &store_order);
+    let labels: Vec<&str> = store_groups.iter().map(|g| g.category.as_str()).collect();
+    assert_eq!(labels, vec!["dairy", "bakery", "produce", "other"]);
+}
+
+#[tokio::test]
+async fn test_grouped_list_view() {
+    let (server, _temp_dir) = setup_test_server().await;
+    server.post("/create_list").form(&serde_json::json!({ "name": "Groceries" })).await;
+    for text in ["2 l milk", "1 loaf bread", "apples"] {
+        server.post("/1/task").form(&serde_json::json!({ "text": text })).await;
+    }
+
+    let response = server.get("/?list_id=1").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Group by aisle");
+    assert!(!response.text().contains("Fruit &amp; vegetables"));
+
+    let response = server.get("/?list_id=1&group=aisle").await;
+    response.assert_status_ok();
+    let body = response.text();
+    let produce = body.find("<th colspan=\"3\">Fruit &amp; vegetables</th>").expect("produce group");
+    let dairy = body.find("<th colspan=\"3\">Dairy &amp; eggs</th>").expect("dairy group");
+    assert!(produce < dairy);
+}
+
+#[tokio::test]
+async fn test_manual_category_is_learned() {
+    let (server, _temp_dir) = setup_test_server().await;
+    server.post("/create_list").form(&serde_json::json!({ "name": "Groceries" })).await;
+    server.post("/1/task").form(&serde_json::json!({ "text": "Birthday candles" })).await;
+
+    let response = server.post("/task/1/category").form(&serde_json::json!({ "category": "household" })).await;
+    response.assert_status_ok();
+    assert_eq!(response.header("HX-Refresh"), "true");
+
+    // A new task for the same item lands in the learned category
+    server.post("/1/task").form(&serde_json::json!({ "text": "2 birthday candles" })).await;
+    let response = server.get("/?list_id=1&group=aisle").await;
+    response.assert_text_contains("<th colspan=\"3\">Household</th>");
+    assert!(!response.text().contains("<th colspan=\"3\">Other</th>"));
+
+    let response = server.post("/task/1/category").form(&serde_json::json!({ "category": "sweets" })).await;
+    response.assert_status_bad_request();
+    let response = server.post("/task/99/category").form(&serde_json::json!({ "category": "dairy" })).await;
+    response.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_store_aisle_order() {
+    let (server, _temp_dir) = setup_test_server().await;
+    server.post("/create_list").form(&serde_json::json!({ "name": "Groceries" })).await;
+    for text in ["milk", "apples"] {
+        server.post("/1/task").form(&serde_json::json!({ "text": text })).await;
+    }
+
+    let response = server.post("/stores").form(&serde_json::json!({ "name": "Corner shop" })).await;
+    response.assert_status_see_other();
+    let response = server
+        .post("/stores/1")
+        .form(&serde_json::json!({ "dairy": "1", "produce": "2" }))
+        .await;
+    response.assert_status_see_other();
+
+    let response = server.get("/stores").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Corner shop");
+
+    let response = server.get("/?list_id=1&group=aisle&store=1").await;
+    let body = response.text();
+    let produce = body.find("<th colspan=\"3\">Fruit &amp; vegetables</th>").expect("produce group");
+    let dairy = body.find("<th colspan=\"3\">Dairy &amp; eggs</th>").expect("dairy group");
+    assert!(dairy < produce);
+
+    let response = server.post("/stores/1").form(&serde_json::json!({ "dairy": "first" })).await;
+    response.assert_status_bad_request();
+    let response = server.post("/stores/7").form(&serde_json::json!({ "dairy": "1" })).await;
+    response.assert_status_not_found();
+}
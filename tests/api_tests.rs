@@ -0,0 +1,226 @@
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+mod common;
+use common::*;
+
+fn error_code(body: &Value) -> &str {
+    body["error"]["code"].as_str().unwrap_or_default()
+}
+
+#[tokio::test]
+async fn test_api_lists_crud() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server.post("/api/v1/lists").json(&json!({ "name": "Groceries" })).await;
+    response.assert_status(StatusCode::CREATED);
+    assert_eq!(response.header("location"), "/api/v1/lists/1");
+    let list: Value = response.json();
+    assert_eq!(list["id"], 1);
+    assert_eq!(list["name"], "Groceries");
+
+    let response = server.put("/api/v1/lists/1").json(&json!({ "name": "Weekly shop" })).await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<Value>()["name"], "Weekly shop");
+
+    let page: Value = server.get("/api/v1/lists").await.json();
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["items"][0]["name"], "Weekly shop");
+
+    server.delete("/api/v1/lists/1").await.assert_status(StatusCode::NO_CONTENT);
+    server.get("/api/v1/lists/1").await.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_api_tasks() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    let page: Value = server.get("/api/v1/lists/1/tasks").await.json();
+    assert_eq!(page["total"], 3);
+
+    let response = server.post("/api/v1/lists/1/tasks").json(&json!({ "text": "Milk" })).await;
+    response.assert_status(StatusCode::CREATED);
+    let task: Value = response.json();
+    assert_eq!(task["text"], "Milk");
+    assert_eq!(task["completed"], false);
+    let id = task["id"].as_u64().unwrap();
+
+    let response = server
+        .patch(&format!("/api/v1/tasks/{}", id))
+        .json(&json!({ "completed": true }))
+        .await;
+    response.assert_status_ok();
+    let task: Value = response.json();
+    assert_eq!(task["completed"], true);
+    assert_eq!(task["text"], "Milk");
+
+    let response = server
+        .patch(&format!("/api/v1/tasks/{}", id))
+        .json(&json!({ "text": "Oat milk" }))
+        .await;
+    let task: Value = response.json();
+    assert_eq!(task["text"], "Oat milk");
+    assert_eq!(task["completed"], true);
+
+    server.delete(&format!("/api/v1/tasks/{}", id)).await.assert_status(StatusCode::NO_CONTENT);
+    server.get(&format!("/api/v1/tasks/{}", id)).await.assert_status_not_found();
+    server.get("/api/v1/lists/42/tasks").await.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_api_pagination() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    let page: Value = server.get("/api/v1/lists/1/tasks?page=2&per_page=2").await.json();
+    assert_eq!(page["page"], 2);
+    assert_eq!(page["per_page"], 2);
+    assert_eq!(page["total"], 3);
+    assert_eq!(page["items"].as_array().unwrap().len(), 1);
+
+    let page: Value = server.get("/api/v1/lists/1/tasks?page=5").await.json();
+    assert!(page["items"].as_array().unwrap().is_empty());
+
+    let response = server.get("/api/v1/lists/1/tasks?per_page=0").await;
+    response.assert_status_bad_request();
+    assert_eq!(error_code(&response.json()), "bad_request");
+    server.get("/api/v1/lists/1/tasks?per_page=500").await.assert_status_bad_request();
+    server.get("/api/v1/lists/1/tasks?page=0").await.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_api_recipes() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server
+        .post("/api/v1/recipes")
+        .json(&json!({
+            "title": "Pancakes",
+            "ingredients": "3 dl flour\n2 eggs",
+            "rating": 4,
+            "servings": 2
+        }))
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let recipe: Value = response.json();
+    assert_eq!(recipe["title"], "Pancakes");
+    assert_eq!(recipe["rating"], 4);
+    assert_eq!(recipe["servings"], 2);
+    let id = recipe["id"].as_u64().unwrap();
+
+    let response = server
+        .put(&format!("/api/v1/recipes/{}", id))
+        .json(&json!({ "title": "Crepes", "favourite": true }))
+        .await;
+    response.assert_status_ok();
+    let recipe: Value = response.json();
+    assert_eq!(recipe["title"], "Crepes");
+    assert_eq!(recipe["favourite"], true);
+
+    let photos: Value = server.get(&format!("/api/v1/recipes/{}/photos", id)).await.json();
+    assert_eq!(photos["total"], 0);
+    server.get("/api/v1/photos/1").await.assert_status_not_found();
+
+    server.delete(&format!("/api/v1/recipes/{}", id)).await.assert_status(StatusCode::NO_CONTENT);
+    server.get(&format!("/api/v1/recipes/{}", id)).await.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_api_meal_plan() {
+    let (server, _temp_dir) = setup_test_server().await;
+    let recipe: Value = server.post("/api/v1/recipes").json(&json!({ "title": "Tacos" })).await.json();
+
+    let response = server
+        .post("/api/v1/meal-plan")
+        .json(&json!({ "date": "2024-06-12", "recipe_id": recipe["id"], "servings": 4 }))
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let entry: Value = response.json();
+    assert_eq!(entry["meal_text"], "Tacos");
+    assert_eq!(entry["servings"], 4);
+    let id = entry["id"].as_u64().unwrap();
+
+    server
+        .post("/api/v1/meal-plan")
+        .json(&json!({ "date": "2024-06-20", "meal_text": "Soup" }))
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    let page: Value = server.get("/api/v1/meal-plan?from=2024-06-10&to=2024-06-16").await.json();
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["items"][0]["date"], "2024-06-12");
+
+    let response = server
+        .put(&format!("/api/v1/meal-plan/{}", id))
+        .json(&json!({ "meal_text": "Fish tacos" }))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<Value>()["meal_text"], "Fish tacos");
+
+    server.delete(&format!("/api/v1/meal-plan/{}", id)).await.assert_status(StatusCode::NO_CONTENT);
+    server.get(&format!("/api/v1/meal-plan/{}", id)).await.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_api_errors() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server.get("/api/v1/lists/42").await;
+    response.assert_status_not_found();
+    let body: Value = response.json();
+    assert_eq!(error_code(&body), "not_found");
+    assert_eq!(body["error"]["message"], "List not found");
+
+    let response = server.get("/api/v1/nothing-here").await;
+    response.assert_status_not_found();
+    assert_eq!(error_code(&response.json()), "not_found");
+
+    let response = server.get("/api/v1/lists/abc").await;
+    response.assert_status_bad_request();
+    assert_eq!(error_code(&response.json()), "bad_request");
+
+    let response = server.post("/api/v1/lists").json(&json!({ "name": "  " })).await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(error_code(&response.json()), "validation_failed");
+
+    let response = server.post("/api/v1/lists").json(&json!({ "title": "Wrong field" })).await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = server.post("/api/v1/lists").text("not json").await;
+    response.assert_status_bad_request();
+
+    let response = server.post("/api/v1/recipes").json(&json!({ "title": "Soup", "rating": 9 })).await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = server.post("/api/v1/meal-plan").json(&json!({ "date": "12/06/2024", "meal_text": "Soup" })).await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = server.post("/api/v1/meal-plan").json(&json!({ "date": "2024-06-12", "recipe_id": 42 })).await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_openapi_document() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server.get("/api/openapi.json").await;
+    response.assert_status_ok();
+    let doc: Value = response.json();
+    assert!(doc["openapi"].as_str().unwrap().starts_with("3."));
+
+    let paths = &doc["paths"];
+    assert!(paths["/api/v1/lists"]["get"].is_object());
+    assert!(paths["/api/v1/lists"]["post"].is_object());
+    assert!(paths["/api/v1/tasks/{id}"]["patch"].is_object());
+    assert!(paths["/api/v1/meal-plan"]["get"]["parameters"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|param| param["name"] == "from"));
+
+    let schemas = &doc["components"]["schemas"];
+    for name in ["Task", "List", "Recipe", "RecipePhoto", "MealPlanEntry", "ErrorBody", "RecipeInput"] {
+        assert!(schemas[name].is_object(), "missing schema {}", name);
+    }
+    assert!(schemas["RecipePhoto"]["properties"]["thumbnail_blob"].is_null());
+    assert!(schemas["Task"]["properties"]["completed"].is_object());
+}
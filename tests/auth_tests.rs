@@ -0,0 +1,135 @@
+use axum::http::StatusCode;
+use htmx_rs_todo::auth;
+use serde_json::{json, Value};
+
+mod common;
+use common::*;
+
+#[test]
+fn test_password_hashing() {
+    let hash = auth::hash_password("correct horse battery").unwrap();
+    assert!(hash.starts_with("$argon2"));
+    assert!(auth::verify_password("correct horse battery", &hash));
+    assert!(!auth::verify_password("wrong password", &hash));
+    assert!(!auth::verify_password("correct horse battery", "not a hash"));
+
+    // Salted, so the same password hashes differently
+    assert_ne!(hash, auth::hash_password("correct horse battery").unwrap());
+}
+
+#[test]
+fn test_safe_next() {
+    assert_eq!(auth::safe_next(Some("/recipes?page=2")), "/recipes?page=2");
+    assert_eq!(auth::safe_next(Some("//evil.example")), "/");
+    assert_eq!(auth::safe_next(Some("https://evil.example")), "/");
+    assert_eq!(auth::safe_next(Some("/\\evil.example")), "/");
+    assert_eq!(auth::safe_next(None), "/");
+}
+
+#[tokio::test]
+async fn test_requests_require_login() {
+    let (server, _temp_dir) = setup_logged_out_server().await;
+
+    let response = server.get("/recipes?sort=rating").await;
+    response.assert_status_see_other();
+    assert_eq!(response.header("location"), "/login?next=/recipes%3Fsort%3Drating");
+
+    let response = server.get("/list/1").add_header("HX-Request", "true").await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    assert_eq!(response.header("HX-Redirect"), "/login");
+
+    server.delete("/list/1").await.assert_status(StatusCode::UNAUTHORIZED);
+    server.post("/create_list").form(&json!({ "name": "Sneaky" })).await.assert_status(StatusCode::UNAUTHORIZED);
+
+    let response = server.get("/api/v1/lists").await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    assert_eq!(response.json::<Value>()["error"]["code"], "unauthorized");
+
+    // The login page and its stylesheet stay reachable
+    server.get("/login").await.assert_status_ok();
+    server.get("/vendor/pico.min.css").await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_login() {
+    let (server, _temp_dir) = setup_logged_out_server().await;
+
+    let response = server
+        .post("/login")
+        .form(&json!({ "username": "ADMIN", "password": TEST_PASSWORD, "next": "/recipes" }))
+        .await;
+    response.assert_status_see_other();
+    assert_eq!(response.header("location"), "/recipes");
+    let cookie = response.header("set-cookie");
+    let cookie = cookie.to_str().unwrap();
+    assert!(cookie.starts_with("session="));
+    assert!(cookie.contains("HttpOnly"));
+
+    server.get("/recipes").await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_logout() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server.get("/settings").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Signed in as <strong>admin</strong>");
+
+    let response = server.post("/logout").await;
+    response.assert_status_see_other();
+    assert!(response.header("set-cookie").to_str().unwrap().contains("Max-Age=0"));
+    server.get("/recipes").await.assert_status_see_other();
+}
+
+#[tokio::test]
+async fn test_login_rejects_wrong_password() {
+    let (server, _temp_dir) = setup_logged_out_server().await;
+
+    let response = server
+        .post("/login")
+        .form(&json!({ "username": TEST_USERNAME, "password": "wrong password" }))
+        .await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    response.assert_text_contains("Wrong username or password");
+    assert!(response.maybe_header("set-cookie").is_none());
+
+    let response = server
+        .post("/login")
+        .form(&json!({ "username": "nobody", "password": TEST_PASSWORD }))
+        .await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+
+    server.get("/").await.assert_status_see_other();
+}
+
+#[tokio::test]
+async fn test_login_ignores_offsite_next() {
+    let (server, _temp_dir) = setup_logged_out_server().await;
+
+    let response = server
+        .post("/login")
+        .form(&json!({ "username": TEST_USERNAME, "password": TEST_PASSWORD, "next": "//evil.example" }))
+        .await;
+    response.assert_status_see_other();
+    assert_eq!(response.header("location"), "/");
+}
+
+#[tokio::test]
+async fn test_forged_session_is_rejected() {
+    let (server, _temp_dir) = setup_logged_out_server().await;
+
+    let response = server.get("/").add_header("cookie", "session=made-up-token").await;
+    response.assert_status_see_other();
+}
+
+#[tokio::test]
+async fn test_create_user_validation() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let db = htmx_rs_todo::database::Database::new(temp_dir.path().join("test.db")).await.unwrap();
+
+    assert!(auth::create_user(&db, "  ", TEST_PASSWORD, true).await.is_err());
+    assert!(auth::create_user(&db, "admin", "short", true).await.is_err());
+    auth::create_user(&db, "admin", TEST_PASSWORD, true).await.unwrap();
+    assert!(auth::create_user(&db, "Admin", TEST_PASSWORD, false).await.is_err(), "usernames are unique ignoring case");
+}
@@ -0,0 +1,113 @@
+use htmx_rs_todo::backup::{self, DATABASE_FILE, PHOTOS_DIR};
+use htmx_rs_todo::database::Database;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// A data directory with a list of one task and a photo
+async fn setup() -> (Database, TempDir) {
+    let data_dir = TempDir::new().unwrap();
+    let db = Database::new(data_dir.path().join(DATABASE_FILE)).await.unwrap();
+    let list_id = db.create_list("Groceries".to_string()).await.unwrap();
+    db.create_task("Milk".to_string(), list_id).await.unwrap();
+    std::fs::create_dir(data_dir.path().join(PHOTOS_DIR)).unwrap();
+    std::fs::write(data_dir.path().join(PHOTOS_DIR).join("cake.jpg"), b"not really a jpeg").unwrap();
+    (db, data_dir)
+}
+
+/// Writes a .tar.zst holding `files`
+fn archive(path: &Path, files: &[(&str, &[u8])]) {
+    let file = std::fs::File::create(path).unwrap();
+    let mut builder = tar::Builder::new(zstd::Encoder::new(file, 0).unwrap());
+    for (name, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, name, *contents).unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap();
+}
+
+#[tokio::test]
+async fn test_backup_and_restore() {
+    let (_db, data_dir) = setup().await;
+    let out_dir = TempDir::new().unwrap();
+    let out = out_dir.path().join("backup.tar.zst");
+
+    // While the database is open, like with the server running
+    backup::create(data_dir.path(), &out).unwrap();
+
+    let restored = TempDir::new().unwrap();
+    backup::restore(&out, restored.path(), false).unwrap();
+    assert_eq!(std::fs::read(restored.path().join(PHOTOS_DIR).join("cake.jpg")).unwrap(), b"not really a jpeg");
+
+    let db = Database::new(restored.path().join(DATABASE_FILE)).await.unwrap();
+    let lists = db.get_lists().await.unwrap();
+    assert_eq!(lists[0].name, "Groceries");
+    let tasks = db.get_tasks(lists[0].id).await.unwrap();
+    assert_eq!(tasks[0].text, "Milk");
+}
+
+#[tokio::test]
+async fn test_restore_only_replaces_with_force() {
+    let (_db, data_dir) = setup().await;
+    let out_dir = TempDir::new().unwrap();
+    let out = out_dir.path().join("backup.tar.zst");
+    backup::create(data_dir.path(), &out).unwrap();
+
+    let (other, other_dir) = setup().await;
+    other.create_list("Hardware".to_string()).await.unwrap();
+    drop(other);
+    std::fs::write(other_dir.path().join(PHOTOS_DIR).join("drill.jpg"), b"drill").unwrap();
+
+    assert!(backup::restore(&out, other_dir.path(), false).is_err());
+    backup::restore(&out, other_dir.path(), true).unwrap();
+
+    let db = Database::new(other_dir.path().join(DATABASE_FILE)).await.unwrap();
+    assert_eq!(db.get_lists().await.unwrap().len(), 1);
+    assert!(!other_dir.path().join(PHOTOS_DIR).join("drill.jpg").exists());
+}
+
+#[test]
+fn test_restore_rejects_other_files() {
+    let dir = TempDir::new().unwrap();
+    let out = dir.path().join("backup.tar.zst");
+    archive(&out, &[("notes.txt", b"hello")]);
+
+    let restored = TempDir::new().unwrap();
+    assert!(backup::restore(&out, restored.path(), false).is_err());
+    assert!(!restored.path().join("notes.txt").exists());
+    assert!(!restored.path().join(DATABASE_FILE).exists());
+}
+
+#[test]
+fn test_restore_rejects_damaged_database() {
+    let dir = TempDir::new().unwrap();
+    let out = dir.path().join("backup.tar.zst");
+    archive(&out, &[(DATABASE_FILE, b"this is not a database")]);
+
+    let restored = TempDir::new().unwrap();
+    assert!(backup::restore(&out, restored.path(), false).is_err());
+    assert!(!restored.path().join(DATABASE_FILE).exists());
+}
+
+#[test]
+fn test_prune_keeps_newest() {
+    let dir = TempDir::new().unwrap();
+    for name in [
+        "todos-20261001T000000Z.tar.zst",
+        "todos-20261002T000000Z.tar.zst",
+        "todos-20261003T000000Z.tar.zst",
+        "keep-me.tar.zst",
+    ] {
+        std::fs::write(dir.path().join(name), b"").unwrap();
+    }
+
+    backup::prune(dir.path(), 2).unwrap();
+
+    let mut left: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    left.sort();
+    assert_eq!(left, ["keep-me.tar.zst", "todos-20261002T000000Z.tar.zst", "todos-20261003T000000Z.tar.zst"]);
+}
@@ -0,0 +1,277 @@
+use axum::http::{Method, StatusCode};
+use axum_test::{TestRequest, TestServer};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use bytes::Bytes;
+use htmx_rs_todo::caldav::{self, DavTask};
+use htmx_rs_todo::{create_app, AppState};
+use tempfile::TempDir;
+
+mod common;
+use common::*;
+
+/// A server on the groceries list
+async fn setup() -> (TestServer, AppState, TempDir) {
+    let (state, temp_dir) = setup_groceries().await;
+    (TestServer::new(create_app(state.clone())).unwrap(), state, temp_dir)
+}
+
+fn dav(server: &TestServer, method: &str, path: &str) -> TestRequest {
+    let credentials = BASE64.encode(format!("{}:{}", TEST_USERNAME, TEST_PASSWORD));
+    server
+        .method(Method::from_bytes(method.as_bytes()).unwrap(), path)
+        .authorization(format!("Basic {}", credentials))
+}
+
+/// A request body recorded from a CalDAV client
+fn fixture(name: &str) -> Bytes {
+    Bytes::from(std::fs::read(format!("tests/fixtures/caldav/{}", name)).unwrap())
+}
+
+async fn dav_task(state: &AppState, name: &str) -> DavTask {
+    state.db.get_dav_task(1, name.to_string()).await.unwrap().unwrap()
+}
+
+#[tokio::test]
+async fn test_requires_basic_auth() {
+    let (server, _state, _temp_dir) = setup().await;
+
+    let response = server.method(Method::from_bytes(b"PROPFIND").unwrap(), "/dav/").await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    assert!(response.header("www-authenticate").to_str().unwrap().starts_with("Basic"));
+
+    let wrong = BASE64.encode(format!("{}:wrong password", TEST_USERNAME));
+    server
+        .method(Method::from_bytes(b"PROPFIND").unwrap(), "/dav/")
+        .authorization(format!("Basic {}", wrong))
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    let response = server.method(Method::from_bytes(b"PROPFIND").unwrap(), "/.well-known/caldav").await;
+    response.assert_status(StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.header("location"), "/dav/");
+}
+
+#[tokio::test]
+async fn test_discovery() {
+    let (server, _state, _temp_dir) = setup().await;
+
+    let response = dav(&server, "OPTIONS", "/dav/").await;
+    assert!(response.header("dav").to_str().unwrap().contains("calendar-access"));
+
+    let response = dav(&server, "PROPFIND", "/dav/").add_header("Depth", "0").bytes(fixture("ios-propfind-root.xml")).await;
+    response.assert_status(StatusCode::MULTI_STATUS);
+    response.assert_text_contains("<d:current-user-principal><d:href>/dav/principal/</d:href></d:current-user-principal>");
+
+    let response = dav(&server, "PROPFIND", "/dav/principal/")
+        .add_header("Depth", "0")
+        .bytes(fixture("ios-propfind-principal.xml"))
+        .await;
+    response.assert_text_contains("<c:calendar-home-set><d:href>/dav/calendars/</d:href></c:calendar-home-set>");
+    response.assert_text_contains("<d:displayname>admin</d:displayname>");
+    // Properties this server doesn't have are listed as not found
+    response.assert_text_contains("<c:calendar-user-address-set/>");
+    response.assert_text_contains("<cs:email-address-set/>");
+    response.assert_text_contains("HTTP/1.1 404 Not Found");
+
+    let response = dav(&server, "PROPFIND", "/dav/calendars/")
+        .add_header("Depth", "1")
+        .bytes(fixture("davx5-propfind-home.xml"))
+        .await;
+    response.assert_status(StatusCode::MULTI_STATUS);
+    let text = response.text();
+    assert!(text.contains("<d:href>/dav/calendars/1/</d:href>"));
+    assert!(text.contains("<d:displayname>Groceries</d:displayname>"));
+    assert!(text.contains("<d:collection/><c:calendar/>"));
+    assert!(text.contains("<c:comp name=\"VTODO\"/>"));
+    assert!(text.contains("<cs:getctag>"));
+    assert!(text.contains("<x:calendar-color xmlns:x=\"http://apple.com/ns/ical/\"/>"));
+}
+
+#[tokio::test]
+async fn test_list_tasks() {
+    let (server, state, _temp_dir) = setup().await;
+    let milk = dav_task(&state, "1.ics").await;
+
+    let response = dav(&server, "PROPFIND", "/dav/calendars/1/")
+        .add_header("Depth", "1")
+        .bytes(fixture("davx5-propfind-calendar.xml"))
+        .await;
+    let text = response.text();
+    assert!(text.contains("<d:href>/dav/calendars/1/1.ics</d:href>"));
+    assert!(text.contains("<d:href>/dav/calendars/1/2.ics</d:href>"));
+    assert!(text.contains(&format!("<d:getetag>{}</d:getetag>", milk.etag().replace('"', "&quot;"))));
+
+    // Depth 0 is only the calendar
+    let response = dav(&server, "PROPFIND", "/dav/calendars/1/")
+        .add_header("Depth", "0")
+        .bytes(fixture("davx5-propfind-calendar.xml"))
+        .await;
+    assert!(!response.text().contains("1.ics"));
+
+    let response = dav(&server, "GET", "/dav/calendars/1/1.ics").await;
+    response.assert_status_ok();
+    assert_eq!(response.header("etag"), milk.etag().as_str());
+    let ical = response.text();
+    assert!(ical.starts_with("BEGIN:VCALENDAR\r\n"));
+    assert!(ical.contains("UID:task-1@htmx-rs-todo\r\n"));
+    assert!(ical.contains("SUMMARY:Milk @store\r\n"));
+    assert!(ical.contains("STATUS:NEEDS-ACTION\r\n"));
+    assert!(ical.contains("CATEGORIES:store\r\n"));
+
+    dav(&server, "GET", "/dav/calendars/1/9.ics").await.assert_status_not_found();
+    dav(&server, "GET", "/dav/calendars/7/1.ics").await.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_reports() {
+    let (server, _state, _temp_dir) = setup().await;
+
+    let response = dav(&server, "REPORT", "/dav/calendars/1/").add_header("Depth", "1").bytes(fixture("tasksorg-report-query.xml")).await;
+    response.assert_status(StatusCode::MULTI_STATUS);
+    let text = response.text();
+    assert!(text.contains("/dav/calendars/1/1.ics") && text.contains("/dav/calendars/1/2.ics"));
+    assert!(!text.contains("calendar-data"));
+
+    // Reminders only asks for what is still to do
+    let response = dav(&server, "REPORT", "/dav/calendars/1/").bytes(fixture("ios-report-incomplete.xml")).await;
+    let text = response.text();
+    assert!(text.contains("SUMMARY:Milk @store"));
+    assert!(!text.contains("2.ics"));
+
+    let response = dav(&server, "REPORT", "/dav/calendars/1/").bytes(fixture("thunderbird-report-events.xml")).await;
+    assert!(!response.text().contains("<d:response>"));
+
+    let response = dav(&server, "REPORT", "/dav/calendars/1/").bytes(fixture("davx5-report-multiget.xml")).await;
+    let text = response.text();
+    assert!(text.contains("SUMMARY:Milk @store"));
+    assert!(text.contains("SUMMARY:Eggs"));
+    assert!(text.contains("STATUS:COMPLETED"));
+    assert!(text.contains("<d:href>/dav/calendars/1/gone.ics</d:href><d:status>HTTP/1.1 404 Not Found</d:status>"));
+}
+
+#[tokio::test]
+async fn test_put_creates_task() {
+    let (server, state, _temp_dir) = setup().await;
+    let path = "/dav/calendars/1/6E0F5B39-7C1A-4D2B-9A5E-0C64B1D1A7F2.ics";
+    let ctag = caldav_ctag(&server).await;
+
+    // Names like those of tasks made elsewhere are kept for them
+    let response = dav(&server, "PUT", "/dav/calendars/1/42.ics").add_header("If-None-Match", "*").bytes(fixture("ios-put-new.ics")).await;
+    response.assert_status(StatusCode::FORBIDDEN);
+    response.assert_text_contains("<number>.ics");
+    assert_eq!(state.db.get_tasks(1).await.unwrap().len(), 2);
+
+    let response = dav(&server, "PUT", path)
+        .add_header("If-None-Match", "*")
+        .content_type("text/calendar; charset=utf-8")
+        .bytes(fixture("ios-put-new.ics"))
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let etag = response.header("etag");
+
+    let tasks = state.db.get_tasks(1).await.unwrap();
+    let task = tasks.last().unwrap();
+    assert_eq!(task.text, "Pick up the parcel, the one from grandma; before noon @errands and post office");
+    assert!(!task.completed);
+    assert_ne!(caldav_ctag(&server).await, ctag);
+
+    // Served back under the client's name and UID
+    let response = dav(&server, "GET", path).await;
+    assert_eq!(response.header("etag"), etag);
+    assert!(response.text().contains("UID:6E0F5B39-7C1A-4D2B-9A5E-0C64B1D1A7F2\r\n"));
+
+    // Creating it again fails, as does another resource with the same UID
+    dav(&server, "PUT", path).add_header("If-None-Match", "*").bytes(fixture("ios-put-new.ics")).await.assert_status(StatusCode::PRECONDITION_FAILED);
+    dav(&server, "PUT", "/dav/calendars/1/copy.ics").bytes(fixture("ios-put-new.ics")).await.assert_status(StatusCode::FORBIDDEN);
+    dav(&server, "PUT", "/dav/calendars/1/bad%20name.ics").bytes(fixture("ios-put-new.ics")).await.assert_status(StatusCode::FORBIDDEN);
+    dav(&server, "PUT", "/dav/calendars/1/empty.ics").bytes(Bytes::from("BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n")).await.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_put_updates_task_with_matching_etag() {
+    let (server, state, _temp_dir) = setup().await;
+    let milk = dav_task(&state, "1.ics").await;
+
+    dav(&server, "PUT", "/dav/calendars/1/1.ics")
+        .add_header("If-Match", "\"0-0\"")
+        .bytes(fixture("tasksorg-put-completed.ics"))
+        .await
+        .assert_status(StatusCode::PRECONDITION_FAILED);
+
+    let response = dav(&server, "PUT", "/dav/calendars/1/1.ics")
+        .add_header("If-Match", milk.etag())
+        .bytes(fixture("tasksorg-put-completed.ics"))
+        .await;
+    response.assert_status(StatusCode::NO_CONTENT);
+
+    let task = state.db.get_task(1).await.unwrap();
+    assert!(task.completed);
+    assert_eq!(task.completed_at, Some(1792325100)); // 2026-10-18 12:05:00 UTC
+    assert_eq!(task.priority.as_deref(), Some("A"));
+    let updated = dav_task(&state, "1.ics").await;
+    assert_ne!(updated.etag(), milk.etag());
+    assert_eq!(response.header("etag"), updated.etag().as_str());
+    // The old ETag is stale now
+    dav(&server, "PUT", "/dav/calendars/1/1.ics")
+        .add_header("If-Match", milk.etag())
+        .bytes(fixture("tasksorg-put-completed.ics"))
+        .await
+        .assert_status(StatusCode::PRECONDITION_FAILED);
+}
+
+#[tokio::test]
+async fn test_delete_task() {
+    let (server, state, _temp_dir) = setup().await;
+    let milk = dav_task(&state, "1.ics").await;
+
+    dav(&server, "DELETE", "/dav/calendars/1/1.ics").add_header("If-Match", "\"0-0\"").await.assert_status(StatusCode::PRECONDITION_FAILED);
+    dav(&server, "DELETE", "/dav/calendars/1/1.ics").add_header("If-Match", milk.etag()).await.assert_status(StatusCode::NO_CONTENT);
+    dav(&server, "GET", "/dav/calendars/1/1.ics").await.assert_status_not_found();
+    assert_eq!(state.db.get_tasks(1).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_other_households_are_hidden() {
+    let (server, state, _temp_dir) = setup().await;
+    let cabin = state.db.create_household("Cabin".to_string()).await.unwrap();
+    let other = state.db.for_household(cabin).create_list("Firewood".to_string()).await.unwrap();
+
+    let response = dav(&server, "PROPFIND", "/dav/calendars/").add_header("Depth", "1").await;
+    assert!(!response.text().contains("Firewood"));
+    dav(&server, "PROPFIND", &format!("/dav/calendars/{}/", other)).await.assert_status_not_found();
+}
+
+#[test]
+fn test_parse_vtodo() {
+    let ical = String::from_utf8(fixture("ios-put-new.ics").to_vec()).unwrap();
+    let vtodo = caldav::parse_vtodo(&ical, chrono_tz::UTC).unwrap();
+    assert_eq!(vtodo.uid.as_deref(), Some("6E0F5B39-7C1A-4D2B-9A5E-0C64B1D1A7F2"));
+    assert!(vtodo.summary.ends_with("@errands and post office"));
+    assert!(!vtodo.completed);
+
+    let ical = String::from_utf8(fixture("tasksorg-put-completed.ics").to_vec()).unwrap();
+    let vtodo = caldav::parse_vtodo(&ical, chrono_tz::UTC).unwrap();
+    assert!(vtodo.completed);
+    assert_eq!(vtodo.priority.as_deref(), Some("A"));
+
+    // Floating times are in the household timezone
+    let ical = "BEGIN:VTODO\nSUMMARY:Call\nCOMPLETED:20261018T150500\nEND:VTODO\n";
+    let vtodo = caldav::parse_vtodo(ical, chrono_tz::Europe::Helsinki).unwrap();
+    assert_eq!(vtodo.completed_at, Some(1792325100));
+    assert!(caldav::parse_vtodo("BEGIN:VEVENT\nSUMMARY:Party\nEND:VEVENT\n", chrono_tz::UTC).is_err());
+}
+
+#[test]
+fn test_priorities() {
+    assert_eq!(caldav::ical_priority("A"), Some(1));
+    assert_eq!(caldav::ical_priority("I"), Some(9));
+    assert_eq!(caldav::ical_priority("Z"), Some(9));
+}
+
+async fn caldav_ctag(server: &TestServer) -> String {
+    let text = dav(server, "PROPFIND", "/dav/calendars/1/").add_header("Depth", "0").await.text();
+    let start = text.find("<cs:getctag>").unwrap() + "<cs:getctag>".len();
+    text[start..start + text[start..].find('<').unwrap()].to_string()
+}
+
@@ -0,0 +1,102 @@
+use htmx_rs_todo::cli::{Backend, Remote};
+use htmx_rs_todo::{create_app, AppState};
+use tempfile::TempDir;
+use tokio::net::TcpListener;
+
+mod common;
+use common::*;
+
+/// State with a shopping list and a recipe
+async fn setup() -> (AppState, TempDir) {
+    let (state, temp_dir) = setup_state().await;
+    state.db.create_list("Groceries".to_string()).await.unwrap();
+    state.db.create_recipe("Pancakes".to_string(), String::new(), String::new(), None).await.unwrap();
+    (state, temp_dir)
+}
+
+/// Serves the app on a free port, returning its URL
+async fn serve(state: AppState) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, create_app(state)).await.unwrap() });
+    format!("http://{}", address)
+}
+
+/// Runs the same commands on either backend
+async fn add_and_check_off(backend: &Backend) {
+    let list = backend.find_list("groceries").await.unwrap();
+    assert_eq!(backend.find_list(&list.id.to_string()).await.unwrap().name, "Groceries");
+    assert!(backend.find_list("Hardware").await.is_err());
+
+    let task = backend.add_task(list.id, "buy batteries").await.unwrap();
+    assert_eq!(task.text, "buy batteries");
+    assert!(backend.add_task(list.id, "  ").await.is_err());
+
+    let done = backend.complete_task(task.id).await.unwrap();
+    assert!(done.completed);
+    let tasks = backend.tasks(list.id).await.unwrap();
+    assert!(tasks.iter().any(|task| task.text == "buy batteries" && task.completed));
+    assert!(backend.complete_task(999).await.is_err());
+
+    let recipe = backend.find_recipe("pancakes").await.unwrap();
+    let meal = backend.add_meal("2026-10-20", None, Some(&recipe)).await.unwrap();
+    assert_eq!(meal.meal_text, "Pancakes");
+    assert_eq!(meal.recipe_id, Some(recipe.id));
+    let meal = backend.add_meal("2026-10-21", Some("Takeaway"), None).await.unwrap();
+    assert_eq!(meal.recipe_id, None);
+    assert!(backend.add_meal("tomorrow", Some("Soup"), None).await.is_err());
+}
+
+#[tokio::test]
+async fn test_commands_on_database() {
+    let (state, _temp_dir) = setup().await;
+    let backend = Backend::Local(state.clone());
+
+    add_and_check_off(&backend).await;
+    assert_eq!(backend.lists().await.unwrap().len(), 1);
+    assert_eq!(backend.recipes().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_household_is_picked_on_database() {
+    let (state, _temp_dir) = setup().await;
+    assert_eq!(Backend::local(state.clone(), None).await.unwrap().lists().await.unwrap().len(), 1);
+
+    let cabin = state.db.create_household("Cabin".to_string()).await.unwrap();
+    state.db.for_household(cabin).create_list("Firewood".to_string()).await.unwrap();
+
+    // Not guessed once there are two
+    let err = Backend::local(state.clone(), None).await.err().unwrap();
+    assert!(err.to_string().contains("--household"), "{}", err);
+    assert!(Backend::local(state.clone(), Some("Boathouse")).await.is_err());
+
+    for household in ["cabin", &cabin.to_string()] {
+        let backend = Backend::local(state.clone(), Some(household)).await.unwrap();
+        let lists = backend.lists().await.unwrap();
+        assert_eq!(lists.len(), 1);
+        assert_eq!(lists[0].name, "Firewood");
+    }
+    let backend = Backend::local(state, Some("Home")).await.unwrap();
+    assert_eq!(backend.lists().await.unwrap()[0].name, "Groceries");
+}
+
+#[tokio::test]
+async fn test_commands_against_server() {
+    let (state, _temp_dir) = setup().await;
+    let url = serve(state.clone()).await;
+    let backend = Backend::Remote(Remote::login(&url, TEST_USERNAME, TEST_PASSWORD).await.unwrap());
+
+    add_and_check_off(&backend).await;
+    // The server's database got the changes
+    let list = state.db.get_lists().await.unwrap()[0].clone();
+    assert_eq!(state.db.get_tasks(list.id).await.unwrap()[0].text, "buy batteries");
+}
+
+#[tokio::test]
+async fn test_remote_login_with_wrong_password() {
+    let (state, _temp_dir) = setup().await;
+    let url = serve(state).await;
+
+    let err = Remote::login(&url, TEST_USERNAME, "wrong password").await.err().unwrap();
+    assert_eq!(err.to_string(), "Wrong username or password");
+}
@@ -1,29 +1,95 @@
-use axum_test::TestServer;
+use axum_test::{TestServer, TestServerConfig};
 use tempfile::TempDir;
 
 // Import from the library
-use htmx_rs_todo::{database::Database, AppState, create_app};
+use htmx_rs_todo::{auth, csrf, database::Database, AppState, create_app};
 
-/// Sets up a test server with a temporary database
-pub async fn setup_test_server() -> (TestServer, TempDir) {
+pub const TEST_USERNAME: &str = "admin";
+pub const TEST_PASSWORD: &str = "correct horse battery";
+
+/// App state on a temporary database with the test user, for tests calling into the
+/// library or serving the app themselves
+#[allow(dead_code)]
+pub async fn setup_state() -> (AppState, TempDir) {
     let temp_dir = TempDir::new().expect("Failed to create temp directory");
     let db_path = temp_dir.path().join("test.db");
     
     let db = Database::new(db_path)
         .await
         .expect("Failed to create test database");
+    auth::create_user(&db, TEST_USERNAME, TEST_PASSWORD, true)
+        .await
+        .expect("Failed to create test user");
     
     let photos_dir = temp_dir.path().join("photos");
     std::fs::create_dir_all(&photos_dir).expect("Failed to create photos directory");
-    let app_state = AppState { db, photos_dir };
+    (AppState { db, photos_dir }, temp_dir)
+}
+
+/// App state whose list 1, Groceries, has "Milk @store" (task 1) and a checked off "Eggs" (task 2)
+#[allow(dead_code)]
+pub async fn setup_groceries() -> (AppState, TempDir) {
+    let (state, temp_dir) = setup_state().await;
+    let list_id = state.db.create_list("Groceries".to_string()).await.unwrap();
+    state.db.create_task("Milk @store".to_string(), list_id).await.unwrap();
+    let eggs = state.db.create_task("Eggs".to_string(), list_id).await.unwrap();
+    state.db.set_task_completed(eggs, true, None).await.unwrap();
+    (state, temp_dir)
+}
+
+/// Sets up a test server with a temporary database and one user, without logging in.
+/// The server keeps cookies between requests, like a browser.
+#[allow(dead_code)] // Only the auth tests start out logged out
+pub async fn setup_logged_out_server() -> (TestServer, TempDir) {
+    let (app_state, temp_dir) = setup_state().await;
     
     let app = create_app(app_state);
-    let server = TestServer::new(app).expect("Failed to create test server");
+    let mut server = TestServerConfig::builder()
+        .save_cookies()
+        .build_server(app)
+        .expect("Failed to create test server");
+    send_csrf_token(&mut server).await;
+    
+    (server, temp_dir)
+}
+
+/// Reads the CSRF token off the login page, which also sets its cookie, and sends it
+/// with every later request like the pages' `hx-headers` do
+#[allow(dead_code)]
+pub async fn send_csrf_token(server: &mut TestServer) -> String {
+    let token = csrf_token(&server.get("/login").await.text());
+    server.add_header(csrf::CSRF_HEADER, token.clone());
+    token
+}
+
+/// Token in the first form of a page
+#[allow(dead_code)]
+pub fn csrf_token(page: &str) -> String {
+    let field = format!("name=\"{}\" value=\"", csrf::CSRF_FIELD);
+    let start = page.find(&field).expect("CSRF token on the page") + field.len();
+    let end = start + page[start..].find('"').unwrap();
+    page[start..end].to_string()
+}
+
+/// Sets up a test server with a temporary database, logged in as the test user
+#[allow(dead_code)]
+pub async fn setup_test_server() -> (TestServer, TempDir) {
+    let (server, temp_dir) = setup_logged_out_server().await;
+
+    server
+        .post("/login")
+        .form(&serde_json::json!({
+            "username": TEST_USERNAME,
+            "password": TEST_PASSWORD
+        }))
+        .await
+        .assert_status_see_other();
     
     (server, temp_dir)
 }
 
 /// Sets up a test server with some pre-populated data
+#[allow(dead_code)]
 pub async fn setup_test_server_with_data() -> (TestServer, TempDir) {
     let (server, temp_dir) = setup_test_server().await;
     
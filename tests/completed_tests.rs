@@ -0,0 +1,97 @@
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+mod common;
+use common::*;
+
+#[tokio::test]
+async fn test_set_completed_is_idempotent() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    for _ in 0..2 {
+        let response = server.post("/task/1/completed").form(&json!({ "completed": true })).await;
+        response.assert_status_ok();
+        response.assert_text_contains("checked");
+    }
+    let task: Value = server.get("/api/v1/tasks/1").await.json();
+    assert_eq!(task["completed"], true);
+    assert_eq!(task["version"], 2);
+    let completed_at = task["completed_at"].as_i64().unwrap();
+
+    // Setting it again keeps when it was first checked off
+    server.post("/task/1/completed").form(&json!({ "completed": true })).await.assert_status_ok();
+    let task: Value = server.get("/api/v1/tasks/1").await.json();
+    assert_eq!(task["completed_at"].as_i64(), Some(completed_at));
+
+    server.post("/task/1/completed").form(&json!({ "completed": false })).await.assert_status_ok();
+    let task: Value = server.get("/api/v1/tasks/1").await.json();
+    assert_eq!(task["completed"], false);
+    assert!(task["completed_at"].is_null());
+}
+
+#[tokio::test]
+async fn test_toggle_still_flips() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    server.post("/task/1").await.assert_text_contains("checked");
+    let task: Value = server.get("/api/v1/tasks/1").await.json();
+    assert_eq!(task["completed"], true);
+    assert!(task["completed_at"].is_i64());
+
+    server.post("/task/1").await.assert_status_ok();
+    let task: Value = server.get("/api/v1/tasks/1").await.json();
+    assert_eq!(task["completed"], false);
+}
+
+#[tokio::test]
+async fn test_set_completed_with_stale_version() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+    server.post("/task/1/completed").form(&json!({ "completed": true })).await.assert_status_ok();
+
+    // Already how it was wanted, so the old version doesn't matter
+    let response = server
+        .post("/task/1/completed")
+        .add_header("If-Match", "\"1\"")
+        .form(&json!({ "completed": true }))
+        .await;
+    response.assert_status_ok();
+
+    server
+        .post("/task/1/completed")
+        .add_header("If-Match", "\"1\"")
+        .form(&json!({ "completed": false }))
+        .await
+        .assert_status(StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_set_completed_on_shared_list() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+    server
+        .post("/shares")
+        .form(&json!({ "target": "list:1", "permission": "edit", "expires_days": "" }))
+        .await
+        .assert_status_see_other();
+    let page = server.get("/shares").await.text();
+    let link = page.split("href=\"").find(|href| href.starts_with("/s/")).unwrap().split('"').next().unwrap().to_string();
+
+    for _ in 0..2 {
+        server
+            .post(&format!("{}/task/2/completed", link))
+            .form(&json!({ "completed": true }))
+            .await
+            .assert_text_contains("afterbegin:#in-cart");
+    }
+    let task: Value = server.get("/api/v1/tasks/2").await.json();
+    assert_eq!(task["completed"], true);
+}
+
+#[tokio::test]
+async fn test_set_completed_missing_task() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+    server
+        .post("/task/42/completed")
+        .form(&json!({ "completed": true }))
+        .await
+        .assert_status_not_found();
+}
@@ -0,0 +1,165 @@
+use axum::http::StatusCode;
+use axum_test::multipart::MultipartForm;
+use serde_json::{json, Value};
+
+mod common;
+use common::*;
+
+fn recipe_form(title: &str, version: &str) -> MultipartForm {
+    MultipartForm::new()
+        .add_text("title", title.to_string())
+        .add_text("ingredients", "2 eggs")
+        .add_text("instructions", "Whisk")
+        .add_text("version", version.to_string())
+}
+
+#[tokio::test]
+async fn test_stale_toggle_gets_a_conflict() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+    server.get("/").await.assert_text_contains(r#"hx-headers='{"If-Match": "\"1\""}'"#);
+
+    // Both devices check it off, the second one doesn't undo the first
+    for _ in 0..2 {
+        let response = server
+            .post("/task/1")
+            .add_header("If-Match", "\"1\"")
+            .form(&json!({ "completed": true }))
+            .await;
+        response.assert_status_ok();
+        response.assert_text_contains("checked");
+        response.assert_text_contains(r#"hx-headers='{"If-Match": "\"2\""}'"#);
+    }
+
+    // Unchecked on one device while the other still wants it checked
+    server.post("/task/1").add_header("If-Match", "\"2\"").await.assert_status_ok();
+    let response = server
+        .post("/task/1")
+        .add_header("If-Match", "\"1\"")
+        .form(&json!({ "completed": true }))
+        .await;
+    response.assert_status(StatusCode::CONFLICT);
+    response.assert_text_contains("was changed on another device");
+    response.assert_text_contains("It is not done there");
+    response.assert_text_contains(r#"hx-headers='{"If-Match": "\"3\""}'"#);
+
+    // "Use mine" sends the version it was shown
+    let response = server
+        .post("/task/1")
+        .add_header("If-Match", "\"3\"")
+        .form(&json!({ "completed": true }))
+        .await;
+    response.assert_status_ok();
+    response.assert_text_contains("afterbegin:#completedtablebody");
+}
+
+#[tokio::test]
+async fn test_keep_theirs_reloads_the_row() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    server.get("/task/1").await.assert_text_contains("Test Task 1");
+    server.get("/task/1?view=shop").await.assert_text_contains("beforeend:#to-buy");
+    server.get("/task/99").await.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_stale_toggle_in_shopping_mode() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+    server.post("/task/2?view=shop").await.assert_status_ok();
+
+    let response = server
+        .post("/task/2?view=shop")
+        .add_header("If-Match", "W/\"1\"")
+        .form(&json!({ "completed": false }))
+        .await;
+    response.assert_status(StatusCode::CONFLICT);
+    response.assert_text_contains(r#"class="shop-row conflict" id="task-2""#);
+    response.assert_text_contains("It is in the cart there");
+    response.assert_text_contains(r#"hx-get="/task/2?view=shop""#);
+}
+
+#[tokio::test]
+async fn test_stale_reorder_shows_both_orders() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+    server.get("/?list_id=1").await.assert_text_contains(r#"data-version="1""#);
+
+    let response = server
+        .post("/reorder?list_id=1")
+        .add_header("If-Match", "\"1\"")
+        .json(&json!({ "order": [3, 2, 1] }))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.header("ETag"), "\"2\"");
+
+    let response = server
+        .post("/reorder?list_id=1")
+        .add_header("If-Match", "\"1\"")
+        .json(&json!({ "order": [1, 2, 3] }))
+        .await;
+    response.assert_status(StatusCode::CONFLICT);
+    assert_eq!(response.header("ETag"), "\"2\"");
+    let text = response.text();
+    let (mine, theirs) = text.split_once("Their order").unwrap();
+    assert!(mine.find("Test Task 1").unwrap() < mine.find("Test Task 3").unwrap());
+    assert!(theirs.find("Test Task 3").unwrap() < theirs.find("Test Task 1").unwrap());
+
+    // Nothing moved
+    let page = server.get("/?list_id=1").await.text();
+    assert!(page.find("Test Task 3").unwrap() < page.find("Test Task 1").unwrap());
+    assert!(page.contains(r#"data-version="2""#));
+}
+
+#[tokio::test]
+async fn test_stale_recipe_edit_shows_both_versions() {
+    let (server, _temp_dir) = setup_test_server().await;
+    server.post("/recipes/new").multipart(recipe_form("Omelette", "")).await.assert_status_see_other();
+    server.get("/recipes/1/edit").await.assert_text_contains(r#"name="version" value="1""#);
+
+    server.post("/recipes/1/edit").multipart(recipe_form("Cheese omelette", "1")).await.assert_status_see_other();
+
+    let response = server.post("/recipes/1/edit").multipart(recipe_form("Ham omelette", "1")).await;
+    response.assert_status(StatusCode::CONFLICT);
+    response.assert_text_contains("saved by someone else");
+    response.assert_text_contains("<strong>Cheese omelette</strong>");
+    response.assert_text_contains(r#"value="Ham omelette""#);
+    response.assert_text_contains(r#"href="/recipes/1/edit""#);
+    let version = response.text().split(r#"name="version" value=""#).nth(1).unwrap().split('"').next().unwrap().to_string();
+    server.get("/recipes/1").await.assert_text_contains("Cheese omelette");
+
+    // Saving again, now against their version, replaces it
+    server.post("/recipes/1/edit").multipart(recipe_form("Ham omelette", &version)).await.assert_status_see_other();
+    server.get("/recipes/1").await.assert_text_contains("Ham omelette");
+}
+
+#[tokio::test]
+async fn test_api_if_match() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    let response = server
+        .patch("/api/v1/tasks/1")
+        .add_header("If-Match", "\"5\"")
+        .json(&json!({ "text": "Lost update" }))
+        .await;
+    response.assert_status(StatusCode::CONFLICT);
+    let body: Value = response.json();
+    assert_eq!(body["error"]["code"], "conflict");
+
+    let response = server
+        .patch("/api/v1/tasks/1")
+        .add_header("If-Match", "\"1\"")
+        .json(&json!({ "text": "Kept update" }))
+        .await;
+    response.assert_status_ok();
+    let task: Value = response.json();
+    assert_eq!(task["text"], "Kept update");
+    assert_eq!(task["version"], 2);
+
+    let recipe = json!({ "title": "Soup", "ingredients": "1 leek", "instructions": "Simmer" });
+    server.post("/api/v1/recipes").json(&recipe).await.assert_status(StatusCode::CREATED);
+    server.put("/api/v1/recipes/1").json(&recipe).await.assert_status_ok();
+    server
+        .put("/api/v1/recipes/1")
+        .add_header("If-Match", "\"1\"")
+        .json(&recipe)
+        .await
+        .assert_status(StatusCode::CONFLICT);
+}
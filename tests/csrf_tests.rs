@@ -0,0 +1,141 @@
+use axum::http::StatusCode;
+use axum_test::{multipart::MultipartForm, TestServer, TestServerConfig};
+use htmx_rs_todo::create_app;
+use serde_json::json;
+use tempfile::TempDir;
+
+mod common;
+use common::*;
+
+/// Logged out server that, unlike the shared helpers, doesn't send the CSRF header
+async fn setup_server_without_header() -> (TestServer, TempDir) {
+    let (state, temp_dir) = setup_state().await;
+    let server = TestServerConfig::builder()
+        .save_cookies()
+        .build_server(create_app(state))
+        .unwrap();
+    (server, temp_dir)
+}
+
+/// Logged in server with a list, without the CSRF header. Returns the token to send by hand.
+async fn setup_logged_in_without_header() -> (TestServer, String, TempDir) {
+    let (server, temp_dir) = setup_server_without_header().await;
+    let token = csrf_token(&server.get("/login").await.text());
+    server
+        .post("/login")
+        .form(&json!({ "username": TEST_USERNAME, "password": TEST_PASSWORD, "csrf_token": token }))
+        .await
+        .assert_status_see_other();
+    server.post("/create_list").add_header("X-CSRF-Token", token.clone()).form(&json!({ "name": "Groceries" })).await;
+    (server, token, temp_dir)
+}
+
+#[tokio::test]
+async fn test_pages_carry_the_token() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    let page = server.get("/").await.text();
+    let token = csrf_token(&server.get("/pantry").await.text());
+    assert!(page.contains(&format!("hx-headers='{{\"X-CSRF-Token\": \"{}\"}}'", token)));
+    server.get("/recipes/new").await.assert_text_contains(format!("value=\"{}\"", token));
+}
+
+#[tokio::test]
+async fn test_requests_without_token_are_rejected() {
+    let (server, _token, _temp_dir) = setup_logged_in_without_header().await;
+
+    server.post("/1/task").form(&json!({ "text": "Forged" })).await.assert_status(StatusCode::FORBIDDEN);
+    server.delete("/list/1").await.assert_status(StatusCode::FORBIDDEN);
+    server
+        .post("/1/task")
+        .add_header("X-CSRF-Token", "not-the-token")
+        .form(&json!({ "text": "Forged" }))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+    server
+        .post("/1/task")
+        .form(&json!({ "text": "Forged", "csrf_token": "not-the-token" }))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    assert!(!server.get("/?list_id=1").await.text().contains("Forged"));
+    server.get("/manage").await.assert_text_contains("Groceries");
+}
+
+#[tokio::test]
+async fn test_token_in_header_or_form_field() {
+    let (server, token, _temp_dir) = setup_logged_in_without_header().await;
+
+    server
+        .post("/1/task")
+        .add_header("X-CSRF-Token", token.clone())
+        .form(&json!({ "text": "From HTMX" }))
+        .await
+        .assert_status_ok();
+    server
+        .post("/pantry")
+        .form(&json!({ "name": "From a form", "quantity": "", "unit": "", "expires": "", "csrf_token": token }))
+        .await
+        .assert_status_see_other();
+
+    let form = MultipartForm::new()
+        .add_text("csrf_token", token.clone())
+        .add_text("title", "Multipart Soup")
+        .add_text("ingredients", "1 leek")
+        .add_text("instructions", "Simmer");
+    server.post("/recipes/new").multipart(form).await.assert_status_see_other();
+
+    server.get("/?list_id=1").await.assert_text_contains("From HTMX");
+    server.get("/pantry").await.assert_text_contains("From a form");
+    server.get("/recipes").await.assert_text_contains("Multipart Soup");
+}
+
+#[tokio::test]
+async fn test_cross_origin_requests_are_rejected() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    server
+        .post("/task/1")
+        .add_header("Origin", "http://evil.example")
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+    server
+        .post("/recipes/1/delete")
+        .add_header("Referer", "http://evil.example/page")
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+    server
+        .post("/api/v1/lists")
+        .add_header("Origin", "null")
+        .json(&json!({ "name": "Forged" }))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    let response = server
+        .post("/task/1")
+        .add_header("Host", "localhost:3000")
+        .add_header("Origin", "http://localhost:3000")
+        .await;
+    response.assert_status_ok();
+    response.assert_text_contains("checked");
+}
+
+#[tokio::test]
+async fn test_login_needs_the_token() {
+    let (server, _temp_dir) = setup_server_without_header().await;
+    server.get("/login").await.assert_status_ok();
+
+    server
+        .post("/login")
+        .form(&json!({ "username": TEST_USERNAME, "password": TEST_PASSWORD }))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_json_api_does_not_need_the_token() {
+    let (server, _token, _temp_dir) = setup_logged_in_without_header().await;
+
+    server.post("/api/v1/lists").json(&json!({ "name": "Scripted" })).await.assert_status(StatusCode::CREATED);
+    server.get("/manage").await.assert_text_contains("Scripted");
+}
@@ -0,0 +1,129 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use futures::StreamExt;
+use htmx_rs_todo::events::{Change, Event};
+use htmx_rs_todo::todo::{SharePermission, ShareTarget};
+use htmx_rs_todo::{auth, create_app, database::Database};
+use std::time::Duration;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+mod common;
+use common::*;
+
+/// The groceries list, with a session cookie for it
+async fn setup() -> (Database, axum::Router, String, TempDir) {
+    let (state, temp_dir) = setup_groceries().await;
+    let (user, _) = state.db.get_user_credentials(TEST_USERNAME.to_string()).await.unwrap().unwrap();
+    let token = auth::start_session(&state.db, &user).await.unwrap();
+    (state.db.clone(), create_app(state), format!("{}={}", auth::SESSION_COOKIE, token), temp_dir)
+}
+
+/// Opens an event stream, failing unless it is one
+async fn open_stream(app: &axum::Router, uri: &str, cookie: &str) -> axum::body::BodyDataStream {
+    let request = Request::builder().uri(uri).header("cookie", cookie).body(Body::empty()).unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+    response.into_body().into_data_stream()
+}
+
+/// Reads the stream until a whole event has arrived
+async fn next_event(stream: &mut axum::body::BodyDataStream) -> String {
+    let mut text = String::new();
+    while !text.ends_with("\n\n") {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("No event within 5 seconds")
+            .expect("Stream ended")
+            .unwrap();
+        text.push_str(&String::from_utf8_lossy(&chunk));
+    }
+    text
+}
+
+#[tokio::test]
+async fn test_database_publishes_changes() {
+    let (db, _app, _cookie, _temp_dir) = setup().await;
+    let mut events = db.subscribe();
+
+    let task_id = db.create_task("Bread".to_string(), 1).await.unwrap();
+    db.toggle_task_completed(task_id, None).await.unwrap();
+    db.reorder(1, vec![3, 2, 1], None).await.unwrap();
+    db.delete_task(task_id).await.unwrap();
+    db.create_meal_plan_entry("2030-01-07".to_string(), "Soup".to_string(), None, None, None).await.unwrap();
+    db.for_household(2).create_store("Corner shop".to_string()).await.unwrap();
+
+    let task = Change::Task { list_id: 1, task_id };
+    let expected = [
+        Event { household_id: 1, change: task },
+        Event { household_id: 1, change: task },
+        Event { household_id: 1, change: Change::Tasks { list_id: 1 } },
+        Event { household_id: 1, change: task },
+        Event { household_id: 1, change: Change::MealPlan },
+        Event { household_id: 2, change: Change::Stores },
+    ];
+    for event in expected {
+        assert_eq!(events.try_recv().unwrap(), event);
+    }
+    assert!(events.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_events_swap_task_rows() {
+    let (db, app, cookie, _temp_dir) = setup().await;
+    let mut stream = open_stream(&app, "/events?list_id=1", &cookie).await;
+
+    db.toggle_task_completed(1, None).await.unwrap();
+    let event = next_event(&mut stream).await;
+    assert!(event.contains("event: task"), "{}", event);
+    assert!(event.contains(r#"<tr id="task-1" hx-swap-oob="delete"></tr>"#));
+    assert!(event.contains("afterbegin:#completedtablebody"));
+    assert!(event.contains("Milk"));
+
+    // Other lists and households don't show up
+    let other = db.create_list("Hardware".to_string()).await.unwrap();
+    db.create_task("Nails".to_string(), other).await.unwrap();
+    db.delete_task(2).await.unwrap();
+    let event = next_event(&mut stream).await;
+    assert!(event.contains(r#"<tr id="task-2" hx-swap-oob="delete"></tr>"#));
+    assert!(!event.contains("Eggs"));
+    assert!(!event.contains("Nails"));
+
+    db.reorder(1, vec![1], None).await.unwrap();
+    assert!(next_event(&mut stream).await.contains("event: list"));
+}
+
+#[tokio::test]
+async fn test_shopping_mode_events() {
+    let (db, app, cookie, _temp_dir) = setup().await;
+    let mut stream = open_stream(&app, "/events?list_id=1&view=shop", &cookie).await;
+
+    db.toggle_task_completed(1, None).await.unwrap();
+    let event = next_event(&mut stream).await;
+    assert!(event.contains(r#"<div id="task-1" hx-swap-oob="delete"></div>"#));
+    assert!(event.contains("afterbegin:#in-cart"));
+    assert!(event.contains("hx-post=\"/task/1/completed?view=shop\""));
+}
+
+#[tokio::test]
+async fn test_shared_list_events() {
+    let (db, app, _cookie, _temp_dir) = setup().await;
+    db.create_share_link("guest-token".to_string(), ShareTarget::List(1), SharePermission::View, None)
+        .await
+        .unwrap();
+
+    // No session needed, the token is enough
+    let mut stream = open_stream(&app, "/s/guest-token/events", "").await;
+    db.toggle_task_completed(1, None).await.unwrap();
+    let event = next_event(&mut stream).await;
+    assert!(event.contains("afterbegin:#in-cart"));
+    assert!(event.contains("disabled"));
+}
+
+#[tokio::test]
+async fn test_events_for_unknown_list() {
+    let (server, _temp_dir) = setup_test_server().await;
+    server.get("/events?list_id=42").await.assert_status(StatusCode::NOT_FOUND);
+    server.get("/s/made-up-token/events").await.assert_status(StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,137 @@
+use htmx_rs_todo::database::Database;
+use htmx_rs_todo::export::{self, Export, EXPORT_FILE};
+use htmx_rs_todo::AppState;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+mod common;
+use common::*;
+
+/// The groceries list as the pantry's shopping list, a recipe with a photo, and a meal plan
+/// using it
+async fn setup() -> (Database, PathBuf, TempDir) {
+    let (AppState { db, photos_dir }, temp_dir) = setup_groceries().await;
+    db.set_pantry_shopping_list(Some(1)).await.unwrap();
+
+    let recipe_id = db
+        .create_recipe("Pancakes".to_string(), "Fry".to_string(), "2 eggs\n3 dl milk".to_string(), Some(4))
+        .await
+        .unwrap();
+    image::RgbImage::new(4, 4).save(photos_dir.join("pancakes.png")).unwrap();
+    db.create_recipe_photo(recipe_id, "pancakes.png".to_string(), "IMG_1.png".to_string(), 70, "image/png".to_string(), 0, None)
+        .await
+        .unwrap();
+
+    let dinner = db
+        .create_meal_plan_entry("2026-10-19".to_string(), "Pancakes".to_string(), Some(recipe_id), None, Some(2))
+        .await
+        .unwrap();
+    db.create_meal_plan_entry("2026-10-20".to_string(), "Pancakes".to_string(), None, Some(dinner), None)
+        .await
+        .unwrap();
+    db.set_meal_plan_note("2026-10-21".to_string(), "Eat out".to_string()).await.unwrap();
+
+    (db, photos_dir, temp_dir)
+}
+
+#[tokio::test]
+async fn test_export_is_tagged_and_holds_everything() {
+    let (db, photos_dir, _temp_dir) = setup().await;
+    let out = TempDir::new().unwrap();
+
+    export::export(&db, &photos_dir, out.path()).await.unwrap();
+
+    let json: Export = serde_json::from_slice(&std::fs::read(out.path().join(EXPORT_FILE)).unwrap()).unwrap();
+    assert_eq!(json.format, export::FORMAT);
+    assert_eq!(json.version, export::FORMAT_VERSION);
+    let home = &json.households[0];
+    assert_eq!(home.lists[0].tasks.len(), 2);
+    assert!(home.lists[0].tasks.iter().any(|task| task.completed && task.completed_at.is_some()));
+    assert_eq!(home.recipes[0].photos[0].filename, "pancakes.png");
+    assert_eq!(home.meal_plan.len(), 2);
+    assert_eq!(home.notes[0].note, "Eat out");
+    assert!(out.path().join("photos").join("pancakes.png").is_file());
+}
+
+#[tokio::test]
+async fn test_import_remaps_ids_next_to_existing_data() {
+    let (db, photos_dir, _temp_dir) = setup().await;
+    let out = TempDir::new().unwrap();
+    export::export(&db, &photos_dir, out.path()).await.unwrap();
+
+    // Another server with its own list and recipe in the IDs the export uses
+    let (other, other_photos, _other_dir) = setup().await;
+    let summary = export::import(&other, &other_photos, out.path()).await.unwrap();
+    assert_eq!((summary.households, summary.lists, summary.tasks), (0, 1, 2));
+    assert_eq!((summary.recipes, summary.photos, summary.meals), (1, 1, 2));
+
+    let lists = other.get_lists().await.unwrap();
+    assert_eq!(lists.len(), 2);
+    let imported = lists.iter().map(|list| list.id).max().unwrap();
+    assert_eq!(other.get_tasks(imported).await.unwrap().len(), 2);
+    // The household already had a shopping list
+    assert_ne!(other.get_pantry_shopping_list().await.unwrap(), Some(imported));
+
+    let recipes = other.get_recipes().await.unwrap();
+    assert_eq!(recipes.len(), 2);
+    let recipe_id = recipes.iter().map(|recipe| recipe.id).max().unwrap();
+    let photos = other.get_recipe_photos(recipe_id).await.unwrap();
+    assert_ne!(photos[0].filename, "pancakes.png");
+    assert!(photos[0].thumbnail_blob.is_some());
+    assert!(other_photos.join(&photos[0].filename).is_file());
+
+    let meals = other.get_meal_plan_between("2026-10-19".to_string(), "2026-10-20".to_string()).await.unwrap();
+    assert_eq!(meals.len(), 4);
+    let dinner = meals.iter().find(|meal| meal.recipe_id == Some(recipe_id)).unwrap();
+    assert!(meals.iter().any(|meal| meal.leftovers_of == Some(dinner.id)));
+}
+
+#[tokio::test]
+async fn test_import_creates_missing_households() {
+    let (db, photos_dir, _temp_dir) = setup().await;
+    let out = TempDir::new().unwrap();
+    export::export(&db, &photos_dir, out.path()).await.unwrap();
+
+    let mut json: Export = serde_json::from_slice(&std::fs::read(out.path().join(EXPORT_FILE)).unwrap()).unwrap();
+    json.households[0].name = "Cabin".to_string();
+    std::fs::write(out.path().join(EXPORT_FILE), serde_json::to_vec(&json).unwrap()).unwrap();
+
+    let fresh = TempDir::new().unwrap();
+    let fresh_db = Database::new(fresh.path().join("todos.db")).await.unwrap();
+    let summary = export::import(&fresh_db, fresh.path(), out.path()).await.unwrap();
+    assert_eq!(summary.households, 1);
+    // Nothing went into the default household
+    assert!(fresh_db.get_lists().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_import_refuses_newer_versions() {
+    let (db, photos_dir, _temp_dir) = setup().await;
+    let out = TempDir::new().unwrap();
+    export::export(&db, &photos_dir, out.path()).await.unwrap();
+
+    let mut json: Export = serde_json::from_slice(&std::fs::read(out.path().join(EXPORT_FILE)).unwrap()).unwrap();
+    json.version = export::FORMAT_VERSION + 1;
+    std::fs::write(out.path().join(EXPORT_FILE), serde_json::to_vec(&json).unwrap()).unwrap();
+
+    assert!(export::import(&db, &photos_dir, out.path()).await.is_err());
+    assert_eq!(db.get_lists().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_import_refuses_malformed_dates() {
+    let (db, photos_dir, _temp_dir) = setup().await;
+    let out = TempDir::new().unwrap();
+    export::export(&db, &photos_dir, out.path()).await.unwrap();
+
+    for (meal_date, note_date) in [("2026-13-01", "2026-10-21"), ("2026-10-19", "next tuesday")] {
+        let mut json: Export = serde_json::from_slice(&std::fs::read(out.path().join(EXPORT_FILE)).unwrap()).unwrap();
+        json.households[0].meal_plan[0].date = meal_date.to_string();
+        json.households[0].notes[0].date = note_date.to_string();
+        std::fs::write(out.path().join(EXPORT_FILE), serde_json::to_vec(&json).unwrap()).unwrap();
+
+        assert!(export::import(&db, &photos_dir, out.path()).await.is_err());
+        assert_eq!(db.get_lists().await.unwrap().len(), 1);
+        assert_eq!(std::fs::read_dir(&photos_dir).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,284 @@
+use axum::http::StatusCode;
+use axum_test::{TestServer, TestServerConfig};
+use htmx_rs_todo::{auth, create_app, database::Database, AppState};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+mod common;
+use common::*;
+
+const OTHER_USERNAME: &str = "bob";
+
+struct Households {
+    alice: TestServer, // The test user, in the first household
+    bob: TestServer,   // In a household of his own
+    db: Database,
+    photo_filename: String,
+    photo_id: usize,
+    _temp_dir: TempDir,
+}
+
+async fn login(app: axum::Router, username: &str) -> TestServer {
+    let mut server = TestServerConfig::builder()
+        .save_cookies()
+        .build_server(app)
+        .expect("Failed to create test server");
+    send_csrf_token(&mut server).await;
+    server
+        .post("/login")
+        .form(&json!({ "username": username, "password": TEST_PASSWORD }))
+        .await
+        .assert_status_see_other();
+    server
+}
+
+/// Two users in separate households, the first one with a list, a recipe with a photo,
+/// a planned meal, a pantry item and a store
+async fn setup_households() -> Households {
+    let (state, temp_dir) = setup_state().await;
+    let AppState { db, photos_dir } = state.clone();
+    let other_household = db.create_household("Bob's place".to_string()).await.unwrap();
+    auth::create_user(&db.for_household(other_household), OTHER_USERNAME, TEST_PASSWORD, false).await.unwrap();
+
+    let app = create_app(state);
+    let alice = login(app.clone(), TEST_USERNAME).await;
+    let bob = login(app, OTHER_USERNAME).await;
+
+    alice.post("/create_list").form(&json!({ "name": "Secret List" })).await;
+    alice.post("/1/task").form(&json!({ "text": "Secret milk" })).await;
+    alice
+        .post("/api/v1/recipes")
+        .json(&json!({ "title": "Secret Stew", "ingredients": "1 onion", "instructions": "Stir" }))
+        .await
+        .assert_status(StatusCode::CREATED);
+    alice.post("/meal-plan/2030-01-07/add").form(&json!({ "meal_text": "Secret Dinner" })).await;
+    alice.post("/pantry").form(&json!({ "name": "Secret flour", "quantity": "", "unit": "", "expires": "" })).await;
+    alice.post("/stores").form(&json!({ "name": "Secret Market" })).await;
+
+    let photo_filename = "secret.jpg".to_string();
+    std::fs::write(photos_dir.join(&photo_filename), b"not really a jpeg").unwrap();
+    let photo_id = db
+        .create_recipe_photo(1, photo_filename.clone(), "secret.jpg".to_string(), 17, "image/jpeg".to_string(), 0, Some(b"thumb".to_vec()))
+        .await
+        .unwrap();
+
+    Households { alice, bob, db, photo_filename, photo_id, _temp_dir: temp_dir }
+}
+
+#[tokio::test]
+async fn test_pages_only_show_own_household() {
+    let h = setup_households().await;
+
+    let pages = [
+        ("/", "Secret"),
+        ("/?list_id=1", "Secret milk"),
+        ("/manage", "Secret List"),
+        ("/recipes", "Secret Stew"),
+        ("/meal-plan?week=2030-01-07", "Secret Dinner"),
+        ("/pantry", "Secret flour"),
+        ("/stores", "Secret Market"),
+    ];
+    for (path, secret) in pages {
+        h.alice.get(path).await.assert_text_contains(secret);
+        let text = h.bob.get(path).await.text();
+        assert!(!text.contains(secret), "{} leaks {:?} to another household", path, secret);
+    }
+
+    h.bob.get("/recipes/1").await.assert_status(StatusCode::NOT_FOUND);
+    h.bob.get("/recipes/1/edit").await.assert_status_not_ok();
+    h.bob.get("/list/1/shop").await.assert_status_not_ok();
+}
+
+#[tokio::test]
+async fn test_photos_are_not_served_to_other_households() {
+    let h = setup_households().await;
+
+    h.alice.get(&format!("/photos/{}", h.photo_filename)).await.assert_status_ok();
+    h.alice.get(&format!("/thumbnails/{}", h.photo_id)).await.assert_status_ok();
+
+    h.bob.get(&format!("/photos/{}", h.photo_filename)).await.assert_status(StatusCode::NOT_FOUND);
+    h.bob.get(&format!("/thumbnails/{}", h.photo_id)).await.assert_status(StatusCode::NOT_FOUND);
+    h.bob.get(&format!("/api/v1/photos/{}", h.photo_id)).await.assert_status(StatusCode::NOT_FOUND);
+    h.bob.get("/api/v1/recipes/1/photos").await.assert_status(StatusCode::NOT_FOUND);
+
+    // Files that aren't a recipe photo at all aren't served either
+    h.alice.get("/photos/..%2Ftest.db").await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_other_households_cannot_change_data() {
+    let h = setup_households().await;
+
+    h.bob.post("/task/1").await;
+    h.bob.delete("/task/1").await;
+    h.bob.delete("/list/1").await;
+    h.bob.post("/1/task").form(&json!({ "text": "Injected" })).await;
+    h.bob.post("/recipes/1/delete").await;
+    h.bob.post("/recipes/1/photos/1/delete").await;
+    h.bob.post("/pantry/1/delete").await;
+    h.bob.post("/stores/1/delete").await;
+    h.bob.patch("/api/v1/tasks/1").json(&json!({ "completed": true })).await.assert_status(StatusCode::NOT_FOUND);
+    h.bob.delete("/api/v1/recipes/1").await.assert_status(StatusCode::NOT_FOUND);
+
+    let tasks = h.db.get_tasks(1).await.unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].text, "Secret milk");
+    assert!(!tasks[0].completed);
+    assert!(h.db.get_recipe(1).await.is_ok());
+    assert_eq!(h.db.get_recipe_photos(1).await.unwrap().len(), 1);
+    assert_eq!(h.db.get_pantry_items().await.unwrap().len(), 1);
+    assert_eq!(h.db.get_stores().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_idempotency_keys_are_per_user() {
+    let h = setup_households().await;
+
+    let response = h.alice.post("/task/1").add_header("Idempotency-Key", "shared-key").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Secret milk");
+
+    // Bob's request runs on its own instead of getting alice's response back
+    let response = h.bob.post("/task/1").add_header("Idempotency-Key", "shared-key").await;
+    response.assert_status_not_found();
+    assert!(!response.text().contains("Secret milk"));
+    assert!(response.maybe_header("Idempotent-Replayed").is_none());
+}
+
+#[tokio::test]
+async fn test_settings_are_per_household() {
+    let h = setup_households().await;
+
+    h.bob
+        .post("/settings")
+        .form(&json!({
+            "language": "nb",
+            "week_start": "sunday",
+            "date_format": "%d.%m.%Y",
+            "timezone": "Pacific/Kiritimati"
+        }))
+        .await
+        .assert_status_see_other();
+
+    let chosen = "value=\"Pacific/Kiritimati\" required";
+    h.bob.get("/settings").await.assert_text_contains(chosen);
+    assert!(!h.alice.get("/settings").await.text().contains(chosen));
+    assert!(h.alice.get("/meal-plan?week=2030-01-07").await.text().contains("Monday"));
+    assert!(h.bob.get("/meal-plan?week=2030-01-07").await.text().contains("Mandag"));
+    assert_eq!(h.db.get_settings().await.unwrap().get("timezone"), None);
+}
+
+#[tokio::test]
+async fn test_api_is_scoped_to_household() {
+    let h = setup_households().await;
+
+    let lists = h.bob.get("/api/v1/lists").await.json::<Value>();
+    assert_eq!(lists["total"], 0);
+    let recipes = h.bob.get("/api/v1/recipes").await.json::<Value>();
+    assert_eq!(recipes["total"], 0);
+    let meals = h.bob.get("/api/v1/meal-plan?from=2030-01-01&to=2030-01-31").await.json::<Value>();
+    assert_eq!(meals["total"], 0);
+    h.bob.get("/api/v1/lists/1").await.assert_status(StatusCode::NOT_FOUND);
+    h.bob.get("/api/v1/lists/1/tasks").await.assert_status(StatusCode::NOT_FOUND);
+    h.bob.get("/api/v1/tasks/1").await.assert_status(StatusCode::NOT_FOUND);
+
+    // Lists created by bob land in his household
+    h.bob.post("/api/v1/lists").json(&json!({ "name": "Bob's List" })).await.assert_status(StatusCode::CREATED);
+    let lists = h.alice.get("/api/v1/lists").await.json::<Value>();
+    assert_eq!(lists["total"], 1);
+    assert_eq!(lists["items"][0]["name"], "Secret List");
+}
+
+#[tokio::test]
+async fn test_switch_household_after_accepting_invite() {
+    let h = setup_households().await;
+
+    // Not a member yet
+    h.bob
+        .post("/households/switch")
+        .form(&json!({ "household_id": 1 }))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    h.alice
+        .post("/households/members")
+        .form(&json!({ "username": OTHER_USERNAME }))
+        .await
+        .assert_status_see_other();
+    let response = h.alice.get("/households").await;
+    response.assert_text_contains("<li>admin</li>");
+    response.assert_text_contains("<li>bob (invited)</li>");
+
+    // Being invited doesn't let bob in until he accepts
+    h.bob.post("/households/switch").form(&json!({ "household_id": 1 })).await.assert_status(StatusCode::FORBIDDEN);
+    h.bob.get("/households").await.assert_text_contains("Invitations");
+    h.bob.post("/households/invites/accept").form(&json!({ "household_id": 1 })).await.assert_status_see_other();
+    h.alice.get("/households").await.assert_text_contains("<li>bob</li>");
+
+    h.bob.post("/households/switch").form(&json!({ "household_id": 1 })).await.assert_status_see_other();
+    h.bob.get("/").await.assert_text_contains("Secret milk");
+
+    h.bob.post("/households/switch").form(&json!({ "household_id": 2 })).await.assert_status_see_other();
+    assert!(!h.bob.get("/").await.text().contains("Secret milk"));
+}
+
+#[tokio::test]
+async fn test_joining_takes_an_invite() {
+    let h = setup_households().await;
+
+    // Alice can't pull bob in, and bob can't join uninvited
+    h.alice.post("/households/invites/accept").form(&json!({ "household_id": 2 })).await.assert_status(StatusCode::NOT_FOUND);
+    h.bob.post("/households/invites/accept").form(&json!({ "household_id": 1 })).await.assert_status(StatusCode::NOT_FOUND);
+
+    // A declined invite is gone
+    h.alice.post("/households/members").form(&json!({ "username": OTHER_USERNAME })).await.assert_status_see_other();
+    h.bob.post("/households/invites/decline").form(&json!({ "household_id": 1 })).await.assert_status_see_other();
+    h.bob.post("/households/invites/accept").form(&json!({ "household_id": 1 })).await.assert_status(StatusCode::NOT_FOUND);
+    h.bob.post("/households/switch").form(&json!({ "household_id": 1 })).await.assert_status(StatusCode::FORBIDDEN);
+    assert!(!h.alice.get("/households").await.text().contains("bob"));
+    assert_eq!(h.db.get_household_members().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_create_household() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    server.post("/households").form(&json!({ "name": "Cabin" })).await.assert_status_see_other();
+    let response = server.get("/households").await;
+    response.assert_text_contains("Cabin");
+    response.assert_text_contains("Home");
+
+    // The new household starts out empty
+    assert!(!server.get("/").await.text().contains("Test Task 1"));
+    let lists = server.get("/api/v1/lists").await.json::<Value>();
+    assert_eq!(lists["total"], 0);
+
+    server.post("/households").form(&json!({ "name": "  " })).await.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_add_unknown_member() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server.post("/households/members").form(&json!({ "username": "nobody" })).await;
+    response.assert_status(StatusCode::NOT_FOUND);
+    response.assert_text_contains("There is no user called nobody");
+}
+
+#[tokio::test]
+async fn test_user_without_household_is_sent_to_create_one() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+    db.create_user(OTHER_USERNAME.to_string(), auth::hash_password(TEST_PASSWORD).unwrap(), false).await.unwrap();
+    let app = create_app(AppState { db, photos_dir: temp_dir.path().join("photos") });
+    let server = login(app, OTHER_USERNAME).await;
+
+    let response = server.get("/recipes").await;
+    response.assert_status_see_other();
+    assert_eq!(response.header("location"), "/households");
+    server.get("/api/v1/lists").await.assert_status(StatusCode::FORBIDDEN);
+    server.get("/households").await.assert_text_contains("You are not in a household yet");
+
+    server.post("/households").form(&json!({ "name": "Flat" })).await.assert_status_see_other();
+    server.get("/recipes").await.assert_status_ok();
+}
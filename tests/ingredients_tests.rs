@@ -0,0 +1,53 @@
+use htmx_rs_todo::ingredients::{self, Ingredient};
+
+fn ingredient(quantity: Option<f64>, unit: Option<&str>, name: &str) -> Ingredient {
+    Ingredient { quantity, unit: unit.map(str::to_string), name: name.to_string() }
+}
+
+#[test]
+fn test_parse_quantity_unit_and_name() {
+    assert_eq!(Ingredient::parse("1 cup flour"), ingredient(Some(1.0), Some("cup"), "flour"));
+    assert_eq!(Ingredient::parse("2 eggs"), ingredient(Some(2.0), None, "eggs"));
+    assert_eq!(Ingredient::parse("200g butter"), ingredient(Some(200.0), Some("g"), "butter"));
+    assert_eq!(Ingredient::parse("2 ss olivenolje"), ingredient(Some(2.0), Some("ss"), "olivenolje"));
+    assert_eq!(Ingredient::parse("0,5 l melk"), ingredient(Some(0.5), Some("l"), "melk"));
+}
+
+#[test]
+fn test_parse_fractions() {
+    assert_eq!(Ingredient::parse("1 1/2 cups milk"), ingredient(Some(1.5), Some("cups"), "milk"));
+    assert_eq!(Ingredient::parse("½ tsp salt"), ingredient(Some(0.5), Some("tsp"), "salt"));
+    assert_eq!(Ingredient::parse("1½ dl cream"), ingredient(Some(1.5), Some("dl"), "cream"));
+    assert_eq!(Ingredient::parse("3/4 cup sugar"), ingredient(Some(0.75), Some("cup"), "sugar"));
+}
+
+#[test]
+fn test_parse_without_quantity() {
+    assert_eq!(Ingredient::parse("Salt and pepper"), ingredient(None, None, "Salt and pepper"));
+    assert_eq!(Ingredient::parse("  Fresh basil  "), ingredient(None, None, "Fresh basil"));
+    // A word that merely starts with a digit is not a quantity
+    assert_eq!(Ingredient::parse("7up"), ingredient(None, None, "7up"));
+}
+
+#[test]
+fn test_scaled() {
+    assert_eq!(Ingredient::parse("1 cup flour").scaled(3.0).to_string(), "3 cup flour");
+    assert_eq!(Ingredient::parse("3 eggs").scaled(0.5).to_string(), "1.5 eggs");
+    assert_eq!(Ingredient::parse("Salt").scaled(4.0).to_string(), "Salt");
+}
+
+#[test]
+fn test_aggregate_sums_same_unit_and_name() {
+    let lines = ingredients::parse_lines("1 cup flour\n2 eggs\nSalt\n\n2 cups Flour\n1 egg\nSalt\n100 g flour");
+    let totals: Vec<String> = ingredients::aggregate(lines).iter().map(|i| i.to_string()).collect();
+
+    assert_eq!(totals, vec!["3 cup flour", "3 eggs", "Salt", "100 g flour"]);
+}
+
+#[test]
+fn test_format_quantity() {
+    assert_eq!(ingredients::format_quantity(2.0), "2");
+    assert_eq!(ingredients::format_quantity(1.5), "1.5");
+    assert_eq!(ingredients::format_quantity(1.0 / 3.0), "0.33");
+    assert_eq!(ingredients::format_quantity(0.999), "1");
+}
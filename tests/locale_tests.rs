@@ -0,0 +1,150 @@
+use chrono::{Datelike, Duration, NaiveDate, Utc, Weekday};
+use htmx_rs_todo::locale::{Language, Locale};
+use std::collections::HashMap;
+
+mod common;
+use common::*;
+
+fn date(s: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+}
+
+#[test]
+fn test_default_locale_matches_previous_behaviour() {
+    let locale = Locale::from_settings(&HashMap::new());
+
+    assert_eq!(locale.language, Language::English);
+    assert_eq!(locale.week_start, Weekday::Mon);
+    assert_eq!(locale.format_date(date("2024-12-31")), "31.12.24");
+    assert_eq!(locale.day_name(Weekday::Mon), "Monday");
+}
+
+#[test]
+fn test_unknown_settings_fall_back_to_defaults() {
+    let settings = HashMap::from([
+        ("language".to_string(), "xx".to_string()),
+        ("week_start".to_string(), "friday".to_string()),
+        ("date_format".to_string(), "%H:%M".to_string()),
+    ]);
+
+    assert_eq!(Locale::from_settings(&settings), Locale::default());
+}
+
+#[test]
+fn test_norwegian_day_names() {
+    let locale = Locale { language: Language::Norwegian, ..Default::default() };
+
+    assert_eq!(locale.day_name(Weekday::Mon), "Mandag");
+    assert_eq!(locale.day_name(Weekday::Sat), "Lørdag");
+    assert_eq!(locale.day_name(Weekday::Sun), "Søndag");
+}
+
+#[test]
+fn test_week_start_for_monday_and_sunday() {
+    let monday_start = Locale::default();
+    let sunday_start = Locale { week_start: Weekday::Sun, ..Default::default() };
+
+    // Wednesday 5 June 2024
+    assert_eq!(monday_start.week_start_for(date("2024-06-05")), date("2024-06-03"));
+    assert_eq!(sunday_start.week_start_for(date("2024-06-05")), date("2024-06-02"));
+
+    // The start day maps to itself
+    assert_eq!(monday_start.week_start_for(date("2024-06-03")), date("2024-06-03"));
+    assert_eq!(sunday_start.week_start_for(date("2024-06-02")), date("2024-06-02"));
+
+    // Sunday belongs to the previous Monday-start week
+    assert_eq!(monday_start.week_start_for(date("2024-06-09")), date("2024-06-03"));
+}
+
+#[test]
+fn test_week_number_for_sunday_start() {
+    let sunday_start = Locale { week_start: Weekday::Sun, ..Default::default() };
+
+    // Sunday 2 June 2024 starts the week that is ISO week 23
+    assert_eq!(sunday_start.week_number(date("2024-06-02")), (23, 2024));
+    assert_eq!(Locale::default().week_number(date("2024-06-03")), (23, 2024));
+}
+
+#[tokio::test]
+async fn test_settings_page_loads() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server.get("/settings").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Language for day names");
+    response.assert_text_contains("First day of the week");
+    response.assert_text_contains("Date format");
+}
+
+#[tokio::test]
+async fn test_invalid_settings_rejected() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server
+        .post("/settings")
+        .form(&serde_json::json!({
+            "language": "klingon",
+            "week_start": "monday",
+            "date_format": "%d.%m.%y",
+            "timezone": "UTC"
+        }))
+        .await;
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_norwegian_meal_plan() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server
+        .post("/settings")
+        .form(&serde_json::json!({
+            "language": "nb",
+            "week_start": "monday",
+            "date_format": "%Y-%m-%d",
+            "timezone": "UTC"
+        }))
+        .await;
+    response.assert_status_see_other();
+
+    let response = server.get("/meal-plan?week=2024-06-03").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Mandag");
+    response.assert_text_contains("Søndag");
+    response.assert_text_contains("2024-06-09");
+
+    let response = server.get("/meal-plan/2024-06-05/add").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Add Meal for 2024-06-05");
+}
+
+#[tokio::test]
+async fn test_sunday_week_start() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server
+        .post("/settings")
+        .form(&serde_json::json!({
+            "language": "en",
+            "week_start": "sunday",
+            "date_format": "%d.%m.%Y",
+            "timezone": "UTC"
+        }))
+        .await;
+    response.assert_status_see_other();
+
+    let response = server.get("/meal-plan").await;
+    response.assert_status_ok();
+    let body = response.text();
+
+    // Sunday is listed before Monday
+    let sunday = body.find("Sunday").expect("Sunday shown");
+    let monday = body.find("Monday").expect("Monday shown");
+    assert!(sunday < monday);
+
+    // The current week starts on the most recent Sunday
+    let today = Utc::now().date_naive();
+    let week_start = today - Duration::days(today.weekday().num_days_from_sunday() as i64);
+    response.assert_text_contains(week_start.format("%d.%m.%Y").to_string());
+    response.assert_text_contains(format!("/meal-plan/{}/add-ingredients", week_start.format("%Y-%m-%d")));
+}
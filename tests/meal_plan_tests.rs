@@ -263,6 +263,185 @@ async fn test_meal_plan_integration_with_recipes() {
     response.assert_text_contains("Test Recipe");
 }
 
+#[tokio::test]
+async fn test_day_note_saved_and_cleared() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server
+        .post("/meal-plan/2024-06-05/note")
+        .form(&serde_json::json!({ "note": "Eat out" }))
+        .await;
+    response.assert_status_ok();
+
+    let response = server.get("/meal-plan?week=2024-06-03").await;
+    response.assert_status_ok();
+    response.assert_text_contains(r#"value="Eat out""#);
+
+    // An empty note removes it
+    let response = server
+        .post("/meal-plan/2024-06-05/note")
+        .form(&serde_json::json!({ "note": "" }))
+        .await;
+    response.assert_status_ok();
+
+    let response = server.get("/meal-plan?week=2024-06-03").await;
+    let body = response.text();
+    assert!(!body.contains("Eat out"));
+}
+
+#[tokio::test]
+async fn test_day_note_invalid_date() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server
+        .post("/meal-plan/not-a-date/note")
+        .form(&serde_json::json!({ "note": "Eat out" }))
+        .await;
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_add_leftovers_meal() {
+    let (server, _temp_dir) = setup_test_server_with_recipe().await;
+
+    let response = server
+        .post("/meal-plan/2024-06-03/add")
+        .form(&serde_json::json!({ "meal_text": "Test Recipe", "recipe_id": "1" }))
+        .await;
+    response.assert_status_see_other();
+
+    // The next day's form offers Monday's meal as leftovers
+    let response = server.get("/meal-plan/2024-06-04/add").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Leftovers from:");
+    response.assert_text_contains("Monday 03.06.24: Test Recipe");
+
+    let response = server
+        .post("/meal-plan/2024-06-04/add")
+        .form(&serde_json::json!({ "meal_text": "", "recipe_id": "", "leftovers_of": "1" }))
+        .await;
+    response.assert_status_see_other();
+
+    let response = server.get("/meal-plan?week=2024-06-03").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Leftovers: Test Recipe");
+    response.assert_text_contains("♻ leftovers");
+}
+
+#[tokio::test]
+async fn test_leftovers_must_come_from_earlier_meal() {
+    let (server, _temp_dir) = setup_test_server_with_recipe().await;
+
+    let response = server
+        .post("/meal-plan/2024-06-05/add")
+        .form(&serde_json::json!({ "meal_text": "Test Recipe", "recipe_id": "1" }))
+        .await;
+    response.assert_status_see_other();
+
+    let response = server
+        .post("/meal-plan/2024-06-04/add")
+        .form(&serde_json::json!({ "meal_text": "", "leftovers_of": "1" }))
+        .await;
+    response.assert_status_bad_request();
+
+    let response = server
+        .post("/meal-plan/2024-06-06/add")
+        .form(&serde_json::json!({ "meal_text": "", "leftovers_of": "99" }))
+        .await;
+    response.assert_status_not_found();
+
+    // Sorts after the meal as a string, but isn't a date
+    let response = server
+        .post("/meal-plan/2024-06-5x/add")
+        .form(&serde_json::json!({ "meal_text": "", "leftovers_of": "1" }))
+        .await;
+    response.assert_status_bad_request();
+    let response = server.get("/meal-plan?week=2024-06-03").await;
+    assert!(!response.text().contains("Leftovers: Test Recipe"));
+}
+
+#[tokio::test]
+async fn test_leftovers_not_added_to_weekly_ingredients() {
+    let (server, _temp_dir) = setup_test_server_with_recipe().await;
+
+    // Cooked on Sunday, eaten again on Monday of the following week
+    let response = server
+        .post("/meal-plan/2024-06-02/add")
+        .form(&serde_json::json!({ "meal_text": "Test Recipe", "recipe_id": "1" }))
+        .await;
+    response.assert_status_see_other();
+    let response = server
+        .post("/meal-plan/2024-06-03/add")
+        .form(&serde_json::json!({ "meal_text": "", "recipe_id": "1", "leftovers_of": "1" }))
+        .await;
+    response.assert_status_see_other();
+
+    let response = server.get("/meal-plan/2024-06-03/add-ingredients").await;
+    response.assert_status_ok();
+    response.assert_text_contains("No Recipe Ingredients Found");
+
+    let response = server.get("/meal-plan/2024-05-27/add-ingredients").await;
+    response.assert_status_ok();
+    response.assert_text_contains("1 cup flour");
+}
+
+#[tokio::test]
+async fn test_weekly_ingredients_scaled_by_servings() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let form = MultipartForm::new()
+        .add_text("title", "Pancakes")
+        .add_text("ingredients", "1 cup flour\n2 eggs\nSalt")
+        .add_text("instructions", "Mix and fry.")
+        .add_text("servings", "2");
+    let response = server.post("/recipes/new").multipart(form).await;
+    response.assert_status_see_other();
+
+    // Planned for 6 on Monday and for the recipe's own 2 servings on Tuesday
+    let response = server
+        .post("/recipes/1/add-to-meal-plan")
+        .form(&serde_json::json!({ "date": "2024-06-03", "meal_text": "Pancakes", "servings": "6" }))
+        .await;
+    response.assert_status_see_other();
+    let response = server
+        .post("/recipes/1/add-to-meal-plan")
+        .form(&serde_json::json!({ "date": "2024-06-04", "meal_text": "Pancakes", "servings": "" }))
+        .await;
+    response.assert_status_see_other();
+
+    let response = server.get("/meal-plan?week=2024-06-03").await;
+    response.assert_status_ok();
+    response.assert_text_contains("(6 servings)");
+
+    let response = server.get("/meal-plan/2024-06-03/add-ingredients").await;
+    response.assert_status_ok();
+    response.assert_text_contains("4 cup flour");
+    response.assert_text_contains("8 eggs");
+    let body = response.text();
+    assert_eq!(body.matches("value=\"Salt\"").count(), 1);
+}
+
+#[tokio::test]
+async fn test_recipe_servings_saved() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let form = MultipartForm::new()
+        .add_text("title", "Soup")
+        .add_text("ingredients", "1 l stock")
+        .add_text("instructions", "Simmer.")
+        .add_text("servings", "4");
+    let response = server.post("/recipes/new").multipart(form).await;
+    response.assert_status_see_other();
+
+    let response = server.get("/recipes/1").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Serves 4");
+
+    let response = server.get("/recipes/1/add-to-meal-plan").await;
+    response.assert_status_ok();
+    response.assert_text_contains("The recipe serves 4.");
+}
+
 /// Helper function to get the start of the week (Monday) for a given date
 fn get_week_start(date: NaiveDate) -> NaiveDate {
     let days_since_monday = date.weekday().num_days_from_monday();
@@ -0,0 +1,146 @@
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+mod common;
+use common::*;
+
+#[tokio::test]
+async fn test_app_files_are_served_without_login() {
+    let (server, _temp_dir) = setup_logged_out_server().await;
+
+    let response = server.get("/vendor/manifest.webmanifest").await;
+    response.assert_status_ok();
+    assert_eq!(response.header("content-type"), "application/manifest+json");
+
+    let response = server.get("/vendor/sw.js").await;
+    response.assert_status_ok();
+    assert_eq!(response.header("Service-Worker-Allowed"), "/");
+
+    server.get("/vendor/offline.js").await.assert_status_ok();
+    server.get("/vendor/icon.svg").await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_list_pages_install_the_worker() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    for page in ["/?list_id=1", "/list/1/shop"] {
+        let response = server.get(page).await;
+        response.assert_text_contains(r#"<link rel="manifest" href="/vendor/manifest.webmanifest">"#);
+        response.assert_text_contains("vendor/offline.js");
+    }
+}
+
+#[tokio::test]
+async fn test_replayed_task_is_created_once() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    let first = server
+        .post("/1/task")
+        .add_header("Idempotency-Key", "4c4b0b9e-5a1a-4c1e-9d7e-0d6c1f0e2a11")
+        .form(&json!({ "text": "Bread" }))
+        .await;
+    first.assert_status_ok();
+    let again = server
+        .post("/1/task")
+        .add_header("Idempotency-Key", "4c4b0b9e-5a1a-4c1e-9d7e-0d6c1f0e2a11")
+        .form(&json!({ "text": "Bread" }))
+        .await;
+    again.assert_status_ok();
+    assert_eq!(again.header("Idempotent-Replayed"), "true");
+    assert_eq!(again.text(), first.text());
+
+    let page = server.get("/?list_id=1").await.text();
+    assert_eq!(page.matches("Bread").count(), 1);
+}
+
+#[tokio::test]
+async fn test_replayed_toggle_is_not_undone() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    for _ in 0..2 {
+        let response = server.post("/task/1").add_header("Idempotency-Key", "toggle-1").await;
+        response.assert_status_ok();
+        response.assert_text_contains("checked");
+    }
+    let task: Value = server.get("/api/v1/tasks/1").await.json();
+    assert_eq!(task["completed"], true);
+
+    // A new key toggles again
+    let response = server.post("/task/1").add_header("Idempotency-Key", "toggle-2").await;
+    response.assert_text_contains("beforeend:#tasktablebody");
+}
+
+#[tokio::test]
+async fn test_key_sent_with_another_body_is_refused() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    server
+        .post("/1/task")
+        .add_header("Idempotency-Key", "same-key")
+        .form(&json!({ "text": "Bread" }))
+        .await
+        .assert_status_ok();
+    server
+        .post("/1/task")
+        .add_header("Idempotency-Key", "same-key")
+        .form(&json!({ "text": "Butter" }))
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+    let page = server.get("/?list_id=1").await.text();
+    assert!(!page.contains("Butter"));
+}
+
+#[tokio::test]
+async fn test_failed_requests_can_be_retried() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    server
+        .post("/42/task")
+        .add_header("Idempotency-Key", "retry")
+        .form(&json!({ "text": "Bread" }))
+        .await
+        .assert_status_not_found();
+    server
+        .post("/1/task")
+        .add_header("Idempotency-Key", "retry")
+        .form(&json!({ "text": "Bread" }))
+        .await
+        .assert_status_ok();
+
+    // Now it's taken
+    server
+        .post("/task/1")
+        .add_header("Idempotency-Key", "retry")
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_shared_list_replays() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+    server
+        .post("/shares")
+        .form(&json!({ "target": "list:1", "permission": "edit", "expires_days": "" }))
+        .await
+        .assert_status_see_other();
+    let page = server.get("/shares").await.text();
+    let link = page.split("href=\"").find(|href| href.starts_with("/s/")).unwrap().split('"').next().unwrap().to_string();
+
+    for _ in 0..2 {
+        server
+            .post(&format!("{}/task", link))
+            .add_header("Idempotency-Key", "shared-add")
+            .form(&json!({ "text": "Apples" }))
+            .await
+            .assert_status_ok();
+        server
+            .post(&format!("{}/task/2", link))
+            .add_header("Idempotency-Key", "shared-toggle")
+            .await
+            .assert_text_contains("afterbegin:#in-cart");
+    }
+    let page = server.get(&link).await.text();
+    assert_eq!(page.matches(r#"<span class="shop-text">Apples</span>"#).count(), 1);
+}
@@ -0,0 +1,244 @@
+use axum_test::multipart::MultipartForm;
+use chrono::{Duration, NaiveDate, Utc};
+use htmx_rs_todo::database::Database;
+use htmx_rs_todo::pantry;
+use htmx_rs_todo::todo::{PantryItem, Recipe};
+use std::collections::HashSet;
+
+mod common;
+use common::*;
+
+fn recipe(id: usize, title: &str, ingredients: &str) -> Recipe {
+    Recipe {
+        id,
+        title: title.to_string(),
+        instructions: String::new(),
+        ingredients: ingredients.to_string(),
+        favourite: false,
+        rating: 0,
+        tags: String::new(),
+        servings: None,
+        version: 1,
+    }
+}
+
+fn item(name: &str, quantity: Option<f64>) -> PantryItem {
+    PantryItem { id: 0, name: name.to_string(), quantity, unit: None, expires: None }
+}
+
+fn date(s: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+}
+
+fn expiring(name: &str, expires: &str) -> PantryItem {
+    PantryItem { expires: Some(expires.to_string()), ..item(name, None) }
+}
+
+#[test]
+fn test_name_matches_whole_words() {
+    assert!(pantry::name_matches("plain flour", "flour"));
+    assert!(pantry::name_matches("Eggs", "egg"));
+    assert!(pantry::name_matches("salt and pepper", "Salt"));
+    assert!(!pantry::name_matches("flourless cake", "flour"));
+    assert!(!pantry::name_matches("flour", ""));
+}
+
+#[test]
+fn test_rank_cookable() {
+    let recipes = vec![
+        recipe(1, "Pancakes", "2 dl flour\n2 eggs\n3 dl milk"),
+        recipe(2, "Omelette", "3 eggs\n1 dl milk"),
+        recipe(3, "Salad", "1 lettuce\n1 cucumber"),
+    ];
+    let pantry = vec![item("Eggs", Some(6.0)), item("milk", None), item("flour", Some(0.0))];
+
+    let ranked = pantry::rank_cookable(recipes, &pantry);
+
+    // Flour is used up, so both recipes have two ingredients at home and the omelette misses nothing
+    let titles: Vec<&str> = ranked.iter().map(|c| c.recipe.title.as_str()).collect();
+    assert_eq!(titles, vec!["Omelette", "Pancakes"]);
+    assert_eq!(ranked[0].percent_in_stock(), 100);
+    assert_eq!(ranked[1].missing, vec!["2 dl flour"]);
+}
+
+#[test]
+fn test_expiring_items() {
+    let recipes = vec![recipe(1, "Omelette", "3 eggs\n1 dl milk"), recipe(2, "Porridge", "2 dl oats\n4 dl milk")];
+    let items = vec![
+        expiring("Milk", "2024-06-05"),
+        expiring("Yoghurt", "2024-06-02"),
+        expiring("Cheese", "2024-07-01"),
+        item("Eggs", Some(6.0)),
+    ];
+
+    let expiring = pantry::expiring_items(items, &recipes, date("2024-06-03"), 3);
+
+    // Already expired first, items that keep or expire later are left out
+    let names: Vec<&str> = expiring.iter().map(|e| e.item.name.as_str()).collect();
+    assert_eq!(names, vec!["Yoghurt", "Milk"]);
+    assert_eq!(expiring[0].days_left, -1);
+    assert!(expiring[0].recipes.is_empty());
+    assert_eq!(expiring[1].days_left, 2);
+    assert_eq!(expiring[1].recipes.len(), 2);
+}
+
+#[test]
+fn test_next_free_date() {
+    let planned: HashSet<NaiveDate> = [date("2024-06-03"), date("2024-06-04"), date("2024-06-06")].into();
+
+    assert_eq!(pantry::next_free_date(date("2024-06-03"), &planned), Some(date("2024-06-05")));
+    assert_eq!(pantry::next_free_date(date("2024-06-06"), &planned), Some(date("2024-06-07")));
+
+    let everything: HashSet<NaiveDate> = (0..pantry::FREE_SLOT_LOOKAHEAD_DAYS).map(|d| date("2024-06-03") + Duration::days(d)).collect();
+    assert_eq!(pantry::next_free_date(date("2024-06-03"), &everything), None);
+}
+
+#[tokio::test]
+async fn test_add_and_remove_pantry_item() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server
+        .post("/pantry")
+        .form(&serde_json::json!({ "name": "Milk", "quantity": "1,5", "unit": "l", "expires": "2024-06-10" }))
+        .await;
+    response.assert_status_see_other();
+
+    let response = server.get("/pantry").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Milk");
+    response.assert_text_contains("1.5 l");
+    response.assert_text_contains("2024-06-10");
+
+    let response = server.post("/pantry/1/delete").await;
+    response.assert_status_see_other();
+
+    let response = server.get("/pantry").await;
+    response.assert_text_contains("The pantry is empty.");
+}
+
+#[tokio::test]
+async fn test_invalid_pantry_item_rejected() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server.post("/pantry").form(&serde_json::json!({ "name": " " })).await;
+    response.assert_status_bad_request();
+
+    let response = server.post("/pantry").form(&serde_json::json!({ "name": "Milk", "quantity": "lots" })).await;
+    response.assert_status_bad_request();
+
+    let response = server.post("/pantry").form(&serde_json::json!({ "name": "Milk", "expires": "tomorrow" })).await;
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_checked_shopping_task_moves_to_pantry() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    // Not the shopping list yet, so checking off does nothing to the pantry
+    let response = server.post("/task/1").await;
+    response.assert_status_ok();
+    server.get("/pantry").await.assert_text_contains("The pantry is empty.");
+
+    let response = server.post("/pantry/shopping-list").form(&serde_json::json!({ "list_id": "1" })).await;
+    response.assert_status_see_other();
+
+    for text in ["2 l milk", "1 l milk"] {
+        server.post("/1/task").form(&serde_json::json!({ "text": text })).await;
+    }
+    server.post("/task/4").await.assert_status_ok();
+    server.post("/task/5").await.assert_status_ok();
+
+    let response = server.get("/pantry").await;
+    response.assert_text_contains("3 l");
+    assert!(!response.text().contains("Test Task 1"));
+
+    // Unchecking does not add it again, and neither does checking it off once more
+    server.post("/task/5").await.assert_status_ok();
+    server.get("/pantry").await.assert_text_contains("3 l");
+    server.post("/task/5").await.assert_status_ok();
+    let response = server.get("/pantry").await;
+    response.assert_text_contains("3 l");
+    assert!(!response.text().contains("4 l"));
+}
+
+#[tokio::test]
+async fn test_shopping_list_must_exist() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server.post("/pantry/shopping-list").form(&serde_json::json!({ "list_id": "42" })).await;
+    response.assert_status_not_found();
+
+    let response = server.post("/pantry/shopping-list").form(&serde_json::json!({ "list_id": "" })).await;
+    response.assert_status_see_other();
+}
+
+#[tokio::test]
+async fn test_cookable_recipes_page() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server.get("/recipes/cookable").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Add what you have at home");
+
+    for (title, ingredients) in [("Omelette", "3 eggs\n1 dl milk"), ("Salad", "1 lettuce")] {
+        let form = MultipartForm::new()
+            .add_text("title", title)
+            .add_text("ingredients", ingredients)
+            .add_text("instructions", "");
+        server.post("/recipes/new").multipart(form).await.assert_status_see_other();
+    }
+    server.post("/pantry").form(&serde_json::json!({ "name": "egg", "quantity": "6" })).await;
+
+    let response = server.get("/recipes/cookable").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Omelette");
+    response.assert_text_contains("1 of 2 in stock (50%)");
+    response.assert_text_contains("1 dl milk");
+    assert!(!response.text().contains("Salad"));
+}
+
+#[tokio::test]
+async fn test_expiring_dashboard_plans_next_free_day() {
+    let (server, temp_dir) = setup_test_server().await;
+    let today = Utc::now().date_naive();
+    let tomorrow = today + Duration::days(1);
+
+    let form = MultipartForm::new()
+        .add_text("title", "Omelette")
+        .add_text("ingredients", "3 eggs\n1 dl milk")
+        .add_text("instructions", "");
+    server.post("/recipes/new").multipart(form).await.assert_status_see_other();
+    for (name, expires) in [("milk", tomorrow), ("cheese", today + Duration::days(10))] {
+        server
+            .post("/pantry")
+            .form(&serde_json::json!({ "name": name, "expires": expires.format("%Y-%m-%d").to_string() }))
+            .await
+            .assert_status_see_other();
+    }
+
+    let response = server.get("/pantry/expiring").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Expires tomorrow");
+    response.assert_text_contains("Omelette");
+    assert!(!response.text().contains("cheese"));
+
+    let response = server.get("/pantry/expiring?days=14").await;
+    response.assert_text_contains("cheese");
+
+    // Today already has a meal, so the recipe goes to tomorrow
+    server
+        .post(&format!("/meal-plan/{}/add", today.format("%Y-%m-%d")))
+        .form(&serde_json::json!({ "meal_text": "Pizza", "recipe_id": "" }))
+        .await
+        .assert_status_see_other();
+    let response = server.post("/pantry/expiring/plan").form(&serde_json::json!({ "recipe_id": "1" })).await;
+    response.assert_status_see_other();
+
+    let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+    let tomorrow_db = tomorrow.format("%Y-%m-%d").to_string();
+    let planned = db.get_meal_plan_for_week(tomorrow_db.clone()).await.unwrap();
+    assert!(planned.iter().any(|entry| entry.date == tomorrow_db && entry.meal_text == "Omelette"));
+
+    let response = server.post("/pantry/expiring/plan").form(&serde_json::json!({ "recipe_id": "99" })).await;
+    response.assert_status_not_found();
+}
@@ -1,6 +1,48 @@
-use std::process::Command;
+use std::io::Write;
+use std::process::{Command, Stdio};
 use tempfile::TempDir;
 
+/// Creates an admin user in the data directory through the CLI
+fn create_admin(data_dir: &std::path::Path) {
+    let mut process = Command::new("cargo")
+        .args(["run", "--", "--data-dir"])
+        .arg(data_dir)
+        .args(["create-admin", "admin"])
+        .stdin(Stdio::piped())
+        .spawn()
+        .expect("Failed to run create-admin");
+    process.stdin.take().unwrap().write_all(b"correct horse battery\n").unwrap();
+    assert!(process.wait().unwrap().success(), "create-admin failed");
+}
+
+/// Logs in with curl, keeping the session and CSRF cookies in the cookie jar.
+/// Returns the CSRF token for the `X-CSRF-Token` header.
+fn login(port: u16, cookie_jar: &std::path::Path) -> String {
+    let page = Command::new("curl")
+        .args(["-s", "-c"])
+        .arg(cookie_jar)
+        .arg(format!("http://127.0.0.1:{}/login", port))
+        .output()
+        .expect("Failed to open the login page");
+    let page = String::from_utf8_lossy(&page.stdout);
+    let field = "name=\"csrf_token\" value=\"";
+    let start = page.find(field).expect("CSRF token on the login page") + field.len();
+    let token = page[start..start + page[start..].find('"').unwrap()].to_string();
+
+    let output = Command::new("curl")
+        .args(["-s", "-o", "/dev/null", "-w", "%{http_code}", "-b"])
+        .arg(cookie_jar)
+        .arg("-c")
+        .arg(cookie_jar)
+        .args(["-d", "username=admin", "-d", "password=correct horse battery", "-d"])
+        .arg(format!("csrf_token={}", token))
+        .arg(format!("http://127.0.0.1:{}/login", port))
+        .output()
+        .expect("Failed to log in");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "303", "Login failed");
+    token
+}
+
 #[tokio::test]
 async fn test_photo_upload_end_to_end() {
     println!("🧪 Testing photo upload end-to-end");
@@ -8,6 +50,8 @@ async fn test_photo_upload_end_to_end() {
     // Create temporary directory for test data
     let temp_dir = TempDir::new().unwrap();
     let data_dir = temp_dir.path().to_path_buf();
+    let cookie_jar = temp_dir.path().join("cookies.txt");
+    create_admin(&data_dir);
 
     // Start the application
     println!("📡 Starting application...");
@@ -19,10 +63,14 @@ async fn test_photo_upload_end_to_end() {
 
     // Wait for application to start
     std::thread::sleep(std::time::Duration::from_secs(3));
+    let csrf_token = login(3003, &cookie_jar);
 
     // Test 1: Create a recipe
     println!("📝 Creating test recipe...");
     let output = Command::new("curl")
+        .arg("-b")
+        .arg(&cookie_jar)
+        .args(["-H", &format!("X-CSRF-Token: {}", csrf_token)])
         .args(&[
             "-X", "POST",
             "-F", "title=Test Recipe for Photo Upload",
@@ -83,6 +131,9 @@ async fn test_photo_upload_end_to_end() {
     // Test 3: Upload the photo
     println!("📤 Uploading photo...");
     let upload_output = Command::new("curl")
+        .arg("-b")
+        .arg(&cookie_jar)
+        .args(["-H", &format!("X-CSRF-Token: {}", csrf_token)])
         .args(&[
             "-X", "POST",
             "-F", &format!("photos=@{}", test_image_path.to_string_lossy()),
@@ -99,6 +150,8 @@ async fn test_photo_upload_end_to_end() {
     // Test 4: Check that recipes page shows the photo
     println!("🔍 Checking recipes page...");
     let recipes_output = Command::new("curl")
+        .arg("-b")
+        .arg(&cookie_jar)
         .args(&["-s", "http://127.0.0.1:3003/recipes"])
         .output()
         .expect("Failed to fetch recipes page");
@@ -124,6 +177,8 @@ async fn test_photo_upload_end_to_end() {
                 println!("  - Found photo filename: {}", filename);
                 
                 let photo_output = Command::new("curl")
+                    .arg("-b")
+                    .arg(&cookie_jar)
                     .args(&["-I", &format!("http://127.0.0.1:3003/photos/{}", filename)])
                     .output()
                     .expect("Failed to test photo serving");
@@ -156,6 +211,8 @@ async fn test_default_photo_fallback() {
 
     let temp_dir = TempDir::new().unwrap();
     let data_dir = temp_dir.path().to_path_buf();
+    let cookie_jar = temp_dir.path().join("cookies.txt");
+    create_admin(&data_dir);
 
     // Start the application
     let mut app_process = Command::new("cargo")
@@ -165,9 +222,12 @@ async fn test_default_photo_fallback() {
         .expect("Failed to start application");
 
     std::thread::sleep(std::time::Duration::from_secs(5));
+    let csrf_token = login(3006, &cookie_jar);
 
     // Check if the app started successfully by trying to connect
     let ping_output = Command::new("curl")
+        .arg("-b")
+        .arg(&cookie_jar)
         .args(&["-s", "-o", "/dev/null", "-w", "%{http_code}", "http://127.0.0.1:3006/"])
         .output()
         .expect("Failed to ping application");
@@ -184,6 +244,9 @@ async fn test_default_photo_fallback() {
 
     // Create a recipe without photo
     let create_output = Command::new("curl")
+        .arg("-b")
+        .arg(&cookie_jar)
+        .args(["-H", &format!("X-CSRF-Token: {}", csrf_token)])
         .args(&[
             "-X", "POST",
             "-F", "title=No Photo Recipe",
@@ -204,6 +267,8 @@ async fn test_default_photo_fallback() {
 
     // Check recipes page shows default image
     let recipes_output = Command::new("curl")
+        .arg("-b")
+        .arg(&cookie_jar)
         .args(&["-s", "http://127.0.0.1:3006/recipes"])
         .output()
         .expect("Failed to fetch recipes page");
@@ -223,6 +288,8 @@ async fn test_default_photo_fallback() {
 
     // Test default photo endpoint
     let default_output = Command::new("curl")
+        .arg("-b")
+        .arg(&cookie_jar)
         .args(&["-I", "http://127.0.0.1:3006/photos/default-recipe.svg"])
         .output()
         .expect("Failed to test default photo");
@@ -17,6 +17,7 @@ async fn test_photo_upload_integration() {
 
     // Initialize database
     let db = database::Database::new(data_dir.join("test.db")).await.unwrap();
+    let cookie = login_cookie(&db).await;
     let state = AppState { db, photos_dir };
 
     // Create the app
@@ -30,7 +31,9 @@ async fn test_photo_upload_integration() {
         boundary, boundary, boundary, boundary
     );
     let request = Request::builder()
+        .header("cookie", &cookie)
         .method("POST")
+        .header(csrf::CSRF_HEADER, CSRF_TOKEN)
         .uri("/recipes/new")
         .header("content-type", &format!("multipart/form-data; boundary={}", boundary))
         .body(Body::from(multipart_body))
@@ -68,7 +71,9 @@ async fn test_photo_upload_integration() {
     // Step 4: Upload the photo
     println!("Uploading photo...");
     let upload_request = Request::builder()
+        .header("cookie", &cookie)
         .method("POST")
+        .header(csrf::CSRF_HEADER, CSRF_TOKEN)
         .uri(format!("/recipes/{}/upload-photos", recipe_id))
         .header("content-type", format!("multipart/form-data; boundary={}", boundary))
         .body(Body::from(multipart_body))
@@ -107,6 +112,7 @@ async fn test_photo_upload_integration() {
     // Step 7: Test photo serving endpoint
     println!("Testing photo serving endpoint...");
     let photo_request = Request::builder()
+        .header("cookie", &cookie)
         .method("GET")
         .uri(format!("/photos/{}", photo.filename))
         .body(Body::empty())
@@ -121,6 +127,7 @@ async fn test_photo_upload_integration() {
     // Step 8: Test recipes page shows thumbnail
     println!("Testing recipes page shows thumbnail...");
     let recipes_request = Request::builder()
+        .header("cookie", &cookie)
         .method("GET")
         .uri("/recipes")
         .body(Body::empty())
@@ -141,6 +148,7 @@ async fn test_photo_upload_integration() {
     // Step 9: Test thumbnail endpoint
     println!("Testing thumbnail endpoint...");
     let thumbnail_request = Request::builder()
+        .header("cookie", &cookie)
         .method("GET")
         .uri(format!("/thumbnails/{}", photo.id))
         .body(Body::empty())
@@ -155,6 +163,17 @@ async fn test_photo_upload_integration() {
     println!("✅ All photo upload tests passed!");
 }
 
+/// CSRF token the requests send in both the cookie and the header
+const CSRF_TOKEN: &str = "test-csrf-token";
+
+/// Session and CSRF cookies for a freshly created user
+async fn login_cookie(db: &database::Database) -> String {
+    auth::create_user(db, "admin", "correct horse battery", true).await.unwrap();
+    let (user, _) = db.get_user_credentials("admin".to_string()).await.unwrap().unwrap();
+    let token = auth::start_session(db, &user).await.unwrap();
+    format!("{}={}; {}={}", auth::SESSION_COOKIE, token, csrf::CSRF_COOKIE, CSRF_TOKEN)
+}
+
 fn create_test_png() -> Vec<u8> {
     // Create a valid 2x2 PNG using the image crate
     use image::{RgbImage, ImageFormat};
@@ -181,6 +200,7 @@ async fn test_default_photo_fallback() {
     std::fs::create_dir_all(&photos_dir).unwrap();
 
     let db = database::Database::new(data_dir.join("test.db")).await.unwrap();
+    let cookie = login_cookie(&db).await;
     let state = AppState { db, photos_dir };
     let app = create_app(state.clone());
 
@@ -191,7 +211,9 @@ async fn test_default_photo_fallback() {
         boundary, boundary, boundary, boundary
     );
     let request = Request::builder()
+        .header("cookie", &cookie)
         .method("POST")
+        .header(csrf::CSRF_HEADER, CSRF_TOKEN)
         .uri("/recipes/new")
         .header("content-type", &format!("multipart/form-data; boundary={}", boundary))
         .body(Body::from(multipart_body))
@@ -202,6 +224,7 @@ async fn test_default_photo_fallback() {
 
     // Test recipes page shows default image
     let recipes_request = Request::builder()
+        .header("cookie", &cookie)
         .method("GET")
         .uri("/recipes")
         .body(Body::empty())
@@ -218,6 +241,7 @@ async fn test_default_photo_fallback() {
 
     // Test default photo endpoint
     let default_request = Request::builder()
+        .header("cookie", &cookie)
         .method("GET")
         .uri("/photos/default-recipe.svg")
         .body(Body::empty())
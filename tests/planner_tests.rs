@@ -0,0 +1,264 @@
+use axum_test::{TestServer, multipart::MultipartForm};
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use htmx_rs_todo::planner::{self, PlanHistory, PlannerConfig};
+use htmx_rs_todo::todo::Recipe;
+use tempfile::TempDir;
+
+mod common;
+use common::*;
+
+fn recipe(id: usize, title: &str, rating: u8, favourite: bool, tags: &str) -> Recipe {
+    Recipe {
+        id,
+        title: title.to_string(),
+        instructions: String::new(),
+        ingredients: String::new(),
+        favourite,
+        rating,
+        tags: tags.to_string(),
+        servings: None,
+        version: 1,
+    }
+}
+
+fn date(s: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+}
+
+#[test]
+fn test_rating_and_favourite_raise_score() {
+    let config = PlannerConfig::default();
+    let monday = date("2024-06-03");
+
+    let plain = planner::score_recipe(&recipe(1, "Plain", 0, false, ""), monday, &[], &config).unwrap();
+    let rated = planner::score_recipe(&recipe(2, "Rated", 4, false, ""), monday, &[], &config).unwrap();
+    let favourite = planner::score_recipe(&recipe(3, "Favourite", 0, true, ""), monday, &[], &config).unwrap();
+
+    assert!(rated > plain);
+    assert!(favourite > plain);
+}
+
+#[test]
+fn test_quick_tag_preferred_on_weekdays_only() {
+    let config = PlannerConfig::default();
+    let quick = recipe(1, "Quick", 0, false, "Quick, pasta");
+    let slow = recipe(2, "Slow", 0, false, "stew");
+
+    let wednesday = date("2024-06-05");
+    let saturday = date("2024-06-08");
+
+    assert!(planner::score_recipe(&quick, wednesday, &[], &config) > planner::score_recipe(&slow, wednesday, &[], &config));
+    assert_eq!(planner::score_recipe(&quick, saturday, &[], &config), planner::score_recipe(&slow, saturday, &[], &config));
+}
+
+#[test]
+fn test_recent_recipes_are_not_repeated() {
+    let config = PlannerConfig { avoid_repeat_days: 7, ..Default::default() };
+    let target = date("2024-06-10");
+    let r = recipe(1, "Tacos", 5, true, "");
+
+    assert!(planner::score_recipe(&r, target, &[date("2024-06-05")], &config).is_none());
+    assert!(planner::score_recipe(&r, target, &[date("2024-06-14")], &config).is_none());
+    assert!(planner::score_recipe(&r, target, &[date("2024-06-03")], &config).is_some());
+}
+
+#[test]
+fn test_longer_since_planned_scores_higher() {
+    let config = PlannerConfig { avoid_repeat_days: 0, ..Default::default() };
+    let target = date("2024-06-10");
+    let r = recipe(1, "Soup", 0, false, "");
+
+    let recent = planner::score_recipe(&r, target, &[date("2024-06-08")], &config).unwrap();
+    let older = planner::score_recipe(&r, target, &[date("2024-05-01")], &config).unwrap();
+    assert!(older > recent);
+}
+
+#[test]
+fn test_week_suggestions_do_not_repeat() {
+    let config = PlannerConfig::default();
+    let recipes = vec![
+        recipe(1, "Best", 5, true, ""),
+        recipe(2, "Good", 3, false, ""),
+    ];
+    let dates: Vec<NaiveDate> = (0..3).map(|i| date("2024-06-03") + Duration::days(i)).collect();
+
+    let suggestions = planner::suggest_for_dates(&recipes, &PlanHistory::new(), &dates, &config);
+
+    assert_eq!(suggestions[0].as_ref().unwrap().recipe.id, 1);
+    assert_eq!(suggestions[1].as_ref().unwrap().recipe.id, 2);
+    assert!(suggestions[2].is_none());
+}
+
+#[test]
+fn test_reroll_skips_excluded_recipes() {
+    let config = PlannerConfig::default();
+    let recipes = vec![
+        recipe(1, "Best", 5, false, ""),
+        recipe(2, "Good", 3, false, ""),
+    ];
+    let target = date("2024-06-03");
+
+    let first = planner::suggest_for_date(&recipes, &PlanHistory::new(), target, &[], &config).unwrap();
+    let second = planner::suggest_for_date(&recipes, &PlanHistory::new(), target, &[first.recipe.id], &config).unwrap();
+
+    assert_eq!(first.recipe.id, 1);
+    assert_eq!(second.recipe.id, 2);
+    assert!(planner::suggest_for_date(&recipes, &PlanHistory::new(), target, &[1, 2], &config).is_none());
+}
+
+#[tokio::test]
+async fn test_recipe_preferences_saved() {
+    let (server, _temp_dir) = setup_test_server_with_recipes(&[("Weeknight Pasta", "4", "on", "quick, pasta")]).await;
+
+    let response = server.get("/recipes/1").await;
+    response.assert_status_ok();
+    response.assert_text_contains("★ Weeknight Pasta");
+    response.assert_text_contains("Rating: 4 / 5");
+    response.assert_text_contains("quick");
+
+    let response = server.get("/recipes/1/edit").await;
+    response.assert_status_ok();
+    response.assert_text_contains("quick, pasta");
+}
+
+#[tokio::test]
+async fn test_suggestions_fill_empty_days() {
+    let (server, _temp_dir) = setup_test_server_with_recipes(&[
+        ("Tacos", "5", "on", ""),
+        ("Lasagna", "3", "", ""),
+    ]).await;
+
+    let week_start = current_week_start();
+
+    // Plan something on the first day, so only the remaining days get suggestions
+    let response = server
+        .post(&format!("/meal-plan/{}/add", week_start.format("%Y-%m-%d")))
+        .form(&serde_json::json!({ "meal_text": "Eat out" }))
+        .await;
+    response.assert_status_see_other();
+
+    let response = server.get(&format!("/meal-plan/{}/suggest", week_start.format("%Y-%m-%d"))).await;
+    response.assert_status_ok();
+    response.assert_text_contains("Meal Suggestions");
+    response.assert_text_contains("Tacos");
+
+    let body = response.text();
+    assert!(!body.contains(&format!("suggestion-{}", week_start.format("%Y-%m-%d"))));
+    assert!(body.contains(&format!("suggestion-{}", (week_start + Duration::days(1)).format("%Y-%m-%d"))));
+}
+
+#[tokio::test]
+async fn test_accept_suggestion_adds_meal() {
+    let (server, _temp_dir) = setup_test_server_with_recipes(&[("Tacos", "5", "", "")]).await;
+
+    let day = current_week_start().format("%Y-%m-%d").to_string();
+    let response = server
+        .post(&format!("/meal-plan/{}/suggestion", day))
+        .form(&serde_json::json!({ "recipe_id": "1" }))
+        .await;
+    response.assert_status_ok();
+    response.assert_text_contains("✓ Planned");
+
+    let response = server.get("/meal-plan").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Tacos");
+    response.assert_text_contains("/recipes/1");
+
+    // The accepted day is no longer empty
+    let response = server.get(&format!("/meal-plan/{}/suggest", day)).await;
+    let body = response.text();
+    assert!(!body.contains(&format!("suggestion-{}", day)));
+}
+
+#[tokio::test]
+async fn test_accepting_twice_plans_once() {
+    let (server, _temp_dir) = setup_test_server_with_recipes(&[
+        ("Tacos", "5", "", ""),
+        ("Lasagna", "3", "", ""),
+    ]).await;
+
+    let day = current_week_start().format("%Y-%m-%d").to_string();
+    for recipe_id in ["1", "1", "2"] {
+        let response = server
+            .post(&format!("/meal-plan/{}/suggestion", day))
+            .form(&serde_json::json!({ "recipe_id": recipe_id }))
+            .await;
+        response.assert_status_ok();
+        // The day keeps the first accepted recipe
+        response.assert_text_contains("✓ Planned");
+        response.assert_text_contains("Tacos");
+    }
+
+    let response = server.get("/meal-plan").await;
+    assert_eq!(response.text().matches("/recipes/1\"").count(), 1);
+    assert!(!response.text().contains("Lasagna"));
+}
+
+#[tokio::test]
+async fn test_reroll_suggestion() {
+    let (server, _temp_dir) = setup_test_server_with_recipes(&[
+        ("Tacos", "5", "", ""),
+        ("Lasagna", "3", "", ""),
+    ]).await;
+
+    let day = current_week_start().format("%Y-%m-%d").to_string();
+
+    let response = server.get(&format!("/meal-plan/{}/suggestion?exclude=1", day)).await;
+    response.assert_status_ok();
+    response.assert_text_contains("Lasagna");
+    response.assert_text_contains("exclude=1,2");
+
+    let response = server.get(&format!("/meal-plan/{}/suggestion?exclude=1,2", day)).await;
+    response.assert_status_ok();
+    response.assert_text_contains("No more recipes to suggest");
+}
+
+#[tokio::test]
+async fn test_recently_planned_recipe_not_suggested() {
+    let (server, _temp_dir) = setup_test_server_with_recipes(&[
+        ("Tacos", "5", "on", ""),
+        ("Lasagna", "1", "", ""),
+    ]).await;
+
+    let day = current_week_start();
+    let response = server
+        .post(&format!("/meal-plan/{}/add", (day - Duration::days(2)).format("%Y-%m-%d")))
+        .form(&serde_json::json!({ "meal_text": "Tacos", "recipe_id": "1" }))
+        .await;
+    response.assert_status_see_other();
+
+    let response = server.get(&format!("/meal-plan/{}/suggestion", day.format("%Y-%m-%d"))).await;
+    response.assert_status_ok();
+    response.assert_text_contains("Lasagna");
+
+    // A shorter repeat window lets the favourite back in
+    let response = server.get(&format!("/meal-plan/{}/suggestion?repeat_days=1", day.format("%Y-%m-%d"))).await;
+    response.assert_text_contains("Tacos");
+}
+
+fn current_week_start() -> NaiveDate {
+    let today = Utc::now().date_naive();
+    today - Duration::days(today.weekday().num_days_from_monday() as i64)
+}
+
+/// Helper function to set up a test server with recipes given as (title, rating, favourite, tags)
+async fn setup_test_server_with_recipes(recipes: &[(&str, &str, &str, &str)]) -> (TestServer, TempDir) {
+    let (server, temp_dir) = setup_test_server().await;
+
+    for (title, rating, favourite, tags) in recipes {
+        let mut form = MultipartForm::new()
+            .add_text("title", *title)
+            .add_text("ingredients", "1 cup flour")
+            .add_text("instructions", "Cook.")
+            .add_text("rating", *rating)
+            .add_text("tags", *tags);
+        if !favourite.is_empty() {
+            form = form.add_text("favourite", *favourite);
+        }
+
+        let response = server.post("/recipes/new").multipart(form).await;
+        response.assert_status_see_other();
+    }
+
+    (server, temp_dir)
+}
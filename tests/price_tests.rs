@@ -0,0 +1,130 @@
+use htmx_rs_todo::locale::{self, Locale};
+use htmx_rs_todo::prices::{self, Estimate, LastPrices, TripPurchase};
+
+mod common;
+use common::*;
+
+fn purchase(trip_id: usize, finished: &str, store: Option<&str>, price: Option<f64>) -> TripPurchase {
+    TripPurchase { trip_id, finished: finished.to_string(), store: store.map(str::to_string), price }
+}
+
+#[test]
+fn test_parse_price() {
+    assert_eq!(prices::parse_price("12.50"), Some(12.5));
+    assert_eq!(prices::parse_price(" 12,50 "), Some(12.5));
+    assert_eq!(prices::parse_price("0"), Some(0.0));
+    assert_eq!(prices::parse_price("-3"), None);
+    assert_eq!(prices::parse_price("cheap"), None);
+}
+
+#[test]
+fn test_estimate_uses_normalised_names() {
+    let prices = LastPrices::from([("milk".to_string(), 20.0), ("egg".to_string(), 35.5)]);
+
+    let estimate = prices::estimate(&["2 l Milk", "12 eggs", "Saffron"], &prices);
+    assert_eq!(estimate, Estimate { total: 55.5, priced: 2, items: 3 });
+    assert!(!estimate.is_complete());
+}
+
+#[test]
+fn test_monthly_spending() {
+    let purchases = vec![
+        purchase(1, "2024-05-31 22:30:00:1717194600", Some("Rema"), Some(100.0)),
+        purchase(1, "2024-05-31 22:30:00:1717194600", None, Some(20.0)),
+        purchase(2, "2024-06-10 12:00:00:1718020800", Some("Kiwi"), Some(50.0)),
+        purchase(2, "2024-06-10 12:00:00:1718020800", Some("Rema"), Some(80.0)),
+        purchase(3, "2024-06-12 12:00:00:1718193600", None, None),
+    ];
+
+    let utc = prices::monthly_spending(&purchases, &Locale::default());
+    assert_eq!(utc.len(), 2);
+    assert_eq!(utc[0].month, "2024-06");
+    assert_eq!(utc[0].trips, 2);
+    assert_eq!(utc[0].total, 130.0);
+    assert_eq!(utc[0].by_store, vec![("Rema".to_string(), 80.0), ("Kiwi".to_string(), 50.0)]);
+    assert_eq!(utc[1].by_store, vec![("Rema".to_string(), 100.0), ("Unknown store".to_string(), 20.0)]);
+
+    // The late May trip was already in June in Oslo
+    let oslo = Locale { timezone: locale::parse_timezone("Europe/Oslo").unwrap(), ..Default::default() };
+    let local = prices::monthly_spending(&purchases, &oslo);
+    assert_eq!(local.len(), 1);
+    assert_eq!(local[0].trips, 3);
+    assert_eq!(local[0].total, 250.0);
+}
+
+#[tokio::test]
+async fn test_prices_feed_estimates_and_report() {
+    let (server, _temp_dir) = setup_test_server().await;
+    server.post("/create_list").form(&serde_json::json!({ "name": "Groceries" })).await;
+    server.post("/stores").form(&serde_json::json!({ "name": "Corner shop" })).await;
+    for text in ["1 l milk", "bread"] {
+        server.post("/1/task").form(&serde_json::json!({ "text": text })).await;
+    }
+
+    // Check off the milk and enter what it cost
+    server.post("/task/1?view=shop").await.assert_status_ok();
+    let response = server
+        .post("/task/1/price")
+        .form(&serde_json::json!({ "price": "21,90", "store_id": "1" }))
+        .await;
+    response.assert_status_ok();
+    // Correcting the price replaces it instead of adding another
+    let response = server
+        .post("/task/1/price")
+        .form(&serde_json::json!({ "price": "22.90", "store_id": "1" }))
+        .await;
+    response.assert_status_ok();
+
+    let response = server.get("/list/1/shop").await;
+    response.assert_text_contains(r#"value="22.90""#);
+    response.assert_text_contains(r#"<strong id="cart-total">22.90</strong>"#);
+
+    server.post("/list/1/shop/finish").await.assert_status_see_other();
+
+    // Milk now has a known price on the next list
+    server.post("/1/task").form(&serde_json::json!({ "text": "2 l milk" })).await;
+    let response = server.get("/list/1/shop").await;
+    response.assert_text_contains(r#"<strong id="estimate-total">22.90</strong>"#);
+    response.assert_text_contains("(1 of 2 items priced)");
+
+    let response = server.get("/reports/spending").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Corner shop: 22.90");
+    response.assert_text_contains(r#"<td class="amount">22.90</td>"#);
+}
+
+#[tokio::test]
+async fn test_invalid_price_rejected() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    let response = server.post("/task/1/price").form(&serde_json::json!({ "price": "free" })).await;
+    response.assert_status_bad_request();
+    let response = server.post("/task/1/price").form(&serde_json::json!({ "price": "10", "store_id": "9" })).await;
+    response.assert_status_not_found();
+    let response = server.post("/task/99/price").form(&serde_json::json!({ "price": "10" })).await;
+    response.assert_status_not_found();
+    let response = server.post("/task/1/price").form(&serde_json::json!({ "price": "" })).await;
+    response.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_weekly_ingredients_estimate() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+    server.post("/1/task").form(&serde_json::json!({ "text": "flour" })).await;
+    server.post("/task/4/price").form(&serde_json::json!({ "price": "15" })).await.assert_status_ok();
+
+    let form = axum_test::multipart::MultipartForm::new()
+        .add_text("title", "Bread")
+        .add_text("ingredients", "500 g flour\n1 tsp salt")
+        .add_text("instructions", "");
+    server.post("/recipes/new").multipart(form).await.assert_status_see_other();
+    server
+        .post("/meal-plan/2024-06-03/add")
+        .form(&serde_json::json!({ "meal_text": "Bread", "recipe_id": "1" }))
+        .await
+        .assert_status_see_other();
+
+    let response = server.get("/meal-plan/2024-06-03/add-ingredients").await;
+    response.assert_text_contains("Estimated cost: <strong>15.00</strong>");
+    response.assert_text_contains("(1 of 2 items have a known price)");
+}
@@ -0,0 +1,165 @@
+use axum::http::StatusCode;
+use axum_test::{multipart::MultipartForm, TestServer};
+use htmx_rs_todo::database::Database;
+use htmx_rs_todo::todo::{SharePermission, ShareTarget};
+use serde_json::json;
+
+mod common;
+use common::*;
+
+/// Shares `target` with `permission` and returns the link's path
+async fn share(server: &TestServer, target: &str, permission: &str) -> String {
+    server
+        .post("/shares")
+        .form(&json!({ "target": target, "permission": permission, "expires_days": "" }))
+        .await
+        .assert_status_see_other();
+    let page = server.get("/shares").await.text();
+    let start = page.find("href=\"/s/").expect("share link on the page") + "href=\"".len();
+    let end = start + page[start..].find('"').unwrap();
+    page[start..end].to_string()
+}
+
+async fn log_out(server: &TestServer) {
+    server.post("/logout").await.assert_status_see_other();
+}
+
+#[tokio::test]
+async fn test_view_only_list_link() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+    let link = share(&server, "list:1", "view").await;
+    log_out(&server).await;
+
+    let response = server.get(&link).await;
+    response.assert_status_ok();
+    response.assert_text_contains("Test Task 1");
+    response.assert_text_contains("disabled");
+    assert!(!response.text().contains("Finish trip"));
+    assert!(!response.text().contains("hx-post"));
+
+    server.post(&format!("{}/task/1", link)).await.assert_status(StatusCode::FORBIDDEN);
+    server.post(&format!("{}/task", link)).form(&json!({ "text": "Sneaky" })).await.assert_status(StatusCode::FORBIDDEN);
+    server.delete(&format!("{}/task/1", link)).await.assert_status(StatusCode::FORBIDDEN);
+
+    // The link opens nothing else
+    server.get("/").await.assert_status_see_other();
+    server.get("/list/1/shop").await.assert_status_see_other();
+}
+
+#[tokio::test]
+async fn test_check_off_list_link() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+    server.post("/create_list").form(&json!({ "name": "Other List" })).await;
+    server.post("/2/task").form(&json!({ "text": "Not shared" })).await;
+    let link = share(&server, "list:1", "check_off").await;
+    log_out(&server).await;
+
+    let response = server.get(&link).await;
+    response.assert_text_contains(format!("hx-post=\"{}/task/1/completed\"", link));
+    assert!(!response.text().contains("Not shared"));
+
+    let response = server.post(&format!("{}/task/1", link)).await;
+    response.assert_status_ok();
+    response.assert_text_contains("in-cart");
+
+    // Tasks of other lists are out of reach
+    server.post(&format!("{}/task/4", link)).await.assert_status(StatusCode::NOT_FOUND);
+    server.post(&format!("{}/task", link)).form(&json!({ "text": "Sneaky" })).await.assert_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_editable_list_link() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+    let link = share(&server, "list:1", "edit").await;
+    log_out(&server).await;
+
+    let response = server.post(&format!("{}/task", link)).form(&json!({ "text": "Guest bananas" })).await;
+    response.assert_status_ok();
+    response.assert_text_contains("Guest bananas");
+    server.delete(&format!("{}/task/1", link)).await.assert_status_ok();
+
+    let response = server.get(&link).await;
+    response.assert_text_contains("Guest bananas");
+    assert!(!response.text().contains("Test Task 1"));
+}
+
+#[tokio::test]
+async fn test_recipe_links() {
+    let (server, _temp_dir) = setup_test_server().await;
+    server
+        .post("/api/v1/recipes")
+        .json(&json!({ "title": "Shared Soup", "ingredients": "1 leek", "instructions": "Simmer" }))
+        .await
+        .assert_status(StatusCode::CREATED);
+    let view_link = share(&server, "recipe:1", "view").await;
+    let edit_link = share(&server, "recipe:1", "edit").await;
+    assert_ne!(view_link, edit_link);
+    log_out(&server).await;
+
+    let response = server.get(&view_link).await;
+    response.assert_status_ok();
+    response.assert_text_contains("Shared Soup");
+    assert!(!response.text().contains("Delete Recipe"));
+    assert!(!response.text().contains("Edit Recipe"));
+    server.get(&format!("{}/edit", view_link)).await.assert_status(StatusCode::FORBIDDEN);
+
+    server.get(&edit_link).await.assert_text_contains(format!("href=\"{}/edit\"", edit_link));
+    server.get(&format!("{}/edit", edit_link)).await.assert_status_ok();
+    let form = MultipartForm::new()
+        .add_text("title", "Shared Soup")
+        .add_text("ingredients", "2 leeks")
+        .add_text("instructions", "Simmer longer");
+    let response = server.post(&format!("{}/edit", edit_link)).multipart(form).await;
+    response.assert_status_see_other();
+    assert_eq!(response.header("location"), edit_link.as_str());
+    server.get(&view_link).await.assert_text_contains("Simmer longer");
+}
+
+#[tokio::test]
+async fn test_deleted_and_unknown_links() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+    let link = share(&server, "list:1", "view").await;
+    let token = link.trim_start_matches("/s/");
+
+    server.post(&format!("/shares/{}/delete", token)).await.assert_status_see_other();
+    log_out(&server).await;
+
+    server.get(&link).await.assert_status(StatusCode::NOT_FOUND);
+    server.get("/s/made-up-token").await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_invalid_shares() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    let invalid = [
+        json!({ "target": "list:99", "permission": "view" }),
+        json!({ "target": "pantry:1", "permission": "view" }),
+        json!({ "target": "list:1", "permission": "admin" }),
+        json!({ "target": "list:1", "permission": "view", "expires_days": "0" }),
+    ];
+    for form in invalid {
+        server.post("/shares").form(&form).await.assert_status_not_ok();
+    }
+    assert!(!server.get("/shares").await.text().contains("href=\"/s/"));
+}
+
+#[tokio::test]
+async fn test_expired_link() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+    let list_id = db.create_list("Groceries".to_string()).await.unwrap();
+    let now = chrono::Utc::now().timestamp();
+
+    db.create_share_link("expired".to_string(), ShareTarget::List(list_id), SharePermission::View, Some(now - 1))
+        .await
+        .unwrap();
+    db.create_share_link("current".to_string(), ShareTarget::List(list_id), SharePermission::View, Some(now + 60))
+        .await
+        .unwrap();
+
+    assert!(db.get_share_link("expired".to_string(), now).await.unwrap().is_none());
+    let link = db.get_share_link("current".to_string(), now).await.unwrap().unwrap();
+    assert_eq!(link.title, "Groceries");
+    assert_eq!(link.target, ShareTarget::List(list_id));
+}
@@ -0,0 +1,60 @@
+mod common;
+use common::*;
+
+#[tokio::test]
+async fn test_shopping_mode_page() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+    server.post("/task/2").await.assert_status_ok();
+
+    let response = server.get("/list/1/shop").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Default List");
+    response.assert_text_contains(r#"To buy (<span id="to-buy-count">2</span>)"#);
+    response.assert_text_contains(r#"In cart (<span id="in-cart-count">1</span>)"#);
+    response.assert_text_contains("Keep screen on");
+    response.assert_text_contains("Finish trip");
+
+    let response = server.get("/list/42/shop").await;
+    response.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_shopping_mode_toggle_moves_item() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    let response = server.post("/task/1?view=shop").await;
+    response.assert_status_ok();
+    response.assert_text_contains(r#"hx-swap-oob="afterbegin:#in-cart""#);
+    response.assert_text_contains(r#"hx-post="/task/1/completed?view=shop""#);
+    response.assert_text_contains("Test Task 1");
+
+    let response = server.post("/task/1?view=shop").await;
+    response.assert_text_contains(r#"hx-swap-oob="beforeend:#to-buy""#);
+
+    // The list view still gets table rows
+    let response = server.post("/task/1").await;
+    response.assert_text_contains("completedtablebody");
+}
+
+#[tokio::test]
+async fn test_finish_trip_archives_checked_items() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+    server.post("/task/1?view=shop").await.assert_status_ok();
+    server.post("/task/3?view=shop").await.assert_status_ok();
+
+    let response = server.post("/list/1/shop/finish").await;
+    response.assert_status_see_other();
+
+    let response = server.get("/list/1/shop").await;
+    response.assert_text_contains(r#"In cart (<span id="in-cart-count">0</span>)"#);
+    response.assert_text_contains("Test Task 2");
+    assert!(!response.text().contains("Test Task 1"));
+
+    let response = server.get("/?list_id=1").await;
+    assert!(!response.text().contains("Test Task 3"));
+
+    // Nothing left in the cart is fine, and lists with trips can still be deleted
+    server.post("/list/1/shop/finish").await.assert_status_see_other();
+    server.delete("/list/1").await.assert_status_see_other();
+    server.post("/list/1/shop/finish").await.assert_status_not_found();
+}
@@ -0,0 +1,180 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use htmx_rs_todo::database::Database;
+use htmx_rs_todo::locale::{self, Locale};
+use tempfile::TempDir;
+
+mod common;
+use common::*;
+
+/// POSIX zone at UTC+14, which needs no zone database
+const FAR_FROM_UTC: &str = "KIRITIMATI-14";
+
+fn utc(s: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+}
+
+fn date(s: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+}
+
+fn in_timezone(name: &str) -> Locale {
+    Locale { timezone: locale::parse_timezone(name).unwrap(), ..Default::default() }
+}
+
+#[test]
+fn test_parse_timezone() {
+    assert!(locale::parse_timezone("Europe/Oslo").is_some());
+    assert!(locale::parse_timezone(" America/New_York ").is_some());
+    assert!(locale::parse_timezone("Mars/Olympus_Mons").is_none());
+}
+
+#[test]
+fn test_today_after_local_midnight() {
+    let oslo = in_timezone("Europe/Oslo");
+
+    // 00:30 in Oslo (CEST, UTC+2) is still the previous day in UTC
+    assert_eq!(oslo.today_at(utc("2024-06-04T22:30:00Z")), date("2024-06-05"));
+    assert_eq!(Locale::default().today_at(utc("2024-06-04T22:30:00Z")), date("2024-06-04"));
+}
+
+#[test]
+fn test_today_across_spring_forward() {
+    let oslo = in_timezone("Europe/Oslo");
+
+    // Clocks go from 02:00 CET to 03:00 CEST on 31 March 2024
+    assert_eq!(oslo.today_at(utc("2024-03-30T22:59:59Z")), date("2024-03-30"));
+    assert_eq!(oslo.today_at(utc("2024-03-30T23:00:00Z")), date("2024-03-31"));
+    assert_eq!(oslo.today_at(utc("2024-03-31T21:59:59Z")), date("2024-03-31"));
+    assert_eq!(oslo.today_at(utc("2024-03-31T22:00:00Z")), date("2024-04-01"));
+}
+
+#[test]
+fn test_today_across_fall_back() {
+    let oslo = in_timezone("Europe/Oslo");
+
+    // Clocks go from 03:00 CEST back to 02:00 CET on 27 October 2024
+    assert_eq!(oslo.today_at(utc("2024-10-26T21:59:59Z")), date("2024-10-26"));
+    assert_eq!(oslo.today_at(utc("2024-10-26T22:00:00Z")), date("2024-10-27"));
+    assert_eq!(oslo.today_at(utc("2024-10-27T22:59:59Z")), date("2024-10-27"));
+    assert_eq!(oslo.today_at(utc("2024-10-27T23:00:00Z")), date("2024-10-28"));
+}
+
+#[test]
+fn test_today_west_of_utc() {
+    let new_york = in_timezone("America/New_York");
+
+    // 23:30 EST on 9 March, just before clocks spring forward
+    assert_eq!(new_york.today_at(utc("2024-03-10T04:30:00Z")), date("2024-03-09"));
+    // 00:30 EDT on 3 November, just before clocks fall back
+    assert_eq!(new_york.today_at(utc("2024-11-03T04:30:00Z")), date("2024-11-03"));
+}
+
+#[test]
+fn test_week_start_uses_local_today() {
+    let oslo = in_timezone("Europe/Oslo");
+
+    // Sunday 31 March 22:30 UTC is already Monday 1 April in Oslo, so a new week has started
+    let now = utc("2024-03-31T22:30:00Z");
+    assert_eq!(oslo.week_start_for(oslo.today_at(now)), date("2024-04-01"));
+    assert_eq!(Locale::default().week_start_for(Locale::default().today_at(now)), date("2024-03-25"));
+}
+
+#[test]
+fn test_local_timestamp_during_fall_back() {
+    let oslo = in_timezone("Europe/Oslo");
+    let local = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+
+    // 02:30 happens twice in Oslo on 27 October 2024
+    assert_eq!(oslo.local_timestamp("2024-10-27 00:30:00:1729989000"), Some(local("2024-10-27 02:30:00")));
+    assert_eq!(oslo.local_timestamp("2024-10-27 01:30:00:1729992600"), Some(local("2024-10-27 02:30:00")));
+    // And 02:30 never happens on 31 March 2024
+    assert_eq!(oslo.local_timestamp("2024-03-31 01:00:00"), Some(local("2024-03-31 03:00:00")));
+    assert_eq!(oslo.local_timestamp("garbage"), None);
+}
+
+#[tokio::test]
+async fn test_timezone_setting_saved() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server
+        .post("/settings")
+        .form(&serde_json::json!({
+            "language": "en",
+            "week_start": "monday",
+            "date_format": "%d.%m.%y",
+            "timezone": "Europe/Oslo"
+        }))
+        .await;
+    response.assert_status_see_other();
+
+    let response = server.get("/settings").await;
+    response.assert_status_ok();
+    response.assert_text_contains(r#"value="Europe/Oslo""#);
+}
+
+#[tokio::test]
+async fn test_unknown_timezone_rejected() {
+    let (server, _temp_dir) = setup_test_server().await;
+
+    let response = server
+        .post("/settings")
+        .form(&serde_json::json!({
+            "language": "en",
+            "week_start": "monday",
+            "date_format": "%d.%m.%y",
+            "timezone": "Mars/Olympus_Mons"
+        }))
+        .await;
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_timestamps_stored_in_utc() {
+    // SQLite's 'localtime' follows TZ, so on a machine in UTC this would pass either way.
+    // Run the test again in a zone 14 hours off, which is where it really checks something.
+    if std::env::var("TZ").as_deref() != Ok(FAR_FROM_UTC) {
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "test_timestamps_stored_in_utc", "--nocapture"])
+            .env("TZ", FAR_FROM_UTC)
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "Failed with TZ={}:\n{}{}", FAR_FROM_UTC, stdout, String::from_utf8_lossy(&output.stderr));
+        assert!(stdout.contains("1 passed"), "Didn't run with TZ={}:\n{}", FAR_FROM_UTC, stdout);
+        return;
+    }
+
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("test.db");
+
+    // A database created before timestamps moved to UTC, with localtime defaults
+    {
+        let conn = rusqlite::Connection::open(&db_path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE lists (
+               id INTEGER PRIMARY KEY,
+               name TEXT NOT NULL,
+               created TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now', 'localtime')),
+               modified TEXT DEFAULT (strftime('%Y-%m-%d %H:%M:%S:%s', 'now', 'localtime'))
+             );",
+        )
+        .unwrap();
+    }
+
+    let db = Database::new(db_path.clone()).await.unwrap();
+    let list_id = db.create_list("Groceries".to_string()).await.unwrap();
+    let task_id = db.create_task("Milk".to_string(), list_id).await.unwrap();
+
+    let conn = rusqlite::Connection::open(&db_path).unwrap();
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+    assert!(version >= 2);
+
+    let now = Utc::now().naive_utc();
+    for (table, id) in [("lists", list_id), ("tasks", task_id)] {
+        let created: String = conn
+            .query_row(&format!("SELECT created FROM {} WHERE id = ?1", table), [id], |row| row.get(0))
+            .unwrap();
+        let created = NaiveDateTime::parse_from_str(&created[..19], "%Y-%m-%d %H:%M:%S").unwrap();
+        assert!((now - created).num_seconds().abs() < 60, "{} created at {} is not UTC", table, created);
+    }
+}
@@ -0,0 +1,173 @@
+use axum_test::multipart::{MultipartForm, Part};
+use chrono::NaiveDate;
+use htmx_rs_todo::todotxt::{self, ImportedTask, TodoTxtTask};
+
+mod common;
+use common::*;
+
+/// The lines of a file without their `+project`, which names the list they were on
+fn without_project(todo_txt: &str) -> Vec<String> {
+    todo_txt
+        .lines()
+        .map(|line| TodoTxtTask { project: None, ..TodoTxtTask::parse(line).unwrap() }.to_line())
+        .collect()
+}
+
+#[test]
+fn test_parse_line() {
+    let task = TodoTxtTask::parse("(A) 2026-10-01 Call the plumber @phone +Chores").unwrap();
+    assert!(!task.completed);
+    assert_eq!(task.priority, Some('A'));
+    assert_eq!(task.created_on, NaiveDate::from_ymd_opt(2026, 10, 1));
+    assert_eq!(task.completed_on, None);
+    assert_eq!(task.text, "Call the plumber");
+    assert_eq!(task.contexts, vec!["phone"]);
+    assert_eq!(task.project.as_deref(), Some("Chores"));
+
+    let done = TodoTxtTask::parse("x 2026-10-03 2026-10-01 Buy batteries +Chores pri:B").unwrap();
+    assert!(done.completed);
+    assert_eq!(done.priority, Some('B'));
+    assert_eq!(done.completed_on, NaiveDate::from_ymd_opt(2026, 10, 3));
+    assert_eq!(done.created_on, NaiveDate::from_ymd_opt(2026, 10, 1));
+    assert_eq!(done.text, "Buy batteries");
+
+    // Not a priority, a completion mark or a date unless at the start
+    let plain = TodoTxtTask::parse("Email (A) to x@example.com 2026-10-01").unwrap();
+    assert_eq!(plain, TodoTxtTask { text: "Email (A) to x@example.com 2026-10-01".to_string(), ..Default::default() });
+    assert!(!TodoTxtTask::parse("xylophone lesson").unwrap().completed);
+    assert!(TodoTxtTask::parse("   ").is_none());
+
+    // Only done tasks have a second date
+    let open = TodoTxtTask::parse("2026-10-18 2026-11-01 dentist").unwrap();
+    assert_eq!(open.created_on, NaiveDate::from_ymd_opt(2026, 10, 18));
+    assert_eq!(open.text, "2026-11-01 dentist");
+}
+
+#[test]
+fn test_lines_round_trip() {
+    for line in [
+        "(A) 2026-10-01 Call the plumber @phone +Chores",
+        "x 2026-10-03 2026-10-01 Buy batteries +Chores pri:B",
+        "2026-10-01 Water plants",
+        "Milk @store",
+    ] {
+        assert_eq!(TodoTxtTask::parse(line).unwrap().to_line(), line);
+    }
+}
+
+#[test]
+fn test_project_names_have_no_spaces() {
+    assert_eq!(todotxt::project_name("Hardware store  list"), "Hardware-store-list");
+}
+
+#[tokio::test]
+async fn test_tasks_from_create_task_round_trip() {
+    let (state, _temp_dir) = setup_state().await;
+    let groceries = state.db.create_list("Groceries".to_string()).await.unwrap();
+    state.db.create_task("Milk @store".to_string(), groceries).await.unwrap();
+    let eggs = state.db.create_task("Eggs @store @market".to_string(), groceries).await.unwrap();
+    state.db.set_task_completed(eggs, true, None).await.unwrap();
+    state.db.create_task("Call grandma".to_string(), groceries).await.unwrap();
+    state.db.create_task("2026-11-01 dentist".to_string(), groceries).await.unwrap();
+    state.db.create_task("Call @phone the plumber".to_string(), groceries).await.unwrap();
+
+    let exported = todotxt::export_list(&state, groceries).await.unwrap();
+    let lines: Vec<&str> = exported.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert!(lines.iter().all(|line| line.ends_with("+Groceries")));
+    assert!(lines.iter().any(|line| line.starts_with("x ") && line.contains("Eggs @store @market")));
+
+    let copy = state.db.create_list("Copy".to_string()).await.unwrap();
+    assert_eq!(todotxt::import_list(&state, copy, &without_project(&exported).join("\n")).await.unwrap(), 5);
+    let again = todotxt::export_list(&state, copy).await.unwrap();
+    assert_eq!(without_project(&again), without_project(&exported));
+    // Except that a context in the middle of the text comes back at its end
+    assert!(exported.contains("Call @phone the plumber +Groceries"));
+    assert!(again.contains("Call the plumber @phone +Copy"));
+
+    let original = state.db.get_tasks(groceries).await.unwrap();
+    let imported = state.db.get_tasks(copy).await.unwrap();
+    assert_eq!(imported.len(), original.len());
+    for (before, after) in original.iter().zip(&imported) {
+        assert_eq!((before.completed, &before.priority), (after.completed, &after.priority));
+        assert_eq!(before.all_tags(), after.all_tags());
+    }
+    // The contexts were moved into the tags
+    assert_eq!(imported[1].text, "Eggs");
+    assert_eq!(imported[1].tags, vec!["store", "market"]);
+    assert!(imported.iter().any(|task| task.text == "2026-11-01 dentist"));
+    assert_eq!(imported[4].text, "Call the plumber");
+    assert_eq!(imported[4].tags, vec!["phone"]);
+}
+
+#[tokio::test]
+async fn test_import_keeps_priority_and_dates() {
+    let (state, _temp_dir) = setup_state().await;
+    let chores = state.db.create_list("Chores".to_string()).await.unwrap();
+    let todo_txt = "(A) 2026-10-01 Call the plumber @phone +Chores\nx 2026-10-03 2026-10-01 Buy batteries +Chores pri:B\n";
+
+    todotxt::import_list(&state, chores, todo_txt).await.unwrap();
+
+    let tasks = state.db.get_tasks(chores).await.unwrap();
+    assert_eq!(tasks[0].priority.as_deref(), Some("A"));
+    assert_eq!(tasks[1].priority.as_deref(), Some("B"));
+    assert!(tasks[1].completed && tasks[1].completed_at.is_some());
+    assert_eq!(todotxt::export_list(&state, chores).await.unwrap(), todo_txt);
+}
+
+#[tokio::test]
+async fn test_import_puts_other_projects_on_their_lists() {
+    let (state, _temp_dir) = setup_state().await;
+    let chores = state.db.create_list("Chores".to_string()).await.unwrap();
+    let hardware = state.db.create_list("Hardware store".to_string()).await.unwrap();
+    let todo_txt = "Mop the floor\nScrews +hardware-store\nBuy a cake +Party\nBalloons +Party\n\n+Party\n";
+
+    assert_eq!(todotxt::import_list(&state, chores, todo_txt).await.unwrap(), 4);
+
+    assert_eq!(state.db.get_tasks(chores).await.unwrap()[0].text, "Mop the floor");
+    assert_eq!(state.db.get_tasks(hardware).await.unwrap()[0].text, "Screws");
+    let lists = state.db.get_lists().await.unwrap();
+    assert_eq!(lists.len(), 3);
+    let party = lists.iter().find(|list| list.name == "Party").unwrap();
+    let texts: Vec<String> = state.db.get_tasks(party.id).await.unwrap().into_iter().map(|task| task.text).collect();
+    assert_eq!(texts, vec!["Buy a cake", "Balloons"]);
+}
+
+#[tokio::test]
+async fn test_failed_import_changes_nothing() {
+    let (state, _temp_dir) = setup_state().await;
+    let chores = state.db.create_list("Chores".to_string()).await.unwrap();
+    let task = |text: &str, priority: Option<&str>| ImportedTask {
+        text: text.to_string(),
+        completed: false,
+        priority: priority.map(str::to_string),
+        tags: vec![],
+        created: None,
+        completed_at: None,
+    };
+    // A priority the database refuses, after a new list was already made
+    let tasks = vec![
+        (None, task("Mop the floor", None)),
+        (Some("Party".to_string()), task("Buy a cake", None)),
+        (None, task("Dust", Some("not a priority"))),
+    ];
+
+    assert!(state.db.import_todo_txt(chores, tasks).await.is_err());
+    assert!(state.db.get_tasks(chores).await.unwrap().is_empty());
+    assert_eq!(state.db.get_lists().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_todo_txt_routes() {
+    let (server, _temp_dir) = setup_test_server_with_data().await;
+
+    let response = server.get("/list/1/todo.txt").await;
+    response.assert_status_ok();
+    assert_eq!(response.header("content-disposition"), "attachment; filename=\"Default-List.txt\"");
+    assert!(response.text().lines().any(|line| line.ends_with("Test Task 1 +Default-List")));
+
+    let form = MultipartForm::new().add_part("file", Part::text("(B) Fix the sink @home").file_name("todo.txt"));
+    server.post("/list/1/todo.txt").multipart(form).await.assert_status_see_other();
+    assert!(server.get("/list/1/todo.txt").await.text().contains("(B) "));
+    server.get("/list/99/todo.txt").await.assert_status_not_found();
+}